@@ -62,7 +62,10 @@ impl PyRaftServiceClient {
 
         future_into_py(py, async move {
             let _ = client
-                .propose(raftify::raft_service::ProposeArgs { msg: proposal })
+                .propose(raftify::raft_service::ProposeArgs {
+                    msg: proposal.into(),
+                    ..Default::default()
+                })
                 .await
                 .unwrap()
                 .into_inner();
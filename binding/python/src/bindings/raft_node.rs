@@ -129,7 +129,7 @@ impl PyRaftNode {
         let raft_node = self.inner.clone();
 
         future_into_py(py, async move {
-            raft_node.demote(term, leader_id).await;
+            raft_node.step_down(term, leader_id).await;
             Ok(())
         })
     }
@@ -17,8 +17,8 @@ use example_harness::config::build_config;
 use memstore_example_harness::{
     state_machine::{HashStore, LogEntry, Raft},
     web_server_api::{
-        campaign, debug, demote, get, leader, leave, leave_joint, peers, put, snapshot,
-        transfer_leader,
+        campaign, debug, demote_voter, get, leader, leave, leave_joint, peers, put, snapshot,
+        step_down, transfer_leader,
     },
 };
 
@@ -162,7 +162,8 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                     .service(leave_joint)
                     .service(transfer_leader)
                     .service(campaign)
-                    .service(demote)
+                    .service(demote_voter)
+                    .service(step_down)
             })
             .bind(addr)
             .unwrap()
@@ -16,7 +16,9 @@ async fn main() {
                 value: "test".to_string(),
             }
             .encode()
-            .unwrap(),
+            .unwrap()
+            .into(),
+            ..Default::default()
         })
         .await
         .unwrap();
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use actix_web::{get, put, web, HttpResponse, Responder};
-use raftify::{raft::Storage, AbstractLogEntry, StableStorage};
+use raftify::{raft::Storage, AbstractLogEntry, AbstractStableStorage};
 use serde_json::Value;
 
 use crate::state_machine::{HashStore, LogEntry, Raft};
@@ -63,42 +63,34 @@ async fn peers(data: web::Data<(HashStore, Raft)>) -> impl Responder {
 
 #[get("/snapshot")]
 async fn snapshot(data: web::Data<(HashStore, Raft)>) -> impl Responder {
-    #[cfg(feature = "inmemory_storage")]
-    {
-        return "Snapshot is not supported with inmemory storage".to_string();
-    }
+    let raft = data.clone();
+    let last_index = raft
+        .1
+        .storage()
+        .await
+        .expect("Failed to get storage")
+        .last_index()
+        .expect("Failed to get last index");
 
-    #[cfg(not(feature = "inmemory_storage"))]
-    {
-        let raft = data.clone();
-        let last_index = raft
-            .1
-            .storage()
-            .await
-            .expect("Failed to get storage")
-            .last_index()
-            .expect("Failed to get last index");
-
-        let hard_state = raft
-            .1
-            .storage()
-            .await
-            .expect("Failed to get storage")
-            .hard_state()
-            .expect("Failed to get hard state");
-
-        raft.1
-            .make_snapshot(last_index, hard_state.term)
-            .await
-            .expect("Failed to make snapshot");
-        "OK".to_string()
-    }
+    let hard_state = raft
+        .1
+        .storage()
+        .await
+        .expect("Failed to get storage")
+        .hard_state()
+        .expect("Failed to get hard state");
+
+    raft.1
+        .make_snapshot(last_index, hard_state.term)
+        .await
+        .expect("Failed to make snapshot");
+    "OK".to_string()
 }
 
 #[get("/leave_joint")]
 async fn leave_joint(data: web::Data<(HashStore, Raft)>) -> impl Responder {
     let raft = data.clone();
-    raft.1.leave_joint().await;
+    raft.1.leave_joint().await.unwrap();
     "OK".to_string()
 }
 
@@ -120,10 +112,21 @@ async fn campaign(data: web::Data<(HashStore, Raft)>) -> impl Responder {
     "OK".to_string()
 }
 
-#[get("/demote/{term}/{leader_id}")]
-async fn demote(data: web::Data<(HashStore, Raft)>, path: web::Path<(u64, u64)>) -> impl Responder {
+#[get("/step_down/{term}/{leader_id}")]
+async fn step_down(
+    data: web::Data<(HashStore, Raft)>,
+    path: web::Path<(u64, u64)>,
+) -> impl Responder {
     let raft = data.clone();
     let (term, leader_id) = path.into_inner();
-    raft.1.demote(term, leader_id).await.unwrap();
+    raft.1.step_down(term, leader_id).await.unwrap();
+    "OK".to_string()
+}
+
+#[get("/demote_voter/{id}")]
+async fn demote_voter(data: web::Data<(HashStore, Raft)>, path: web::Path<u64>) -> impl Responder {
+    let raft = data.clone();
+    let node_id: u64 = path.into_inner();
+    raft.1.demote_voter(node_id).await.unwrap();
     "OK".to_string()
 }
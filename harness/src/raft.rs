@@ -1,19 +1,24 @@
 use futures::future;
 use raftify::{
     raft::{formatter::set_custom_formatter, logger::Slogger},
-    CustomFormatter, HeedStorage, Peers, Raft as Raft_, Result,
+    Config, CustomFormatter, HeedStorage, Peers, Raft as Raft_, Result,
 };
 use std::{
     collections::HashMap,
     sync::{mpsc, Arc},
+    time::Duration,
 };
-use tokio::task::JoinHandle;
+use tokio::{task::JoinHandle, time::sleep};
 
 use crate::{
     config::build_config,
+    constant::THREE_NODE_EXAMPLE,
     logger::get_logger,
     state_machine::{HashStore, LogEntry},
-    utils::{build_logger, ensure_directory_exist, get_storage_path},
+    utils::{
+        build_logger, cleanup_storage, ensure_directory_exist, get_storage_path,
+        kill_previous_raft_processes, load_peers, wait_for_until_cluster_size_increase,
+    },
 };
 
 pub type Raft = Raft_<LogEntry, HeedStorage, HashStore>;
@@ -49,9 +54,11 @@ fn run_raft(
     node_id: &u64,
     peers: Peers,
     should_be_leader: bool,
+    configure: fn(&mut Config),
 ) -> Result<JoinHandle<Result<()>>> {
     let peer = peers.get(node_id).unwrap();
     let mut cfg = build_config(*node_id);
+    configure(&mut cfg);
     cfg.initial_peers = if should_be_leader {
         None
     } else {
@@ -95,6 +102,15 @@ fn run_raft(
 pub async fn build_raft_cluster(
     tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
     peers: Peers,
+) -> Result<()> {
+    build_raft_cluster_with_config(tx_initialized_raft, peers, |_| {}).await
+}
+
+/// Like `build_raft_cluster`, with the config of every node adjusted by `configure`.
+pub async fn build_raft_cluster_with_config(
+    tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
+    peers: Peers,
+    configure: fn(&mut Config),
 ) -> Result<()> {
     let logger = get_logger();
 
@@ -109,6 +125,7 @@ pub async fn build_raft_cluster(
             &node_id,
             peers.clone(),
             should_be_leader,
+            configure,
         )?;
         raft_handles.push(raft_handle);
 
@@ -132,6 +149,29 @@ pub async fn build_raft_cluster(
     Ok(())
 }
 
+/// Starts the three node example cluster, and waits until the nodes joined it.
+pub async fn build_three_node_cluster() -> HashMap<u64, Raft> {
+    build_three_node_cluster_with_config(|_| {}).await
+}
+
+/// Like `build_three_node_cluster`, with the config of every node adjusted by `configure`.
+pub async fn build_three_node_cluster_with_config(
+    configure: fn(&mut Config),
+) -> HashMap<u64, Raft> {
+    cleanup_storage("./logs");
+    kill_previous_raft_processes();
+
+    let peers = load_peers(THREE_NODE_EXAMPLE).await.unwrap();
+    let (tx_raft, rx_raft) = mpsc::channel::<(u64, Raft)>();
+
+    let _raft_tasks = tokio::spawn(build_raft_cluster_with_config(tx_raft, peers, configure));
+    sleep(Duration::from_secs(1)).await;
+
+    let rafts = wait_until_rafts_ready(None, rx_raft, 3).await;
+    wait_for_until_cluster_size_increase(rafts.get(&1).unwrap().clone(), 3).await;
+    rafts
+}
+
 pub async fn spawn_extra_node(
     tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
     node_id: u64,
@@ -163,6 +203,23 @@ pub async fn spawn_and_join_extra_node(
     tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
     raft_addr: &str,
     peer_addr: &str,
+) -> Result<JoinHandle<Result<()>>> {
+    spawn_and_join_extra_node_with_role(tx_initialized_raft, raft_addr, peer_addr, false).await
+}
+
+pub async fn spawn_and_join_extra_learner(
+    tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
+    raft_addr: &str,
+    peer_addr: &str,
+) -> Result<JoinHandle<Result<()>>> {
+    spawn_and_join_extra_node_with_role(tx_initialized_raft, raft_addr, peer_addr, true).await
+}
+
+async fn spawn_and_join_extra_node_with_role(
+    tx_initialized_raft: mpsc::Sender<(u64, Raft)>,
+    raft_addr: &str,
+    peer_addr: &str,
+    as_learner: bool,
 ) -> Result<JoinHandle<Result<()>>> {
     let logger = Arc::new(Slogger {
         slog: build_logger(),
@@ -192,9 +249,15 @@ pub async fn spawn_and_join_extra_node(
     raft.add_peers(join_ticket.peers.clone())
         .await
         .expect("Failed to add peers");
-    raft.join_cluster(vec![join_ticket])
-        .await
-        .expect("Failed to join cluster");
+    if as_learner {
+        raft.join_cluster_as_learner(vec![join_ticket])
+            .await
+            .expect("Failed to join cluster");
+    } else {
+        raft.join_cluster(vec![join_ticket])
+            .await
+            .expect("Failed to join cluster");
+    }
 
     Ok(raft_handle)
 }
@@ -100,6 +100,42 @@ pub async fn wait_for_until_cluster_size_increase(raft: Raft, target: usize) {
     sleep(Duration::from_secs_f32(1.0)).await;
 }
 
+pub async fn wait_for_until_voters_increase(raft: Raft, target: usize) {
+    raft.logger.debug(&format!(
+        "Waiting for the number of voters to increase to... {}",
+        target
+    ));
+
+    loop {
+        let voters = raft.get_voters().await.unwrap();
+        if voters.len() >= target {
+            break;
+        }
+        sleep(Duration::from_secs_f32(0.1)).await;
+    }
+
+    // Wait for the conf_change reflected to the cluster
+    sleep(Duration::from_secs_f32(1.0)).await;
+}
+
+pub async fn wait_for_until_learners_increase(raft: Raft, target: usize) {
+    raft.logger.debug(&format!(
+        "Waiting for the number of learners to increase to... {}",
+        target
+    ));
+
+    loop {
+        let learners = raft.get_learners().await.unwrap();
+        if learners.len() >= target {
+            break;
+        }
+        sleep(Duration::from_secs_f32(0.1)).await;
+    }
+
+    // Wait for the conf_change reflected to the cluster
+    sleep(Duration::from_secs_f32(1.0)).await;
+}
+
 pub async fn wait_for_until_cluster_size_decrease(raft: Raft, target: usize) {
     raft.logger.debug(&format!(
         "Waiting for cluster size to decrease to {}...",
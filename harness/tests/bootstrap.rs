@@ -1,12 +1,22 @@
-use std::{sync::mpsc, time::Duration};
+use raftify::{raft::logger::Slogger, AbstractLogEntry, HeedStorage};
+use std::{
+    sync::{mpsc, Arc},
+    time::Duration,
+};
 use tokio::time::sleep;
 
 use harness::{
+    config::build_config,
     constant::{ONE_NODE_EXAMPLE, RAFT_ADDRS, THREE_NODE_EXAMPLE},
-    raft::{build_raft_cluster, spawn_and_join_extra_node, wait_until_rafts_ready, Raft},
+    raft::{
+        build_raft_cluster, spawn_and_join_extra_learner, spawn_and_join_extra_node,
+        wait_until_rafts_ready, Raft,
+    },
+    state_machine::{HashStore, LogEntry},
     utils::{
-        cleanup_storage, kill_previous_raft_processes, load_peers,
-        wait_for_until_cluster_size_increase,
+        build_logger, cleanup_storage, ensure_directory_exist, get_storage_path,
+        kill_previous_raft_processes, load_peers, wait_for_until_cluster_size_increase,
+        wait_for_until_learners_increase,
     },
 };
 
@@ -73,6 +83,151 @@ pub async fn test_dynamic_bootstrap() {
     }
 }
 
+#[tokio::test]
+pub async fn test_dynamic_bootstrap_with_learner() {
+    cleanup_storage("./logs");
+    kill_previous_raft_processes();
+    let (tx_raft, rx_raft) = mpsc::channel::<(u64, Raft)>();
+
+    let peers = load_peers(ONE_NODE_EXAMPLE).await.unwrap();
+    let _raft_tasks = tokio::spawn(build_raft_cluster(tx_raft.clone(), peers.clone()));
+
+    sleep(Duration::from_secs(1)).await;
+
+    tokio::spawn(spawn_and_join_extra_node(
+        tx_raft.clone(),
+        "127.0.0.1:60062",
+        RAFT_ADDRS[0],
+    ))
+    .await
+    .unwrap()
+    .unwrap();
+
+    tokio::spawn(spawn_and_join_extra_learner(
+        tx_raft.clone(),
+        "127.0.0.1:60063",
+        RAFT_ADDRS[0],
+    ))
+    .await
+    .unwrap()
+    .unwrap();
+
+    let mut rafts = wait_until_rafts_ready(None, rx_raft, 3).await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+
+    wait_for_until_learners_increase(raft_1.clone(), 1).await;
+
+    assert_eq!(raft_1.get_voters().await.unwrap(), vec![1, 2]);
+    assert_eq!(raft_1.get_learners().await.unwrap(), vec![3]);
+
+    let joined = raft_1
+        .membership_history()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|record| record.error.is_none())
+        .flat_map(|record| record.changes)
+        .map(|change| change.node_id)
+        .collect::<Vec<_>>();
+    assert!(joined.contains(&2) && joined.contains(&3));
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_bootstrap_from_existing_storage() {
+    cleanup_storage("./logs");
+    kill_previous_raft_processes();
+    let (tx_raft, rx_raft) = mpsc::channel::<(u64, Raft)>();
+
+    let peers = load_peers(ONE_NODE_EXAMPLE).await.unwrap();
+    let _raft_tasks = tokio::spawn(build_raft_cluster(tx_raft, peers.clone()));
+    let mut rafts = wait_until_rafts_ready(None, rx_raft, 1).await;
+
+    sleep(Duration::from_secs(1)).await;
+
+    let raft_1 = rafts.remove(&1).unwrap();
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    raft_1.propose(entry).await.unwrap();
+    raft_1.quit().await.expect("Failed to quit raft node");
+    drop(raft_1);
+
+    sleep(Duration::from_secs(1)).await;
+
+    // The peers are not given, they are read back from the log directory.
+    let config = build_config(1);
+    let log_dir = config.log_dir.clone();
+    let store = HashStore::new();
+    let raft = Raft::bootstrap_from_existing(
+        &log_dir,
+        store.clone(),
+        config,
+        Arc::new(Slogger {
+            slog: build_logger(),
+        }),
+    )
+    .unwrap();
+    tokio::spawn(raft.clone().run());
+
+    sleep(Duration::from_secs(3)).await;
+
+    assert_eq!(raft.get_id().await.unwrap(), 1);
+    assert_eq!(raft.get_leader_id().await.unwrap(), 1);
+    assert_eq!(store.get(1), Some("test".to_string()));
+
+    raft.quit().await.expect("Failed to quit raft node");
+}
+
+#[tokio::test]
+pub async fn test_run_on_given_runtime() {
+    cleanup_storage("./logs");
+    kill_previous_raft_processes();
+
+    // Isolated from the runtime of the test, which only drives `run`.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("raft-io")
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut config = build_config(1);
+    config.runtime = Some(runtime.handle().clone());
+    let logger = Arc::new(Slogger {
+        slog: build_logger(),
+    });
+    let storage_pth = get_storage_path(config.log_dir.as_str(), 1);
+    ensure_directory_exist(storage_pth.as_str()).unwrap();
+    let storage = HeedStorage::create(&storage_pth, &config, logger.clone()).unwrap();
+
+    let store = HashStore::new();
+    let raft = Raft::bootstrap(1, RAFT_ADDRS[0], storage, store.clone(), config, logger).unwrap();
+    tokio::spawn(raft.clone().run());
+
+    sleep(Duration::from_secs(2)).await;
+
+    assert!(runtime.metrics().num_alive_tasks() > 0);
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    raft.propose(entry).await.unwrap();
+    assert_eq!(store.get(1), Some("test".to_string()));
+
+    raft.quit().await.expect("Failed to quit raft node");
+    runtime.shutdown_background();
+}
+
 // #[tokio::test]
 // pub async fn test_dynamic_bootstrap_using_joint_consensus() {
 //     kill_previous_raft_processes();
@@ -1,13 +1,23 @@
-use raftify::AbstractLogEntry;
-use std::{sync::mpsc, time::Duration};
+use raftify::{
+    raft::{logger::Slogger, Storage},
+    AbstractLogEntry, AbstractStableStorage, Config, HeedStorage, Peers,
+};
+use std::{
+    sync::{mpsc, Arc},
+    time::Duration,
+};
 use tokio::time::sleep;
 
 use harness::{
+    config::build_config,
     constant::{RAFT_ADDRS, THREE_NODE_EXAMPLE},
-    raft::{build_raft_cluster, spawn_and_join_extra_node, wait_until_rafts_ready, Raft},
-    state_machine::LogEntry,
+    raft::{
+        build_raft_cluster, build_three_node_cluster, spawn_and_join_extra_node,
+        wait_until_rafts_ready, Raft,
+    },
+    state_machine::{HashStore, LogEntry},
     utils::{
-        cleanup_storage, kill_previous_raft_processes, load_peers,
+        build_logger, cleanup_storage, kill_previous_raft_processes, load_peers,
         wait_for_until_cluster_size_increase,
     },
 };
@@ -92,3 +102,171 @@ pub async fn test_data_replication() {
         raft.quit().await.expect("Failed to quit the raft node");
     }
 }
+
+fn insert_entry(key: u64, value: &str) -> Vec<u8> {
+    LogEntry::Insert {
+        key,
+        value: value.to_string(),
+    }
+    .encode()
+    .unwrap()
+}
+
+fn create_storage(config: &Config) -> HeedStorage {
+    HeedStorage::create(
+        &config.log_dir,
+        config,
+        Arc::new(Slogger {
+            slog: build_logger(),
+        }),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+pub async fn test_propose_returns_the_outcome() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    let entry = insert_entry(1, "test");
+    let outcome = raft_1.propose(entry.clone()).await.unwrap();
+    assert!(outcome.index > 0);
+    assert_eq!(outcome.term, raft_1.get_term().await.unwrap());
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_propose_returns_the_apply_result() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    let entry = insert_entry(1, "test");
+    let outcome = raft_1.propose(entry.clone()).await.unwrap();
+    // The harness state machine echoes the applied entry back to the proposer.
+    assert_eq!(outcome.data, entry);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_subscribe_to_applied_entries() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    let entry = insert_entry(1, "test");
+    let mut applied_entries = raft_1.subscribe();
+    let outcome = raft_1.propose(entry.clone()).await.unwrap();
+
+    let (index, applied_entry) = applied_entries.recv().await.unwrap();
+    assert_eq!(index, outcome.index);
+    assert_eq!(applied_entry.encode().unwrap(), entry);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_get_log_entries() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    let entry = insert_entry(1, "test");
+    let outcome = raft_1.propose(entry.clone()).await.unwrap();
+
+    let entries = raft_1
+        .get_log_entries(outcome.index, outcome.index + 1)
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].term, outcome.term);
+    assert_eq!(entries[0].data, entry);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_compact_applied_logs() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    raft_1.propose(insert_entry(1, "test")).await.unwrap();
+
+    // Compacting the applied logs takes a snapshot first, since the last one doesn't cover them.
+    let applied = raft_1.get_last_applied().await.unwrap();
+    assert!(raft_1.compact(applied + 1).await.is_err());
+    raft_1.compact(applied).await.unwrap();
+    let stats = raft_1.storage_stats().await.unwrap();
+    assert_eq!(stats.first_index, applied);
+    assert!(stats.snapshot_index >= applied);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_backup_and_restore() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    raft_1.propose(insert_entry(1, "test")).await.unwrap();
+    let applied = raft_1.get_last_applied().await.unwrap();
+
+    // The backup can be restored into the storage of a new node.
+    raft_1.backup("./logs/backup.bin").await.unwrap();
+
+    let config = build_config(5);
+    let mut storage = create_storage(&config);
+    let mut fsm = HashStore::new();
+    let peers = Raft::restore("./logs/backup.bin", &mut storage, &mut fsm, &config)
+        .await
+        .unwrap();
+
+    assert_eq!(peers.len(), 3);
+    assert!(storage.last_index().unwrap() >= applied);
+    assert_eq!(fsm.get(1).unwrap(), "test");
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
+
+#[tokio::test]
+pub async fn test_export_and_import_snapshot() {
+    let mut rafts = build_three_node_cluster().await;
+    let raft_1 = rafts.get(&1).unwrap();
+
+    raft_1.propose(insert_entry(1, "test")).await.unwrap();
+
+    // The exported snapshot can be imported into a new cluster with different node ids.
+    raft_1.export_snapshot("./logs/snapshot.bin").await.unwrap();
+
+    let config = build_config(6);
+    let mut storage = create_storage(&config);
+    let mut fsm = HashStore::new();
+    let new_peers = Peers::new(10, "127.0.0.1:60070");
+    Raft::import_snapshot(
+        "./logs/snapshot.bin",
+        &mut storage,
+        &mut fsm,
+        &new_peers,
+        &config,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(storage.conf_state().unwrap().voters, vec![10]);
+    assert_eq!(fsm.get(1).unwrap(), "test");
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit the raft node");
+    }
+}
@@ -1,9 +1,17 @@
+use raftify::{
+    create_client, AbstractLogEntry, ConfChangeResponseResult, ConnectionState, DeadVoterRemoval,
+    Error, NodeRole, ResponseResult,
+};
 use std::{sync::mpsc, time::Duration};
 use tokio::time::{sleep, timeout};
 
 use harness::{
     constant::{FIVE_NODE_EXAMPLE, THREE_NODE_EXAMPLE},
-    raft::{build_raft_cluster, wait_until_rafts_ready, Raft},
+    raft::{
+        build_raft_cluster, build_three_node_cluster, build_three_node_cluster_with_config,
+        wait_until_rafts_ready, Raft,
+    },
+    state_machine::LogEntry,
     utils::{
         cleanup_storage, kill_previous_raft_processes, load_peers,
         wait_for_until_cluster_size_decrease, wait_for_until_cluster_size_increase,
@@ -58,6 +66,172 @@ pub async fn test_leader_election_in_three_node_example() {
     raft_3.quit().await.expect("Failed to quit");
 }
 
+#[tokio::test]
+pub async fn test_status_of_the_leader() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let status = raft_1.status().await.unwrap();
+    assert_eq!(status.role, NodeRole::Leader);
+    assert_eq!(status.leader_id, 1);
+    assert_eq!(status.progress.len(), 3);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_role_and_term_accessors() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(raft_1.get_role().await.unwrap(), NodeRole::Leader);
+    let term = raft_1.get_term().await.unwrap();
+    assert!(term > 0);
+
+    let raft_2 = rafts.get(&2).unwrap();
+    assert_eq!(raft_2.get_role().await.unwrap(), NodeRole::Follower);
+    assert_eq!(raft_2.get_term().await.unwrap(), term);
+    assert!(raft_2.get_last_applied().await.unwrap() <= raft_2.get_commit_index().await.unwrap());
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_nodes_share_the_cluster_uuid() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let cluster_uuid = raft_1.cluster_uuid();
+    assert!(cluster_uuid.is_some());
+    for raft in rafts.values() {
+        assert_eq!(raft.cluster_uuid(), cluster_uuid);
+    }
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_status_reports_the_connections_to_the_peers() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    // The connections to the followers are kept and reused by the heartbeats.
+    let status = raft_1.status().await.unwrap();
+    assert_eq!(status.connections.len(), 2);
+    for connection in status.connections.values() {
+        assert_eq!(connection.state, ConnectionState::Ready);
+        assert_eq!(connection.consecutive_failures, 0);
+    }
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_graceful_shutdown_hands_off_leadership() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.remove(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let term = raft_1.get_term().await.unwrap();
+    raft_1.shutdown().await.expect("Failed to shut down");
+
+    // The leadership moved before node 1 quit, without waiting for an election timeout.
+    let raft_2 = rafts.get(&2).unwrap();
+    let leader_id = raft_2.get_leader_id().await.unwrap();
+    assert!(
+        [2, 3].contains(&leader_id),
+        "Actual leader_id: {}",
+        leader_id
+    );
+    // A lost election round during the hand-off advances the term once more.
+    let leader = rafts.get(&leader_id).unwrap();
+    assert!(leader.is_leader().await.unwrap());
+    assert!(leader.get_term().await.unwrap() > term);
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_quit_tears_down_the_server() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_3 = rafts.remove(&3).unwrap();
+
+    raft_3.quit().await.expect("Failed to quit");
+    assert!(raft_3.cancellation_token().is_cancelled());
+
+    // The RaftServer stops listening along with the node, instead of being left running.
+    let peers = load_peers(THREE_NODE_EXAMPLE).await.unwrap();
+    let addr = peers.get(&3).unwrap().addr;
+    timeout(Duration::from_secs(5), async {
+        while create_client(addr).await.is_ok() {
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("RaftServer of node 3 still serves");
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_drain_moves_leadership_and_rejects_proposals() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    raft_1.drain().await.expect("Failed to drain");
+
+    let status = raft_1.status().await.unwrap();
+    assert!(status.draining);
+    assert!(
+        [2, 3].contains(&status.leader_id),
+        "Actual leader_id: {}",
+        status.leader_id
+    );
+
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    let error = raft_1.propose(entry.clone()).await.unwrap_err();
+    assert!(matches!(error, Error::Draining(_)), "{}", error);
+    // The drained node still votes and replicates the log.
+    rafts.get(&2).unwrap().propose(entry.clone()).await.unwrap();
+
+    raft_1.undrain().await.expect("Failed to undrain");
+    assert!(!raft_1.status().await.unwrap().draining);
+    raft_1.propose(entry).await.unwrap();
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
 // TODO: Fix this test.
 #[tokio::test]
 #[ignore]
@@ -120,3 +294,143 @@ pub async fn test_leader_election_in_five_node_example() {
         raft.quit().await.expect("Failed to quit the raft node");
     }
 }
+
+#[tokio::test]
+pub async fn test_force_remove_unreachable_peer() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap().clone();
+
+    // The leader can't be force-removed.
+    assert!(matches!(
+        raft_1.force_remove_peer(1).await.unwrap(),
+        ConfChangeResponseResult::Error(_)
+    ));
+
+    let raft_3 = rafts.get_mut(&3).unwrap();
+    raft_3.quit().await.expect("Failed to quit");
+    sleep(Duration::from_secs(1)).await;
+
+    assert!(matches!(
+        raft_1.force_remove_peer(3).await.unwrap(),
+        ConfChangeResponseResult::RemoveSuccess
+    ));
+    wait_for_until_cluster_size_decrease(raft_1.clone(), 2).await;
+    let mut voters = raft_1.get_voters().await.unwrap();
+    voters.sort();
+    assert_eq!(voters, vec![1, 2]);
+
+    raft_1.quit().await.expect("Failed to quit");
+    let raft_2 = rafts.get_mut(&2).unwrap();
+    raft_2.quit().await.expect("Failed to quit");
+}
+
+#[tokio::test]
+pub async fn test_dead_voter_removal() {
+    let mut rafts = build_three_node_cluster_with_config(|cfg| {
+        cfg.raft_config.check_quorum = true;
+        cfg.dead_voter_removal = Some(DeadVoterRemoval {
+            unreachable_for: 2.0,
+            min_voters: 2,
+        });
+    })
+    .await;
+
+    let raft_1 = rafts.get(&1).unwrap().clone();
+
+    let raft_3 = rafts.get_mut(&3).unwrap();
+    raft_3.quit().await.expect("Failed to quit");
+
+    timeout(
+        Duration::from_secs(10),
+        wait_for_until_cluster_size_decrease(raft_1.clone(), 2),
+    )
+    .await
+    .expect("Dead voter 3 wasn't removed");
+
+    // Node 2 doesn't receive any proposal, but it answers the heartbeats and stays.
+    sleep(Duration::from_secs(3)).await;
+    let mut voters = raft_1.get_voters().await.unwrap();
+    voters.sort();
+    assert_eq!(voters, vec![1, 2]);
+
+    raft_1.quit().await.expect("Failed to quit");
+    let raft_2 = rafts.get_mut(&2).unwrap();
+    raft_2.quit().await.expect("Failed to quit");
+}
+
+#[tokio::test]
+pub async fn test_transfer_leader() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    // Resolves once node 2 took the leadership over.
+    raft_1
+        .transfer_leader(2)
+        .await
+        .expect("Failed to transfer the leadership");
+    let raft_2 = rafts.get(&2).unwrap();
+    assert!(raft_2.is_leader().await.unwrap());
+    assert_eq!(raft_1.get_leader_id().await.unwrap(), 2);
+
+    // The caller stops waiting before the transfer completes.
+    let _ = timeout(Duration::from_millis(1), raft_2.transfer_leader(3)).await;
+    sleep(Duration::from_secs(2)).await;
+
+    let leader_id = raft_2.get_leader_id().await.unwrap();
+    assert!(
+        [2, 3].contains(&leader_id),
+        "Actual leader_id: {}",
+        leader_id
+    );
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    rafts.get(&leader_id).unwrap().propose(entry).await.unwrap();
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_campaign() {
+    let mut rafts = build_three_node_cluster().await;
+
+    sleep(Duration::from_secs(1)).await;
+
+    // Resolves once node 2 won the election.
+    let raft_2 = rafts.get(&2).unwrap();
+    let result = raft_2.campaign().await.unwrap();
+    assert!(matches!(result, ResponseResult::Success), "{:?}", result);
+    assert!(raft_2.is_leader().await.unwrap());
+    assert_eq!(rafts.get(&1).unwrap().get_leader_id().await.unwrap(), 2);
+
+    // The caller stops waiting before the election completes.
+    let raft_3 = rafts.get(&3).unwrap();
+    let _ = timeout(Duration::from_millis(1), raft_3.campaign()).await;
+    sleep(Duration::from_secs(2)).await;
+
+    let leader_id = raft_3.get_leader_id().await.unwrap();
+    assert!(
+        [2, 3].contains(&leader_id),
+        "Actual leader_id: {}",
+        leader_id
+    );
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    rafts.get(&leader_id).unwrap().propose(entry).await.unwrap();
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
@@ -0,0 +1,89 @@
+use raftify::{AbstractLogEntry, ReadOnlyOption};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+use harness::{
+    raft::{build_three_node_cluster, build_three_node_cluster_with_config},
+    state_machine::LogEntry,
+};
+
+#[tokio::test]
+pub async fn test_read_lease_on_the_leader() {
+    let mut rafts = build_three_node_cluster_with_config(|cfg| {
+        cfg.raft_config.check_quorum = true;
+        cfg.raft_config.read_only_option = ReadOnlyOption::LeaseBased;
+    })
+    .await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    raft_1.propose(entry).await.unwrap();
+
+    // The entry proposed before is visible once the lease read resolves.
+    raft_1.read_lease().await.expect("Failed to read the lease");
+    let store = raft_1.state_machine().await.unwrap();
+    assert_eq!(store.0.read().unwrap().get(&1).unwrap(), "test");
+
+    // The followers don't hold the lease.
+    let raft_2 = rafts.get(&2).unwrap();
+    assert!(raft_2.read_lease().await.is_err());
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_read_index_on_a_follower() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    let entry = LogEntry::Insert {
+        key: 1,
+        value: "test".to_string(),
+    }
+    .encode()
+    .unwrap();
+    raft_1.propose(entry).await.unwrap();
+
+    // The ReadIndex request is forwarded to the leader, and resolves once node 2 applied the entry.
+    let raft_2 = rafts.get(&2).unwrap();
+    raft_2.read_index().await.expect("Failed to read the index");
+    let store = raft_2.state_machine().await.unwrap();
+    assert_eq!(store.0.read().unwrap().get(&1).unwrap(), "test");
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
+
+#[tokio::test]
+pub async fn test_read_index_after_the_caller_went_away() {
+    let mut rafts = build_three_node_cluster().await;
+
+    let raft_1 = rafts.get(&1).unwrap();
+    sleep(Duration::from_secs(1)).await;
+
+    // The callers stop waiting before the reads resolve.
+    let _ = timeout(Duration::from_millis(1), raft_1.read_index()).await;
+    let raft_2 = rafts.get(&2).unwrap();
+    let _ = timeout(Duration::from_millis(1), raft_2.read_index()).await;
+    sleep(Duration::from_secs(1)).await;
+
+    // The nodes keep serving the reads.
+    raft_1.read_index().await.expect("Failed to read the index");
+    raft_2.read_index().await.expect("Failed to read the index");
+
+    for (_, raft) in rafts.iter_mut() {
+        raft.quit().await.expect("Failed to quit");
+    }
+}
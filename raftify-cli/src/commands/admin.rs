@@ -0,0 +1,123 @@
+use raftify::{
+    raft_admin_service::{
+        raft_admin_service_client::RaftAdminServiceClient, PurgeTombstonesArgs, RemovePeerArgs,
+        SetPeerLabelsArgs, SetTicksArgs, UpdateRuntimeConfigArgs,
+    },
+    raft_service::ResultCode,
+    Error, Result,
+};
+
+pub async fn admin_remove_peer(addr: &str, node_id: u64, force: bool) -> Result<()> {
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .remove_peer(RemovePeerArgs { node_id, force })
+        .await?
+        .into_inner();
+
+    if response.code() != ResultCode::Ok {
+        return Err(Error::Rejected(format!(
+            "Failed to remove node {}: {}",
+            node_id,
+            String::from_utf8_lossy(&response.error)
+        )));
+    }
+
+    println!("Node {} removed from the cluster", node_id);
+    Ok(())
+}
+
+pub async fn admin_purge_tombstones(addr: &str, node_ids: Vec<u64>) -> Result<()> {
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .purge_tombstones(PurgeTombstonesArgs {
+            node_ids: node_ids.clone(),
+        })
+        .await?
+        .into_inner();
+
+    if response.code() != ResultCode::Ok {
+        return Err(Error::Rejected(format!(
+            "Failed to purge the tombstones: {}",
+            String::from_utf8_lossy(&response.error)
+        )));
+    }
+
+    if node_ids.is_empty() {
+        println!("Purged all tombstones");
+    } else {
+        println!("Purged the tombstones of the nodes {:?}", node_ids);
+    }
+    Ok(())
+}
+
+pub async fn admin_update_runtime_config(addr: &str, config_json: String) -> Result<()> {
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .update_runtime_config(UpdateRuntimeConfigArgs { config_json })
+        .await?
+        .into_inner();
+
+    if response.code() != ResultCode::Ok {
+        return Err(Error::Rejected(format!(
+            "Failed to update the runtime config: {}",
+            String::from_utf8_lossy(&response.error)
+        )));
+    }
+
+    println!("Runtime config updated on the cluster");
+    Ok(())
+}
+
+pub async fn admin_set_ticks(addr: &str, election_tick: u64, heartbeat_tick: u64) -> Result<()> {
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .set_ticks(SetTicksArgs {
+            election_tick,
+            heartbeat_tick,
+        })
+        .await?
+        .into_inner();
+
+    if response.code() != ResultCode::Ok {
+        return Err(Error::Rejected(format!(
+            "Failed to set the ticks: {}",
+            String::from_utf8_lossy(&response.error)
+        )));
+    }
+
+    println!(
+        "Election tick set to {}, heartbeat tick set to {} on {}",
+        election_tick, heartbeat_tick, addr
+    );
+    Ok(())
+}
+
+pub async fn admin_set_peer_labels(addr: &str, node_id: u64, labels: Vec<String>) -> Result<()> {
+    let labels = labels
+        .iter()
+        .map(|label| match label.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+            _ => Err(Error::Rejected(format!(
+                "Invalid label \"{}\", expected <key>=<value>",
+                label
+            ))),
+        })
+        .collect::<Result<_>>()?;
+
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .set_peer_labels(SetPeerLabelsArgs { node_id, labels })
+        .await?
+        .into_inner();
+
+    if response.code() != ResultCode::Ok {
+        return Err(Error::Rejected(format!(
+            "Failed to set the labels of node {}: {}",
+            node_id,
+            String::from_utf8_lossy(&response.error)
+        )));
+    }
+
+    println!("Labels of node {} set", node_id);
+    Ok(())
+}
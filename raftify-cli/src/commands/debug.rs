@@ -1,6 +1,6 @@
 use core::panic;
 use serde_json::Value;
-use std::{collections::HashMap, fs, path::Path, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, fs, path::Path, sync::Arc};
 
 use raftify::{
     create_client,
@@ -9,12 +9,16 @@ use raftify::{
         logger::Slogger,
         Storage,
     },
-    raft_node::utils::format_debugging_info,
-    raft_service, Config, Result, StableStorage,
-    HeedStorage, StorageType,
+    raft_admin_service::raft_admin_service_client::RaftAdminServiceClient,
+    raft_node::utils::{format_debugging_info, persisted_storage_json},
+    raft_service, AbstractLogEntry, AbstractStableStorage, Config, HeedStorage,
+    MembershipChangeRecord, Result, StorageType,
 };
 
-pub fn debug_persisted<LogStorage: StableStorage>(path: &str, logger: slog::Logger) -> Result<()> {
+pub(crate) fn open_persisted_storage<LogStorage: AbstractStableStorage>(
+    path: &str,
+    logger: slog::Logger,
+) -> Result<HeedStorage> {
     let config = Config {
         log_dir: path.to_string(),
         ..Default::default()
@@ -35,6 +39,14 @@ pub fn debug_persisted<LogStorage: StableStorage>(path: &str, logger: slog::Logg
             panic!("Unsupported storage type");
         }
     };
+    Ok(storage)
+}
+
+pub fn debug_persisted<LogStorage: AbstractStableStorage>(
+    path: &str,
+    logger: slog::Logger,
+) -> Result<()> {
+    let storage = open_persisted_storage::<LogStorage>(path, logger)?;
 
     let entries = storage.all_entries()?;
 
@@ -60,7 +72,51 @@ pub fn debug_persisted<LogStorage: StableStorage>(path: &str, logger: slog::Logg
     Ok(())
 }
 
-pub fn debug_persitsted_all<LogStorage: StableStorage>(path_str: &str, logger: slog::Logger) -> Result<()> {
+pub fn debug_persisted_json<
+    LogEntry: AbstractLogEntry + Debug,
+    LogStorage: AbstractStableStorage,
+>(
+    path: &str,
+    logger: slog::Logger,
+) -> Result<()> {
+    let storage = open_persisted_storage::<LogStorage>(path, logger)?;
+    let json = persisted_storage_json::<LogEntry, _>(&storage)?;
+
+    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+    Ok(())
+}
+
+pub fn debug_stats<LogStorage: AbstractStableStorage>(
+    path: &str,
+    logger: slog::Logger,
+) -> Result<()> {
+    let storage = open_persisted_storage::<LogStorage>(path, logger)?;
+    let stats = storage.stats()?;
+
+    println!("---- Storage stats ----");
+    println!("First index: {}", stats.first_index);
+    println!("Last index: {}", stats.last_index);
+    println!("Entry count: {}", stats.entry_count);
+    println!("Disk size: {} bytes", stats.disk_size);
+    if let (Some(map_size), Some(map_used)) = (stats.map_size, stats.map_used) {
+        println!(
+            "Map usage: {} / {} bytes ({:.1}%)",
+            map_used,
+            map_size,
+            map_used as f64 / map_size as f64 * 100.0
+        );
+    }
+    println!(
+        "Snapshot: index {}, term {}",
+        stats.snapshot_index, stats.snapshot_term
+    );
+    Ok(())
+}
+
+pub fn debug_persisted_all<LogStorage: AbstractStableStorage>(
+    path_str: &str,
+    logger: slog::Logger,
+) -> Result<()> {
     let path = match fs::canonicalize(Path::new(&path_str)) {
         Ok(absolute_path) => absolute_path,
         Err(e) => {
@@ -111,6 +167,44 @@ pub async fn debug_node(addr: &str) -> Result<()> {
     Ok(())
 }
 
+pub async fn debug_membership(addr: &str) -> Result<()> {
+    let mut client = RaftAdminServiceClient::connect(format!("http://{}", addr)).await?;
+    let response = client
+        .get_membership_history(raft_service::Empty {})
+        .await?;
+    let json = response.into_inner().result_json;
+    let history: Vec<MembershipChangeRecord> = serde_json::from_str(&json).unwrap();
+
+    println!("---- Membership history ----");
+    for record in history.iter() {
+        let changes = record
+            .changes
+            .iter()
+            .map(|change| match change.addr {
+                Some(addr) => format!("{:?} {} ({})", change.change_type, change.node_id, addr),
+                None => format!("{:?} {}", change.change_type, change.node_id),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let changes = if changes.is_empty() {
+            "LeaveJoint".to_owned()
+        } else {
+            changes
+        };
+
+        println!(
+            "Index: {}, Term: {}, Applied at: {}, Requested by: {}, Changes: [{}], Result: {}",
+            record.index,
+            record.term,
+            record.applied_at,
+            record.requested_by.as_deref().unwrap_or("-"),
+            changes,
+            record.error.as_deref().unwrap_or("Ok"),
+        );
+    }
+    Ok(())
+}
+
 pub async fn debug_entries(_addr: &str) -> Result<()> {
     todo!()
 }
@@ -1 +1,4 @@
+pub mod admin;
 pub mod debug;
+pub mod recover;
+pub mod storage;
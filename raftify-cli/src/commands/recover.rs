@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use raftify::{AbstractStableStorage, Error, Result};
+
+use super::debug::open_persisted_storage;
+
+/// Infers the node id from a log directory named `node-<id>`.
+fn node_id_from_path(path: &str) -> Option<u64> {
+    Path::new(path)
+        .file_name()?
+        .to_str()?
+        .strip_prefix("node-")?
+        .parse()
+        .ok()
+}
+
+pub fn recover_force_new_cluster<LogStorage: AbstractStableStorage>(
+    path: &str,
+    node_id: Option<u64>,
+    logger: slog::Logger,
+) -> Result<()> {
+    let node_id = node_id.or_else(|| node_id_from_path(path)).ok_or_else(|| {
+        Error::Rejected(format!(
+            "Failed to infer the node id from \"{}\". Pass it with --node-id.",
+            path
+        ))
+    })?;
+
+    println!("WARNING: Forcing a new cluster is UNSAFE.");
+    println!("The uncommitted entries are discarded, and the committed entries not replicated to this node are lost.");
+    println!("Never restart the other nodes with their previous logs afterwards.");
+    println!();
+
+    let mut storage = open_persisted_storage::<LogStorage>(path, logger)?;
+    let report = storage.force_new_cluster(node_id)?;
+
+    println!("---- Forced a new cluster ----");
+    println!("Voters: [{}]", node_id);
+    println!("Last index: {}", report.last_index);
+    println!(
+        "Discarded uncommitted entries: {}",
+        report.discarded_entries
+    );
+    println!(
+        "Replaced conf change entries: {}",
+        report.replaced_conf_changes
+    );
+    println!();
+    println!("Restart the node without initial_peers, then join the new nodes to it.");
+    Ok(())
+}
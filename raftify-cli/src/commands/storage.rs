@@ -0,0 +1,38 @@
+use raftify::{AbstractStableStorage, Result};
+
+use super::debug::open_persisted_storage;
+
+pub fn storage_scrub<LogStorage: AbstractStableStorage>(
+    path: &str,
+    repair: bool,
+    logger: slog::Logger,
+) -> Result<()> {
+    let mut storage = open_persisted_storage::<LogStorage>(path, logger)?;
+    let report = storage.scrub(repair)?;
+
+    println!("---- Storage scrub ----");
+    println!("Scanned entries: {}", report.entry_count);
+
+    if report.is_clean() {
+        println!("No damage found");
+        return Ok(());
+    }
+
+    println!("Found {} damaged record(s):", report.damages.len());
+    for damage in report.damages.iter() {
+        match damage.index {
+            Some(index) => println!("  Entry {}: {}", index, damage.reason),
+            None => println!("  Metadata: {}", damage.reason),
+        }
+    }
+
+    match report.truncated_from {
+        Some(index) => println!(
+            "Truncated the log from index {}. Restart the node to re-replicate the log from the leader.",
+            index
+        ),
+        None if repair => println!("No damaged log entry to truncate"),
+        None => println!("Run with --repair to truncate the log at the first damaged entry"),
+    }
+    Ok(())
+}
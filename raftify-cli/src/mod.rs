@@ -3,12 +3,23 @@ include!(concat!(env!("OUT_DIR"), "/built.rs"));
 mod commands;
 
 use clap::{Args, Parser, Subcommand};
-use commands::debug::{debug_entries, debug_node, debug_persisted, debug_persisted_all};
+use commands::{
+    admin::{
+        admin_purge_tombstones, admin_remove_peer, admin_set_peer_labels, admin_set_ticks,
+        admin_update_runtime_config,
+    },
+    debug::{
+        debug_entries, debug_membership, debug_node, debug_persisted, debug_persisted_all,
+        debug_persisted_json, debug_stats,
+    },
+    recover::recover_force_new_cluster,
+    storage::storage_scrub,
+};
 use std::fmt::Debug;
 
 use raftify::{
     raft::{default_logger, formatter::set_custom_formatter},
-    AbstractLogEntry, AbstractStateMachine, CustomFormatter, Result, StableStorage,
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, CustomFormatter, Result,
 };
 
 #[derive(Parser)]
@@ -26,6 +37,23 @@ enum Commands {
     /// Debug tools
     #[command(subcommand)]
     Debug(DebugSubcommands),
+    /// Operator actions
+    #[command(subcommand)]
+    Admin(AdminSubcommands),
+    /// Storage maintenance tools
+    #[command(subcommand)]
+    Storage(StorageSubcommands),
+    /// Recover a node which lost the quorum permanently (UNSAFE)
+    Recover {
+        /// The log directory path of the surviving node
+        path: String,
+        /// Rewrite the membership to a single-voter configuration of the surviving node (UNSAFE)
+        #[arg(long, required = true)]
+        force_new_cluster: bool,
+        /// The id of the surviving node. Inferred from a log directory named "node-<id>" if omitted
+        #[arg(long)]
+        node_id: Option<u64>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -34,12 +62,20 @@ enum DebugSubcommands {
     Persisted {
         /// The log directory path
         path: String,
+        /// Print the decoded entries and metadata as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// List persisted log entries and metadata for all local nodes
     PersistedAll {
         /// The log directory path
         path: String,
     },
+    /// Show the size and bounds of the persisted log
+    Stats {
+        /// The log directory path
+        path: String,
+    },
     /// List all log entries
     Entries {
         /// The address of the RaftNode
@@ -50,6 +86,66 @@ enum DebugSubcommands {
         /// The address of the RaftNode
         address: String,
     },
+    /// List the membership changes applied on the RaftNode
+    Membership {
+        /// The address of the RaftNode
+        address: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminSubcommands {
+    /// Remove a node from the cluster
+    RemovePeer {
+        /// The address of the RaftNode. Must be the leader with --force
+        address: String,
+        /// The id of the node to remove
+        node_id: u64,
+        /// Remove the node even if it's unreachable, as long as the other voters form a quorum
+        #[arg(long)]
+        force: bool,
+    },
+    /// Let the ids of removed nodes be handed out again by purging their tombstones
+    PurgeTombstones {
+        /// The address of the RaftNode. The tombstones are kept on every node
+        address: String,
+        /// The ids of the removed nodes. Purges all tombstones if omitted
+        node_ids: Vec<u64>,
+    },
+    /// Change settings on every node without restarting them, e.g. '{"snapshot_interval": 30.0}'
+    UpdateConfig {
+        /// The address of the leader
+        address: String,
+        /// JSON object of the settings to change. A null value disables the setting
+        config_json: String,
+    },
+    /// Replace the labels of a node on every node, e.g. 'zone=us-east-1a rack=r1'
+    SetLabels {
+        /// The address of the leader
+        address: String,
+        node_id: u64,
+        /// Labels given as <key>=<value>. No label clears the labels of the node
+        labels: Vec<String>,
+    },
+    /// Change the election and heartbeat ticks of a node, e.g. during a network maintenance
+    SetTicks {
+        /// The address of the RaftNode. Only this node is changed
+        address: String,
+        election_tick: u64,
+        heartbeat_tick: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum StorageSubcommands {
+    /// Validate the checksums and index continuity of the persisted log
+    Scrub {
+        /// The log directory path
+        path: String,
+        /// Truncate the log at the first damaged entry
+        #[arg(long)]
+        repair: bool,
+    },
 }
 
 #[derive(Args)]
@@ -62,7 +158,7 @@ struct Dump {
 
 pub async fn cli_handler<
     LogEntry: AbstractLogEntry + Debug + Send + 'static,
-    LogStorage: StableStorage + Send + Sync + Clone + 'static,
+    LogStorage: AbstractStableStorage + Send + Sync + Clone + 'static,
     FSM: AbstractStateMachine + Debug + Clone + Send + Sync + 'static,
 >(
     args: Option<Vec<String>>,
@@ -76,19 +172,73 @@ pub async fn cli_handler<
 
     match app.command {
         Commands::Debug(x) => match x {
-            DebugSubcommands::Persisted { path } => {
-                debug_persisted::<LogStorage>(path.as_str(), logger.clone())?;
+            DebugSubcommands::Persisted { path, json } => {
+                if json {
+                    debug_persisted_json::<LogEntry, LogStorage>(path.as_str(), logger.clone())?;
+                } else {
+                    debug_persisted::<LogStorage>(path.as_str(), logger.clone())?;
+                }
             }
             DebugSubcommands::PersistedAll { path } => {
                 debug_persisted_all::<LogStorage>(path.as_str(), logger.clone())?;
             }
+            DebugSubcommands::Stats { path } => {
+                debug_stats::<LogStorage>(path.as_str(), logger.clone())?;
+            }
             DebugSubcommands::Entries { address } => {
                 debug_entries(address.as_str()).await?;
             }
             DebugSubcommands::Node { address } => {
                 debug_node(address.as_str()).await?;
             }
+            DebugSubcommands::Membership { address } => {
+                debug_membership(address.as_str()).await?;
+            }
+        },
+        Commands::Admin(x) => match x {
+            AdminSubcommands::RemovePeer {
+                address,
+                node_id,
+                force,
+            } => {
+                admin_remove_peer(address.as_str(), node_id, force).await?;
+            }
+            AdminSubcommands::PurgeTombstones { address, node_ids } => {
+                admin_purge_tombstones(address.as_str(), node_ids).await?;
+            }
+            AdminSubcommands::UpdateConfig {
+                address,
+                config_json,
+            } => {
+                admin_update_runtime_config(address.as_str(), config_json).await?;
+            }
+            AdminSubcommands::SetLabels {
+                address,
+                node_id,
+                labels,
+            } => {
+                admin_set_peer_labels(address.as_str(), node_id, labels).await?;
+            }
+            AdminSubcommands::SetTicks {
+                address,
+                election_tick,
+                heartbeat_tick,
+            } => {
+                admin_set_ticks(address.as_str(), election_tick, heartbeat_tick).await?;
+            }
+        },
+        Commands::Storage(x) => match x {
+            StorageSubcommands::Scrub { path, repair } => {
+                storage_scrub::<LogStorage>(path.as_str(), repair, logger.clone())?;
+            }
         },
+        Commands::Recover {
+            path,
+            force_new_cluster: _,
+            node_id,
+        } => {
+            recover_force_new_cluster::<LogStorage>(path.as_str(), node_id, logger.clone())?;
+        }
     }
 
     Ok(())
@@ -3,7 +3,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build_client(true)
         .build_server(true)
         .extern_path(".eraftpb", "::raft::eraftpb")
-        .compile(&["proto/raft_service.proto"], &["proto/"])?;
+        // Shares the proposal payload between the handlers instead of copying it.
+        .bytes([
+            ".raft_service.ProposeArgs.msg",
+            ".raft_service.ProposeArgs.batch",
+        ])
+        .compile(
+            &["proto/raft_service.proto", "proto/raft_admin_service.proto"],
+            &["proto/"],
+        )?;
 
     built::write_built_file().expect("Failed to acquire build-time information");
     Ok(())
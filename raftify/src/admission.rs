@@ -0,0 +1,137 @@
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc, time::Instant};
+use tonic::Request;
+
+/// Metadata key identifying the client of a proposal, see `AdmissionConfig::max_proposals_per_sec_per_client`.
+/// The clients are identified by the IP address they connect from when it's missing.
+pub const CLIENT_ID_HEADER: &str = "x-raftify-client-id";
+
+// Clients tracked at most by `ClientRateLimiter` before the idle ones are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Token bucket admitting `rate` proposals per second, with bursts of up to a second of proposals.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate.max(1.0),
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub(crate) fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    fn burst(&self) -> f64 {
+        self.rate.max(1.0)
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst());
+        self.last_refill = now;
+    }
+
+    /// Takes the tokens of `count` proposals if there are enough of them.
+    /// Batches larger than a burst take a whole burst.
+    pub(crate) fn try_acquire(&mut self, count: usize) -> bool {
+        self.refill();
+        let needed = (count as f64).min(self.burst());
+        if self.tokens < needed {
+            return false;
+        }
+        self.tokens -= needed;
+        true
+    }
+
+    fn is_idle(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.burst()
+    }
+}
+
+/// A `RateLimiter` per client of the `RaftServer`.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientRateLimiter {
+    rate: f64,
+    clients: Arc<Mutex<HashMap<String, RateLimiter>>>,
+}
+
+impl ClientRateLimiter {
+    pub(crate) fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            clients: Arc::default(),
+        }
+    }
+
+    pub(crate) fn try_acquire(&self, client: &str) -> bool {
+        let mut clients = self.clients.lock();
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(client) {
+            clients.retain(|_, limiter| !limiter.is_idle());
+        }
+        clients
+            .entry(client.to_owned())
+            .or_insert_with(|| RateLimiter::new(self.rate))
+            .try_acquire(1)
+    }
+}
+
+/// Returns the identity of the client which sent `request`.
+pub(crate) fn client_id_of<T>(request: &Request<T>) -> String {
+    if let Some(client_id) = request
+        .metadata()
+        .get(CLIENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return client_id.to_owned();
+    }
+    request
+        .remote_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_rate_limiters() {
+        let mut limiter = RateLimiter::new(2.0);
+        assert!(limiter.try_acquire(1));
+        assert!(limiter.try_acquire(1));
+        assert!(!limiter.try_acquire(1));
+
+        let limiter = ClientRateLimiter::new(1.0);
+        assert!(limiter.try_acquire("a"));
+        assert!(!limiter.try_acquire("a"));
+        assert!(limiter.try_acquire("b"));
+
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(CLIENT_ID_HEADER, "client-1".parse().unwrap());
+        assert_eq!(client_id_of(&request), "client-1");
+    }
+
+    #[test]
+    fn test_overload_status_round_trip() {
+        let status = Error::RateLimited("too fast".to_owned())
+            .overload_status()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(matches!(Error::from(status), Error::RateLimited(cause) if cause == "too fast"));
+        assert!(Error::Timeout.overload_status().is_none());
+    }
+}
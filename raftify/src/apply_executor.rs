@@ -0,0 +1,84 @@
+use std::{fmt, future::Future, sync::Arc};
+use tokio::{runtime::Handle, sync::oneshot};
+
+use crate::{Error, Result};
+
+/// Runs a blocking job, e.g. on a thread pool of the application.
+pub trait BlockingExecutor: Send + Sync {
+    fn execute(&self, job: Box<dyn FnOnce() + Send>);
+}
+
+/// Where the `apply`, `snapshot` and `restore` of the state machine run.
+/// State machines doing synchronous disk or CPU heavy work should run them off the async runtime.
+#[derive(Clone, Default)]
+pub enum ApplyExecutor {
+    /// On the async runtime, like the rest of the node.
+    #[default]
+    Async,
+    /// On the blocking threads of the tokio runtime.
+    Blocking,
+    /// On the given executor.
+    Custom(Arc<dyn BlockingExecutor>),
+}
+
+impl ApplyExecutor {
+    pub(crate) async fn run<F>(&self, future: F) -> Result<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self {
+            ApplyExecutor::Async => Ok(future.await),
+            ApplyExecutor::Blocking => {
+                let handle = Handle::current();
+                tokio::task::spawn_blocking(move || handle.block_on(future))
+                    .await
+                    .map_err(|e| Error::Other(Box::new(e)))
+            }
+            ApplyExecutor::Custom(executor) => {
+                let handle = Handle::current();
+                let (tx, rx) = oneshot::channel();
+                executor.execute(Box::new(move || {
+                    let _ = tx.send(handle.block_on(future));
+                }));
+                Ok(rx.await?)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ApplyExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyExecutor::Async => write!(f, "Async"),
+            ApplyExecutor::Blocking => write!(f, "Blocking"),
+            ApplyExecutor::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ThreadExecutor;
+
+    impl BlockingExecutor for ThreadExecutor {
+        fn execute(&self, job: Box<dyn FnOnce() + Send>) {
+            std::thread::spawn(job);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_on_executors() {
+        let executors = [
+            ApplyExecutor::Async,
+            ApplyExecutor::Blocking,
+            ApplyExecutor::Custom(Arc::new(ThreadExecutor)),
+        ];
+        for executor in executors {
+            let result = executor.run(async { 1 + 1 }).await.unwrap();
+            assert_eq!(result, 2, "{:?}", executor);
+        }
+    }
+}
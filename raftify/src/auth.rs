@@ -0,0 +1,158 @@
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    service::Interceptor,
+    Request, Status,
+};
+
+use crate::{raft_group::attach_group_id, telemetry, ClusterUuid};
+
+const AUTHORIZATION_HEADER: &str = "authorization";
+
+/// Token based authentication of the raft RPCs.
+///
+/// Every outgoing request carries `token`, and incoming requests are rejected
+/// unless they carry `token` or one of `accepted_tokens`.
+/// Use the same `token` on every node for a shared secret, or give each node its own token
+/// and list the tokens of the other nodes in `accepted_tokens`.
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    pub token: String,
+    pub accepted_tokens: Vec<String>,
+}
+
+impl AuthConfig {
+    fn accepts(&self, token: &[u8]) -> bool {
+        std::iter::once(&self.token)
+            .chain(self.accepted_tokens.iter())
+            .any(|accepted| constant_time_eq(accepted.as_bytes(), token))
+    }
+}
+
+// Keep the tokens out of the logs.
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("accepted_tokens", &self.accepted_tokens.len())
+            .finish()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn bearer(token: &str) -> String {
+    format!("Bearer {}", token)
+}
+
+/// Authorization hook of `RaftAdminService`, called before every admin operation.
+///
+/// `operation` is the name of the RPC, e.g. `"transfer_leader"`.
+/// `AuthConfig` implements this by accepting the requests carrying one of its tokens.
+pub trait AdminAuthorizer: Send + Sync {
+    fn authorize(&self, operation: &str, metadata: &MetadataMap) -> Result<(), Status>;
+}
+
+impl AdminAuthorizer for AuthConfig {
+    fn authorize(&self, _operation: &str, metadata: &MetadataMap) -> Result<(), Status> {
+        match bearer_token(metadata) {
+            Some(token) if self.accepts(token) => Ok(()),
+            _ => Err(Status::unauthenticated("Invalid or missing auth token")),
+        }
+    }
+}
+
+fn bearer_token(metadata: &MetadataMap) -> Option<&[u8]> {
+    metadata
+        .get(AUTHORIZATION_HEADER)
+        .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "))
+}
+
+/// Attaches the auth token, the cluster UUID, the raft group and the trace context to the requests sent to other peers.
+#[derive(Clone, Default)]
+pub struct AuthInterceptor {
+    token: Option<String>,
+    cluster_uuid: ClusterUuid,
+    group_id: Option<u64>,
+}
+
+impl AuthInterceptor {
+    pub fn new(token: Option<String>) -> Self {
+        Self {
+            token,
+            cluster_uuid: ClusterUuid::default(),
+            group_id: None,
+        }
+    }
+
+    pub fn with_cluster_uuid(mut self, cluster_uuid: ClusterUuid) -> Self {
+        self.cluster_uuid = cluster_uuid;
+        self
+    }
+
+    pub fn with_group_id(mut self, group_id: Option<u64>) -> Self {
+        self.group_id = group_id;
+        self
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(token) = &self.token {
+            let value = MetadataValue::try_from(bearer(token))
+                .map_err(|_| Status::invalid_argument("Invalid auth token"))?;
+            request.metadata_mut().insert(AUTHORIZATION_HEADER, value);
+        }
+        self.cluster_uuid.attach(request.metadata_mut())?;
+        if let Some(group_id) = self.group_id {
+            attach_group_id(request.metadata_mut(), group_id);
+        }
+        telemetry::inject_metadata(request.metadata_mut());
+        Ok(request)
+    }
+}
+
+/// Rejects the incoming requests without an accepted token. Accepts everything if `auth` is `None`.
+pub(crate) fn check_auth(
+    auth: Option<AuthConfig>,
+) -> impl Interceptor + Clone + Send + Sync + 'static {
+    move |request: Request<()>| -> Result<Request<()>, Status> {
+        match &auth {
+            Some(auth) => auth
+                .authorize("raft_service", request.metadata())
+                .map(|_| request),
+            None => Ok(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_token(token: &str) -> Request<()> {
+        AuthInterceptor::new(Some(token.to_owned()))
+            .call(Request::new(()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check_auth_accepts_known_tokens() {
+        let auth = AuthConfig {
+            token: "node-1".to_owned(),
+            accepted_tokens: vec!["node-2".to_owned()],
+        };
+        let mut interceptor = check_auth(Some(auth));
+
+        assert!(interceptor.call(request_with_token("node-1")).is_ok());
+        assert!(interceptor.call(request_with_token("node-2")).is_ok());
+        assert!(interceptor.call(request_with_token("node-3")).is_err());
+        assert!(interceptor.call(Request::new(())).is_err());
+    }
+
+    #[test]
+    fn test_check_auth_disabled() {
+        let mut interceptor = check_auth(None);
+        assert!(interceptor.call(Request::new(())).is_ok());
+    }
+}
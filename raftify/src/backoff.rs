@@ -0,0 +1,254 @@
+use rand::Rng;
+use std::{future::Future, time::Duration};
+use tokio::time::sleep;
+
+use crate::{Error, RejoinConfig, Result, RetryPolicy, RetryableError};
+
+/// Exponential backoff with jitter between the attempts of a request,
+/// e.g. of a node to join the cluster.
+pub(crate) struct Backoff {
+    max_backoff: f32,
+    multiplier: f32,
+    max_attempts: Option<u32>,
+    failures: u32,
+    current: f32,
+}
+
+impl From<&RejoinConfig> for Backoff {
+    fn from(config: &RejoinConfig) -> Self {
+        Self {
+            max_backoff: config.max_backoff,
+            multiplier: config.multiplier,
+            max_attempts: config.max_attempts,
+            failures: 0,
+            current: config.initial_backoff,
+        }
+    }
+}
+
+impl From<&RetryPolicy> for Backoff {
+    fn from(policy: &RetryPolicy) -> Self {
+        Self {
+            max_backoff: policy.max_backoff,
+            multiplier: policy.multiplier,
+            max_attempts: Some(policy.max_attempts),
+            failures: 0,
+            current: policy.initial_backoff,
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns the delay before the next attempt once one failed, or `None` after `max_attempts` failures.
+    /// The delay is drawn between half and all of the current backoff,
+    /// so that the nodes restarted together don't retry in lockstep.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        self.failures += 1;
+        if matches!(self.max_attempts, Some(max_attempts) if self.failures >= max_attempts) {
+            return None;
+        }
+        let backoff = self.current;
+        self.current = (self.current * self.multiplier).min(self.max_backoff);
+        Some(Duration::from_secs_f32(
+            backoff * rand::thread_rng().gen_range(0.5..=1.0),
+        ))
+    }
+}
+
+impl RetryableError {
+    fn matches(&self, error: &Error) -> bool {
+        match self {
+            Self::Connect => matches!(error, Error::Grpc(_) | Error::ConnectionError(_)),
+            Self::Unavailable => {
+                matches!(error, Error::RemoteCall(status) if status.code() == tonic::Code::Unavailable)
+            }
+            Self::Timeout => match error {
+                Error::Timeout => true,
+                Error::RemoteCall(status) => status.code() == tonic::Code::DeadlineExceeded,
+                _ => false,
+            },
+            Self::Overloaded => matches!(
+                error,
+                Error::Busy(_) | Error::TooManyInFlight(_) | Error::RateLimited(_)
+            ),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        self.retryable.iter().any(|class| class.matches(error))
+    }
+
+    /// Calls `attempt` until it succeeds, fails with an error which isn't retryable,
+    /// or `max_attempts` attempts failed, sleeping for the backoff between the attempts.
+    ///
+    /// ```ignore
+    /// let response = options
+    ///     .retry
+    ///     .retry(|| async {
+    ///         let mut client = create_client_with_options(&leader_addr, &options).await?;
+    ///         Ok(client.propose(request.clone()).await?)
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn retry<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = Backoff::from(self);
+        loop {
+            match attempt().await {
+                Err(error) if self.is_retryable(&error) => match backoff.next_delay() {
+                    Some(delay) => sleep(delay).await,
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Like `retry`, for a proposal forwarded to the leader. A proposal without a session is not retried
+    /// on `Timeout`, since the leader may have committed it before the response was lost,
+    /// and it would be applied twice.
+    pub async fn retry_proposal<T, F, Fut>(&self, has_session: bool, attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if has_session {
+            return self.retry(attempt).await;
+        }
+        let policy = RetryPolicy {
+            retryable: self
+                .retryable
+                .iter()
+                .copied()
+                .filter(|class| *class != RetryableError::Timeout)
+                .collect(),
+            ..self.clone()
+        };
+        policy.retry(attempt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::from(&RejoinConfig {
+            initial_backoff: 1.0,
+            max_backoff: 3.0,
+            multiplier: 2.0,
+            max_attempts: Some(5),
+        });
+
+        for max in [1.0, 2.0, 3.0, 3.0] {
+            let delay = backoff.next_delay().unwrap().as_secs_f32();
+            assert!(
+                max / 2.0 <= delay && delay <= max,
+                "{} not in [{}, {}]",
+                delay,
+                max / 2.0,
+                max
+            );
+        }
+        assert_eq!(backoff.next_delay(), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: 0.001,
+            max_backoff: 0.001,
+            ..Default::default()
+        };
+
+        let mut attempts = 0;
+        let result = policy
+            .retry(|| {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    match attempt {
+                        1 => Err(Error::ConnectionError("refused".to_owned())),
+                        2 => Err(Error::RemoteCall(tonic::Status::unavailable(
+                            "shutting down",
+                        ))),
+                        _ => Ok(attempt),
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        // Gives up after max_attempts.
+        let mut attempts = 0;
+        let result: Result<()> = policy
+            .retry(|| {
+                attempts += 1;
+                async { Err(Error::ConnectionError("refused".to_owned())) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::ConnectionError(_))));
+        assert_eq!(attempts, 3);
+
+        // Not retried on the errors it doesn't list.
+        let mut attempts = 0;
+        let result: Result<()> = policy
+            .retry(|| {
+                attempts += 1;
+                async { Err(Error::Busy("apply lag".to_owned())) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Busy(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_proposal_without_session() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: 0.001,
+            max_backoff: 0.001,
+            retryable: vec![RetryableError::Connect, RetryableError::Timeout],
+            ..Default::default()
+        };
+
+        // The proposal may have been committed before it timed out.
+        let mut attempts = 0;
+        let result: Result<()> = policy
+            .retry_proposal(false, || {
+                attempts += 1;
+                async { Err(Error::Timeout) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result: Result<()> = policy
+            .retry_proposal(true, || {
+                attempts += 1;
+                async { Err(Error::Timeout) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_eq!(attempts, 3);
+
+        // Not reaching the leader is retried either way.
+        let mut attempts = 0;
+        let result: Result<()> = policy
+            .retry_proposal(false, || {
+                attempts += 1;
+                async { Err(Error::ConnectionError("refused".to_owned())) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::ConnectionError(_))));
+        assert_eq!(attempts, 3);
+    }
+}
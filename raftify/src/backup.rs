@@ -0,0 +1,260 @@
+use prost::Message as PMessage;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::{
+    encryption::{decrypt, encrypt},
+    raft::eraftpb::{ConfState, Entry, HardState, Snapshot},
+    session::split_sessions,
+    AbstractStableStorage, AbstractStateMachine, Config, Error, Peers, Result,
+};
+
+const BACKUP_ARCHIVE_VERSION: u32 = 1;
+
+/// Writes `bytes` followed by their CRC32 checksum.
+fn write_checksummed(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut bytes = bytes.to_vec();
+    bytes.extend_from_slice(&crc32fast::hash(&bytes).to_le_bytes());
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Reads the bytes written by `write_checksummed`, verifying their checksum.
+fn read_checksummed(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = fs::read(path)?;
+    if bytes.len() < 4 {
+        return Err(Error::DecodingError(format!(
+            "Archive is truncated: {}",
+            path.display()
+        )));
+    }
+
+    let checksum = bytes.split_off(bytes.len() - 4);
+    if crc32fast::hash(&bytes) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(Error::DecodingError(format!(
+            "Archive checksum mismatch: {}",
+            path.display()
+        )));
+    }
+    Ok(bytes)
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if version != BACKUP_ARCHIVE_VERSION {
+        return Err(Error::DecodingError(format!(
+            "Unsupported archive version {}",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// Portable archive of the state persisted on a node, written by `Raft::backup`.
+/// The raft-rs types are kept protobuf encoded since they don't implement serde.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BackupArchive {
+    version: u32,
+    hard_state: Vec<u8>,
+    conf_state: Vec<u8>,
+    snapshot: Vec<u8>,
+    // The entries following the snapshot
+    entries: Vec<Vec<u8>>,
+    peers: Peers,
+}
+
+impl BackupArchive {
+    pub fn capture<T: AbstractStableStorage>(storage: &T, peers: Peers) -> Result<Self> {
+        // The hard state is read before the entries so that its commit index never goes past them,
+        // and the snapshot is read last so that no entry is compacted away in between.
+        let hard_state = storage.hard_state()?;
+        let conf_state = storage.conf_state()?;
+        let entries = storage.all_entries()?;
+        let snapshot = storage.snapshot(0, 0)?;
+        let snapshot_index = snapshot.get_metadata().index;
+
+        Ok(Self {
+            version: BACKUP_ARCHIVE_VERSION,
+            hard_state: hard_state.encode_to_vec(),
+            conf_state: conf_state.encode_to_vec(),
+            snapshot: snapshot.encode_to_vec(),
+            entries: entries
+                .iter()
+                .filter(|entry| entry.index > snapshot_index)
+                .map(|entry| entry.encode_to_vec())
+                .collect(),
+            peers,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_checksummed(path, &bincode::serialize(self)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let archive: Self = bincode::deserialize(&read_checksummed(path)?)?;
+        check_version(archive.version)?;
+        Ok(archive)
+    }
+
+    /// Restores the archive into the empty `storage` and `fsm`, and returns the archived peers.
+    pub async fn restore<T: AbstractStableStorage, FSM: AbstractStateMachine>(
+        self,
+        storage: &mut T,
+        fsm: &mut FSM,
+        config: &Config,
+    ) -> Result<Peers> {
+        if storage.last_index()? != 0 {
+            return Err(Error::Rejected(
+                "A backup can only be restored into an empty storage".to_owned(),
+            ));
+        }
+
+        let snapshot = Snapshot::decode(self.snapshot.as_slice())?;
+        let mut hard_state = HardState::decode(self.hard_state.as_slice())?;
+        let conf_state = ConfState::decode(self.conf_state.as_slice())?;
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| Entry::decode(entry.as_slice()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let snapshot_index = snapshot.get_metadata().index;
+        let last_index = entries.last().map_or(snapshot_index, |entry| entry.index);
+
+        if snapshot_index > 0 {
+            let data = match config.encryption_key_provider.as_deref() {
+                Some(provider) => decrypt(provider, snapshot.get_data())?,
+                None => snapshot.get_data().to_vec(),
+            };
+            // The sessions stay in the stored snapshot, where the node loads them from.
+            let (data, _) = split_sessions(data)?;
+            fsm.restore(data).await?;
+        }
+
+        storage.apply_snapshot(snapshot)?;
+        storage.append(&entries)?;
+
+        hard_state.commit = hard_state.commit.clamp(snapshot_index, last_index);
+        storage.set_hard_state(&hard_state)?;
+        storage.set_conf_state(&conf_state)?;
+
+        Ok(self.peers)
+    }
+}
+
+/// State machine snapshot written by `Raft::export_snapshot`, independent of the cluster membership.
+/// Its data is kept unencrypted, so that it can be imported into a cluster using another key.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SnapshotExport {
+    version: u32,
+    index: u64,
+    term: u64,
+    data: Vec<u8>,
+}
+
+impl SnapshotExport {
+    pub fn new(index: u64, term: u64, data: Vec<u8>) -> Self {
+        Self {
+            version: BACKUP_ARCHIVE_VERSION,
+            index,
+            term,
+            data,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_checksummed(path, &bincode::serialize(self)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let export: Self = bincode::deserialize(&read_checksummed(path)?)?;
+        check_version(export.version)?;
+        Ok(export)
+    }
+
+    /// Imports the snapshot into the empty `storage` and `fsm` of a node of a new cluster,
+    /// replacing the exported membership with the voters and learners of `peers`.
+    /// Every node of the new cluster must import the same snapshot, so that they start from the same index.
+    pub async fn import<T: AbstractStableStorage, FSM: AbstractStateMachine>(
+        self,
+        storage: &mut T,
+        fsm: &mut FSM,
+        peers: &Peers,
+        config: &Config,
+    ) -> Result<()> {
+        if storage.last_index()? != 0 {
+            return Err(Error::Rejected(
+                "A snapshot can only be imported into an empty storage".to_owned(),
+            ));
+        }
+
+        let mut conf_state = ConfState::default();
+        conf_state.set_voters(peers.voters());
+        conf_state.set_learners(peers.learners());
+
+        let data = match config.encryption_key_provider.as_deref() {
+            Some(provider) => encrypt(provider, &self.data)?,
+            None => self.data.clone(),
+        };
+        fsm.restore(self.data).await?;
+
+        let mut snapshot = Snapshot::default();
+        snapshot.set_data(data);
+        let metadata = snapshot.mut_metadata();
+        metadata.index = self.index;
+        metadata.term = self.term;
+        metadata.set_conf_state(conf_state.clone());
+
+        storage.apply_snapshot(snapshot)?;
+        storage.set_conf_state(&conf_state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_corrupted_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.bin");
+
+        let archive = BackupArchive {
+            version: BACKUP_ARCHIVE_VERSION,
+            hard_state: vec![],
+            conf_state: vec![],
+            snapshot: vec![],
+            entries: vec![vec![1, 2, 3]],
+            peers: Peers::new(1, "127.0.0.1:60061"),
+        };
+        archive.write(&path).unwrap();
+
+        let restored = BackupArchive::read(&path).unwrap();
+        assert_eq!(restored.entries, archive.entries);
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[0] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        assert!(BackupArchive::read(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_export_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.bin");
+
+        SnapshotExport::new(10, 2, b"data".to_vec())
+            .write(&path)
+            .unwrap();
+
+        let export = SnapshotExport::read(&path).unwrap();
+        assert_eq!(export.index, 10);
+        assert_eq!(export.term, 2);
+        assert_eq!(export.data, b"data");
+    }
+}
@@ -0,0 +1,111 @@
+use parking_lot::RwLock;
+use std::sync::Arc;
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    service::Interceptor,
+    Request, Status,
+};
+
+const CLUSTER_UUID_HEADER: &str = "x-raftify-cluster-uuid";
+
+/// UUID of the cluster a node belongs to.
+///
+/// The UUID is generated when the cluster is bootstrapped and persisted in the storage.
+/// Nodes which don't know it yet adopt it from the first raft message carrying it.
+/// Every raft RPC carries it, and the RPCs carrying the UUID of another cluster are rejected,
+/// so that a misconfigured peer address can't splice two clusters together.
+#[derive(Clone, Debug, Default)]
+pub struct ClusterUuid(Arc<RwLock<Option<String>>>);
+
+impl ClusterUuid {
+    pub(crate) fn generate() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    pub fn get(&self) -> Option<String> {
+        self.0.read().clone()
+    }
+
+    pub(crate) fn set(&self, cluster_uuid: String) {
+        *self.0.write() = Some(cluster_uuid);
+    }
+
+    pub(crate) fn attach(&self, metadata: &mut MetadataMap) -> Result<(), Status> {
+        if let Some(cluster_uuid) = self.0.read().as_deref() {
+            let value = MetadataValue::try_from(cluster_uuid)
+                .map_err(|_| Status::invalid_argument("Invalid cluster UUID"))?;
+            metadata.insert(CLUSTER_UUID_HEADER, value);
+        }
+        Ok(())
+    }
+
+    /// Returns an error if `cluster_uuid` is not the UUID of this cluster.
+    /// Accepts anything until the UUID of this cluster is known.
+    pub(crate) fn check(&self, cluster_uuid: &str) -> Result<(), Status> {
+        match self.0.read().as_deref() {
+            Some(expected) if expected != cluster_uuid => {
+                Err(Status::failed_precondition(format!(
+                    "Cluster UUID mismatch, expected {} but got {}",
+                    expected, cluster_uuid
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the cluster UUID attached to the request, if any.
+pub(crate) fn cluster_uuid_of(metadata: &MetadataMap) -> Option<String> {
+    metadata
+        .get(CLUSTER_UUID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Rejects the incoming requests carrying the UUID of another cluster.
+/// The requests without a cluster UUID, e.g. the ones of the clients, are accepted.
+pub(crate) fn check_cluster_uuid(
+    cluster_uuid: ClusterUuid,
+) -> impl Interceptor + Clone + Send + Sync + 'static {
+    move |request: Request<()>| -> Result<Request<()>, Status> {
+        match cluster_uuid_of(request.metadata()) {
+            Some(received) => cluster_uuid.check(&received).map(|_| request),
+            None => Ok(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_from(cluster_uuid: &ClusterUuid) -> Request<()> {
+        let mut request = Request::new(());
+        cluster_uuid.attach(request.metadata_mut()).unwrap();
+        request
+    }
+
+    #[test]
+    fn test_reject_other_cluster() {
+        let local = ClusterUuid::default();
+        local.set("cluster-a".to_owned());
+        let other = ClusterUuid::default();
+        other.set("cluster-b".to_owned());
+
+        let mut interceptor = check_cluster_uuid(local.clone());
+        assert!(interceptor.call(request_from(&local)).is_ok());
+        assert!(interceptor.call(request_from(&other)).is_err());
+        assert!(interceptor.call(Request::new(())).is_ok());
+    }
+
+    #[test]
+    fn test_accept_until_known() {
+        let local = ClusterUuid::default();
+        let leader = ClusterUuid::default();
+        leader.set(ClusterUuid::generate());
+
+        let request = request_from(&leader);
+        assert_eq!(cluster_uuid_of(request.metadata()), leader.get());
+        assert!(check_cluster_uuid(local).call(request).is_ok());
+    }
+}
@@ -1,6 +1,307 @@
-use std::fmt;
+use serde::Deserialize;
+use std::{fmt, sync::Arc, time::Duration};
+use tokio::runtime::Handle;
+use tonic::transport::{Endpoint, Server};
 
-use crate::{error::Error, raft::Config as RaftConfig, InitialRole, Peers, Result};
+use crate::{
+    error::Error,
+    raft::{Config as RaftConfig, ReadOnlyOption},
+    AdminAuthorizer, ApplyExecutor, AuthConfig, ChannelPool, ClientOptions, InitialRole,
+    KeyProvider, LeaderPlacementPolicy, Peers, Result, SnapshotStore, TlsConfig,
+};
+
+/// Bounds the compacted log segments kept under `Config::compacted_log_dir`.
+/// Segments are kept forever when neither `max_age` nor `max_total_bytes` is set.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompactedLogRetention {
+    /// Segments older than this many seconds are swept.
+    pub max_age: Option<f32>,
+    /// The oldest segments are swept while all segments take more bytes than this.
+    pub max_total_bytes: Option<u64>,
+    /// Swept segments are moved to this directory instead of being deleted.
+    pub archive_dir: Option<String>,
+    /// Seconds between the sweeps.
+    pub sweep_interval: f32,
+}
+
+impl Default for CompactedLogRetention {
+    fn default() -> Self {
+        Self {
+            max_age: None,
+            max_total_bytes: None,
+            archive_dir: None,
+            sweep_interval: 60.0,
+        }
+    }
+}
+
+impl CompactedLogRetention {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.max_total_bytes.is_some()
+    }
+}
+
+/// Policy of the automatic removal of the voters which have been unreachable for too long,
+/// e.g. the nodes of an autoscaled group which were terminated without leaving the cluster.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeadVoterRemoval {
+    /// Seconds a voter must stay unreachable before the leader proposes its removal.
+    pub unreachable_for: f32,
+    /// The cluster never shrinks below this many voters through the automatic removal.
+    pub min_voters: usize,
+}
+
+impl Default for DeadVoterRemoval {
+    fn default() -> Self {
+        Self {
+            unreachable_for: 600.0,
+            min_voters: 3,
+        }
+    }
+}
+
+/// Configuration of the SWIM-style gossip layer, see `Gossip`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GossipConfig {
+    /// Addresses of the nodes contacted until some other node is discovered.
+    pub seeds: Vec<String>,
+    /// Address the other nodes reach this node at. Defaults to the raft address.
+    pub advertise_addr: Option<String>,
+    /// Seconds between the probes of the other nodes.
+    pub interval: f32,
+    /// Seconds a probe waits for the answer before the node is probed indirectly.
+    pub probe_timeout: f32,
+    /// Number of nodes asked to probe a node which didn't answer a probe.
+    pub indirect_probes: usize,
+    /// Seconds a suspected node has to refute the suspicion before it is considered dead.
+    pub suspicion_timeout: f32,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            seeds: vec![],
+            advertise_addr: None,
+            interval: 1.0,
+            probe_timeout: 0.5,
+            indirect_probes: 3,
+            suspicion_timeout: 5.0,
+        }
+    }
+}
+
+/// How LMDB syncs the commits of `HeedStorage` to the disk.
+///
+/// Anything but `Full` trades the durability for the latency: a node crashing with unsynced commits
+/// may forget its vote or the entries it acknowledged, which can break the safety of raft.
+/// Only use them if the nodes don't fail together, e.g. on separate power supplies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LmdbSyncMode {
+    /// Syncs the data and the metadata on each commit.
+    #[default]
+    Full,
+    /// Skips the sync of the metadata (`MDB_NOMETASYNC`), so a crash may lose the last commit,
+    /// but never corrupts the database.
+    NoMetaSync,
+    /// Leaves the sync to the OS (`MDB_NOSYNC`), so a crash may lose the commits since the last one.
+    NoSync,
+}
+
+/// What the node does once the disk of `Config::log_dir` is used beyond `DiskUsageConfig::hard_threshold`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskFullAction {
+    /// Rejects the proposals with `Error::StorageFull` until the usage goes back below the threshold.
+    #[default]
+    RejectProposals,
+    /// Takes a snapshot to compact the log, so that LMDB reuses the pages of the compacted entries
+    /// instead of growing.
+    Compact,
+}
+
+/// Thresholds of the monitoring of the disk of `Config::log_dir`, see `RaftStatus::disk_usage`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskUsageConfig {
+    /// Seconds between the measures of the free space.
+    pub interval: f32,
+    /// Used fraction of the disk beyond which the node warns.
+    pub soft_threshold: f64,
+    /// Used fraction of the disk beyond which the node takes `hard_action`.
+    pub hard_threshold: f64,
+    pub hard_action: DiskFullAction,
+}
+
+impl Default for DiskUsageConfig {
+    fn default() -> Self {
+        Self {
+            interval: 10.0,
+            soft_threshold: 0.8,
+            hard_threshold: 0.95,
+            hard_action: DiskFullAction::default(),
+        }
+    }
+}
+
+/// Backoff of the attempts of a node to join the cluster, see `Raft::bootstrap_and_join`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RejoinConfig {
+    /// Seconds before the first retry.
+    pub initial_backoff: f32,
+    /// Seconds the backoff grows up to.
+    pub max_backoff: f32,
+    /// Factor the backoff grows by after each failed attempt.
+    pub multiplier: f32,
+    /// Attempts before giving up with the error of the last one. Retries until it succeeds when `None`.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RejoinConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: 0.5,
+            max_backoff: 30.0,
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+/// Options of the gRPC connections between the nodes, applied by both the clients and the `RaftServer`.
+///
+/// The HTTP/2 pings detect a dead peer within a few seconds, where the TCP connection would take minutes
+/// to time out, so that the messages aren't queued on a connection nobody answers anymore.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcConfig {
+    /// Seconds between the HTTP/2 pings sent on the connections, even idle ones. No pings are sent when `None`.
+    pub keepalive_interval: Option<f32>,
+    /// Seconds to wait for the ack of a ping before closing the connection.
+    pub keepalive_timeout: f32,
+    /// Seconds to wait for a connection to a peer to be established.
+    pub connect_timeout: f32,
+    /// Seconds a request to a peer may take before failing with DEADLINE_EXCEEDED, unlimited when `None`.
+    /// Snapshots streamed to the followers have to be sent within it too.
+    pub request_timeout: Option<f32>,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            keepalive_interval: Some(1.0),
+            keepalive_timeout: 2.0,
+            connect_timeout: 2.0,
+            request_timeout: None,
+        }
+    }
+}
+
+impl GrpcConfig {
+    pub(crate) fn apply_to_endpoint(&self, endpoint: Endpoint) -> Endpoint {
+        let mut endpoint = endpoint
+            .connect_timeout(Duration::from_secs_f32(self.connect_timeout))
+            .keep_alive_timeout(Duration::from_secs_f32(self.keepalive_timeout))
+            .keep_alive_while_idle(true);
+        if let Some(interval) = self.keepalive_interval {
+            endpoint = endpoint.http2_keep_alive_interval(Duration::from_secs_f32(interval));
+        }
+        if let Some(timeout) = self.request_timeout {
+            endpoint = endpoint.timeout(Duration::from_secs_f32(timeout));
+        }
+        endpoint
+    }
+
+    pub(crate) fn server_builder(&self) -> Server {
+        Server::builder()
+            .http2_keepalive_interval(self.keepalive_interval.map(Duration::from_secs_f32))
+            .http2_keepalive_timeout(Some(Duration::from_secs_f32(self.keepalive_timeout)))
+    }
+}
+
+/// Stops sending messages to a peer failing them repeatedly, e.g. down for hours,
+/// rather than spending CPU and logs on attempts bound to fail. Raft still learns that the peer is unreachable.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Requests failed in a row after which the messages to the peer are held back.
+    pub failure_threshold: u32,
+    /// Seconds the messages are held back for, before a single one is sent to probe the peer.
+    pub cooldown: f32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: 30.0,
+        }
+    }
+}
+
+/// Classes of errors a `RetryPolicy` retries the request on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryableError {
+    /// The connection to the peer couldn't be established, e.g. while it restarts.
+    Connect,
+    /// The peer answered UNAVAILABLE, e.g. while its server shuts down.
+    Unavailable,
+    /// The request timed out, see `GrpcConfig::request_timeout`.
+    Timeout,
+    /// The leader rejected the proposal with `Error::Busy`, `Error::TooManyInFlight` or `Error::RateLimited`.
+    Overloaded,
+}
+
+/// Retries of the requests a node makes to another one on its behalf: the join requests,
+/// the `request_id` of a joining node and the proposals and queries forwarded to the leader.
+///
+/// A forwarded proposal whose response is lost would be proposed again when retried on `Timeout`,
+/// so only the proposals made with `RaftNode::propose_with_session` are retried on it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts before failing with the error of the last one. The requests aren't retried when 1.
+    pub max_attempts: u32,
+    /// Seconds before the first retry.
+    pub initial_backoff: f32,
+    /// Seconds the backoff grows up to.
+    pub max_backoff: f32,
+    /// Factor the backoff grows by after each failed attempt.
+    pub multiplier: f32,
+    /// Errors the requests are retried on. The others are returned right away.
+    pub retryable: Vec<RetryableError>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: 0.1,
+            max_backoff: 1.0,
+            multiplier: 2.0,
+            retryable: vec![RetryableError::Connect, RetryableError::Unavailable],
+        }
+    }
+}
+
+/// Limits on the proposals a node takes, rejected beyond them before they reach raft.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdmissionConfig {
+    /// The leader rejects the proposals with `Error::TooManyInFlight` while this many proposals
+    /// are waiting for their entry to be applied. The reads and configuration changes are not counted.
+    pub max_in_flight: Option<usize>,
+    /// The leader rejects the proposals beyond this rate with `Error::RateLimited`.
+    pub max_proposals_per_sec: Option<f64>,
+    /// The node rejects the proposals it receives over gRPC beyond this rate per client with `Error::RateLimited`.
+    /// The clients are identified by the `CLIENT_ID_HEADER` metadata, or by their IP address.
+    pub max_proposals_per_sec_per_client: Option<f64>,
+}
 
 #[derive(Clone)]
 pub struct Config {
@@ -11,14 +312,102 @@ pub struct Config {
     pub save_compacted_logs: bool,
     pub compacted_log_dir: String,
     pub compacted_log_size_threshold: u64,
+    /// Retention of the rotated compacted log segments under `compacted_log_dir`.
+    pub compacted_log_retention: CompactedLogRetention,
 
     pub tick_interval: f32,
+    /// Initial size of the LMDB map, see `lmdb_map_growth_factor`.
     pub lmdb_map_size: u64,
+    /// Factor `HeedStorage` grows the LMDB map by when it is full, before retrying the write.
+    /// `None` surfaces the full map as an error instead.
+    pub lmdb_map_growth_factor: Option<f32>,
+    pub lmdb_sync_mode: LmdbSyncMode,
+    /// Read transactions which can be open at once, by the node and by `HeedStorage` clones
+    /// used from other threads, e.g. to serve the followers or the admin RPCs.
+    pub lmdb_max_readers: u32,
+    /// Seconds beyond which a commit of `HeedStorage` is logged as slow. A slow disk delays the heartbeat
+    /// responses and the votes, and is a common cause of spurious elections.
+    pub slow_write_threshold: Option<f32>,
+    /// Recent log entries `HeedStorage` keeps in memory to replicate them to the followers
+    /// without reading them back from LMDB. 0 disables the cache.
+    pub entry_cache_size: usize,
+    /// Client sessions the nodes keep to detect the retried proposals, see `RaftNode::propose_with_session`.
+    /// The session applied the longest ago is forgotten beyond it. Set the same value on every node,
+    /// so that they detect the same duplicates.
+    pub max_sessions: usize,
     pub cluster_id: String,
     pub conf_change_request_timeout: f32,
 
     pub initial_peers: Option<Peers>,
+    /// Seconds between the automatic snapshots. A snapshot is taken only if
+    /// new entries were applied since the last one.
     pub snapshot_interval: Option<f32>,
+    /// A snapshot is taken after this many entries are applied since the last one.
+    pub snapshot_log_count_threshold: Option<u64>,
+    /// Followers lagging at most this many entries behind the applied index receive
+    /// a state machine delta instead of the full snapshot. Deltas are disabled when `None`.
+    pub delta_snapshot_max_lag: Option<u64>,
+    /// Entry data larger than this many bytes is compressed with LZ4 before it is proposed.
+    /// Enable it only after every node of the cluster is upgraded to decode compressed entries.
+    pub entry_compression_threshold: Option<usize>,
+    /// Snapshots are sent to the followers in chunks of this many bytes.
+    pub snapshot_chunk_size: usize,
+    /// Snapshots are uploaded to this store and sent to the followers as references
+    /// instead of through the gRPC channel when set.
+    pub snapshot_store: Option<Arc<dyn SnapshotStore>>,
+    /// Encrypts the snapshots and the rotated compacted log segments at rest when set.
+    pub encryption_key_provider: Option<Arc<dyn KeyProvider>>,
+    pub learner_auto_promote_threshold: Option<u64>,
+    /// The leader proposes the removal of the voters unreachable for too long when set.
+    /// Requires `raft_config.check_quorum`, which the leader relies on to detect the unreachable voters.
+    pub dead_voter_removal: Option<DeadVoterRemoval>,
+    /// Hands the leadership over to the node preferred by the policy when set, e.g. `PreferredZone`.
+    pub leader_placement: Option<Arc<dyn LeaderPlacementPolicy>>,
+    /// Runs the gossip layer discovering the other nodes and the leader when set, see `Gossip`.
+    pub gossip: Option<GossipConfig>,
+    pub is_witness: bool,
+    /// Where the state machine applies the entries, and takes and restores the snapshots.
+    pub apply_executor: ApplyExecutor,
+    /// The leader rejects the proposals with `Error::Busy` while more committed entries than this
+    /// are waiting to be applied, instead of queuing them while the state machine can't keep up.
+    pub max_apply_lag: Option<u64>,
+    /// Limits on the in-flight proposals and the proposal rate when set.
+    pub admission: Option<AdmissionConfig>,
+    /// Monitors the free space of the disk of `log_dir` when set.
+    pub disk_usage: Option<DiskUsageConfig>,
+    /// Backoff of the retried `request_id` and join requests of a node joining the cluster.
+    pub rejoin: RejoinConfig,
+    /// Keepalive and timeouts of the gRPC connections between the nodes.
+    pub grpc: GrpcConfig,
+    /// Retries of the requests made to the other nodes, see `RetryPolicy`.
+    pub retry: RetryPolicy,
+    /// Holds back the messages to the peers failing them repeatedly when set, see `CircuitBreakerConfig`.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Messages the ready loop handles at most before processing the Ready,
+    /// so that the requests queued meanwhile are appended, persisted and sent together.
+    pub max_messages_per_ready: usize,
+    /// Persists the entries on a dedicated task, letting the leader send the next appends to the followers
+    /// while it syncs the previous ones. Requires a storage whose clones share their state, as the built-in ones do.
+    pub async_append: bool,
+    /// With `async_append`, the Readies persisted within this many seconds of each other are written
+    /// in a single transaction with a single sync, delaying them by up to this much when set.
+    pub group_commit_window: Option<f32>,
+    /// Sends the messages to the same peer of a Ready in a single `SendMessages` call.
+    /// Unset it while upgrading a cluster from a version without this RPC.
+    pub batch_messages: bool,
+    /// Raft group of the node when it is hosted by a `RaftGroupManager`, which sets it.
+    /// Sent with every request to the other peers, to reach the same group on their side.
+    pub group_id: Option<u64>,
+    /// Connections to the peers are shared through the pool when set.
+    /// `RaftGroupManager` sets it, so that its groups share one connection per peer.
+    pub channel_pool: Option<ChannelPool>,
+    /// Runtime the node, its RaftServer and their tasks are spawned on when set, e.g. to run the raft I/O
+    /// on threads of its own. The runtime `Raft::run` is called from is used otherwise.
+    pub runtime: Option<Handle>,
+    pub tls: Option<TlsConfig>,
+    pub auth: Option<AuthConfig>,
+    /// Authorizes `RaftAdminService` requests. Every admin request is accepted when `None`.
+    pub admin_authorizer: Option<Arc<dyn AdminAuthorizer>>,
 }
 
 impl Config {
@@ -28,14 +417,50 @@ impl Config {
         save_compacted_logs: bool,
         compacted_log_dir: String,
         compacted_log_size_threshold: u64,
+        compacted_log_retention: CompactedLogRetention,
         raft_config: RaftConfig,
         tick_interval: f32,
         lmdb_map_size: u64,
+        lmdb_map_growth_factor: Option<f32>,
+        lmdb_sync_mode: LmdbSyncMode,
+        lmdb_max_readers: u32,
+        slow_write_threshold: Option<f32>,
+        entry_cache_size: usize,
+        max_sessions: usize,
         cluster_id: String,
         bootstrap_from_snapshot: bool,
         conf_change_request_timeout: f32,
         initial_peers: Option<Peers>,
         snapshot_interval: Option<f32>,
+        snapshot_log_count_threshold: Option<u64>,
+        delta_snapshot_max_lag: Option<u64>,
+        entry_compression_threshold: Option<usize>,
+        snapshot_chunk_size: usize,
+        snapshot_store: Option<Arc<dyn SnapshotStore>>,
+        encryption_key_provider: Option<Arc<dyn KeyProvider>>,
+        learner_auto_promote_threshold: Option<u64>,
+        dead_voter_removal: Option<DeadVoterRemoval>,
+        leader_placement: Option<Arc<dyn LeaderPlacementPolicy>>,
+        gossip: Option<GossipConfig>,
+        is_witness: bool,
+        apply_executor: ApplyExecutor,
+        max_apply_lag: Option<u64>,
+        admission: Option<AdmissionConfig>,
+        disk_usage: Option<DiskUsageConfig>,
+        rejoin: RejoinConfig,
+        grpc: GrpcConfig,
+        retry: RetryPolicy,
+        circuit_breaker: Option<CircuitBreakerConfig>,
+        max_messages_per_ready: usize,
+        async_append: bool,
+        group_commit_window: Option<f32>,
+        batch_messages: bool,
+        group_id: Option<u64>,
+        channel_pool: Option<ChannelPool>,
+        runtime: Option<Handle>,
+        tls: Option<TlsConfig>,
+        auth: Option<AuthConfig>,
+        admin_authorizer: Option<Arc<dyn AdminAuthorizer>>,
     ) -> Self {
         Self {
             raft_config,
@@ -43,9 +468,45 @@ impl Config {
             save_compacted_logs,
             compacted_log_dir,
             compacted_log_size_threshold,
+            compacted_log_retention,
             snapshot_interval,
+            snapshot_log_count_threshold,
+            delta_snapshot_max_lag,
+            entry_compression_threshold,
+            snapshot_chunk_size,
+            snapshot_store,
+            encryption_key_provider,
+            learner_auto_promote_threshold,
+            dead_voter_removal,
+            leader_placement,
+            gossip,
+            is_witness,
+            apply_executor,
+            max_apply_lag,
+            admission,
+            disk_usage,
+            rejoin,
+            grpc,
+            retry,
+            circuit_breaker,
+            max_messages_per_ready,
+            async_append,
+            group_commit_window,
+            batch_messages,
+            group_id,
+            channel_pool,
+            runtime,
+            tls,
+            auth,
+            admin_authorizer,
             tick_interval,
             lmdb_map_size,
+            lmdb_map_growth_factor,
+            lmdb_sync_mode,
+            lmdb_max_readers,
+            slow_write_threshold,
+            entry_cache_size,
+            max_sessions,
             initial_peers,
             cluster_id,
             conf_change_request_timeout,
@@ -55,6 +516,26 @@ impl Config {
 }
 
 impl Config {
+    /// Options of the connections this node makes to the other peers.
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            tls: self.tls.clone(),
+            auth_token: self.auth.as_ref().map(|auth| auth.token.clone()),
+            group_id: self.group_id,
+            channel_pool: self.channel_pool.clone(),
+            grpc: self.grpc.clone(),
+            retry: self.retry.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Handle of the runtime the tasks of the node are spawned on, see `runtime`.
+    ///
+    /// Panics if `runtime` isn't set and it's called outside of a runtime.
+    pub(crate) fn runtime_handle(&self) -> Handle {
+        self.runtime.clone().unwrap_or_else(Handle::current)
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.initial_peers.is_some() {
             let leaders = self
@@ -74,6 +555,175 @@ impl Config {
             }
         }
 
+        if self.raft_config.read_only_option == ReadOnlyOption::LeaseBased
+            && !self.raft_config.check_quorum
+        {
+            return Err(Error::ConfigInvalid(
+                "read_only_option LeaseBased requires check_quorum to be enabled".to_owned(),
+            ));
+        }
+
+        if let Some(disk_usage) = &self.disk_usage {
+            if disk_usage.interval <= 0.0 {
+                return Err(Error::ConfigInvalid(
+                    "disk_usage.interval should be greater than 0".to_owned(),
+                ));
+            }
+            if !(0.0 < disk_usage.soft_threshold
+                && disk_usage.soft_threshold <= disk_usage.hard_threshold
+                && disk_usage.hard_threshold <= 1.0)
+            {
+                return Err(Error::ConfigInvalid(
+                    "disk_usage thresholds should satisfy 0 < soft_threshold <= hard_threshold <= 1"
+                        .to_owned(),
+                ));
+            }
+        }
+
+        if self.rejoin.initial_backoff <= 0.0
+            || self.rejoin.max_backoff < self.rejoin.initial_backoff
+            || self.rejoin.multiplier < 1.0
+        {
+            return Err(Error::ConfigInvalid(
+                "rejoin should satisfy 0 < initial_backoff <= max_backoff and multiplier >= 1"
+                    .to_owned(),
+            ));
+        }
+        if self.rejoin.max_attempts == Some(0) {
+            return Err(Error::ConfigInvalid(
+                "rejoin.max_attempts should be greater than 0".to_owned(),
+            ));
+        }
+
+        let grpc = &self.grpc;
+        if grpc.keepalive_timeout <= 0.0
+            || grpc.connect_timeout <= 0.0
+            || matches!(grpc.keepalive_interval, Some(interval) if interval <= 0.0)
+            || matches!(grpc.request_timeout, Some(timeout) if timeout <= 0.0)
+        {
+            return Err(Error::ConfigInvalid(
+                "grpc intervals and timeouts should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.max_sessions == 0 {
+            return Err(Error::ConfigInvalid(
+                "max_sessions should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.retry.max_attempts == 0 {
+            return Err(Error::ConfigInvalid(
+                "retry.max_attempts should be greater than 0".to_owned(),
+            ));
+        }
+        if self.retry.initial_backoff <= 0.0
+            || self.retry.max_backoff < self.retry.initial_backoff
+            || self.retry.multiplier < 1.0
+        {
+            return Err(Error::ConfigInvalid(
+                "retry should satisfy 0 < initial_backoff <= max_backoff and multiplier >= 1"
+                    .to_owned(),
+            ));
+        }
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            if circuit_breaker.failure_threshold == 0 || circuit_breaker.cooldown <= 0.0 {
+                return Err(Error::ConfigInvalid(
+                    "circuit_breaker failure_threshold and cooldown should be greater than 0"
+                        .to_owned(),
+                ));
+            }
+        }
+
+        if matches!(self.slow_write_threshold, Some(threshold) if threshold <= 0.0) {
+            return Err(Error::ConfigInvalid(
+                "slow_write_threshold should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.lmdb_max_readers == 0 {
+            return Err(Error::ConfigInvalid(
+                "lmdb_max_readers should be greater than 0".to_owned(),
+            ));
+        }
+
+        if matches!(self.lmdb_map_growth_factor, Some(factor) if factor <= 1.0) {
+            return Err(Error::ConfigInvalid(
+                "lmdb_map_growth_factor should be greater than 1".to_owned(),
+            ));
+        }
+
+        if matches!(self.group_commit_window, Some(window) if window < 0.0) {
+            return Err(Error::ConfigInvalid(
+                "group_commit_window should not be negative".to_owned(),
+            ));
+        }
+
+        if matches!(self.snapshot_interval, Some(interval) if interval <= 0.0) {
+            return Err(Error::ConfigInvalid(
+                "snapshot_interval should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.compacted_log_retention.sweep_interval <= 0.0 {
+            return Err(Error::ConfigInvalid(
+                "compacted_log_retention.sweep_interval should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.snapshot_log_count_threshold == Some(0) {
+            return Err(Error::ConfigInvalid(
+                "snapshot_log_count_threshold should be greater than 0".to_owned(),
+            ));
+        }
+
+        if let Some(removal) = &self.dead_voter_removal {
+            if !self.raft_config.check_quorum {
+                return Err(Error::ConfigInvalid(
+                    "dead_voter_removal requires check_quorum to be enabled".to_owned(),
+                ));
+            }
+            if removal.unreachable_for <= 0.0 {
+                return Err(Error::ConfigInvalid(
+                    "dead_voter_removal.unreachable_for should be greater than 0".to_owned(),
+                ));
+            }
+        }
+
+        if let Some(gossip) = &self.gossip {
+            if gossip.interval <= 0.0 || gossip.probe_timeout <= 0.0 {
+                return Err(Error::ConfigInvalid(
+                    "gossip.interval and gossip.probe_timeout should be greater than 0".to_owned(),
+                ));
+            }
+        }
+
+        if self.raft_config.heartbeat_tick >= self.raft_config.election_tick {
+            return Err(Error::ConfigInvalid(
+                "raft_config.heartbeat_tick should be less than raft_config.election_tick"
+                    .to_owned(),
+            ));
+        }
+
+        if self.tick_interval <= 0.0 {
+            return Err(Error::ConfigInvalid(
+                "tick_interval should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.save_compacted_logs && self.compacted_log_size_threshold == 0 {
+            return Err(Error::ConfigInvalid(
+                "compacted_log_size_threshold should be greater than 0".to_owned(),
+            ));
+        }
+
+        if self.snapshot_chunk_size == 0 {
+            return Err(Error::ConfigInvalid(
+                "snapshot_chunk_size should be greater than 0".to_owned(),
+            ));
+        }
+
         self.raft_config.validate()?;
         Ok(())
     }
@@ -87,12 +737,48 @@ impl Default for Config {
             save_compacted_logs: false,
             compacted_log_dir: String::from("./"),
             compacted_log_size_threshold: 1024 * 1024 * 1024,
+            compacted_log_retention: CompactedLogRetention::default(),
             tick_interval: 0.1,
             lmdb_map_size: 1024 * 1024 * 1024,
+            lmdb_map_growth_factor: Some(2.0),
+            lmdb_sync_mode: LmdbSyncMode::Full,
+            lmdb_max_readers: 126,
+            slow_write_threshold: Some(1.0),
+            entry_cache_size: 1024,
+            max_sessions: 100_000,
             cluster_id: String::from("default"),
             conf_change_request_timeout: 2.0,
             initial_peers: None,
             snapshot_interval: None,
+            snapshot_log_count_threshold: None,
+            delta_snapshot_max_lag: None,
+            entry_compression_threshold: None,
+            snapshot_chunk_size: 1024 * 1024,
+            snapshot_store: None,
+            encryption_key_provider: None,
+            learner_auto_promote_threshold: None,
+            dead_voter_removal: None,
+            leader_placement: None,
+            gossip: None,
+            is_witness: false,
+            apply_executor: ApplyExecutor::default(),
+            max_apply_lag: None,
+            admission: None,
+            disk_usage: None,
+            rejoin: RejoinConfig::default(),
+            grpc: GrpcConfig::default(),
+            retry: RetryPolicy::default(),
+            circuit_breaker: None,
+            max_messages_per_ready: 256,
+            async_append: false,
+            group_commit_window: None,
+            batch_messages: true,
+            group_id: None,
+            channel_pool: None,
+            runtime: None,
+            tls: None,
+            auth: None,
+            admin_authorizer: None,
             bootstrap_from_snapshot: false,
         }
     }
@@ -126,10 +812,46 @@ impl fmt::Debug for Config {
                 save_compacted_logs: {save_compacted_logs}, \
                 compacted_log_dir: {compacted_log_dir}, \
                 compacted_log_size_threshold: {compacted_log_size_threshold}, \
+                compacted_log_retention: {compacted_log_retention:?}, \
                 snapshot_interval: {snapshot_interval:?}, \
+                snapshot_log_count_threshold: {snapshot_log_count_threshold:?}, \
+                delta_snapshot_max_lag: {delta_snapshot_max_lag:?}, \
+                entry_compression_threshold: {entry_compression_threshold:?}, \
+                snapshot_chunk_size: {snapshot_chunk_size}, \
+                snapshot_store: {snapshot_store:?}, \
+                encryption_key_provider: {encryption_key_provider:?}, \
+                learner_auto_promote_threshold: {learner_auto_promote_threshold:?}, \
+                dead_voter_removal: {dead_voter_removal:?}, \
+                leader_placement: {leader_placement}, \
+                gossip: {gossip:?}, \
+                is_witness: {is_witness}, \
+                apply_executor: {apply_executor:?}, \
+                max_apply_lag: {max_apply_lag:?}, \
+                admission: {admission:?}, \
+                disk_usage: {disk_usage:?}, \
+                rejoin: {rejoin:?}, \
+                grpc: {grpc:?}, \
+                retry: {retry:?}, \
+                circuit_breaker: {circuit_breaker:?}, \
+                max_messages_per_ready: {max_messages_per_ready}, \
+                async_append: {async_append}, \
+                group_commit_window: {group_commit_window:?}, \
+                batch_messages: {batch_messages}, \
+                group_id: {group_id:?}, \
+                channel_pool: {channel_pool}, \
+                runtime: {runtime}, \
+                tls: {tls:?}, \
+                auth: {auth:?}, \
+                admin_authorizer: {admin_authorizer}, \
                 tick_interval: {tick_interval}, \
                 initial_peers: {initial_peers:?}, \
                 lmdb_map_size: {lmdb_map_size}, \
+                lmdb_map_growth_factor: {lmdb_map_growth_factor:?}, \
+                lmdb_sync_mode: {lmdb_sync_mode:?}, \
+                lmdb_max_readers: {lmdb_max_readers}, \
+                slow_write_threshold: {slow_write_threshold:?}, \
+                entry_cache_size: {entry_cache_size}, \
+                max_sessions: {max_sessions}, \
                 cluster_id: {cluster_id}, \
                 conf_change_request_timeout: {conf_change_request_timeout}, \
             }}",
@@ -153,9 +875,45 @@ impl fmt::Debug for Config {
             save_compacted_logs = self.save_compacted_logs,
             compacted_log_dir = self.compacted_log_dir,
             compacted_log_size_threshold = self.compacted_log_size_threshold,
+            compacted_log_retention = self.compacted_log_retention,
             snapshot_interval = self.snapshot_interval,
+            snapshot_log_count_threshold = self.snapshot_log_count_threshold,
+            delta_snapshot_max_lag = self.delta_snapshot_max_lag,
+            entry_compression_threshold = self.entry_compression_threshold,
+            snapshot_chunk_size = self.snapshot_chunk_size,
+            snapshot_store = self.snapshot_store,
+            encryption_key_provider = self.encryption_key_provider,
+            learner_auto_promote_threshold = self.learner_auto_promote_threshold,
+            dead_voter_removal = self.dead_voter_removal,
+            leader_placement = self.leader_placement.is_some(),
+            gossip = self.gossip,
+            is_witness = self.is_witness,
+            apply_executor = self.apply_executor,
+            max_apply_lag = self.max_apply_lag,
+            admission = self.admission,
+            disk_usage = self.disk_usage,
+            rejoin = self.rejoin,
+            grpc = self.grpc,
+            retry = self.retry,
+            circuit_breaker = self.circuit_breaker,
+            max_messages_per_ready = self.max_messages_per_ready,
+            async_append = self.async_append,
+            group_commit_window = self.group_commit_window,
+            batch_messages = self.batch_messages,
+            group_id = self.group_id,
+            channel_pool = self.channel_pool.is_some(),
+            runtime = self.runtime.is_some(),
+            tls = self.tls,
+            auth = self.auth,
+            admin_authorizer = self.admin_authorizer.is_some(),
             tick_interval = self.tick_interval,
             lmdb_map_size = self.lmdb_map_size,
+            lmdb_map_growth_factor = self.lmdb_map_growth_factor,
+            lmdb_sync_mode = self.lmdb_sync_mode,
+            lmdb_max_readers = self.lmdb_max_readers,
+            slow_write_threshold = self.slow_write_threshold,
+            entry_cache_size = self.entry_cache_size,
+            max_sessions = self.max_sessions,
             initial_peers = self.initial_peers,
             cluster_id = self.cluster_id,
             conf_change_request_timeout = self.conf_change_request_timeout,
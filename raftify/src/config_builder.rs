@@ -0,0 +1,265 @@
+use std::{fs, path::Path, sync::Arc};
+use tokio::runtime::Handle;
+
+use crate::{
+    raft::ReadOnlyOption, AdminAuthorizer, AdmissionConfig, ApplyExecutor, AuthConfig,
+    CircuitBreakerConfig, CompactedLogRetention, Config, DeadVoterRemoval, DiskUsageConfig, Error,
+    GossipConfig, GrpcConfig, KeyProvider, LeaderPlacementPolicy, LmdbSyncMode, Peers, RaftConfig,
+    RejoinConfig, Result, RetryPolicy, SnapshotStore, TlsConfig,
+};
+
+/// Generates the setters of the `Config` fields.
+macro_rules! setters {
+    (raft_config $($field:ident: $type:ty),* $(,)?) => {
+        $(#[doc = concat!("Sets `raft_config.", stringify!($field), "`.")]
+        pub fn $field(mut self, $field: $type) -> Self {
+            self.config.raft_config.$field = $field;
+            self
+        })*
+    };
+    (optional $($field:ident: $type:ty),* $(,)?) => {
+        $(pub fn $field(mut self, $field: $type) -> Self {
+            self.config.$field = Some($field);
+            self
+        })*
+    };
+    ($($field:ident: $type:ty),* $(,)?) => {
+        $(pub fn $field(mut self, $field: $type) -> Self {
+            self.config.$field = $field;
+            self
+        })*
+    };
+}
+
+/// Builds a `Config`, checking its invariants upfront in `build`
+/// instead of letting the node fail on them once it runs.
+///
+/// ```ignore
+/// let config = Config::builder()
+///     .id(1)
+///     .election_tick(10)
+///     .heartbeat_tick(3)
+///     .log_dir("./logs/node-1")
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    setters! {
+        raft_config: RaftConfig,
+        bootstrap_from_snapshot: bool,
+        save_compacted_logs: bool,
+        compacted_log_size_threshold: u64,
+        compacted_log_retention: CompactedLogRetention,
+        tick_interval: f32,
+        lmdb_map_size: u64,
+        lmdb_sync_mode: LmdbSyncMode,
+        lmdb_max_readers: u32,
+        entry_cache_size: usize,
+        max_sessions: usize,
+        conf_change_request_timeout: f32,
+        snapshot_chunk_size: usize,
+        is_witness: bool,
+        apply_executor: ApplyExecutor,
+        max_messages_per_ready: usize,
+        async_append: bool,
+        batch_messages: bool,
+        rejoin: RejoinConfig,
+        grpc: GrpcConfig,
+        retry: RetryPolicy,
+    }
+
+    setters! {
+        optional
+        initial_peers: Peers,
+        snapshot_interval: f32,
+        snapshot_log_count_threshold: u64,
+        delta_snapshot_max_lag: u64,
+        max_apply_lag: u64,
+        group_commit_window: f32,
+        lmdb_map_growth_factor: f32,
+        slow_write_threshold: f32,
+        entry_compression_threshold: usize,
+        snapshot_store: Arc<dyn SnapshotStore>,
+        encryption_key_provider: Arc<dyn KeyProvider>,
+        learner_auto_promote_threshold: u64,
+        dead_voter_removal: DeadVoterRemoval,
+        leader_placement: Arc<dyn LeaderPlacementPolicy>,
+        gossip: GossipConfig,
+        admission: AdmissionConfig,
+        disk_usage: DiskUsageConfig,
+        tls: TlsConfig,
+        auth: AuthConfig,
+        admin_authorizer: Arc<dyn AdminAuthorizer>,
+        runtime: Handle,
+        circuit_breaker: CircuitBreakerConfig,
+    }
+
+    pub fn log_dir(mut self, log_dir: impl Into<String>) -> Self {
+        self.config.log_dir = log_dir.into();
+        self
+    }
+
+    pub fn compacted_log_dir(mut self, compacted_log_dir: impl Into<String>) -> Self {
+        self.config.compacted_log_dir = compacted_log_dir.into();
+        self
+    }
+
+    pub fn cluster_id(mut self, cluster_id: impl Into<String>) -> Self {
+        self.config.cluster_id = cluster_id.into();
+        self
+    }
+
+    setters! {
+        raft_config
+        id: u64,
+        election_tick: usize,
+        heartbeat_tick: usize,
+        check_quorum: bool,
+        pre_vote: bool,
+        read_only_option: ReadOnlyOption,
+        // Throughput tuning knobs of raft-rs, see `RaftConfig` for their meaning.
+        max_size_per_msg: u64,
+        max_inflight_msgs: usize,
+        max_committed_size_per_ready: u64,
+        max_uncommitted_size: u64,
+        batch_append: bool,
+    }
+
+    /// Validates the config and checks that its log directories are writable, creating them if needed.
+    /// Skip the directory checks with `build_unchecked_dirs` for the storages which don't use them.
+    pub fn build(self) -> Result<Config> {
+        let config = self.build_unchecked_dirs()?;
+        check_writable("log_dir", &config.log_dir)?;
+        if config.save_compacted_logs {
+            check_writable("compacted_log_dir", &config.compacted_log_dir)?;
+        }
+        Ok(config)
+    }
+
+    /// Validates the config, without checking its log directories, e.g. for `MemStorage`.
+    pub fn build_unchecked_dirs(self) -> Result<Config> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+fn check_writable(field: &str, dir: &str) -> Result<()> {
+    let probe = Path::new(dir).join(".raftify-write-check");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| Error::ConfigInvalid(format!("{} \"{}\" is not writable: {}", field, dir, e)))
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("node-1");
+
+        let config = Config::builder()
+            .id(1)
+            .election_tick(10)
+            .heartbeat_tick(3)
+            .log_dir(log_dir.to_str().unwrap())
+            .snapshot_interval(30.0)
+            .build()
+            .unwrap();
+
+        assert!(log_dir.exists());
+        assert_eq!(config.raft_config.election_tick, 10);
+        assert_eq!(config.snapshot_interval, Some(30.0));
+    }
+
+    #[test]
+    fn test_build_tuned_raft_config() {
+        let config = Config::builder()
+            .id(1)
+            .check_quorum(true)
+            .read_only_option(ReadOnlyOption::LeaseBased)
+            .max_size_per_msg(4 * 1024 * 1024)
+            .max_inflight_msgs(512)
+            .max_uncommitted_size(64 * 1024 * 1024)
+            .build_unchecked_dirs()
+            .unwrap();
+
+        assert_eq!(config.raft_config.max_size_per_msg, 4 * 1024 * 1024);
+        assert_eq!(config.raft_config.max_inflight_msgs, 512);
+        assert_eq!(config.raft_config.max_uncommitted_size, 64 * 1024 * 1024);
+
+        // Rejected by raft-rs itself.
+        let error = Config::builder()
+            .id(1)
+            .max_inflight_msgs(0)
+            .build_unchecked_dirs()
+            .unwrap_err();
+        assert!(error.to_string().contains("max inflight"), "{}", error);
+    }
+
+    #[test]
+    fn test_reject_invalid_config() {
+        let error = Config::builder()
+            .id(1)
+            .election_tick(3)
+            .heartbeat_tick(3)
+            .build_unchecked_dirs()
+            .unwrap_err();
+        assert!(error.to_string().contains("heartbeat_tick"), "{}", error);
+
+        let error = Config::builder()
+            .id(1)
+            .grpc(GrpcConfig {
+                keepalive_interval: Some(0.0),
+                ..Default::default()
+            })
+            .build_unchecked_dirs()
+            .unwrap_err();
+        assert!(error.to_string().contains("grpc"), "{}", error);
+
+        let error = Config::builder()
+            .id(1)
+            .save_compacted_logs(true)
+            .compacted_log_size_threshold(0)
+            .build_unchecked_dirs()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("compacted_log_size_threshold"),
+            "{}",
+            error
+        );
+
+        let error = Config::builder()
+            .id(1)
+            .read_only_option(ReadOnlyOption::LeaseBased)
+            .build_unchecked_dirs()
+            .unwrap_err();
+        assert!(error.to_string().contains("check_quorum"), "{}", error);
+    }
+
+    #[test]
+    fn test_reject_unwritable_log_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+
+        let error = Config::builder()
+            .id(1)
+            .log_dir(file.join("logs").to_str().unwrap())
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("log_dir"), "{}", error);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{env, str::FromStr};
+
+use crate::{Config, Error, GossipConfig, InitialRole, Peers, Result};
+
+/// Parses the value of the environment variable `name`, if it is set.
+fn parse_var<T: FromStr>(vars: &impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>> {
+    match vars(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::ConfigInvalid(format!("{}: invalid value \"{}\"", name, value))),
+        None => Ok(None),
+    }
+}
+
+/// Parses `<node_id>=<addr>[=<role>]` entries separated by commas.
+fn parse_peers(name: &str, value: &str) -> Result<Peers> {
+    let invalid = |entry: &str| {
+        Error::ConfigInvalid(format!(
+            "{}: invalid peer \"{}\", expected <node_id>=<addr>[=<role>]",
+            name, entry
+        ))
+    };
+
+    let mut peers = Peers::with_empty();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let mut parts = entry.split('=');
+        let node_id = parts
+            .next()
+            .and_then(|node_id| node_id.parse::<u64>().ok())
+            .ok_or_else(|| invalid(entry))?;
+        let addr = parts.next().ok_or_else(|| invalid(entry))?;
+        let role = match parts.next() {
+            Some(role) => InitialRole::from_str(role).map_err(|_| invalid(entry))?,
+            None => InitialRole::Voter,
+        };
+        if parts.next().is_some() {
+            return Err(invalid(entry));
+        }
+        peers.add_peer(node_id, addr, Some(role));
+    }
+    Ok(peers)
+}
+
+impl Config {
+    /// Overrides the fields of the config with the `RAFTIFY_*` environment variables which are set,
+    /// and validates the result. Apply it after the config is read from a file or built in code,
+    /// so that a container image can keep a single config file and vary it per instance.
+    ///
+    /// - `RAFTIFY_NODE_ID`, `RAFTIFY_ELECTION_TICK`, `RAFTIFY_HEARTBEAT_TICK`: the fields of `raft_config`
+    /// - `RAFTIFY_LOG_DIR`, `RAFTIFY_COMPACTED_LOG_DIR`, `RAFTIFY_CLUSTER_ID`, `RAFTIFY_TICK_INTERVAL`
+    /// - `RAFTIFY_INITIAL_PEERS`: `<node_id>=<addr>[=<role>]` entries separated by commas,
+    ///   e.g. `1=10.0.0.1:60061=leader,2=10.0.0.2:60061`
+    /// - `RAFTIFY_GOSSIP_SEEDS` (separated by commas), `RAFTIFY_GOSSIP_ADVERTISE_ADDR`: enable the gossip if it isn't
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        self.apply_overrides(|name| env::var(name).ok())
+    }
+
+    fn apply_overrides(&mut self, vars: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(id) = parse_var(&vars, "RAFTIFY_NODE_ID")? {
+            self.raft_config.id = id;
+        }
+        if let Some(election_tick) = parse_var(&vars, "RAFTIFY_ELECTION_TICK")? {
+            self.raft_config.election_tick = election_tick;
+        }
+        if let Some(heartbeat_tick) = parse_var(&vars, "RAFTIFY_HEARTBEAT_TICK")? {
+            self.raft_config.heartbeat_tick = heartbeat_tick;
+        }
+        if let Some(tick_interval) = parse_var(&vars, "RAFTIFY_TICK_INTERVAL")? {
+            self.tick_interval = tick_interval;
+        }
+        if let Some(log_dir) = vars("RAFTIFY_LOG_DIR") {
+            self.log_dir = log_dir;
+        }
+        if let Some(compacted_log_dir) = vars("RAFTIFY_COMPACTED_LOG_DIR") {
+            self.compacted_log_dir = compacted_log_dir;
+        }
+        if let Some(cluster_id) = vars("RAFTIFY_CLUSTER_ID") {
+            self.cluster_id = cluster_id;
+        }
+        if let Some(peers) = vars("RAFTIFY_INITIAL_PEERS") {
+            self.initial_peers = Some(parse_peers("RAFTIFY_INITIAL_PEERS", &peers)?);
+        }
+
+        if let Some(seeds) = vars("RAFTIFY_GOSSIP_SEEDS") {
+            self.gossip.get_or_insert_with(GossipConfig::default).seeds = seeds
+                .split(',')
+                .map(str::trim)
+                .filter(|seed| !seed.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+        if let Some(advertise_addr) = vars("RAFTIFY_GOSSIP_ADVERTISE_ADDR") {
+            self.gossip
+                .get_or_insert_with(GossipConfig::default)
+                .advertise_addr = Some(advertise_addr);
+        }
+
+        self.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut config = Config {
+            log_dir: "./logs".to_owned(),
+            ..Default::default()
+        };
+        config.raft_config.id = 1;
+
+        config
+            .apply_overrides(vars(&[
+                ("RAFTIFY_NODE_ID", "2"),
+                ("RAFTIFY_LOG_DIR", "/data/raftify"),
+                (
+                    "RAFTIFY_INITIAL_PEERS",
+                    "1=127.0.0.1:60061=leader, 2=127.0.0.1:60062",
+                ),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.raft_config.id, 2);
+        assert_eq!(config.log_dir, "/data/raftify");
+        let peers = config.initial_peers.unwrap();
+        assert_eq!(peers.voters(), vec![1, 2]);
+        assert_eq!(peers.get(&1).unwrap().role, InitialRole::Leader);
+        assert!(config.gossip.is_none());
+    }
+
+    #[test]
+    fn test_invalid_env_override_names_the_variable() {
+        let mut config = Config::default();
+        config.raft_config.id = 1;
+
+        let error = config
+            .apply_overrides(vars(&[("RAFTIFY_ELECTION_TICK", "ten")]))
+            .unwrap_err();
+        assert!(error.to_string().contains("RAFTIFY_ELECTION_TICK"));
+
+        let error = config
+            .apply_overrides(vars(&[("RAFTIFY_INITIAL_PEERS", "1@127.0.0.1:60061")]))
+            .unwrap_err();
+        assert!(error.to_string().contains("RAFTIFY_INITIAL_PEERS"));
+    }
+}
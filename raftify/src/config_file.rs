@@ -0,0 +1,329 @@
+use serde::Deserialize;
+use std::{fs, path::Path, str::FromStr};
+
+use crate::{
+    raft::ReadOnlyOption, AdmissionConfig, CircuitBreakerConfig, CompactedLogRetention, Config,
+    DeadVoterRemoval, DiskUsageConfig, Error, GossipConfig, GrpcConfig, InitialRole, LmdbSyncMode,
+    PeerLabels, Peers, RejoinConfig, Result, RetryPolicy,
+};
+
+/// Sets the fields of `$target` given in `$file`, leaving the others as they are.
+macro_rules! set_given {
+    ($file:expr, $target:expr, [$($field:ident),* $(,)?]) => {
+        $(if let Some(value) = $file.$field {
+            $target.$field = value;
+        })*
+    };
+    ($file:expr, $target:expr, optional [$($field:ident),* $(,)?]) => {
+        $(if let Some(value) = $file.$field {
+            $target.$field = Some(value);
+        })*
+    };
+}
+
+/// `RaftConfig` fields of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RaftConfigFile {
+    id: Option<u64>,
+    election_tick: Option<usize>,
+    heartbeat_tick: Option<usize>,
+    min_election_tick: Option<usize>,
+    max_election_tick: Option<usize>,
+    applied: Option<u64>,
+    max_size_per_msg: Option<u64>,
+    max_inflight_msgs: Option<usize>,
+    check_quorum: Option<bool>,
+    pre_vote: Option<bool>,
+    // "safe" or "lease_based"
+    read_only_option: Option<String>,
+    skip_bcast_commit: Option<bool>,
+    batch_append: Option<bool>,
+    priority: Option<i64>,
+    max_uncommitted_size: Option<u64>,
+    max_committed_size_per_ready: Option<u64>,
+    omit_heartbeat_log: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PeerEntry {
+    node_id: u64,
+    addr: String,
+    // "leader", "voter" or "learner". Defaults to "voter".
+    role: Option<String>,
+    #[serde(default)]
+    labels: PeerLabels,
+}
+
+/// Configuration file read by `Config::from_file`. Every field is optional,
+/// and the fields which aren't given keep their default value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    raft: RaftConfigFile,
+    log_dir: Option<String>,
+    bootstrap_from_snapshot: Option<bool>,
+    save_compacted_logs: Option<bool>,
+    compacted_log_dir: Option<String>,
+    compacted_log_size_threshold: Option<u64>,
+    compacted_log_retention: Option<CompactedLogRetention>,
+    tick_interval: Option<f32>,
+    lmdb_map_size: Option<u64>,
+    lmdb_map_growth_factor: Option<f32>,
+    lmdb_sync_mode: Option<LmdbSyncMode>,
+    lmdb_max_readers: Option<u32>,
+    slow_write_threshold: Option<f32>,
+    entry_cache_size: Option<usize>,
+    max_sessions: Option<usize>,
+    cluster_id: Option<String>,
+    conf_change_request_timeout: Option<f32>,
+    initial_peers: Option<Vec<PeerEntry>>,
+    snapshot_interval: Option<f32>,
+    snapshot_log_count_threshold: Option<u64>,
+    delta_snapshot_max_lag: Option<u64>,
+    entry_compression_threshold: Option<usize>,
+    snapshot_chunk_size: Option<usize>,
+    learner_auto_promote_threshold: Option<u64>,
+    dead_voter_removal: Option<DeadVoterRemoval>,
+    gossip: Option<GossipConfig>,
+    is_witness: Option<bool>,
+    max_apply_lag: Option<u64>,
+    max_messages_per_ready: Option<usize>,
+    async_append: Option<bool>,
+    group_commit_window: Option<f32>,
+    batch_messages: Option<bool>,
+    admission: Option<AdmissionConfig>,
+    disk_usage: Option<DiskUsageConfig>,
+    rejoin: Option<RejoinConfig>,
+    grpc: Option<GrpcConfig>,
+    retry: Option<RetryPolicy>,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl ConfigFile {
+    fn parse(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|ext| ext.to_str());
+
+        let parsed = match extension {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+            _ => Err("the extension should be .toml, .yaml or .yml".to_owned()),
+        };
+        parsed.map_err(|e| Error::ConfigInvalid(format!("{}: {}", path.display(), e)))
+    }
+
+    fn apply(self, config: &mut Config) -> Result<()> {
+        let raft = self.raft;
+        set_given!(
+            raft,
+            config.raft_config,
+            [
+                id,
+                election_tick,
+                heartbeat_tick,
+                min_election_tick,
+                max_election_tick,
+                applied,
+                max_size_per_msg,
+                max_inflight_msgs,
+                check_quorum,
+                pre_vote,
+                skip_bcast_commit,
+                batch_append,
+                priority,
+                max_uncommitted_size,
+                max_committed_size_per_ready,
+                omit_heartbeat_log,
+            ]
+        );
+        if let Some(read_only_option) = raft.read_only_option {
+            config.raft_config.read_only_option = match read_only_option.as_str() {
+                "safe" => ReadOnlyOption::Safe,
+                "lease_based" => ReadOnlyOption::LeaseBased,
+                _ => {
+                    return Err(Error::ConfigInvalid(format!(
+                        "raft.read_only_option should be \"safe\" or \"lease_based\", not \"{}\"",
+                        read_only_option
+                    )))
+                }
+            };
+        }
+
+        set_given!(
+            self,
+            config,
+            [
+                log_dir,
+                bootstrap_from_snapshot,
+                save_compacted_logs,
+                compacted_log_dir,
+                compacted_log_size_threshold,
+                compacted_log_retention,
+                tick_interval,
+                lmdb_map_size,
+                lmdb_sync_mode,
+                lmdb_max_readers,
+                entry_cache_size,
+                max_sessions,
+                cluster_id,
+                conf_change_request_timeout,
+                snapshot_chunk_size,
+                is_witness,
+                max_messages_per_ready,
+                async_append,
+                batch_messages,
+                rejoin,
+                grpc,
+                retry,
+            ]
+        );
+        set_given!(self, config, optional [
+            snapshot_interval,
+            snapshot_log_count_threshold,
+            delta_snapshot_max_lag,
+            entry_compression_threshold,
+            learner_auto_promote_threshold,
+            dead_voter_removal,
+            gossip,
+            max_apply_lag,
+            group_commit_window,
+            lmdb_map_growth_factor,
+            slow_write_threshold,
+            admission,
+            disk_usage,
+            circuit_breaker,
+        ]);
+
+        if let Some(entries) = self.initial_peers {
+            let mut peers = Peers::with_empty();
+            for entry in entries {
+                let role = match entry.role.as_deref() {
+                    Some(role) => InitialRole::from_str(role).map_err(|_| {
+                        Error::ConfigInvalid(format!(
+                            "initial_peers: invalid role \"{}\" of node {}",
+                            role, entry.node_id
+                        ))
+                    })?,
+                    None => InitialRole::Voter,
+                };
+                peers.add_peer(entry.node_id, entry.addr, Some(role));
+                if let Some(peer) = peers.get_mut(&entry.node_id) {
+                    peer.labels = entry.labels;
+                }
+            }
+            config.initial_peers = Some(peers);
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Reads the configuration from a TOML or YAML file, depending on its extension, and validates it.
+    /// The fields which aren't given keep their default value, and `RaftConfig` fields are given under `raft`.
+    ///
+    /// ```toml
+    /// log_dir = "/var/lib/raftify"
+    /// snapshot_interval = 60.0
+    ///
+    /// [raft]
+    /// id = 1
+    /// election_tick = 10
+    /// heartbeat_tick = 3
+    ///
+    /// [[initial_peers]]
+    /// node_id = 1
+    /// addr = "127.0.0.1:60061"
+    /// role = "leader"
+    /// ```
+    ///
+    /// Options holding trait objects, e.g. `snapshot_store`, and TLS or auth settings can only be set in code.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut config = Config::default();
+        ConfigFile::parse(path.as_ref())?.apply(&mut config)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, content: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let (_dir, path) = write_config(
+            "raftify.toml",
+            r#"
+log_dir = "/tmp/raftify"
+snapshot_interval = 30.0
+
+[raft]
+id = 2
+election_tick = 20
+heartbeat_tick = 4
+read_only_option = "safe"
+
+[[initial_peers]]
+node_id = 1
+addr = "127.0.0.1:60061"
+role = "leader"
+
+[[initial_peers]]
+node_id = 2
+addr = "127.0.0.1:60062"
+labels = { zone = "zone-b", rack = "rack-1" }
+"#,
+        );
+
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.log_dir, "/tmp/raftify");
+        assert_eq!(config.snapshot_interval, Some(30.0));
+        assert_eq!(config.raft_config.id, 2);
+        assert_eq!(config.raft_config.election_tick, 20);
+        let peers = config.initial_peers.unwrap();
+        assert_eq!(peers.voters(), vec![1, 2]);
+        assert_eq!(peers.get(&2).unwrap().zone(), Some("zone-b"));
+        assert_eq!(peers.get(&2).unwrap().rack(), Some("rack-1"));
+    }
+
+    #[test]
+    fn test_config_from_yaml() {
+        let (_dir, path) = write_config(
+            "raftify.yaml",
+            "log_dir: /tmp/raftify\nraft:\n  id: 3\n  check_quorum: true\n",
+        );
+
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.raft_config.id, 3);
+        assert!(config.raft_config.check_quorum);
+
+        let (_dir, path) = write_config(
+            "raftify.yaml",
+            "lmdb_sync_mode: no_meta_sync\nlmdb_max_readers: 512\n",
+        );
+
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.lmdb_sync_mode, LmdbSyncMode::NoMetaSync);
+        assert_eq!(config.lmdb_max_readers, 512);
+    }
+
+    #[test]
+    fn test_config_file_errors_name_the_field() {
+        let (_dir, path) = write_config("raftify.toml", "[raft]\nelection_tik = 10\n");
+        let error = Config::from_file(&path).unwrap_err().to_string();
+        assert!(error.contains("election_tik"), "{}", error);
+
+        let (_dir, path) = write_config("raftify.toml", "snapshot_chunk_size = 0\n");
+        let error = Config::from_file(&path).unwrap_err().to_string();
+        assert!(error.contains("snapshot_chunk_size"), "{}", error);
+    }
+}
@@ -0,0 +1,37 @@
+use std::mem::size_of;
+
+/// Marks the context of a snapshot message whose data is a state machine delta
+/// produced by `AbstractStateMachine::snapshot_delta` rather than a full snapshot.
+const DELTA_SNAPSHOT_CONTEXT_PREFIX: &[u8] = b"raftify-delta:";
+
+/// Encodes the context of a delta snapshot message taken since `base_index`.
+pub(crate) fn encode_delta_context(base_index: u64) -> Vec<u8> {
+    let mut context = DELTA_SNAPSHOT_CONTEXT_PREFIX.to_vec();
+    context.extend_from_slice(&base_index.to_be_bytes());
+    context
+}
+
+/// Returns the base index of a delta snapshot message, or `None` for a full snapshot.
+pub(crate) fn decode_delta_context(context: &[u8]) -> Option<u64> {
+    let base_index = context.strip_prefix(DELTA_SNAPSHOT_CONTEXT_PREFIX)?;
+    if base_index.len() != size_of::<u64>() {
+        return None;
+    }
+    Some(u64::from_be_bytes(base_index.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_context_roundtrip() {
+        assert_eq!(decode_delta_context(&encode_delta_context(42)), Some(42));
+    }
+
+    #[test]
+    fn test_full_snapshot_context() {
+        assert_eq!(decode_delta_context(&[]), None);
+        assert_eq!(decode_delta_context(b"raftify-delta:"), None);
+    }
+}
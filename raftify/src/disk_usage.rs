@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::{io, path::Path, sync::Arc, time::Duration};
+use tokio::{sync::watch, time::interval};
+
+use crate::{raft::logger::Logger, DiskUsageConfig};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiskUsageLevel {
+    #[default]
+    Normal,
+    /// Beyond `DiskUsageConfig::soft_threshold`, only warned about.
+    Soft,
+    /// Beyond `DiskUsageConfig::hard_threshold`, where the node takes `DiskUsageConfig::hard_action`.
+    Hard,
+}
+
+/// Usage of the disk of `Config::log_dir`, as last measured by the disk monitor.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    /// Bytes available to the node, excluding the ones reserved to the superuser.
+    pub free_bytes: u64,
+    pub level: DiskUsageLevel,
+}
+
+impl DiskUsage {
+    pub(crate) fn new(total_bytes: u64, free_bytes: u64, config: &DiskUsageConfig) -> Self {
+        let mut usage = Self {
+            total_bytes,
+            free_bytes,
+            level: DiskUsageLevel::Normal,
+        };
+        let used_ratio = usage.used_ratio();
+        if used_ratio >= config.hard_threshold {
+            usage.level = DiskUsageLevel::Hard;
+        } else if used_ratio >= config.soft_threshold {
+            usage.level = DiskUsageLevel::Soft;
+        }
+        usage
+    }
+
+    pub fn used_ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        1.0 - self.free_bytes as f64 / self.total_bytes as f64
+    }
+}
+
+/// Returns the total and the available bytes of the file system holding `path`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn statvfs(path: &Path) -> io::Result<(u64, u64)> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let block_size = stat.f_frsize as u64;
+    Ok((
+        stat.f_blocks as u64 * block_size,
+        stat.f_bavail as u64 * block_size,
+    ))
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "disk usage monitoring is only supported on unix",
+    ))
+}
+
+/// Measures the disk usage of `log_dir` every `config.interval` seconds, publishing it to `tx_usage`.
+/// Warns when the usage crosses a threshold, and stops when the node drops the receiver.
+pub(crate) async fn run_disk_monitor(
+    log_dir: String,
+    config: DiskUsageConfig,
+    tx_usage: watch::Sender<Option<DiskUsage>>,
+    logger: Arc<dyn Logger>,
+) {
+    let mut ticker = interval(Duration::from_secs_f32(config.interval));
+    let mut last_level = DiskUsageLevel::Normal;
+
+    loop {
+        ticker.tick().await;
+        let usage = match statvfs(Path::new(&log_dir)) {
+            Ok((total_bytes, free_bytes)) => DiskUsage::new(total_bytes, free_bytes, &config),
+            Err(e) => {
+                logger.error(&format!(
+                    "Failed to measure the disk usage of {}: {}",
+                    log_dir, e
+                ));
+                continue;
+            }
+        };
+
+        if usage.level != last_level {
+            let message = format!(
+                "Disk of {} is {:.1}% used, {} bytes free.",
+                log_dir,
+                usage.used_ratio() * 100.0,
+                usage.free_bytes
+            );
+            match usage.level {
+                DiskUsageLevel::Normal => logger.info(&message),
+                DiskUsageLevel::Soft => logger.warn(&message),
+                DiskUsageLevel::Hard => logger.error(&format!(
+                    "{} Taking the action {:?}.",
+                    message, config.hard_action
+                )),
+            }
+            last_level = usage.level;
+        }
+
+        if tx_usage.send(Some(usage)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_usage_level() {
+        let config = DiskUsageConfig::default();
+        assert_eq!(
+            DiskUsage::new(100, 50, &config).level,
+            DiskUsageLevel::Normal
+        );
+        assert_eq!(DiskUsage::new(100, 10, &config).level, DiskUsageLevel::Soft);
+        assert_eq!(DiskUsage::new(100, 2, &config).level, DiskUsageLevel::Hard);
+
+        let dir = tempfile::tempdir().unwrap();
+        let (total_bytes, free_bytes) = statvfs(dir.path()).unwrap();
+        assert!(total_bytes > 0);
+        assert!(free_bytes <= total_bytes);
+    }
+}
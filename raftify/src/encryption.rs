@@ -0,0 +1,100 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+
+use crate::{Error, Result};
+
+const NONCE_SIZE: usize = 12;
+
+/// Provides the AES-256-GCM key encrypting the snapshots and compacted log segments at rest.
+///
+/// Every node of the cluster must provide the same key,
+/// since the snapshots are sent to the followers as they are persisted.
+pub trait KeyProvider: Send + Sync {
+    fn key(&self) -> Result<[u8; 32]>;
+}
+
+impl std::fmt::Debug for dyn KeyProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyProvider")
+    }
+}
+
+/// `KeyProvider` returning a key given up front, e.g. read from a secret file.
+#[derive(Clone)]
+pub struct StaticKeyProvider {
+    key: [u8; 32],
+}
+
+impl StaticKeyProvider {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn key(&self) -> Result<[u8; 32]> {
+        Ok(self.key)
+    }
+}
+
+/// Encrypts `plaintext`, prepending the random nonce to the ciphertext.
+pub(crate) fn encrypt(provider: &dyn KeyProvider, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let key = provider.key()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| Error::EncodingError(format!("Failed to encrypt: {}", e)))?;
+
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(encrypted)
+}
+
+/// Decrypts the data encrypted by `encrypt`, verifying its integrity.
+pub(crate) fn decrypt(provider: &dyn KeyProvider, encrypted: &[u8]) -> Result<Vec<u8>> {
+    if encrypted.len() < NONCE_SIZE {
+        return Err(Error::DecodingError(
+            "Encrypted data is shorter than the nonce".to_owned(),
+        ));
+    }
+
+    let key = provider.key()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_SIZE);
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| Error::DecodingError(format!("Failed to decrypt: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let provider = StaticKeyProvider::new([1; 32]);
+        let encrypted = encrypt(&provider, b"snapshot").unwrap();
+
+        assert_ne!(&encrypted[NONCE_SIZE..], b"snapshot");
+        assert_eq!(decrypt(&provider, &encrypted).unwrap(), b"snapshot");
+    }
+
+    #[test]
+    fn test_reject_wrong_key() {
+        let encrypted = encrypt(&StaticKeyProvider::new([1; 32]), b"snapshot").unwrap();
+        assert!(decrypt(&StaticKeyProvider::new([2; 32]), &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_reject_tampered_data() {
+        let provider = StaticKeyProvider::new([1; 32]);
+        let mut encrypted = encrypt(&provider, b"snapshot").unwrap();
+        *encrypted.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&provider, &encrypted).is_err());
+    }
+}
@@ -17,15 +17,35 @@ pub enum Error {
 
     #[error("Request timeout")]
     Timeout,
+    #[error("Proposal dropped")]
+    ProposalDropped,
+    /// The state machine can't keep up with the committed entries. Retry later.
+    #[error("Node busy, cause: `{0}`")]
+    Busy(String),
+    /// Too many proposals are waiting to be applied, see `AdmissionConfig::max_in_flight`.
+    #[error("Too many proposals in flight, cause: `{0}`")]
+    TooManyInFlight(String),
+    /// The node or the client proposes faster than `AdmissionConfig` allows.
+    #[error("Rate limited, cause: `{0}`")]
+    RateLimited(String),
+    /// The storage can't persist the log anymore, or its disk is used beyond `DiskUsageConfig::hard_threshold`.
+    /// The node still votes and serves the reads. Retry once space is reclaimed.
+    #[error("Storage full, cause: `{0}`")]
+    StorageFull(String),
+    /// The node is drained for a maintenance, see `RaftNode::drain`. Retry on another node.
+    #[error("Node draining, cause: `{0}`")]
+    Draining(String),
     #[error("gRPC error: `{0}`")]
     Grpc(#[from] tonic::transport::Error),
     #[error("Error calling remote procedure: `{0}`")]
-    RemoteCall(#[from] tonic::Status),
+    RemoteCall(#[source] tonic::Status),
 
     #[error("IO error: {0}")]
     Io(#[from] tokio::io::Error),
     #[error("Storage error: `{0}`")]
-    Database(#[from] heed::Error),
+    Database(heed::Error),
+    #[error("Storage corruption detected: `{0}`")]
+    StorageCorruption(String),
     #[error("Unexpected error")]
     Other(#[source] Box<dyn std::error::Error + Sync + Send + 'static>),
     #[error("Unknown error")]
@@ -53,6 +73,83 @@ pub enum SendMessageError {
     TransmissionError(String),
 }
 
+/// Decoding error of a persisted value whose checksum doesn't match its contents,
+/// e.g. after a torn write.
+#[derive(Debug, ThisError)]
+#[error("{0}")]
+pub(crate) struct ChecksumMismatch(pub String);
+
+impl From<heed::Error> for Error {
+    fn from(e: heed::Error) -> Self {
+        match e {
+            heed::Error::Decoding(inner) if inner.is::<ChecksumMismatch>() => {
+                Self::StorageCorruption(inner.to_string())
+            }
+            e => Self::Database(e),
+        }
+    }
+}
+
+// Metadata key telling which overload error a RESOURCE_EXHAUSTED status stands for.
+const OVERLOAD_HEADER: &str = "x-raftify-overload";
+
+impl Error {
+    /// Whether the storage failed to write since its map or its disk is full.
+    pub(crate) fn is_storage_full(&self) -> bool {
+        match self {
+            Self::Database(heed::Error::Mdb(heed::MdbError::MapFull)) => true,
+            Self::Database(heed::Error::Mdb(heed::MdbError::Other(code))) => *code == libc::ENOSPC,
+            Self::Database(heed::Error::Io(e)) | Self::Io(e) => {
+                e.raw_os_error() == Some(libc::ENOSPC)
+            }
+            _ => false,
+        }
+    }
+
+    /// Converts the errors rejecting the requests of an overloaded node to RESOURCE_EXHAUSTED,
+    /// which `From<tonic::Status>` converts back on the other side.
+    pub(crate) fn overload_status(&self) -> Option<tonic::Status> {
+        let (kind, cause) = match self {
+            Self::Busy(cause) => ("busy", cause),
+            Self::TooManyInFlight(cause) => ("too_many_in_flight", cause),
+            Self::RateLimited(cause) => ("rate_limited", cause),
+            Self::StorageFull(cause) => ("storage_full", cause),
+            Self::Draining(cause) => ("draining", cause),
+            _ => return None,
+        };
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        metadata.insert(
+            OVERLOAD_HEADER,
+            tonic::metadata::MetadataValue::from_static(kind),
+        );
+        Some(tonic::Status::with_metadata(
+            tonic::Code::ResourceExhausted,
+            cause.clone(),
+            metadata,
+        ))
+    }
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        if status.code() != tonic::Code::ResourceExhausted {
+            return Self::RemoteCall(status);
+        }
+        let cause = status.message().to_owned();
+        match status
+            .metadata()
+            .get(OVERLOAD_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some("too_many_in_flight") => Self::TooManyInFlight(cause),
+            Some("rate_limited") => Self::RateLimited(cause),
+            Some("storage_full") => Self::StorageFull(cause),
+            Some("draining") => Self::Draining(cause),
+            _ => Self::Busy(cause),
+        }
+    }
+}
+
 impl From<prost::DecodeError> for Error {
     fn from(e: prost::DecodeError) -> Self {
         Self::Other(Box::new(e))
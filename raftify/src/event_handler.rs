@@ -0,0 +1,48 @@
+use crate::{raft::eraftpb::ConfState, Error};
+
+/// Callbacks invoked on cluster transitions of the local node.
+///
+/// Handlers are called from the Raft loop, so they should return quickly.
+/// Spawn a task for anything that may block.
+/// Every method has an empty default implementation, so implement only what you need.
+pub trait RaftEventHandler: Send + Sync {
+    /// Called when this node becomes the leader of `term`.
+    fn on_become_leader(&self, _term: u64) {}
+
+    /// Called when this node stops being the leader.
+    /// `leader_id` is 0 if the new leader is not known yet.
+    fn on_step_down(&self, _term: u64, _leader_id: u64) {}
+
+    /// Called when this node steps down because it didn't hear from a quorum of voters
+    /// for an election timeout, with `raft_config.check_quorum` enabled. Followed by `on_step_down`.
+    /// Repeated calls hint at a flapping network between the leader and the rest of the cluster.
+    fn on_quorum_lost(&self, _term: u64) {}
+
+    /// Called after a configuration change entry is applied.
+    fn on_membership_change(&self, _conf_state: &ConfState) {}
+
+    /// Called after a snapshot received from the leader is restored.
+    fn on_snapshot_applied(&self, _index: u64, _term: u64) {}
+
+    /// Called before this node creates a snapshot at `index`.
+    fn on_before_snapshot(&self, _index: u64, _term: u64) {}
+
+    /// Called after this node created a snapshot at `index`.
+    fn on_after_snapshot(&self, _index: u64, _term: u64) {}
+
+    /// Called before the log entries in `first_index..index` are compacted.
+    /// The entries can still be read from the storage here, e.g. to archive them.
+    fn on_before_compaction(&self, _first_index: u64, _index: u64) {}
+
+    /// Called after the log entries in `first_index..index` are compacted.
+    fn on_after_compaction(&self, _first_index: u64, _index: u64) {}
+
+    /// Called when applying an entry fails, or right before the Raft loop quits with an error.
+    fn on_error(&self, _error: &Error) {}
+}
+
+impl std::fmt::Debug for dyn RaftEventHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RaftEventHandler")
+    }
+}
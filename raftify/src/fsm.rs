@@ -0,0 +1,205 @@
+//! Ready-made state machines, to get a replicated service running before writing one's own.
+
+use async_trait::async_trait;
+use bincode::{deserialize, serialize};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{AbstractLogEntry, AbstractStateMachine, AbstractStateMachineQuery, KeyRange, Result};
+
+/// Log entry of `HashStore`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashStoreEntry {
+    Insert { key: String, value: String },
+    Remove { key: String },
+}
+
+impl AbstractLogEntry for HashStoreEntry {
+    fn encode(&self) -> Result<Vec<u8>> {
+        Ok(serialize(self)?)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(deserialize(bytes)?)
+    }
+}
+
+/// Replicated in-memory key-value store.
+///
+/// Applying an entry returns the previous value of its key, encoded with bincode as an `Option<String>`.
+/// The clones share the same map, so keep one to read the values the node applies.
+/// The keys can be moved between raft groups, see `Router::move_range`,
+/// and a key is queried from another node with `RaftNode::query`.
+///
+/// ```ignore
+/// let store = HashStore::new();
+/// let raft = Raft::bootstrap(node_id, raft_addr, log_storage, store.clone(), config, logger)?;
+/// tokio::spawn(raft.clone().run());
+///
+/// raft.propose(HashStoreEntry::Insert { key: "a".into(), value: "1".into() }.encode()?).await?;
+/// assert_eq!(store.get("a"), Some("1".to_owned()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HashStore(Arc<RwLock<HashMap<String, String>>>);
+
+impl HashStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.0.read().get(key).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.read().is_empty()
+    }
+}
+
+#[async_trait]
+impl AbstractStateMachine for HashStore {
+    async fn apply(&mut self, log_entry: Vec<u8>) -> Result<Vec<u8>> {
+        let previous = match HashStoreEntry::decode(&log_entry)? {
+            HashStoreEntry::Insert { key, value } => self.0.write().insert(key, value),
+            HashStoreEntry::Remove { key } => self.0.write().remove(&key),
+        };
+        Ok(serialize(&previous)?)
+    }
+
+    async fn apply_batch(&mut self, log_entries: Vec<Vec<u8>>) -> Vec<Result<Vec<u8>>> {
+        let mut map = self.0.write();
+        log_entries
+            .iter()
+            .map(|log_entry| {
+                let previous = match HashStoreEntry::decode(log_entry)? {
+                    HashStoreEntry::Insert { key, value } => map.insert(key, value),
+                    HashStoreEntry::Remove { key } => map.remove(&key),
+                };
+                Ok(serialize(&previous)?)
+            })
+            .collect()
+    }
+
+    async fn snapshot(&self) -> Result<Vec<u8>> {
+        Ok(serialize(&*self.0.read())?)
+    }
+
+    async fn restore(&mut self, snapshot: Vec<u8>) -> Result<()> {
+        *self.0.write() = deserialize(&snapshot)?;
+        Ok(())
+    }
+
+    fn key_of(&self, log_entry: &[u8]) -> Option<Vec<u8>> {
+        match HashStoreEntry::decode(log_entry).ok()? {
+            HashStoreEntry::Insert { key, .. } | HashStoreEntry::Remove { key } => {
+                Some(key.into_bytes())
+            }
+        }
+    }
+
+    async fn snapshot_range(&self, range: &KeyRange) -> Result<Vec<u8>> {
+        let entries: HashMap<&String, &String> = self
+            .0
+            .read()
+            .iter()
+            .filter(|(key, _)| range.contains(key.as_bytes()))
+            .collect();
+        Ok(serialize(&entries)?)
+    }
+
+    async fn restore_range(&mut self, snapshot: Vec<u8>) -> Result<()> {
+        let entries: HashMap<String, String> = deserialize(&snapshot)?;
+        self.0.write().extend(entries);
+        Ok(())
+    }
+
+    async fn remove_range(&mut self, range: &KeyRange) -> Result<()> {
+        self.0
+            .write()
+            .retain(|key, _| !range.contains(key.as_bytes()));
+        Ok(())
+    }
+
+    async fn query_encoded(&self, query: &[u8]) -> Result<Vec<u8>> {
+        self.answer_encoded(query).await
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        Ok(serialize(&*self.0.read())?)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(Self(Arc::new(RwLock::new(deserialize(bytes)?))))
+    }
+}
+
+/// Queries the value of a key.
+#[async_trait]
+impl AbstractStateMachineQuery for HashStore {
+    type Query = String;
+    type QueryResponse = Option<String>;
+
+    async fn query(&self, key: String) -> Result<Option<String>> {
+        Ok(self.get(&key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(key: &str, value: &str) -> Vec<u8> {
+        HashStoreEntry::Insert {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        }
+        .encode()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hash_store() {
+        let mut store = HashStore::new();
+        let previous = store.apply(insert("a", "1")).await.unwrap();
+        assert_eq!(deserialize::<Option<String>>(&previous).unwrap(), None);
+        let previous = store.apply(insert("a", "2")).await.unwrap();
+        assert_eq!(
+            deserialize::<Option<String>>(&previous).unwrap(),
+            Some("1".to_owned())
+        );
+        let results = store.apply_batch(vec![insert("m", "3"), vec![0xff]]).await;
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        let snapshot = store.snapshot().await.unwrap();
+        let mut restored = HashStore::new();
+        restored.restore(snapshot).await.unwrap();
+        assert_eq!(restored.get("a"), Some("2".to_owned()));
+        assert_eq!(restored.len(), 2);
+
+        let answer = restored
+            .query_encoded(&serialize("a").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            deserialize::<Option<String>>(&answer).unwrap(),
+            Some("2".to_owned())
+        );
+
+        // Moves the keys from "m" to another store.
+        let range = KeyRange::new(b"m".to_vec(), None);
+        assert_eq!(store.key_of(&insert("z", "4")), Some(b"z".to_vec()));
+        let moved = store.snapshot_range(&range).await.unwrap();
+        let mut other = HashStore::new();
+        other.restore_range(moved).await.unwrap();
+        store.remove_range(&range).await.unwrap();
+        assert_eq!(other.get("m"), Some("3".to_owned()));
+        assert_eq!(store.get("m"), None);
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,419 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::time::timeout;
+
+use crate::{
+    create_client_with_options, raft::logger::Logger, raft_service, AbstractLogEntry,
+    AbstractStableStorage, AbstractStateMachine, ClientOptions, Error, GossipConfig, RaftNode,
+    Result,
+};
+
+/// State of a node as known through the gossip, from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GossipMemberState {
+    Alive,
+    Suspect,
+    Dead,
+}
+
+/// A node as known through the gossip.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GossipMember {
+    pub addr: String,
+    /// 0 until the node is known by its node id.
+    pub node_id: u64,
+    /// Bumped by the node itself whenever it refutes a suspicion or its raft state changes,
+    /// so that the latest news about a node override the older ones.
+    pub incarnation: u64,
+    pub state: GossipMemberState,
+    /// Leader known by the node, 0 if none.
+    pub leader_id: u64,
+    pub term: u64,
+}
+
+impl GossipMember {
+    fn new(addr: String, node_id: u64, incarnation: u64) -> Self {
+        Self {
+            addr,
+            node_id,
+            incarnation,
+            state: GossipMemberState::Alive,
+            leader_id: 0,
+            term: 0,
+        }
+    }
+
+    /// Whether `self` is newer than `other`, both being about the same node.
+    fn overrides(&self, other: &Self) -> bool {
+        self.incarnation > other.incarnation
+            || (self.incarnation == other.incarnation && self.state > other.state)
+    }
+}
+
+struct GossipState {
+    members: HashMap<String, GossipMember>,
+    suspected_since: HashMap<String, Instant>,
+    // Round-robin position of the next member to probe.
+    next_probe: usize,
+}
+
+/// SWIM-style gossip layer through which the nodes discover each other and the leader,
+/// without a static list of peers.
+///
+/// Every `GossipConfig::interval`, each node probes another one, and both exchange the members they know.
+/// A node which doesn't answer is probed indirectly through `indirect_probes` other nodes,
+/// then suspected, and considered dead unless it refutes the suspicion within `suspicion_timeout`.
+/// The gossip only informs: the raft membership stays the source of truth for the voting members.
+#[derive(Clone)]
+pub struct Gossip {
+    config: GossipConfig,
+    addr: String,
+    state: Arc<Mutex<GossipState>>,
+}
+
+impl Gossip {
+    pub(crate) fn new(config: GossipConfig, addr: String, node_id: u64) -> Self {
+        let mut members = HashMap::new();
+        for seed in config.seeds.iter().filter(|seed| **seed != addr) {
+            // Seeds start from the lowest incarnation, so that any news about them override it.
+            members.insert(seed.clone(), GossipMember::new(seed.clone(), 0, 0));
+        }
+        members.insert(addr.clone(), GossipMember::new(addr.clone(), node_id, 1));
+
+        Self {
+            config,
+            addr,
+            state: Arc::new(Mutex::new(GossipState {
+                members,
+                suspected_since: HashMap::new(),
+                next_probe: 0,
+            })),
+        }
+    }
+
+    /// Returns the known nodes, including this one, ordered by address.
+    pub fn members(&self) -> Vec<GossipMember> {
+        let mut members: Vec<_> = self.state.lock().members.values().cloned().collect();
+        members.sort_by(|a, b| a.addr.cmp(&b.addr));
+        members
+    }
+
+    /// Returns the leader of the highest term reported by the live nodes, if it is known.
+    pub fn leader(&self) -> Option<GossipMember> {
+        let state = self.state.lock();
+        let members = &state.members;
+        let live = || {
+            members
+                .values()
+                .filter(|member| member.state != GossipMemberState::Dead)
+        };
+
+        let leader_id = live()
+            .filter(|member| member.leader_id != 0)
+            .max_by_key(|member| member.term)?
+            .leader_id;
+        live().find(|member| member.node_id == leader_id).cloned()
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.members())?)
+    }
+
+    /// Merges the members gossiped by another node, refuting the suspicions about this node.
+    fn merge(&self, members: Vec<GossipMember>) {
+        let mut state = self.state.lock();
+        for member in members {
+            if member.addr == self.addr {
+                let local = state.members.get_mut(&self.addr).unwrap();
+                if member.state != GossipMemberState::Alive
+                    && member.incarnation >= local.incarnation
+                {
+                    local.incarnation = member.incarnation + 1;
+                }
+                continue;
+            }
+
+            let is_newer = state
+                .members
+                .get(&member.addr)
+                .map_or(true, |known| member.overrides(known));
+            if !is_newer {
+                continue;
+            }
+
+            if member.state == GossipMemberState::Suspect {
+                state
+                    .suspected_since
+                    .entry(member.addr.clone())
+                    .or_insert_with(Instant::now);
+            } else {
+                state.suspected_since.remove(&member.addr);
+            }
+            state.members.insert(member.addr.clone(), member);
+        }
+    }
+
+    /// Updates the raft state this node gossips about itself.
+    fn update_local(&self, node_id: u64, leader_id: u64, term: u64) {
+        let mut state = self.state.lock();
+        let local = state.members.get_mut(&self.addr).unwrap();
+        if (local.node_id, local.leader_id, local.term) != (node_id, leader_id, term) {
+            local.node_id = node_id;
+            local.leader_id = leader_id;
+            local.term = term;
+            local.incarnation += 1;
+        }
+    }
+
+    fn suspect(&self, addr: &str) {
+        let state = &mut *self.state.lock();
+        if let Some(member) = state.members.get_mut(addr) {
+            if member.state == GossipMemberState::Alive {
+                member.state = GossipMemberState::Suspect;
+                state
+                    .suspected_since
+                    .insert(addr.to_owned(), Instant::now());
+            }
+        }
+    }
+
+    /// Declares dead the suspected nodes which didn't refute the suspicion in time.
+    fn expire_suspects(&self) -> Vec<String> {
+        let suspicion_timeout = Duration::from_secs_f32(self.config.suspicion_timeout);
+        let mut state = self.state.lock();
+        let expired: Vec<String> = state
+            .suspected_since
+            .iter()
+            .filter(|(_, since)| since.elapsed() >= suspicion_timeout)
+            .map(|(addr, _)| addr.clone())
+            .collect();
+
+        for addr in expired.iter() {
+            state.suspected_since.remove(addr);
+            if let Some(member) = state.members.get_mut(addr) {
+                member.state = GossipMemberState::Dead;
+            }
+        }
+        expired
+    }
+
+    /// Returns the next node to probe in round-robin order, along with the nodes to probe it indirectly.
+    fn next_targets(&self) -> Option<(String, Vec<String>)> {
+        let mut state = self.state.lock();
+        let mut candidates: Vec<String> = state
+            .members
+            .values()
+            .filter(|member| member.addr != self.addr && member.state != GossipMemberState::Dead)
+            .map(|member| member.addr.clone())
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.sort();
+
+        let index = state.next_probe % candidates.len();
+        state.next_probe = state.next_probe.wrapping_add(1);
+        let target = candidates.remove(index);
+
+        candidates.rotate_left(index % candidates.len().max(1));
+        candidates.truncate(self.config.indirect_probes);
+        Some((target, candidates))
+    }
+
+    /// Sends the known members to `addr`, asking it to probe `probe_target` if not empty,
+    /// and merges the members it answers with.
+    async fn probe(
+        &self,
+        addr: &str,
+        probe_target: &str,
+        wait: Duration,
+        options: &ClientOptions,
+    ) -> Result<raft_service::GossipResponse> {
+        let call = async {
+            let addr = tokio::net::lookup_host(addr)
+                .await?
+                .next()
+                .ok_or_else(|| Error::Rejected(format!("{} resolved to no address", addr)))?;
+            let mut client = create_client_with_options(addr, options).await?;
+            let response = client
+                .gossip(raft_service::GossipArgs {
+                    members: self.encode()?,
+                    probe_target: probe_target.to_owned(),
+                })
+                .await?
+                .into_inner();
+            Ok::<_, Error>(response)
+        };
+
+        let response = timeout(wait, call).await.map_err(|_| Error::Timeout)??;
+        self.merge(bincode::deserialize(&response.members)?);
+        Ok(response)
+    }
+
+    /// Handles the gossip of another node, probing `args.probe_target` on its behalf if asked to.
+    pub(crate) async fn handle(
+        &self,
+        args: raft_service::GossipArgs,
+        options: &ClientOptions,
+    ) -> Result<raft_service::GossipResponse> {
+        self.merge(bincode::deserialize(&args.members)?);
+
+        let acked = args.probe_target.is_empty()
+            || self
+                .probe(&args.probe_target, "", self.probe_timeout(), options)
+                .await
+                .is_ok();
+
+        Ok(raft_service::GossipResponse {
+            members: self.encode()?,
+            acked,
+        })
+    }
+
+    /// Announces to the live members that this node leaves, so that they consider it dead right away
+    /// instead of suspecting it first. It refutes its death as usual if it comes back.
+    pub(crate) async fn leave(&self, options: &ClientOptions) {
+        {
+            let mut state = self.state.lock();
+            let local = state.members.get_mut(&self.addr).unwrap();
+            local.state = GossipMemberState::Dead;
+            local.incarnation += 1;
+        }
+
+        let live: Vec<String> = self
+            .members()
+            .into_iter()
+            .filter(|member| member.addr != self.addr && member.state != GossipMemberState::Dead)
+            .map(|member| member.addr)
+            .collect();
+        for addr in live {
+            let _ = self.probe(&addr, "", self.probe_timeout(), options).await;
+        }
+    }
+
+    fn probe_timeout(&self) -> Duration {
+        Duration::from_secs_f32(self.config.probe_timeout)
+    }
+
+    /// Probes a node every `interval`, until the task is aborted.
+    pub(crate) async fn run<
+        LogEntry: AbstractLogEntry + Send + 'static,
+        LogStorage: AbstractStableStorage + Send + Clone + 'static,
+        FSM: AbstractStateMachine + Clone + Send + 'static,
+    >(
+        self,
+        raft_node: RaftNode<LogEntry, LogStorage, FSM>,
+        options: ClientOptions,
+        logger: Arc<dyn Logger>,
+    ) {
+        let mut interval = tokio::time::interval(Duration::from_secs_f32(self.config.interval));
+
+        loop {
+            interval.tick().await;
+
+            if let (Ok(node_id), Ok(leader_id), Ok(term)) = (
+                raft_node.get_id().await,
+                raft_node.get_leader_id().await,
+                raft_node.get_term().await,
+            ) {
+                self.update_local(node_id, leader_id, term);
+            }
+
+            for addr in self.expire_suspects() {
+                logger.warn(&format!("Gossip: {} is considered dead.", addr));
+            }
+
+            let (target, helpers) = match self.next_targets() {
+                Some(targets) => targets,
+                None => continue,
+            };
+
+            if self
+                .probe(&target, "", self.probe_timeout(), &options)
+                .await
+                .is_ok()
+            {
+                continue;
+            }
+
+            // The helpers probe the target themselves, so they get twice the timeout.
+            let mut acked = false;
+            for helper in helpers {
+                if let Ok(response) = self
+                    .probe(&helper, &target, self.probe_timeout() * 2, &options)
+                    .await
+                {
+                    if response.acked {
+                        acked = true;
+                        break;
+                    }
+                }
+            }
+
+            if !acked {
+                logger.debug(&format!(
+                    "Gossip: {} didn't answer. Suspecting it...",
+                    target
+                ));
+                self.suspect(&target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gossip(addr: &str, node_id: u64, seeds: &[&str]) -> Gossip {
+        let config = GossipConfig {
+            seeds: seeds.iter().map(|seed| seed.to_string()).collect(),
+            ..Default::default()
+        };
+        Gossip::new(config, addr.to_owned(), node_id)
+    }
+
+    #[test]
+    fn test_merge_newer_members() {
+        let node_1 = gossip("127.0.0.1:60061", 1, &["127.0.0.1:60062"]);
+        let node_2 = gossip("127.0.0.1:60062", 2, &[]);
+        node_2.update_local(2, 2, 3);
+
+        node_1.merge(node_2.members());
+        let leader = node_1.leader().unwrap();
+        assert_eq!(leader.node_id, 2);
+        assert_eq!(leader.addr, "127.0.0.1:60062");
+
+        // Older news about node 2 are ignored.
+        let mut stale = leader.clone();
+        stale.incarnation = 0;
+        stale.state = GossipMemberState::Dead;
+        node_1.merge(vec![stale]);
+        assert_eq!(node_1.leader().unwrap(), leader);
+    }
+
+    #[test]
+    fn test_refute_suspicion() {
+        let node_1 = gossip("127.0.0.1:60061", 1, &[]);
+        let node_2 = gossip("127.0.0.1:60062", 2, &[]);
+
+        node_2.merge(node_1.members());
+        node_2.suspect("127.0.0.1:60061");
+        node_1.merge(node_2.members());
+
+        // Node 1 refuted the suspicion with a higher incarnation, which overrides it on node 2.
+        node_2.merge(node_1.members());
+        let member = node_2
+            .members()
+            .into_iter()
+            .find(|member| member.node_id == 1)
+            .unwrap();
+        assert_eq!(member.state, GossipMemberState::Alive);
+        assert_eq!(member.incarnation, 2);
+    }
+}
@@ -0,0 +1,223 @@
+use hyper::{
+    body::to_bytes,
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{Instrument, Span};
+
+use crate::{
+    create_client_with_options,
+    raft::logger::Logger,
+    raft_service::ProposeArgs,
+    request::server_request_message::ServerRequestMsg,
+    response::server_response_message::{ProposeResponseResult, ServerResponseMsg},
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, Config, Error, Result,
+};
+
+/// REST+JSON front of the requests otherwise served by `RaftServer` over gRPC.
+///
+/// - `GET /status`: debugging information of the node
+/// - `GET /peers`: the cluster members
+/// - `POST /propose`: proposes the request body, which should be an encoded log entry
+pub struct HttpGateway<
+    LogEntry: AbstractLogEntry,
+    LogStorage: AbstractStableStorage + 'static,
+    FSM: AbstractStateMachine,
+> {
+    tx: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
+    config: Config,
+    logger: Arc<dyn Logger>,
+}
+
+impl<
+        LogEntry: AbstractLogEntry,
+        LogStorage: AbstractStableStorage + 'static,
+        FSM: AbstractStateMachine,
+    > Clone for HttpGateway<LogEntry, LogStorage, FSM>
+{
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            config: self.config.clone(),
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Send + Sync + 'static,
+        FSM: AbstractStateMachine + 'static,
+    > HttpGateway<LogEntry, LogStorage, FSM>
+{
+    pub fn new(
+        tx: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
+        config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Self {
+        Self { tx, config, logger }
+    }
+
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        self.logger
+            .debug(&format!("HttpGateway starts to listen on \"{}\"...", addr));
+
+        let make_service = make_service_fn(move |_| {
+            let gateway = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let gateway = gateway.clone();
+                    async move { Ok::<_, Infallible>(gateway.handle(request).await) }
+                }))
+            }
+        });
+
+        Server::bind(&addr)
+            .serve(make_service)
+            .await
+            .map_err(|e| Error::Other(Box::new(e)))
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let result = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/status") => self.status().await,
+            (&Method::GET, "/peers") => self.peers().await,
+            (&Method::POST, "/propose") => {
+                self.propose(request)
+                    .instrument(tracing::info_span!("HttpGateway::propose"))
+                    .await
+            }
+            _ => Ok(json_response(
+                StatusCode::NOT_FOUND,
+                json!({ "error": "Not found" }),
+            )),
+        };
+
+        result.unwrap_or_else(|e| {
+            let status = match e {
+                Error::Busy(_) | Error::TooManyInFlight(_) | Error::Draining(_) => {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }
+                Error::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+                Error::StorageFull(_) => StatusCode::INSUFFICIENT_STORAGE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            json_response(status, json!({ "error": e.to_string() }))
+        })
+    }
+
+    async fn request(
+        &self,
+        message: impl FnOnce(
+            oneshot::Sender<ServerResponseMsg>,
+        ) -> ServerRequestMsg<LogEntry, LogStorage, FSM>,
+    ) -> Result<ServerResponseMsg> {
+        let (tx_msg, rx_msg) = oneshot::channel();
+        self.tx
+            .send(message(tx_msg))
+            .await
+            .map_err(|_| Error::Other("RaftNode is not running".into()))?;
+        Ok(rx_msg.await?)
+    }
+
+    async fn status(&self) -> Result<Response<Body>> {
+        match self
+            .request(|tx_msg| ServerRequestMsg::DebugNode { tx_msg })
+            .await?
+        {
+            ServerResponseMsg::DebugNode { result_json } => {
+                let status: Value = serde_json::from_str(&result_json)
+                    .map_err(|e| Error::DecodingError(e.to_string()))?;
+                Ok(json_response(StatusCode::OK, status))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn peers(&self) -> Result<Response<Body>> {
+        match self
+            .request(|tx_msg| ServerRequestMsg::GetPeers { tx_msg })
+            .await?
+        {
+            ServerResponseMsg::GetPeers { peers } => {
+                let peers: Value = serde_json::from_str(&peers.to_json())
+                    .map_err(|e| Error::DecodingError(e.to_string()))?;
+                Ok(json_response(StatusCode::OK, peers))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn propose(&self, request: Request<Body>) -> Result<Response<Body>> {
+        let proposal = to_bytes(request.into_body())
+            .await
+            .map_err(|e| Error::Other(Box::new(e)))?;
+
+        let result = match self
+            .request(|tx_msg| ServerRequestMsg::Propose {
+                proposals: vec![proposal.clone()],
+                session: None,
+                span: Span::current(),
+                tx_msg,
+            })
+            .await?
+        {
+            ServerResponseMsg::Propose { result } => result,
+            _ => unreachable!(),
+        };
+
+        match result {
+            ProposeResponseResult::Success(outcome) => Ok(json_response(
+                StatusCode::OK,
+                json!({ "index": outcome.index, "term": outcome.term, "data": outcome.data }),
+            )),
+            ProposeResponseResult::Error(
+                e @ (Error::Busy(_)
+                | Error::TooManyInFlight(_)
+                | Error::RateLimited(_)
+                | Error::StorageFull(_)
+                | Error::Draining(_)),
+            ) => Err(e),
+            ProposeResponseResult::Error(e) => Ok(json_response(
+                StatusCode::BAD_REQUEST,
+                json!({ "error": e.to_string() }),
+            )),
+            ProposeResponseResult::WrongLeader { leader_addr, .. } => {
+                let options = self.config.client_options();
+                let reply = options
+                    .retry
+                    .retry_proposal(false, || async {
+                        let mut client = create_client_with_options(&leader_addr, &options).await?;
+                        let reply = client
+                            .propose(ProposeArgs::new(proposal.clone(), None))
+                            .await?;
+                        Ok(reply.into_inner())
+                    })
+                    .await?;
+
+                if !reply.error.is_empty() {
+                    return Ok(json_response(
+                        StatusCode::BAD_REQUEST,
+                        json!({ "error": String::from_utf8_lossy(&reply.error) }),
+                    ));
+                }
+                Ok(json_response(
+                    StatusCode::OK,
+                    json!({ "index": reply.index, "term": reply.term, "data": reply.data }),
+                ))
+            }
+        }
+    }
+}
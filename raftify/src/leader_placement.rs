@@ -0,0 +1,61 @@
+use crate::{Peers, ZONE_LABEL};
+
+/// Decides which node should hold the leadership, checked by the leader after every ready loop.
+///
+/// Leadership landing on another node, e.g. after an election, is handed over to the node returned
+/// by the policy. A manual `RaftNode::transfer_leader` to a node the policy doesn't prefer is undone as well.
+pub trait LeaderPlacementPolicy: Send + Sync {
+    /// Returns the node to transfer the leadership to, or `None` to keep it on `leader_id`.
+    /// `candidates` are the other voters which are active and up to date with the leader's log.
+    fn transferee(&self, leader_id: u64, candidates: &[u64], peers: &Peers) -> Option<u64>;
+}
+
+/// Prefers the leaders labeled with the given zone. A leader elected in another zone
+/// transfers the leadership to a candidate of the zone, and keeps it if there is none.
+#[derive(Debug, Clone)]
+pub struct PreferredZone(pub String);
+
+impl PreferredZone {
+    fn contains(&self, node_id: u64, peers: &Peers) -> bool {
+        peers
+            .get(&node_id)
+            .and_then(|peer| peer.labels.get(ZONE_LABEL))
+            .is_some_and(|zone| *zone == self.0)
+    }
+}
+
+impl LeaderPlacementPolicy for PreferredZone {
+    fn transferee(&self, leader_id: u64, candidates: &[u64], peers: &Peers) -> Option<u64> {
+        if self.contains(leader_id, peers) {
+            return None;
+        }
+        candidates
+            .iter()
+            .copied()
+            .find(|node_id| self.contains(*node_id, peers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_zone() {
+        let mut peers = Peers::with_empty();
+        for (node_id, zone) in [(1, "zone-a"), (2, "zone-b"), (3, "zone-b")] {
+            peers.add_peer(node_id, format!("127.0.0.1:6006{}", node_id), None);
+            peers
+                .get_mut(&node_id)
+                .unwrap()
+                .labels
+                .insert(ZONE_LABEL.to_owned(), zone.to_owned());
+        }
+
+        let policy = PreferredZone("zone-b".to_owned());
+        assert_eq!(policy.transferee(1, &[2, 3], &peers), Some(2));
+        assert_eq!(policy.transferee(1, &[3], &peers), Some(3));
+        assert_eq!(policy.transferee(1, &[], &peers), None);
+        assert_eq!(policy.transferee(2, &[1, 3], &peers), None);
+    }
+}
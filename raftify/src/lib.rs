@@ -1,46 +1,115 @@
+mod admission;
+mod apply_executor;
+mod auth;
+mod backoff;
+mod backup;
+mod cluster_uuid;
 mod config;
+mod config_builder;
+mod config_env;
+mod config_file;
+mod delta_snapshot;
+mod disk_usage;
+mod encryption;
 mod error;
+mod event_handler;
 mod formatter;
+mod gossip;
+mod leader_placement;
 mod log_entry;
+mod membership_history;
 mod peer;
 mod peers;
+mod raft_admin_server;
 mod raft_bootstrapper;
 mod raft_client;
+mod raft_group;
 mod raft_server;
+mod router;
+mod runtime_config;
+mod session;
+mod shard;
+mod snapshot_store;
+mod snapshot_stream;
 mod state_machine;
+mod statefulset;
 mod storage;
+mod systemd;
+mod telemetry;
+mod tls;
+mod tracing_logger;
 mod utils;
 
 mod request;
 mod response;
 
 pub mod cluster_join_ticket;
+pub mod raft_admin_service;
 pub mod raft_node;
 pub mod raft_service;
 
 pub use {
-    async_trait::async_trait, bincode, formatter::CustomFormatter, raft,
-    raft::Config as RaftConfig, tonic, tonic::transport::Channel,
+    async_trait::async_trait, bincode, bytes, formatter::CustomFormatter, raft,
+    raft::Config as RaftConfig, raft::ReadOnlyOption, tonic, tonic::transport::Channel,
 };
 
 pub use crate::{
+    admission::CLIENT_ID_HEADER,
+    apply_executor::{ApplyExecutor, BlockingExecutor},
+    auth::{AdminAuthorizer, AuthConfig, AuthInterceptor},
     cluster_join_ticket::ClusterJoinTicket,
-    config::Config,
+    cluster_uuid::ClusterUuid,
+    config::{
+        AdmissionConfig, CircuitBreakerConfig, CompactedLogRetention, Config, DeadVoterRemoval,
+        DiskFullAction, DiskUsageConfig, GossipConfig, GrpcConfig, LmdbSyncMode, RejoinConfig,
+        RetryPolicy, RetryableError,
+    },
+    config_builder::ConfigBuilder,
+    disk_usage::{DiskUsage, DiskUsageLevel},
+    encryption::{KeyProvider, StaticKeyProvider},
     error::{Error, Result},
+    event_handler::RaftEventHandler,
+    gossip::{Gossip, GossipMember, GossipMemberState},
+    leader_placement::{LeaderPlacementPolicy, PreferredZone},
     log_entry::AbstractLogEntry,
-    peer::Peer,
+    membership_history::{
+        MembershipChange, MembershipChangeRecord, MembershipChangeType, NodeTombstone,
+    },
+    peer::{ConnectionState, Peer, PeerConnection, PeerLabels, RACK_LABEL, ZONE_LABEL},
     peers::Peers,
     raft_bootstrapper::Raft,
-    raft_client::create_client,
-    raft_node::{role::InitialRole, utils::format_debugging_info, RaftNode},
+    raft_client::{
+        create_client, create_client_with_options, ChannelPool, ClientOptions, RaftClient,
+    },
+    raft_group::RaftGroupManager,
+    raft_node::{
+        role::InitialRole,
+        status::{NodeRole, PeerProgress, RaftStatus, ReplicationState},
+        utils::format_debugging_info,
+        RaftNode,
+    },
     raft_service::raft_service_client::RaftServiceClient,
     request::common::confchange_request::ConfChangeRequest,
-    state_machine::AbstractStateMachine,
-    storage::{StableStorage, StorageType},
+    response::server_response_message::{
+        ConfChangeResponseResult, ProposeOutcome, ProposeResponseResult, ResponseResult,
+    },
+    router::{HashPartitioner, KeyPartitioner, RangePartitioner, Router},
+    runtime_config::RuntimeConfig,
+    session::ProposalSession,
+    shard::KeyRange,
+    snapshot_store::{FileSnapshotStore, SnapshotStore},
+    state_machine::{AbstractStateMachine, AbstractStateMachineQuery, QueryConsistency},
+    statefulset::StatefulSetTopology,
+    storage::{AbstractStableStorage, NodeIdentity, StorageStats, StorageType},
+    tls::TlsConfig,
+    tracing_logger::TracingLogger,
 };
 
+// Kept for backward compatibility. Prefer `AbstractStableStorage`.
+pub use storage::AbstractStableStorage as StableStorage;
+
 #[cfg(feature = "heed_storage")]
-pub use storage::heed_storage::HeedStorage;
+pub use storage::heed_storage::{ForceNewClusterReport, HeedStorage, ScrubReport, StorageDamage};
 
 #[cfg(feature = "inmemory_storage")]
 pub use storage::inmemory_storage::MemStorage;
@@ -48,4 +117,12 @@ pub use storage::inmemory_storage::MemStorage;
 #[cfg(feature = "rocksdb_storage")]
 pub use storage::rocksdb_storage::RocksDBStorage;
 
+#[cfg(feature = "hash_store")]
+pub mod fsm;
+
+#[cfg(feature = "http_gateway")]
+mod http_gateway;
+#[cfg(feature = "http_gateway")]
+pub use http_gateway::HttpGateway;
+
 pub(crate) use crate::utils::macros::macro_utils;
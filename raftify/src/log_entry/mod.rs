@@ -1,3 +1,5 @@
+use bytes::Bytes;
+
 use crate::Result;
 
 pub trait AbstractLogEntry: Clone + Send + Sync {
@@ -5,4 +7,18 @@ pub trait AbstractLogEntry: Clone + Send + Sync {
     fn decode(bytes: &[u8]) -> Result<Self>
     where
         Self: Sized;
+
+    /// Encodes the entry into a buffer shared, instead of copied, on its way to raft.
+    fn encode_bytes(&self) -> Result<Bytes> {
+        Ok(self.encode()?.into())
+    }
+
+    /// Decodes the entry from a shared buffer.
+    /// Override it to keep slices of `bytes` in the entry, e.g. for large payloads, instead of copying them.
+    fn decode_bytes(bytes: Bytes) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::decode(&bytes)
+    }
 }
@@ -0,0 +1,238 @@
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+use std::{mem::size_of, net::SocketAddr};
+
+use crate::{
+    raft::eraftpb::{ConfChangeType, ConfChangeV2},
+    Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipChangeType {
+    AddNode,
+    AddLearnerNode,
+    RemoveNode,
+}
+
+impl From<ConfChangeType> for MembershipChangeType {
+    fn from(change_type: ConfChangeType) -> Self {
+        match change_type {
+            ConfChangeType::AddNode => MembershipChangeType::AddNode,
+            ConfChangeType::AddLearnerNode => MembershipChangeType::AddLearnerNode,
+            ConfChangeType::RemoveNode => MembershipChangeType::RemoveNode,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipChange {
+    pub node_id: u64,
+    pub change_type: MembershipChangeType,
+    pub addr: Option<SocketAddr>,
+}
+
+/// An entry of the membership change audit log, written when a conf change entry is applied.
+///
+/// Records are kept in the storage apart from the raft log, so they survive log compaction.
+/// A node restored from a snapshot only has the records of the changes applied after the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipChangeRecord {
+    /// Index of the conf change entry. Re-applying the entry overwrites its record.
+    pub index: u64,
+    pub term: u64,
+    /// Unix timestamp in milliseconds, taken when this node applied the change.
+    pub applied_at: i64,
+    /// Who requested the change, e.g. `node 1` or the address of the gRPC client.
+    /// `None` for the initial members and the entries written by older versions.
+    pub requested_by: Option<String>,
+    /// Empty when leaving the joint configuration.
+    pub changes: Vec<MembershipChange>,
+    /// Set when the change could not be applied.
+    pub error: Option<String>,
+}
+
+impl MembershipChangeRecord {
+    pub(crate) fn new(
+        index: u64,
+        term: u64,
+        conf_change: &ConfChangeV2,
+        requested_by: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        let addrs: Vec<SocketAddr> = deserialize(conf_change.get_context()).unwrap_or_default();
+        let changes = conf_change
+            .get_changes()
+            .iter()
+            .enumerate()
+            .map(|(idx, change)| MembershipChange {
+                node_id: change.get_node_id(),
+                change_type: change.get_change_type().into(),
+                addr: addrs.get(idx).copied(),
+            })
+            .collect();
+
+        Self {
+            index,
+            term,
+            applied_at: chrono::Utc::now().timestamp_millis(),
+            requested_by,
+            changes,
+            error,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(serialize(self)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(deserialize(bytes)?)
+    }
+}
+
+/// Left behind by a removed node, so that `RequestId` doesn't hand out its id again
+/// while messages of the removed node may still be in flight.
+///
+/// Like the membership change records, tombstones are written by every node when it applies the removal,
+/// and kept until they are purged with `RaftNode::purge_tombstones`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeTombstone {
+    pub node_id: u64,
+    /// Index of the conf change entry removing the node.
+    pub index: u64,
+    /// Unix timestamp in milliseconds, taken when this node applied the removal.
+    pub removed_at: i64,
+}
+
+/// Applies the conf change at `index` to `tombstones`. Removed nodes leave a tombstone,
+/// and nodes added back with their old id clear theirs.
+pub(crate) fn update_tombstones(
+    tombstones: &mut Vec<NodeTombstone>,
+    index: u64,
+    conf_change: &ConfChangeV2,
+) {
+    for change in conf_change.get_changes() {
+        let node_id = change.get_node_id();
+        tombstones.retain(|tombstone| tombstone.node_id != node_id);
+
+        if change.get_change_type() == ConfChangeType::RemoveNode {
+            tombstones.push(NodeTombstone {
+                node_id,
+                index,
+                removed_at: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+    }
+}
+
+/// Context attached to conf change entries proposed through `RaftNode`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ConfChangeContext {
+    pub response_seq: u64,
+    pub requested_by: Option<String>,
+}
+
+impl ConfChangeContext {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(serialize(self)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        // Entries written by older versions only carry the response sequence.
+        if bytes.len() == size_of::<u64>() {
+            return Ok(Self {
+                response_seq: deserialize(bytes)?,
+                requested_by: None,
+            });
+        }
+        Ok(deserialize(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raft::eraftpb::ConfChangeSingle;
+
+    #[test]
+    fn test_record_from_conf_change() {
+        let addr: SocketAddr = "127.0.0.1:60062".parse().unwrap();
+        let mut change = ConfChangeSingle::default();
+        change.set_node_id(2);
+        change.set_change_type(ConfChangeType::AddLearnerNode);
+
+        let mut conf_change = ConfChangeV2::default();
+        conf_change.set_changes(vec![change]);
+        conf_change.set_context(serialize(&vec![addr]).unwrap());
+
+        let record =
+            MembershipChangeRecord::new(5, 1, &conf_change, Some("node 1".to_owned()), None);
+        assert_eq!(
+            record.changes,
+            vec![MembershipChange {
+                node_id: 2,
+                change_type: MembershipChangeType::AddLearnerNode,
+                addr: Some(addr),
+            }]
+        );
+
+        let decoded = MembershipChangeRecord::decode(&record.encode().unwrap()).unwrap();
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn test_conf_change_context_decodes_legacy_context() {
+        let legacy = serialize(&7u64).unwrap();
+        let context = ConfChangeContext::decode(&legacy).unwrap();
+        assert_eq!(context.response_seq, 7);
+        assert!(context.requested_by.is_none());
+    }
+
+    #[test]
+    fn test_update_tombstones() {
+        let conf_change = |changes: Vec<(u64, ConfChangeType)>| {
+            let mut conf_change = ConfChangeV2::default();
+            conf_change.set_changes(
+                changes
+                    .into_iter()
+                    .map(|(node_id, change_type)| {
+                        let mut change = ConfChangeSingle::default();
+                        change.set_node_id(node_id);
+                        change.set_change_type(change_type);
+                        change
+                    })
+                    .collect(),
+            );
+            conf_change
+        };
+
+        let mut tombstones = vec![];
+        update_tombstones(
+            &mut tombstones,
+            5,
+            &conf_change(vec![(2, ConfChangeType::RemoveNode)]),
+        );
+        update_tombstones(
+            &mut tombstones,
+            6,
+            &conf_change(vec![(3, ConfChangeType::RemoveNode)]),
+        );
+        assert_eq!(
+            tombstones
+                .iter()
+                .map(|t| (t.node_id, t.index))
+                .collect::<Vec<_>>(),
+            vec![(2, 5), (3, 6)]
+        );
+
+        update_tombstones(
+            &mut tombstones,
+            7,
+            &conf_change(vec![(2, ConfChangeType::AddNode)]),
+        );
+        assert_eq!(
+            tombstones.iter().map(|t| t.node_id).collect::<Vec<_>>(),
+            vec![3]
+        );
+    }
+}
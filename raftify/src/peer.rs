@@ -1,17 +1,82 @@
 use serde::{Deserialize, Serialize};
-use std::net::{SocketAddr, ToSocketAddrs};
-use tonic::transport::Channel;
+use std::{
+    collections::BTreeMap,
+    net::{SocketAddr, ToSocketAddrs},
+    time::{Duration, Instant},
+};
 
-use crate::InitialRole;
+use crate::{CircuitBreakerConfig, ClientOptions, Error, InitialRole, RaftClient};
 
-use super::{create_client, error::Result, raft_service::raft_service_client::RaftServiceClient};
+use super::{create_client_with_options, error::Result};
+
+/// Returns the port of `addr` if it is a `host:port` hostname rather than a socket address.
+fn parse_host(addr: &str) -> Option<u16> {
+    if addr.parse::<SocketAddr>().is_ok() {
+        return None;
+    }
+    let (host, port) = addr.rsplit_once(':')?;
+    if host.is_empty() {
+        return None;
+    }
+    port.parse().ok()
+}
+
+/// Label of the zone a peer runs in, e.g. its availability zone.
+pub const ZONE_LABEL: &str = "zone";
+/// Label of the rack a peer runs in.
+pub const RACK_LABEL: &str = "rack";
+
+/// Free-form labels of a peer, e.g. `zone`, `rack` or the role the node plays for the application.
+pub type PeerLabels = BTreeMap<String, String>;
+
+/// Failed requests in a row after which the connection to a peer is evicted and made again.
+const EVICT_AFTER_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Not connected yet.
+    #[default]
+    Idle,
+    /// The last request sent to the peer succeeded.
+    Ready,
+    /// The last request sent to the peer failed, or the peer couldn't be connected.
+    Failing,
+    /// The peer failed too many requests in a row, so no message is sent to it until the cooldown
+    /// of the circuit breaker elapses, see `Config::circuit_breaker`.
+    Open,
+}
+
+/// Connection of the node to a peer, reused by the messages sent to it until it fails repeatedly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeerConnection {
+    pub state: ConnectionState,
+    /// Requests failed in a row since the last one which succeeded.
+    pub consecutive_failures: u32,
+    /// Connections made to the peer, the first one included.
+    pub connects: u64,
+    #[serde(skip)]
+    last_failure: Option<Instant>,
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Peer {
+    /// Address of the peer. For a hostname, the address it was last resolved to.
     pub addr: SocketAddr,
+    /// Hostname the peer was given as, e.g. the DNS name of a Kubernetes pod.
+    /// It is resolved again whenever the peer is reconnected, so that a changed IP is picked up.
+    #[serde(default)]
+    pub host: Option<String>,
     pub role: InitialRole,
+    /// Election priority. Voters refuse to vote for lower-priority candidates with an equally up-to-date log.
+    #[serde(default)]
+    pub priority: i64,
+    /// Set through `RaftNode::set_peer_labels`, which persists them on every node.
+    #[serde(default)]
+    pub labels: PeerLabels,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub client: Option<RaftClient>,
     #[serde(skip_serializing, skip_deserializing)]
-    pub client: Option<RaftServiceClient<Channel>>,
+    pub connection: PeerConnection,
 }
 
 // TODO: Implement From<Peer> for raft_service::Peer
@@ -25,18 +90,184 @@ pub struct Peer {
 // }
 
 impl Peer {
-    pub fn new<A: ToSocketAddrs>(addr: A, initial_role: InitialRole) -> Self {
-        let addr = addr.to_socket_addrs().unwrap().next().unwrap();
+    /// Creates a peer at `addr`, which can be a hostname.
+    /// A hostname which can't be resolved yet is resolved when the peer is connected.
+    pub fn new<A: ToSocketAddrs + ToString>(addr: A, initial_role: InitialRole) -> Self {
+        let (addr, host) = Self::resolve(addr);
         Peer {
             addr,
+            host,
             role: initial_role,
+            priority: 0,
+            labels: PeerLabels::new(),
             client: None,
+            connection: PeerConnection::default(),
+        }
+    }
+
+    /// Points the peer to `addr`, dropping the client connected to the old address.
+    pub fn set_addr<A: ToSocketAddrs + ToString>(&mut self, addr: A) {
+        (self.addr, self.host) = Self::resolve(addr);
+        self.client = None;
+    }
+
+    fn resolve<A: ToSocketAddrs + ToString>(addr: A) -> (SocketAddr, Option<String>) {
+        let host = addr.to_string();
+        match parse_host(&host) {
+            Some(port) => {
+                let resolved = addr
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], port)));
+                (resolved, Some(host))
+            }
+            None => (addr.to_socket_addrs().unwrap().next().unwrap(), None),
         }
     }
 
-    pub async fn connect(&mut self) -> Result<()> {
-        let client = create_client(&self.addr).await?;
+    pub async fn connect(&mut self, options: &ClientOptions) -> Result<()> {
+        if let Some(host) = &self.host {
+            self.addr = tokio::net::lookup_host(host.as_str())
+                .await?
+                .next()
+                .ok_or_else(|| Error::Rejected(format!("{} resolved to no address", host)))?;
+        }
+        let client = create_client_with_options(&self.addr, options).await?;
         self.client = Some(client);
+        self.connection.connects += 1;
         Ok(())
     }
+
+    pub(crate) fn record_success(&mut self) {
+        self.connection.state = ConnectionState::Ready;
+        self.connection.consecutive_failures = 0;
+        self.connection.last_failure = None;
+    }
+
+    /// Records a failed request, or connection attempt, to the peer.
+    /// Evicts the client once `EVICT_AFTER_FAILURES` requests failed in a row, or right away
+    /// when `evict` is set, so that the next message reconnects instead of waiting on a dead connection.
+    pub(crate) fn record_failure(&mut self, evict: bool) {
+        self.connection.state = ConnectionState::Failing;
+        self.connection.consecutive_failures += 1;
+        self.connection.last_failure = Some(Instant::now());
+        if evict || self.connection.consecutive_failures % EVICT_AFTER_FAILURES == 0 {
+            self.client = None;
+        }
+    }
+
+    /// Whether a message may be sent to the peer. The circuit breaker holds the messages back for `cooldown`
+    /// seconds once the peer failed `failure_threshold` requests in a row, then lets a single one through
+    /// to probe the peer, which closes the circuit if it succeeds.
+    pub(crate) fn allow_request(&mut self, circuit_breaker: &CircuitBreakerConfig) -> bool {
+        let connection = &mut self.connection;
+        if connection.consecutive_failures < circuit_breaker.failure_threshold {
+            return true;
+        }
+        let now = Instant::now();
+        match connection.last_failure {
+            Some(at) if now - at < Duration::from_secs_f32(circuit_breaker.cooldown) => {
+                connection.state = ConnectionState::Open;
+                false
+            }
+            _ => {
+                // Holds the other messages back until the probe fails or succeeds.
+                connection.last_failure = Some(now);
+                true
+            }
+        }
+    }
+
+    pub fn zone(&self) -> Option<&str> {
+        self.labels.get(ZONE_LABEL).map(String::as_str)
+    }
+
+    pub fn rack(&self) -> Option<&str> {
+        self.labels.get(RACK_LABEL).map(String::as_str)
+    }
+
+    /// Returns the address the peer was given as, i.e. its hostname if it has one.
+    pub fn addr_string(&self) -> String {
+        self.host.clone().unwrap_or_else(|| self.addr.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthInterceptor, RaftServiceClient};
+
+    #[test]
+    fn test_parse_host() {
+        assert_eq!(parse_host("raft-0.raft.default.svc:60061"), Some(60061));
+        assert_eq!(parse_host("localhost:60061"), Some(60061));
+        assert_eq!(parse_host("127.0.0.1:60061"), None);
+        assert_eq!(parse_host("[::1]:60061"), None);
+        assert_eq!(parse_host("wrong peer addr"), None);
+    }
+
+    #[test]
+    fn test_unresolvable_host() {
+        let peer = Peer::new("raftify-unresolvable.invalid:60061", InitialRole::Voter);
+        assert_eq!(
+            peer.host.as_deref(),
+            Some("raftify-unresolvable.invalid:60061")
+        );
+        assert_eq!(peer.addr.port(), 60061);
+    }
+
+    #[tokio::test]
+    async fn test_evict_failing_connection() {
+        let mut peer = Peer::new("127.0.0.1:60061", InitialRole::Voter);
+        assert_eq!(peer.connection.state, ConnectionState::Idle);
+        let channel =
+            tonic::transport::Channel::from_static("http://127.0.0.1:60061").connect_lazy();
+        peer.client = Some(RaftServiceClient::with_interceptor(
+            channel,
+            AuthInterceptor::new(None),
+        ));
+
+        for _ in 1..EVICT_AFTER_FAILURES {
+            peer.record_failure(false);
+        }
+        assert_eq!(peer.connection.state, ConnectionState::Failing);
+        assert!(peer.client.is_some());
+
+        peer.record_success();
+        assert_eq!(peer.connection.state, ConnectionState::Ready);
+        assert_eq!(peer.connection.consecutive_failures, 0);
+
+        // The failures are counted again from the last success.
+        for _ in 1..EVICT_AFTER_FAILURES {
+            peer.record_failure(false);
+        }
+        assert!(peer.client.is_some());
+        peer.record_failure(false);
+        assert!(peer.client.is_none());
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let circuit_breaker = CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: 0.05,
+        };
+        let mut peer = Peer::new("127.0.0.1:60061", InitialRole::Voter);
+
+        peer.record_failure(false);
+        assert!(peer.allow_request(&circuit_breaker));
+        peer.record_failure(false);
+        assert!(!peer.allow_request(&circuit_breaker));
+        assert_eq!(peer.connection.state, ConnectionState::Open);
+
+        // A single probe is let through once the cooldown elapsed.
+        std::thread::sleep(Duration::from_secs_f32(circuit_breaker.cooldown));
+        assert!(peer.allow_request(&circuit_breaker));
+        assert!(!peer.allow_request(&circuit_breaker));
+
+        peer.record_success();
+        assert!(peer.allow_request(&circuit_breaker));
+        assert_eq!(peer.connection.state, ConnectionState::Ready);
+    }
 }
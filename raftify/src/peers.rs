@@ -2,10 +2,11 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
 };
 
 use super::Peer;
-use crate::{error::Result, raft_service, InitialRole};
+use crate::{error::Result, raft_service, ClientOptions, InitialRole};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Peers {
@@ -22,7 +23,13 @@ impl From<raft_service::Peers> for Peers {
     fn from(raft_service_peers: raft_service::Peers) -> Self {
         let mut peers = Peers::with_empty();
         for peer in raft_service_peers.peers {
-            peers.add_peer(peer.node_id, peer.addr, Some(InitialRole::Voter));
+            // Peers sent by older nodes don't carry the role.
+            let role = InitialRole::from_str(&peer.role).unwrap_or(InitialRole::Voter);
+            peers.add_peer(peer.node_id, peer.addr, Some(role));
+            if let Some(added) = peers.get_mut(&peer.node_id) {
+                added.priority = peer.priority;
+                added.labels = peer.labels.into_iter().collect();
+            }
         }
         peers
     }
@@ -35,7 +42,10 @@ impl From<Peers> for raft_service::Peers {
             .into_iter()
             .map(|(id, peer)| raft_service::Peer {
                 node_id: id,
-                addr: peer.addr.to_string(),
+                addr: peer.addr_string(),
+                role: peer.role.to_string(),
+                priority: peer.priority,
+                labels: peer.labels.into_iter().collect(),
             })
             .collect();
 
@@ -60,7 +70,7 @@ impl From<HashMap<u64, SocketAddr>> for Peers {
 }
 
 impl Peers {
-    pub fn new<A: ToSocketAddrs>(self_id: u64, self_addr: A) -> Self {
+    pub fn new<A: ToSocketAddrs + ToString>(self_id: u64, self_addr: A) -> Self {
         let mut inner = HashMap::new();
         inner.insert(self_id, Peer::new(self_addr, InitialRole::Voter));
         Self { inner }
@@ -111,18 +121,48 @@ impl Peers {
         self.inner.remove(id)
     }
 
-    pub fn add_peer<A: ToSocketAddrs>(
+    pub fn add_peer<A: ToSocketAddrs + ToString>(
         &mut self,
         id: u64,
         addr: A,
         initial_role: Option<InitialRole>,
     ) {
-        let addr = addr.to_socket_addrs().unwrap().next().unwrap();
         let initial_role = initial_role.unwrap_or(InitialRole::Voter);
-        let peer = Peer::new(addr, initial_role);
+        let mut peer = Peer::new(addr, initial_role);
+        // A peer added again, e.g. a learner promoted to voter, keeps its labels.
+        if let Some(replaced) = self.inner.get(&id) {
+            peer.labels = replaced.labels.clone();
+        }
         self.inner.insert(id, peer);
     }
 
+    /// Points the peer to `addr`, keeping its role and priority.
+    /// The client connected to the old address is dropped. Returns false if the peer doesn't exist.
+    pub fn update_addr<A: ToSocketAddrs + ToString>(&mut self, id: u64, addr: A) -> bool {
+        match self.inner.get_mut(&id) {
+            Some(peer) => {
+                peer.set_addr(addr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn voters(&self) -> Vec<u64> {
+        self.ids_with(|role| role != &InitialRole::Learner)
+    }
+
+    pub fn learners(&self) -> Vec<u64> {
+        self.ids_with(|role| role == &InitialRole::Learner)
+    }
+
+    fn ids_with<F: Fn(&InitialRole) -> bool>(&self, predicate: F) -> Vec<u64> {
+        self.iter()
+            .filter(|(_, peer)| predicate(&peer.role))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
     pub fn reserve_id(&mut self) -> u64 {
         match self.inner.keys().max() {
             Some(id) => id + 1,
@@ -138,9 +178,9 @@ impl Peers {
             .map(|(id, _)| *id)
     }
 
-    pub async fn connect(&mut self, id: u64) -> Result<()> {
+    pub async fn connect(&mut self, id: u64, options: &ClientOptions) -> Result<()> {
         let peer = self.get_mut(&id).unwrap();
-        peer.connect().await
+        peer.connect(options).await
     }
 }
 
@@ -167,6 +207,7 @@ impl<'a> Iterator for SortedPeersIter<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ZONE_LABEL;
 
     #[test]
     #[should_panic(expected = "invalid socket address")]
@@ -196,4 +237,44 @@ mod tests {
         peers.add_peer(next_id, "127.0.0.1:8085", None);
         assert_eq!(next_id, 5);
     }
+
+    #[test]
+    fn test_peers_voters_and_learners() {
+        let mut peers = Peers::new(1, "127.0.0.1:8081");
+        peers.add_peer(2, "127.0.0.1:8082", Some(InitialRole::Learner));
+        peers.add_peer(3, "127.0.0.1:8083", Some(InitialRole::Voter));
+
+        assert_eq!(peers.voters(), vec![1, 3]);
+        assert_eq!(peers.learners(), vec![2]);
+
+        let restored: Peers = raft_service::Peers::from(peers).into();
+        assert_eq!(restored.voters(), vec![1, 3]);
+        assert_eq!(restored.learners(), vec![2]);
+    }
+
+    #[test]
+    fn test_update_peer_addr() {
+        let mut peers = Peers::new(1, "127.0.0.1:8081");
+        peers.add_peer(2, "127.0.0.1:8082", Some(InitialRole::Learner));
+        peers.get_mut(&2).unwrap().priority = 3;
+        peers
+            .get_mut(&2)
+            .unwrap()
+            .labels
+            .insert(ZONE_LABEL.to_owned(), "us-east-1a".to_owned());
+
+        assert!(peers.update_addr(2, "127.0.0.1:9082"));
+        let peer = peers.get(&2).unwrap();
+        assert_eq!(peer.addr.to_string(), "127.0.0.1:9082");
+        assert_eq!(peer.role, InitialRole::Learner);
+        assert_eq!(peer.priority, 3);
+        assert_eq!(peer.zone(), Some("us-east-1a"));
+
+        // Labels are kept when the learner is promoted, and sent along with the peers.
+        peers.add_peer(2, "127.0.0.1:9082", Some(InitialRole::Voter));
+        let restored: Peers = raft_service::Peers::from(peers.clone()).into();
+        assert_eq!(restored.get(&2).unwrap().zone(), Some("us-east-1a"));
+
+        assert!(!peers.update_addr(3, "127.0.0.1:9083"));
+    }
 }
@@ -0,0 +1,387 @@
+use std::time::Duration;
+use tokio::{sync::oneshot, time::timeout};
+use tonic::{Request, Response, Status};
+
+use crate::{
+    macro_utils::function_name,
+    raft::eraftpb::{ConfChangeSingle, ConfChangeType},
+    raft_admin_service::{self, raft_admin_service_server::RaftAdminService},
+    raft_server::RaftServer,
+    raft_service::{self, raft_service_server::RaftService},
+    request::server_request_message::ServerRequestMsg,
+    response::server_response_message::{
+        ConfChangeResponseResult, ProposeResponseResult, ResponseResult, ServerResponseMsg,
+    },
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, RuntimeConfig,
+};
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Sync + Send + 'static,
+        FSM: AbstractStateMachine + 'static,
+    > RaftServer<LogEntry, LogStorage, FSM>
+{
+    fn authorize<T>(&self, operation: &str, request: &Request<T>) -> Result<(), Status> {
+        match &self.config.admin_authorizer {
+            Some(authorizer) => authorizer.authorize(operation, request.metadata()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Sync + Send + 'static,
+        FSM: AbstractStateMachine + 'static,
+    > RaftServer<LogEntry, LogStorage, FSM>
+{
+    async fn force_remove_peer(
+        &self,
+        node_id: u64,
+        requested_by: Option<String>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::ForceRemovePeer {
+                node_id,
+                requested_by,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+
+        let response = timeout(
+            Duration::from_secs_f32(self.config.conf_change_request_timeout),
+            rx_msg,
+        )
+        .await
+        .map_err(|_| Status::deadline_exceeded("Timed out removing the peer"))?
+        .unwrap();
+
+        let response = match response {
+            ServerResponseMsg::ConfigChange { result } => match result {
+                ConfChangeResponseResult::RemoveSuccess => raft_admin_service::AdminResponse {
+                    code: raft_service::ResultCode::Ok as i32,
+                    ..Default::default()
+                },
+                ConfChangeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                } => raft_admin_service::AdminResponse {
+                    code: raft_service::ResultCode::WrongLeader as i32,
+                    error: format!("Node {} ({}) is the leader", leader_id, leader_addr)
+                        .into_bytes(),
+                },
+                ConfChangeResponseResult::Error(e) => raft_admin_service::AdminResponse {
+                    code: raft_service::ResultCode::Error as i32,
+                    error: e.to_string().into_bytes(),
+                },
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        Ok(Response::new(response))
+    }
+}
+
+fn admin_propose_response(result: ProposeResponseResult) -> raft_admin_service::AdminResponse {
+    match result {
+        ProposeResponseResult::Success(_) => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::Ok as i32,
+            ..Default::default()
+        },
+        ProposeResponseResult::WrongLeader {
+            leader_id,
+            leader_addr,
+        } => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::WrongLeader as i32,
+            error: format!("Node {} ({}) is the leader", leader_id, leader_addr).into_bytes(),
+        },
+        ProposeResponseResult::Error(e) => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::Error as i32,
+            error: e.to_string().into_bytes(),
+        },
+    }
+}
+
+fn admin_response(result: ResponseResult) -> raft_admin_service::AdminResponse {
+    match result {
+        ResponseResult::Success => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::Ok as i32,
+            ..Default::default()
+        },
+        ResponseResult::Error(e) => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::Error as i32,
+            error: e.to_string().as_bytes().to_vec(),
+        },
+        ResponseResult::WrongLeader { .. } => raft_admin_service::AdminResponse {
+            code: raft_service::ResultCode::WrongLeader as i32,
+            ..Default::default()
+        },
+    }
+}
+
+// The operations also available on RaftService are delegated to its handlers.
+#[tonic::async_trait]
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Sync + Send + 'static,
+        FSM: AbstractStateMachine + 'static,
+    > RaftAdminService for RaftServer<LogEntry, LogStorage, FSM>
+{
+    async fn transfer_leader(
+        &self,
+        request: Request<raft_service::TransferLeaderArgs>,
+    ) -> Result<Response<raft_service::TransferLeaderResponse>, Status> {
+        self.authorize("transfer_leader", &request)?;
+        RaftService::transfer_leader(self, request).await
+    }
+
+    async fn create_snapshot(
+        &self,
+        request: Request<raft_service::Empty>,
+    ) -> Result<Response<raft_service::Empty>, Status> {
+        self.authorize("create_snapshot", &request)?;
+        RaftService::create_snapshot(self, request).await
+    }
+
+    async fn compact_logs(
+        &self,
+        request: Request<raft_admin_service::CompactLogsArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("compact_logs", &request)?;
+        let request_args = request.into_inner();
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::CompactLogs {
+                index: request_args.index,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::CompactLogs { result } => Ok(Response::new(admin_response(result))),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn purge_tombstones(
+        &self,
+        request: Request<raft_admin_service::PurgeTombstonesArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("purge_tombstones", &request)?;
+        let request_args = request.into_inner();
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::PurgeTombstones {
+                node_ids: request_args.node_ids,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::PurgeTombstones { result } => {
+                Ok(Response::new(admin_response(result)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_ticks(
+        &self,
+        request: Request<raft_admin_service::SetTicksArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("set_ticks", &request)?;
+        let request_args = request.into_inner();
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::SetTicks {
+                election_tick: request_args.election_tick as usize,
+                heartbeat_tick: request_args.heartbeat_tick as usize,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::SetTicks { result } => Ok(Response::new(admin_response(result))),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn update_runtime_config(
+        &self,
+        request: Request<raft_admin_service::UpdateRuntimeConfigArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("update_runtime_config", &request)?;
+        let request_args = request.into_inner();
+        let update = RuntimeConfig::decode(request_args.config_json.as_bytes())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::UpdateRuntimeConfig { update, tx_msg })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+
+        match rx_msg.await.unwrap() {
+            ServerResponseMsg::Propose { result } => {
+                Ok(Response::new(admin_propose_response(result)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_peer_labels(
+        &self,
+        request: Request<raft_admin_service::SetPeerLabelsArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("set_peer_labels", &request)?;
+        let request_args = request.into_inner();
+
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::SetPeerLabels {
+                node_id: request_args.node_id,
+                labels: request_args.labels.into_iter().collect(),
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+
+        match rx_msg.await.unwrap() {
+            ServerResponseMsg::Propose { result } => {
+                Ok(Response::new(admin_propose_response(result)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn remove_peer(
+        &self,
+        request: Request<raft_admin_service::RemovePeerArgs>,
+    ) -> Result<Response<raft_admin_service::AdminResponse>, Status> {
+        self.authorize("remove_peer", &request)?;
+        let requested_by = request.remote_addr().map(|addr| addr.to_string());
+        let request_args = request.into_inner();
+        let node_id = request_args.node_id;
+
+        if request_args.force {
+            return self.force_remove_peer(node_id, requested_by).await;
+        }
+
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender.send(ServerRequestMsg::GetPeers { tx_msg }).await {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+
+        let peer_addr = match rx_msg.await.unwrap() {
+            ServerResponseMsg::GetPeers { peers } => match peers.get(&node_id) {
+                Some(peer) => peer.addr.to_string(),
+                None => {
+                    return Err(Status::not_found(format!(
+                        "Node {} not found from the peers",
+                        node_id
+                    )))
+                }
+            },
+            _ => unreachable!(),
+        };
+
+        let mut change = ConfChangeSingle::default();
+        change.set_node_id(node_id);
+        change.set_change_type(ConfChangeType::RemoveNode);
+
+        // Goes through the ChangeConfig handler, which forwards the request to the leader if needed.
+        let reply = RaftService::change_config(
+            self,
+            Request::new(raft_service::ChangeConfigArgs {
+                addrs: vec![peer_addr],
+                changes: vec![change],
+            }),
+        )
+        .await?
+        .into_inner();
+
+        let response = match reply.result_type() {
+            raft_service::ChangeConfigResultType::ChangeConfigSuccess => {
+                raft_admin_service::AdminResponse {
+                    code: raft_service::ResultCode::Ok as i32,
+                    ..Default::default()
+                }
+            }
+            _ => raft_admin_service::AdminResponse {
+                code: raft_service::ResultCode::Error as i32,
+                error: reply.error,
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn debug_node(
+        &self,
+        request: Request<raft_service::Empty>,
+    ) -> Result<Response<raft_service::DebugNodeResponse>, Status> {
+        self.authorize("debug_node", &request)?;
+        RaftService::debug_node(self, request).await
+    }
+
+    async fn get_membership_history(
+        &self,
+        request: Request<raft_service::Empty>,
+    ) -> Result<Response<raft_admin_service::MembershipHistoryResponse>, Status> {
+        self.authorize("get_membership_history", &request)?;
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::GetMembershipHistory { tx_msg })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+
+        match rx_msg.await.unwrap() {
+            ServerResponseMsg::GetMembershipHistory { result } => {
+                let history = result.map_err(|e| Status::internal(e.to_string()))?;
+                Ok(Response::new(
+                    raft_admin_service::MembershipHistoryResponse {
+                        result_json: serde_json::to_string(&history).unwrap(),
+                    },
+                ))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
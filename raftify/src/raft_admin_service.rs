@@ -0,0 +1 @@
+tonic::include_proto!("raft_admin_service");
@@ -1,16 +1,30 @@
 use crate::{
-    raft::logger::Logger, request::server_request_message::ServerRequestMsg, ClusterJoinTicket,
-    InitialRole, Peers, StableStorage,
+    backoff::Backoff,
+    backup::{BackupArchive, SnapshotExport},
+    raft::{logger::Logger, Storage},
+    request::server_request_message::ServerRequestMsg,
+    storage::utils::run_compacted_log_sweeper,
+    AbstractStableStorage, ClientOptions, ClusterJoinTicket, ClusterUuid, Gossip, InitialRole,
+    Peers, RejoinConfig, StatefulSetTopology,
 };
 use bincode::deserialize;
-use std::{net::ToSocketAddrs, ops::Deref, sync::Arc};
-use tokio::{
-    signal,
-    sync::{mpsc, oneshot},
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
+use tokio::{signal, sync::mpsc, time::sleep};
+use tokio_util::task::TaskTracker;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    server::NamedService,
+    transport::Body,
 };
 
 use super::{
-    create_client,
+    create_client_with_options,
     error::{Error, Result},
     raft_node::RaftNode,
     raft_server::RaftServer,
@@ -24,18 +38,20 @@ use super::{
 #[derive(Clone)]
 pub struct Raft<
     LogEntry: AbstractLogEntry + 'static,
-    LogStorage: StableStorage + Send + Clone + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
     FSM: AbstractStateMachine + Clone + 'static,
 > {
     pub raft_node: RaftNode<LogEntry, LogStorage, FSM>,
     pub raft_server: RaftServer<LogEntry, LogStorage, FSM>,
     pub tx_server: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
+    /// Set when `Config::gossip` is.
+    pub gossip: Option<Gossip>,
     pub logger: Arc<dyn Logger>,
 }
 
 impl<
         LogEntry: AbstractLogEntry + 'static,
-        LogStorage: StableStorage + Send + Clone + 'static,
+        LogStorage: AbstractStableStorage + Send + Clone + 'static,
         FSM: AbstractStateMachine + Clone + 'static,
     > Deref for Raft<LogEntry, LogStorage, FSM>
 {
@@ -48,7 +64,7 @@ impl<
 
 impl<
         LogEntry: AbstractLogEntry,
-        LogStorage: StableStorage + Send + Sync + Clone + 'static,
+        LogStorage: AbstractStableStorage + Send + Sync + Clone + 'static,
         FSM: AbstractStateMachine + Send + Sync + Clone + 'static,
     > Raft<LogEntry, LogStorage, FSM>
 {
@@ -84,6 +100,7 @@ impl<
         }
 
         let (tx_server, rx_server) = mpsc::channel(100);
+        let cluster_uuid = ClusterUuid::default();
         let raft_node = RaftNode::bootstrap(
             node_id,
             should_be_leader,
@@ -91,39 +108,276 @@ impl<
             fsm,
             config.clone(),
             raft_addr,
+            cluster_uuid.clone(),
             logger.clone(),
             tx_server.clone(),
             rx_server,
         )?;
 
-        let raft_server =
-            RaftServer::new(tx_server.clone(), raft_addr, config.clone(), logger.clone());
+        let gossip = config.gossip.clone().map(|gossip_config| {
+            let addr = gossip_config
+                .advertise_addr
+                .clone()
+                .unwrap_or_else(|| raft_addr.to_string());
+            Gossip::new(gossip_config, addr, node_id)
+        });
+
+        let raft_server = RaftServer::new(
+            tx_server.clone(),
+            raft_addr,
+            config.clone(),
+            cluster_uuid,
+            gossip.clone(),
+            logger.clone(),
+        );
 
         Ok(Self {
             tx_server: tx_server.clone(),
             raft_node,
             raft_server,
+            gossip,
             logger,
         })
     }
 
+    /// Creates a new Raft instance for the pod of a Kubernetes StatefulSet this process runs in.
+    /// `config.raft_config.id` and `config.initial_peers` are derived from the pod name as described
+    /// in `StatefulSetTopology`, and the node listens on `0.0.0.0:<port>`.
+    pub fn bootstrap_in_statefulset(
+        topology: &StatefulSetTopology,
+        log_storage: LogStorage,
+        fsm: FSM,
+        mut config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        let node_id = topology.node_id()?;
+        config.raft_config.id = node_id;
+        config.initial_peers = Some(topology.peers()?);
+
+        Self::bootstrap(
+            node_id,
+            SocketAddr::from(([0, 0, 0, 0], topology.port)),
+            log_storage,
+            fsm,
+            config,
+            logger,
+        )
+    }
+
+    /// Restarts the node which owns `log_storage`, with the id and address it was first bootstrapped with
+    /// and the peers of its last membership change, so that only the storage has to be kept across restarts.
+    /// `config.raft_config.id` and `config.initial_peers` are overwritten with the persisted ones.
+    ///
+    /// Fails if the storage was never bootstrapped.
+    pub fn bootstrap_from_storage(
+        log_storage: LogStorage,
+        fsm: FSM,
+        mut config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        let identity = log_storage.node_identity()?.ok_or_else(|| {
+            Error::ConfigInvalid(
+                "no node identity persisted in the storage, bootstrap the node first".to_owned(),
+            )
+        })?;
+
+        let mut peers = log_storage
+            .peers()?
+            .unwrap_or_else(|| Peers::new(identity.node_id, identity.raft_addr));
+        // The leader is elected again, the initial one doesn't get the leadership back on restart.
+        for (_, peer) in peers.inner.iter_mut() {
+            if peer.role == InitialRole::Leader {
+                peer.role = InitialRole::Voter;
+            }
+        }
+
+        let hard_state = log_storage.hard_state()?;
+        logger.info(&format!(
+            "Restarting node {} ({}) from term {}, commit {}, snapshot index {}, with {} peers.",
+            identity.node_id,
+            identity.raft_addr,
+            hard_state.term,
+            hard_state.commit,
+            log_storage.snapshot(0, 0)?.get_metadata().index,
+            peers.len()
+        ));
+
+        config.raft_config.id = identity.node_id;
+        config.initial_peers = Some(peers);
+        config.bootstrap_from_snapshot = false;
+
+        Self::bootstrap(
+            identity.node_id,
+            identity.raft_addr,
+            log_storage,
+            fsm,
+            config,
+            logger,
+        )
+    }
+
+    /// Bootstraps a node joining the cluster of `peer_addrs`, retrying `request_id` against the peers in turn
+    /// and then the join with the backoff of `config.rejoin`, so that the caller doesn't orchestrate the retries.
+    ///
+    /// A node restarted from a storage in which it is still a member restarts as is, see `bootstrap_from_storage`.
+    /// A fresh or removed one joins with a newly reserved id once it runs.
+    pub async fn bootstrap_and_join<A: ToSocketAddrs>(
+        raft_addr: A,
+        peer_addrs: Vec<String>,
+        log_storage: LogStorage,
+        fsm: FSM,
+        mut config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        if let Some(identity) = log_storage.node_identity()? {
+            let conf_state = log_storage.initial_state()?.conf_state;
+            if conf_state.voters.contains(&identity.node_id)
+                || conf_state.learners.contains(&identity.node_id)
+            {
+                return Self::bootstrap_from_storage(log_storage, fsm, config, logger);
+            }
+            logger.info(&format!(
+                "Node {} is no longer a member of the cluster, joining it with a new id.",
+                identity.node_id
+            ));
+        }
+
+        let raft_addr = raft_addr.to_socket_addrs()?.next().unwrap();
+        let ticket = Self::request_id_with_backoff(
+            raft_addr,
+            &peer_addrs,
+            &config.client_options(),
+            &config.rejoin,
+        )
+        .await?;
+
+        config.raft_config.id = ticket.reserved_id;
+        config.initial_peers = Some(ticket.peers.clone().into());
+        let raft = Self::bootstrap(
+            ticket.reserved_id,
+            raft_addr,
+            log_storage,
+            fsm,
+            config,
+            logger,
+        )?;
+
+        // Handled once the node runs, given up if it quits meanwhile.
+        let raft_node = raft.raft_node.clone();
+        let logger = raft.logger.clone();
+        let shutdown = raft_node.cancellation_token();
+        raft.raft_server.config.runtime_handle().spawn(async move {
+            let joined = async {
+                raft_node.add_peers(ticket.peers.clone()).await?;
+                raft_node.join_cluster(vec![ticket]).await
+            };
+            if let Some(Err(e)) = shutdown.run_until_cancelled(joined).await {
+                logger.error(&format!("Failed to join the cluster: {}", e));
+            }
+        });
+
+        Ok(raft)
+    }
+
+    /// Serves a user-defined tonic service on the listener of the RaftServer, so the application API
+    /// doesn't need a port of its own. Call it before `run`.
+    ///
+    /// The service doesn't go through the auth and cluster UUID checks of the raft services.
+    ///
+    /// ```ignore
+    /// let raft = Raft::bootstrap(node_id, raft_addr, log_storage, fsm, config, logger)?
+    ///     .add_service(KvServiceServer::new(KvService::new(store)));
+    /// raft.run().await?;
+    /// ```
+    pub fn add_service<S>(mut self, service: S) -> Self
+    where
+        S: Service<
+                http::Request<Body>,
+                Response = http::Response<BoxBody>,
+                Error = std::convert::Infallible,
+            > + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.raft_server.add_service(service);
+        self
+    }
+
     /// Starts the RaftNode and RaftServer.
     pub async fn run(self) -> Result<()> {
-        let (tx_quit_signal, rx_quit_signal) = oneshot::channel::<()>();
+        self.serve(true).await
+    }
+
+    /// Same as `run`, but shuts the node down gracefully with `shutdown` on SIGTERM or SIGINT,
+    /// e.g. when its pod is deleted or on Ctrl+C, and returns once it quit.
+    pub async fn run_until_signal(self) -> Result<()> {
+        let raft = self.clone();
+        let shutdown = self.raft_node.cancellation_token();
+        let runtime = self.raft_server.config.runtime_handle();
+        runtime.spawn(shutdown.run_until_cancelled_owned(async move {
+            if let Err(e) = wait_for_shutdown_signal().await {
+                raft.logger
+                    .error(&format!("Failed to listen to the shutdown signals: {}", e));
+                return;
+            }
+            raft.logger
+                .info("Shutdown signal received. Shutting down gracefully...");
+            if let Err(e) = raft.shutdown().await {
+                raft.logger
+                    .error(&format!("Failed to shut down gracefully: {}", e));
+            }
+        }));
+
+        self.serve(false).await
+    }
+
+    /// Runs the RaftNode and RaftServer until one of them quits, or until Ctrl+C if `quit_on_ctrl_c`.
+    /// Returns once every task they run is torn down.
+    ///
+    /// They run on `Config::runtime` when set, along with the tasks they spawn.
+    async fn serve(self, quit_on_ctrl_c: bool) -> Result<()> {
+        let shutdown = self.raft_node.cancellation_token();
+        let tasks = TaskTracker::new();
+        let config = &self.raft_server.config;
+        let runtime = config.runtime_handle();
 
         let raft_node = self.raft_node.clone();
-        let raft_node_handle = tokio::spawn(raft_node.run());
+        let mut raft_node_handle = tasks.spawn_on(raft_node.run(), &runtime);
         let raft_server = self.raft_server.clone();
-        let raft_server_handle = tokio::spawn(raft_server.run(rx_quit_signal));
+        let mut raft_server_handle = tasks.spawn_on(raft_server.run(shutdown.clone()), &runtime);
+
+        if config.save_compacted_logs && config.compacted_log_retention.is_enabled() {
+            tasks.spawn_on(
+                shutdown
+                    .clone()
+                    .run_until_cancelled_owned(run_compacted_log_sweeper(
+                        config.clone(),
+                        self.logger.clone(),
+                    )),
+                &runtime,
+            );
+        }
+
+        if let Some(gossip) = self.gossip.clone() {
+            tasks.spawn_on(
+                shutdown.clone().run_until_cancelled_owned(gossip.run(
+                    self.raft_node.clone(),
+                    self.raft_server.client_options(),
+                    self.logger.clone(),
+                )),
+                &runtime,
+            );
+        }
 
-        tokio::select! {
-            _ = signal::ctrl_c() => {
+        let result = tokio::select! {
+            _ = signal::ctrl_c(), if quit_on_ctrl_c => {
                 self.logger.info("Ctrl+C signal detected. Shutting down...");
                 Ok(())
             }
-            result = raft_node_handle => {
-                tx_quit_signal.send(()).unwrap();
-
+            result = &mut raft_node_handle => {
                 match result {
                     Ok(raft_node_result) => {
                         match raft_node_result {
@@ -143,7 +397,7 @@ impl<
                     }
                 }
             }
-            result = raft_server_handle => {
+            result = &mut raft_server_handle => {
                 match result {
                     Ok(raft_server_result) => {
                         match raft_server_result {
@@ -163,7 +417,104 @@ impl<
                     }
                 }
             }
+        };
+
+        shutdown.cancel();
+        tasks.close();
+        tasks.wait().await;
+        result
+    }
+
+    /// Shuts the node down gracefully, see `RaftNode::shutdown`.
+    /// With `Config::gossip`, the other nodes are told that this one leaves once it handed the leadership over.
+    pub async fn shutdown(&self) -> Result<()> {
+        if let Some(gossip) = &self.gossip {
+            if let Err(e) = self.raft_node.hand_off_leadership().await {
+                self.logger.warn(&format!(
+                    "Failed to hand the leadership over before shutting down: {}",
+                    e
+                ));
+            }
+            gossip.leave(&self.raft_server.client_options()).await;
+        }
+        self.raft_node.shutdown().await
+    }
+
+    /// Writes the snapshot, the log entries following it, the hard state, the conf state
+    /// and the peers persisted on this node into a single archive at `path`.
+    pub async fn backup<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let storage = self.raft_node.storage().await?;
+        let peers = self.raft_node.get_peers().await?;
+        BackupArchive::capture(&storage, peers)?.write(path.as_ref())?;
+
+        self.logger
+            .info(&format!("Backed up to {}.", path.as_ref().display()));
+        Ok(())
+    }
+
+    /// Restores the archive written by `backup` into the empty `log_storage` and `fsm` of a new node,
+    /// before bootstrapping it. The snapshot is decrypted with the `encryption_key_provider` of `config`.
+    ///
+    /// Returns the peers at the time of the backup. To restore the cluster onto machines with
+    /// different addresses, rewrite their addresses with `Peers::add_peer`
+    /// and pass them to `bootstrap` as `Config::initial_peers`.
+    pub async fn restore<P: AsRef<Path>>(
+        path: P,
+        log_storage: &mut LogStorage,
+        fsm: &mut FSM,
+        config: &Config,
+    ) -> Result<Peers> {
+        BackupArchive::read(path.as_ref())?
+            .restore(log_storage, fsm, config)
+            .await
+    }
+
+    /// Writes a snapshot of the current state machine into `path`,
+    /// to be imported into another cluster with `import_snapshot`.
+    pub async fn export_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        // The applied index is read first, so the snapshot covers at least the entries up to it.
+        let index = self.raft_node.get_last_applied().await?;
+        if index == 0 {
+            return Err(Error::Rejected("No entry has been applied yet".to_owned()));
         }
+        let term = self.raft_node.get_term().await?;
+        let data = self.raft_node.state_machine().await?.snapshot().await?;
+
+        SnapshotExport::new(index, term, data).write(path.as_ref())?;
+
+        self.logger.info(&format!(
+            "Exported the snapshot at index {} to {}.",
+            index,
+            path.as_ref().display()
+        ));
+        Ok(())
+    }
+
+    /// Imports the snapshot written by `export_snapshot` into the empty `log_storage` and `fsm`
+    /// of a node of a new cluster, before bootstrapping it.
+    /// The exported membership is replaced with `peers`, which may have different node ids and addresses.
+    /// Every node of the new cluster must import the same snapshot and be bootstrapped with `peers`
+    /// as `Config::initial_peers`.
+    pub async fn import_snapshot<P: AsRef<Path>>(
+        path: P,
+        log_storage: &mut LogStorage,
+        fsm: &mut FSM,
+        peers: &Peers,
+        config: &Config,
+    ) -> Result<()> {
+        SnapshotExport::read(path.as_ref())?
+            .import(log_storage, fsm, peers, config)
+            .await
+    }
+
+    /// Creates a REST+JSON gateway to this node. Run it with `HttpGateway::serve`.
+    #[cfg(feature = "http_gateway")]
+    pub fn http_gateway(&self) -> crate::HttpGateway<LogEntry, LogStorage, FSM> {
+        crate::HttpGateway::new(
+            self.tx_server.clone(),
+            self.raft_server.config.clone(),
+            self.logger.clone(),
+        )
     }
 
     /// Requests a cluster join ticket from the peer.
@@ -171,6 +522,16 @@ impl<
     pub async fn request_id<A: ToSocketAddrs>(
         raft_addr: A,
         peer_addr: String,
+    ) -> Result<ClusterJoinTicket> {
+        Self::request_id_with_options(raft_addr, peer_addr, &ClientOptions::default()).await
+    }
+
+    /// Same as `request_id`, but connects to the peer with the given TLS and auth options,
+    /// and retries the request following `options.retry`.
+    pub async fn request_id_with_options<A: ToSocketAddrs>(
+        raft_addr: A,
+        peer_addr: String,
+        options: &ClientOptions,
     ) -> Result<ClusterJoinTicket> {
         let raft_addr = raft_addr
             .to_socket_addrs()
@@ -179,13 +540,18 @@ impl<
             .unwrap()
             .to_string();
 
-        let mut client = create_client(&peer_addr).await?;
-        let response = client
-            .request_id(raft_service::RequestIdArgs {
-                raft_addr: raft_addr.to_string(),
+        let response = options
+            .retry
+            .retry(|| async {
+                let mut client = create_client_with_options(&peer_addr, options).await?;
+                let response = client
+                    .request_id(raft_service::RequestIdArgs {
+                        raft_addr: raft_addr.clone(),
+                    })
+                    .await?;
+                Ok(response.into_inner())
             })
-            .await?
-            .into_inner();
+            .await?;
 
         let peers: Peers = deserialize(&response.peers)?;
         match response.code() {
@@ -201,4 +567,100 @@ impl<
             }
         }
     }
+
+    /// Same as `request_id_with_options`, but asks the peers in turn and retries with the backoff of `rejoin`
+    /// until one of them answers. Fails with the last error after `rejoin.max_attempts` attempts.
+    pub async fn request_id_with_backoff<A: ToSocketAddrs>(
+        raft_addr: A,
+        peer_addrs: &[String],
+        options: &ClientOptions,
+        rejoin: &RejoinConfig,
+    ) -> Result<ClusterJoinTicket> {
+        if peer_addrs.is_empty() {
+            return Err(Error::ConfigInvalid(
+                "no peer to request the id from".to_owned(),
+            ));
+        }
+        let raft_addr = raft_addr.to_socket_addrs()?.next().unwrap();
+        let mut backoff = Backoff::from(rejoin);
+
+        for peer_addr in peer_addrs.iter().cycle() {
+            let error =
+                match Self::request_id_with_options(raft_addr, peer_addr.clone(), options).await {
+                    Ok(ticket) => return Ok(ticket),
+                    Err(e) => e,
+                };
+            match backoff.next_delay() {
+                Some(delay) => sleep(delay).await,
+                None => return Err(error),
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Waits for SIGTERM or SIGINT, or only for Ctrl+C on the platforms without Unix signals.
+#[cfg(unix)]
+async fn wait_for_shutdown_signal() -> std::io::Result<()> {
+    use signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown_signal() -> std::io::Result<()> {
+    signal::ctrl_c().await
+}
+
+#[cfg(feature = "heed_storage")]
+impl<LogEntry: AbstractLogEntry, FSM: AbstractStateMachine + Send + Sync + Clone + 'static>
+    Raft<LogEntry, crate::HeedStorage, FSM>
+{
+    /// Restarts the node whose `HeedStorage` is in `log_dir`, see `bootstrap_from_storage`.
+    pub fn bootstrap_from_existing(
+        log_dir: &str,
+        fsm: FSM,
+        mut config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        if !Path::new(log_dir).exists() {
+            return Err(Error::ConfigInvalid(format!(
+                "log_dir \"{}\" doesn't exist",
+                log_dir
+            )));
+        }
+        config.log_dir = log_dir.to_owned();
+        let log_storage = crate::HeedStorage::create(log_dir, &config, logger.clone())?;
+        Self::bootstrap_from_storage(log_storage, fsm, config, logger)
+    }
+}
+
+#[cfg(feature = "inmemory_storage")]
+impl<LogEntry: AbstractLogEntry, FSM: AbstractStateMachine + Send + Sync + Clone + 'static>
+    Raft<LogEntry, crate::MemStorage, FSM>
+{
+    /// Creates a new Raft instance backed by `MemStorage`.
+    /// Nothing is persisted on disk, so `config.log_dir` is not used.
+    pub fn bootstrap_in_memory<A: ToSocketAddrs>(
+        node_id: u64,
+        raft_addr: A,
+        fsm: FSM,
+        config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        Self::bootstrap(
+            node_id,
+            raft_addr,
+            crate::MemStorage::create(),
+            fsm,
+            config,
+            logger,
+        )
+    }
 }
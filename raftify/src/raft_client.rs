@@ -1,23 +1,115 @@
 use bytes::Bytes;
-use std::net::ToSocketAddrs;
-use tonic::transport::{Channel, Error as TonicError};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
+};
+use tonic::{
+    service::interceptor::InterceptedService,
+    transport::{Channel, Error as TonicError},
+};
 
-use super::RaftServiceClient;
+use super::{AuthInterceptor, ClusterUuid, GrpcConfig, RaftServiceClient, RetryPolicy, TlsConfig};
 
-// TODO: Support https schema
-pub async fn create_client<A: ToSocketAddrs>(
+pub type RaftClient = RaftServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+
+/// Options applied to the connections made to other peers.
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Connects over https when set.
+    pub tls: Option<TlsConfig>,
+    /// Sent with every request, see `AuthConfig`.
+    pub auth_token: Option<String>,
+    /// Sent with every request once known, see `ClusterUuid`.
+    pub cluster_uuid: ClusterUuid,
+    /// Sent with every request when set, to reach a group hosted by a `RaftGroupManager`.
+    pub group_id: Option<u64>,
+    /// Connections are shared through the pool when set, see `ChannelPool`.
+    pub channel_pool: Option<ChannelPool>,
+    /// Keepalive and timeouts of the connections, see `Config::grpc`.
+    pub grpc: GrpcConfig,
+    /// Retries of the requests made on the node's behalf, see `Config::retry`.
+    pub retry: RetryPolicy,
+}
+
+/// Connections to the peers shared by the raft groups of a `RaftGroupManager`.
+///
+/// The groups talking to the same peer share one HTTP/2 connection instead of opening one each.
+/// Their requests are multiplexed as separate streams, whose frames are interleaved on the connection,
+/// so that a group streaming a snapshot doesn't hold back the heartbeats of the others.
+/// The other side dispatches them by the group id they carry.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelPool {
+    channels: Arc<Mutex<HashMap<SocketAddr, Channel>>>,
+}
+
+impl ChannelPool {
+    /// Returns the channel to `addr`, connecting it if there is none yet.
+    async fn channel(
+        &self,
+        addr: SocketAddr,
+        options: &ClientOptions,
+    ) -> Result<Channel, TonicError> {
+        if let Some(channel) = self.channels.lock().get(&addr) {
+            return Ok(channel.clone());
+        }
+        let channel = connect(addr, options).await?;
+        // Keep the channel connected concurrently by another group, if any.
+        Ok(self.channels.lock().entry(addr).or_insert(channel).clone())
+    }
+
+    /// Number of peers the pool holds a connection to.
+    pub fn len(&self) -> usize {
+        self.channels.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub async fn create_client<A: ToSocketAddrs>(addr: A) -> Result<RaftClient, TonicError> {
+    create_client_with_options(addr, &ClientOptions::default()).await
+}
+
+pub async fn create_client_with_options<A: ToSocketAddrs>(
     addr: A,
-) -> Result<RaftServiceClient<Channel>, TonicError> {
+    options: &ClientOptions,
+) -> Result<RaftClient, TonicError> {
     let addr = addr
         .to_socket_addrs()
         .expect("Invalid socket address format")
         .next()
         .unwrap();
-    let addr = format!("http://{}", addr);
-    let addr = Bytes::copy_from_slice(addr.as_bytes());
-
-    let channel = Channel::from_shared(addr).unwrap().connect().await?;
-    let client = RaftServiceClient::new(channel);
+    let channel = match &options.channel_pool {
+        Some(pool) => pool.channel(addr, options).await?,
+        None => connect(addr, options).await?,
+    };
+    let client = RaftServiceClient::with_interceptor(
+        channel,
+        AuthInterceptor::new(options.auth_token.clone())
+            .with_cluster_uuid(options.cluster_uuid.clone())
+            .with_group_id(options.group_id),
+    );
 
     Ok(client)
 }
+
+async fn connect(addr: SocketAddr, options: &ClientOptions) -> Result<Channel, TonicError> {
+    let scheme = if options.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let addr = format!("{}://{}", scheme, addr);
+    let addr = Bytes::copy_from_slice(addr.as_bytes());
+
+    let mut endpoint = options
+        .grpc
+        .apply_to_endpoint(Channel::from_shared(addr).unwrap());
+    if let Some(tls) = &options.tls {
+        endpoint = endpoint.tls_config(tls.client_tls_config())?;
+    }
+    endpoint.connect().await
+}
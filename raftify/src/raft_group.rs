@@ -0,0 +1,377 @@
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{signal, sync::watch, task::JoinHandle};
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    Request, Response, Status,
+};
+use tonic_health::{server::health_reporter, ServingStatus};
+
+use crate::{
+    auth::check_auth,
+    cluster_uuid::cluster_uuid_of,
+    raft::{eraftpb::Message as RaftMessage, logger::Logger},
+    raft_admin_service::{
+        self,
+        raft_admin_service_server::{RaftAdminService, RaftAdminServiceServer},
+    },
+    raft_server::RaftServer,
+    raft_service::{
+        self,
+        raft_service_server::{RaftService, RaftServiceServer},
+    },
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, ChannelPool, ClientOptions,
+    Config, Error, Raft, RaftNode, Result,
+};
+
+const GROUP_ID_HEADER: &str = "x-raftify-group-id";
+
+pub(crate) fn attach_group_id(metadata: &mut MetadataMap, group_id: u64) {
+    metadata.insert(GROUP_ID_HEADER, MetadataValue::from(group_id));
+}
+
+/// Returns the raft group the request is sent to, if any.
+pub(crate) fn group_id_of(metadata: &MetadataMap) -> Option<u64> {
+    metadata
+        .get(GROUP_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+struct Group<
+    LogEntry: AbstractLogEntry + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
+    FSM: AbstractStateMachine + Clone + 'static,
+> {
+    raft: Raft<LogEntry, LogStorage, FSM>,
+    task: JoinHandle<()>,
+}
+
+type Groups<LogEntry, LogStorage, FSM> =
+    Arc<RwLock<HashMap<u64, Group<LogEntry, LogStorage, FSM>>>>;
+
+/// Hosts many raft groups in one process, e.g. one per shard of the data.
+///
+/// Every group is a `RaftNode` with its own state machine, storage and `Config`,
+/// and the groups share the gRPC server listening on `raft_addr`, the ticker driving their clocks
+/// and the connections to the other peers, see `ChannelPool`.
+/// The requests carry the id of the group they are sent to, which the nodes of a group attach
+/// through `Config::group_id`, so the same group has to be hosted under the same id on every peer.
+///
+/// ```ignore
+/// let manager = RaftGroupManager::new("127.0.0.1:60061", Config::default(), logger)?;
+/// for shard in 1..=3 {
+///     let config = Config { log_dir: format!("./logs/shard-{}", shard), ..Default::default() };
+///     manager.add_group(shard, node_id, HeedStorage::create(..)?, HashStore::new(), config)?;
+/// }
+/// manager.run().await?;
+/// ```
+#[derive(Clone)]
+pub struct RaftGroupManager<
+    LogEntry: AbstractLogEntry + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
+    FSM: AbstractStateMachine + Clone + 'static,
+> {
+    raft_addr: SocketAddr,
+    config: Config,
+    groups: Groups<LogEntry, LogStorage, FSM>,
+    ticker: Arc<watch::Sender<u64>>,
+    channel_pool: ChannelPool,
+    logger: Arc<dyn Logger>,
+}
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Send + Sync + Clone + 'static,
+        FSM: AbstractStateMachine + Send + Sync + Clone + 'static,
+    > RaftGroupManager<LogEntry, LogStorage, FSM>
+{
+    /// `config.tls`, `config.auth` and `config.tick_interval` apply to the shared server and ticker,
+    /// and the groups and the ticker run on `config.runtime` when set.
+    pub fn new<A: ToSocketAddrs>(
+        raft_addr: A,
+        config: Config,
+        logger: Arc<dyn Logger>,
+    ) -> Result<Self> {
+        let raft_addr = raft_addr.to_socket_addrs()?.next().unwrap();
+        Ok(Self {
+            raft_addr,
+            config,
+            groups: Arc::new(RwLock::new(HashMap::new())),
+            ticker: Arc::new(watch::channel(0).0),
+            channel_pool: ChannelPool::default(),
+            logger,
+        })
+    }
+
+    /// Bootstraps the node `node_id` of the group `group_id` and starts running it.
+    /// Should be called within a tokio runtime, before or after `run`.
+    ///
+    /// `config.group_id` is set to `group_id`, and the group ticks at the `tick_interval` of the manager
+    /// and connects to the peers through its `ChannelPool`.
+    /// The gossip and the compacted log sweeper aren't run for the groups.
+    pub fn add_group(
+        &self,
+        group_id: u64,
+        node_id: u64,
+        log_storage: LogStorage,
+        fsm: FSM,
+        mut config: Config,
+    ) -> Result<RaftNode<LogEntry, LogStorage, FSM>> {
+        let mut groups = self.groups.write();
+        if groups.contains_key(&group_id) {
+            return Err(Error::Rejected(format!(
+                "Raft group {} already exists",
+                group_id
+            )));
+        }
+
+        config.group_id = Some(group_id);
+        config.tick_interval = self.config.tick_interval;
+        config.channel_pool = Some(self.channel_pool.clone());
+        let raft = Raft::bootstrap(
+            node_id,
+            self.raft_addr,
+            log_storage,
+            fsm,
+            config,
+            self.logger.clone(),
+        )?;
+
+        let raft_node = raft.raft_node.clone();
+        let ticker = self.ticker.subscribe();
+        let logger = self.logger.clone();
+        let task = self.config.runtime_handle().spawn(async move {
+            match raft_node.run_with_ticker(ticker).await {
+                Ok(_) => logger.info(&format!("Raft group {} quitted.", group_id)),
+                Err(err) => logger.error(&format!(
+                    "Raft group {} quitted with the error. {:?}",
+                    group_id, err
+                )),
+            }
+        });
+
+        let raft_node = raft.raft_node.clone();
+        groups.insert(group_id, Group { raft, task });
+        Ok(raft_node)
+    }
+
+    /// Stops the node of the group `group_id`, which isn't served anymore, and returns it.
+    /// The group keeps its storage, so it can be added again later.
+    pub fn remove_group(&self, group_id: u64) -> Option<RaftNode<LogEntry, LogStorage, FSM>> {
+        let group = self.groups.write().remove(&group_id)?;
+        // The node tears its tasks down before its own task ends.
+        group.raft.raft_node.cancellation_token().cancel();
+        Some(group.raft.raft_node)
+    }
+
+    pub fn group(&self, group_id: u64) -> Option<RaftNode<LogEntry, LogStorage, FSM>> {
+        self.groups
+            .read()
+            .get(&group_id)
+            .map(|group| group.raft.raft_node.clone())
+    }
+
+    /// Options of the connections made to the groups hosted by other nodes.
+    pub(crate) fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            channel_pool: Some(self.channel_pool.clone()),
+            ..self.config.client_options()
+        }
+    }
+
+    pub fn group_ids(&self) -> Vec<u64> {
+        let mut group_ids = self.groups.read().keys().copied().collect::<Vec<_>>();
+        group_ids.sort();
+        group_ids
+    }
+
+    /// Serves the raft RPCs of every group and ticks them until Ctrl+C is pressed.
+    pub async fn run(self) -> Result<()> {
+        self.logger.debug(&format!(
+            "RaftGroupManager starts to listen gRPC requests on \"{}\"...",
+            self.raft_addr
+        ));
+
+        let tick_interval = Duration::from_secs_f32(self.config.tick_interval);
+        let ticker = self.ticker.clone();
+        let ticker_handle = self.config.runtime_handle().spawn(async move {
+            let mut interval = tokio::time::interval(tick_interval);
+            loop {
+                interval.tick().await;
+                ticker.send_modify(|ticks| *ticks += 1);
+            }
+        });
+
+        let mut builder = self.config.grpc.server_builder();
+        if let Some(tls) = &self.config.tls {
+            builder = builder.tls_config(tls.server_tls_config())?;
+        }
+
+        let (mut health_reporter, health_service) = health_reporter();
+        health_reporter
+            .set_service_status("", ServingStatus::Serving)
+            .await;
+
+        let router = RaftGroupRouter {
+            groups: self.groups.clone(),
+        };
+        let logger = self.logger.clone();
+        let result = builder
+            .add_service(health_service)
+            .add_service(RaftAdminServiceServer::new(router.clone()))
+            .add_service(RaftServiceServer::with_interceptor(
+                router,
+                check_auth(self.config.auth.clone()),
+            ))
+            .serve_with_shutdown(self.raft_addr, async move {
+                signal::ctrl_c().await.ok();
+                logger.info("Ctrl+C signal detected. Shutting down...");
+            })
+            .await;
+
+        ticker_handle.abort();
+        let groups: Vec<_> = self
+            .groups
+            .write()
+            .drain()
+            .map(|(_, group)| group)
+            .collect();
+        for group in groups.iter() {
+            group.raft.raft_node.cancellation_token().cancel();
+        }
+        for group in groups {
+            let _ = group.task.await;
+        }
+        result?;
+        Ok(())
+    }
+}
+
+/// Dispatches the requests of the shared server to the `RaftServer` of the group they are sent to.
+#[derive(Clone)]
+struct RaftGroupRouter<
+    LogEntry: AbstractLogEntry + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
+    FSM: AbstractStateMachine + Clone + 'static,
+> {
+    groups: Groups<LogEntry, LogStorage, FSM>,
+}
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Send + Clone + 'static,
+        FSM: AbstractStateMachine + Clone + 'static,
+    > RaftGroupRouter<LogEntry, LogStorage, FSM>
+{
+    /// The cluster UUID is checked here, as it differs between the groups sharing the server.
+    fn route<T>(
+        &self,
+        request: &Request<T>,
+    ) -> std::result::Result<RaftServer<LogEntry, LogStorage, FSM>, Status> {
+        let group_id = group_id_of(request.metadata())
+            .ok_or_else(|| Status::invalid_argument("Missing raft group id"))?;
+        let server = self
+            .groups
+            .read()
+            .get(&group_id)
+            .map(|group| group.raft.raft_server.clone())
+            .ok_or_else(|| {
+                Status::not_found(format!("Raft group {} is not hosted here", group_id))
+            })?;
+
+        if let Some(cluster_uuid) = cluster_uuid_of(request.metadata()) {
+            server.cluster_uuid.check(&cluster_uuid)?;
+        }
+        Ok(server)
+    }
+}
+
+/// Implements `$service` for `RaftGroupRouter` by routing every call to the `RaftServer` of the group.
+macro_rules! route_to_group {
+    (
+        $service:ident { $(type $name:ident = $type:ty;)* }
+        $($method:ident($args:ty) -> $response:ty),* $(,)?
+    ) => {
+        #[tonic::async_trait]
+        impl<
+                LogEntry: AbstractLogEntry + 'static,
+                LogStorage: AbstractStableStorage + Sync + Send + Clone + 'static,
+                FSM: AbstractStateMachine + Clone + 'static,
+            > $service for RaftGroupRouter<LogEntry, LogStorage, FSM>
+        {
+            $(type $name = $type;)*
+
+            $(async fn $method(
+                &self,
+                request: Request<$args>,
+            ) -> std::result::Result<Response<$response>, Status> {
+                let server = self.route(&request)?;
+                $service::$method(&server, request).await
+            })*
+        }
+    };
+}
+
+route_to_group! {
+    RaftService {
+        type WatchClusterEventsStream =
+            <RaftServer<LogEntry, LogStorage, FSM> as RaftService>::WatchClusterEventsStream;
+    }
+    request_id(raft_service::RequestIdArgs) -> raft_service::RequestIdResponse,
+    change_config(raft_service::ChangeConfigArgs) -> raft_service::ChangeConfigResponse,
+    send_message(RaftMessage) -> raft_service::Empty,
+    send_messages(raft_service::MessageBatch) -> raft_service::Empty,
+    install_snapshot(tonic::Streaming<raft_service::SnapshotChunk>) -> raft_service::Empty,
+    get_snapshot_offset(raft_service::SnapshotOffsetArgs) -> raft_service::SnapshotOffsetResponse,
+    propose(raft_service::ProposeArgs) -> raft_service::ProposeResponse,
+    debug_node(raft_service::Empty) -> raft_service::DebugNodeResponse,
+    get_peers(raft_service::Empty) -> raft_service::GetPeersResponse,
+    leave_joint(raft_service::Empty) -> raft_service::Empty,
+    set_peers(raft_service::Peers) -> raft_service::Empty,
+    create_snapshot(raft_service::Empty) -> raft_service::Empty,
+    read_index(raft_service::Empty) -> raft_service::ReadIndexResponse,
+    query(raft_service::QueryArgs) -> raft_service::QueryResponse,
+    transfer_leader(raft_service::TransferLeaderArgs) -> raft_service::TransferLeaderResponse,
+    set_priority(raft_service::SetPriorityArgs) -> raft_service::Empty,
+    watch_cluster_events(raft_service::Empty) -> Self::WatchClusterEventsStream,
+    gossip(raft_service::GossipArgs) -> raft_service::GossipResponse,
+}
+
+route_to_group! {
+    RaftAdminService {}
+    transfer_leader(raft_service::TransferLeaderArgs) -> raft_service::TransferLeaderResponse,
+    create_snapshot(raft_service::Empty) -> raft_service::Empty,
+    compact_logs(raft_admin_service::CompactLogsArgs) -> raft_admin_service::AdminResponse,
+    purge_tombstones(raft_admin_service::PurgeTombstonesArgs) -> raft_admin_service::AdminResponse,
+    set_ticks(raft_admin_service::SetTicksArgs) -> raft_admin_service::AdminResponse,
+    update_runtime_config(raft_admin_service::UpdateRuntimeConfigArgs) -> raft_admin_service::AdminResponse,
+    set_peer_labels(raft_admin_service::SetPeerLabelsArgs) -> raft_admin_service::AdminResponse,
+    remove_peer(raft_admin_service::RemovePeerArgs) -> raft_admin_service::AdminResponse,
+    debug_node(raft_service::Empty) -> raft_service::DebugNodeResponse,
+    get_membership_history(raft_service::Empty) -> raft_admin_service::MembershipHistoryResponse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuthInterceptor;
+    use tonic::service::Interceptor;
+
+    #[test]
+    fn test_group_id_round_trip() {
+        let request = AuthInterceptor::new(None)
+            .with_group_id(Some(7))
+            .call(Request::new(()))
+            .unwrap();
+        assert_eq!(group_id_of(request.metadata()), Some(7));
+
+        let request = AuthInterceptor::new(None).call(Request::new(())).unwrap();
+        assert_eq!(group_id_of(request.metadata()), None);
+    }
+}
@@ -0,0 +1,55 @@
+use tokio::sync::mpsc;
+use tracing::{Instrument, Span};
+
+use crate::{AbstractStateMachine, ApplyExecutor, Result};
+
+pub(crate) enum ApplyRequest<FSM> {
+    Batch(ApplyBatch),
+    /// Replaces the state machine of the task with the one the ready loop restored,
+    /// e.g. from a snapshot, once the batches handed before are applied.
+    Replace(FSM),
+}
+
+/// Consecutive committed entries handed to the apply task.
+pub(crate) struct ApplyBatch {
+    /// Index and data of each entry.
+    pub log_entries: Vec<(u64, Vec<u8>)>,
+    pub span: Span,
+}
+
+/// Applies the batches to the state machine off the ready loop, so that a slow `apply`
+/// doesn't delay the heartbeats, the persistence and the messages of the node.
+/// The results are sent back in the order of the batches.
+pub(crate) async fn run_apply_task<FSM: AbstractStateMachine + 'static>(
+    mut fsm: FSM,
+    executor: ApplyExecutor,
+    mut rx_apply: mpsc::Receiver<ApplyRequest<FSM>>,
+    tx_results: mpsc::UnboundedSender<Vec<Result<Vec<u8>>>>,
+) {
+    while let Some(request) = rx_apply.recv().await {
+        let ApplyBatch { log_entries, span } = match request {
+            ApplyRequest::Batch(batch) => batch,
+            ApplyRequest::Replace(restored) => {
+                fsm = restored;
+                continue;
+            }
+        };
+        let applied = executor
+            .run(
+                async move {
+                    let results = fsm.apply_indexed_batch(log_entries).await;
+                    (fsm, results)
+                }
+                .instrument(span),
+            )
+            .await;
+        // The state machine is lost if the executor couldn't run the batch, e.g. if it panicked.
+        let Ok((applied_fsm, results)) = applied else {
+            break;
+        };
+        fsm = applied_fsm;
+        if tx_results.send(results).is_err() {
+            break;
+        }
+    }
+}
@@ -9,14 +9,14 @@ use crate::{
         eraftpb::{ConfChange, ConfChangeType, Entry, EntryType},
         raw_node::RawNode,
     },
-    storage::StableStorage,
+    storage::AbstractStableStorage,
     Peers,
 };
 
 /// Commit the configuration change to add all follower nodes to the cluster.
 #[deprecated]
 #[allow(dead_code)]
-pub async fn bootstrap_peers<T: StableStorage>(
+pub async fn bootstrap_peers<T: AbstractStableStorage>(
     peers: Arc<Mutex<Peers>>,
     raw_node: &mut RawNode<T>,
 ) -> Result<()> {
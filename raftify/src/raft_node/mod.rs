@@ -1,33 +1,49 @@
+mod apply_task;
 mod bootstrap;
+mod persist_task;
 mod response_sender;
 pub mod role;
+pub mod status;
 pub mod utils;
 
 use bincode::{deserialize, serialize};
+use bytes::Bytes;
 use prost::Message as PMessage;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
     marker::PhantomData,
     net::{SocketAddr, ToSocketAddrs},
     str::FromStr,
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
     time::{Duration, Instant},
 };
 use tokio::{
-    sync::{mpsc, oneshot, Mutex},
-    time::timeout,
+    sync::{broadcast, mpsc, oneshot, watch, Mutex},
+    time::{sleep, timeout},
 };
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tonic::Request;
+use tracing::Span;
 
+use apply_task::{run_apply_task, ApplyBatch, ApplyRequest};
+use persist_task::{run_persist_task, PersistBatch, PersistedBatch};
 use response_sender::ResponseSender;
+use status::{NodeRole, RaftStatus};
 use utils::inspect_raftnode;
 
 use crate::{
-    create_client,
+    admission::RateLimiter,
+    backoff::Backoff,
+    create_client_with_options,
+    delta_snapshot::{decode_delta_context, encode_delta_context},
+    disk_usage::{run_disk_monitor, DiskUsage, DiskUsageLevel},
+    encryption::{decrypt, encrypt},
     error::{Result, SendMessageError},
+    membership_history::{update_tombstones, ConfChangeContext},
     raft::{
         eraftpb::{
             ConfChange, ConfChangeSingle, ConfChangeTransition, ConfChangeType, ConfChangeV2,
@@ -36,8 +52,12 @@ use crate::{
         formatter::{format_confchangev2, format_message},
         logger::Logger,
         raw_node::RawNode,
+        GetEntriesContext, ReadOnlyOption, ReadState, Ready, SnapshotStatus, StateRole, INVALID_ID,
+    },
+    raft_server::forwarded_read_index_request,
+    raft_service::{
+        self, ChangeConfigResultType, ClusterEvent, ClusterEventType, ProposeArgs, ResultCode,
     },
-    raft_service::{self, ChangeConfigResultType, ProposeArgs},
     request::{
         common::confchange_request::ConfChangeRequest, local_request_message::LocalRequestMsg,
         self_request_message::SelfMessage, server_request_message::ServerRequestMsg,
@@ -45,19 +65,32 @@ use crate::{
     response::{
         local_response_message::LocalResponseMsg,
         server_response_message::{
-            ConfChangeResponseResult, RequestIdResponseResult, ResponseResult, ServerResponseMsg,
+            ConfChangeResponseResult, ProposeOutcome, ProposeResponseResult, QueryResponseResult,
+            ReadIndexResponseResult, RequestIdResponseResult, ResponseResult, ServerResponseMsg,
         },
         ResponseMessage,
     },
+    session::{
+        compress_entry_data, split_sessions, ProposalContext, SessionCheck, SessionRecord,
+        SessionTable, PEER_LABELS_ENTRY, RUNTIME_CONFIG_ENTRY, SHARD_ENTRY,
+    },
+    shard::ShardCommand,
+    snapshot_store::upload_snapshot,
+    snapshot_stream::split_snapshot,
+    systemd::SystemdNotifier,
+    telemetry,
     utils::{membership::to_confchange_v2, oneshot_mutex::OneShotMutex},
-    AbstractLogEntry, AbstractStateMachine, ClusterJoinTicket, Config, Error, InitialRole, Peers,
-    RaftServiceClient, StableStorage,
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, AbstractStateMachineQuery,
+    ClientOptions, ClusterJoinTicket, ClusterUuid, Config, ConnectionState, DiskFullAction, Error,
+    InitialRole, KeyRange, MembershipChangeRecord, NodeIdentity, NodeTombstone, PeerLabels, Peers,
+    ProposalSession, QueryConsistency, RaftClient, RaftEventHandler, RejoinConfig, RuntimeConfig,
+    SnapshotStore, StorageStats,
 };
 
 #[derive(Clone)]
 pub struct RaftNode<
     LogEntry: AbstractLogEntry + Send + 'static,
-    LogStorage: StableStorage + Send + Clone + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
     FSM: AbstractStateMachine + Clone + 'static,
 > {
     // The lock of RaftNodeCore is locked when RaftNode.run is called and is never released until program terminates.
@@ -65,11 +98,22 @@ pub struct RaftNode<
     inner: Arc<OneShotMutex<RaftNodeCore<LogEntry, LogStorage, FSM>>>,
     // RaftNode.(method_call) >>> RaftNodeCore.run
     tx_local: mpsc::Sender<LocalRequestMsg<LogEntry, LogStorage, FSM>>,
+    // RaftNodeCore.run >>> RaftNode.subscribe receivers
+    tx_applied: broadcast::Sender<(u64, LogEntry)>,
+    // Used to forward requests to the leader.
+    client_options: ClientOptions,
+    // Backoff of the retried joins.
+    rejoin: RejoinConfig,
+    // Cancelled once the node quits, tearing down the tasks of the node and the RaftServer.
+    shutdown: CancellationToken,
+    // Tasks spawned by RaftNodeCore.run, closed once they are cancelled.
+    tasks: TaskTracker,
+    logger: Arc<dyn Logger>,
 }
 
 impl<
         LogEntry: AbstractLogEntry + Send + 'static,
-        LogStorage: StableStorage + Send + Clone + 'static,
+        LogStorage: AbstractStableStorage + Send + Clone + 'static,
         FSM: AbstractStateMachine + Clone + Send + 'static,
     > RaftNode<LogEntry, LogStorage, FSM>
 {
@@ -81,6 +125,7 @@ impl<
         fsm: FSM,
         config: Config,
         raft_addr: SocketAddr,
+        cluster_uuid: ClusterUuid,
         logger: Arc<dyn Logger>,
         tx_server: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
         rx_server: mpsc::Receiver<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
@@ -94,6 +139,7 @@ impl<
             fsm,
             config,
             raft_addr,
+            cluster_uuid,
             logger,
             tx_server,
             rx_server,
@@ -101,11 +147,30 @@ impl<
             rx_local,
         )
         .map(|core| Self {
+            tx_applied: core.tx_applied.clone(),
+            client_options: core.client_options(),
+            rejoin: core.config.rejoin.clone(),
+            shutdown: core.shutdown.clone(),
+            tasks: core.tasks.clone(),
+            logger: core.logger.clone(),
             inner: Arc::new(OneShotMutex::new(core)),
             tx_local: tx_local.clone(),
         })
     }
 
+    /// Returns the UUID of the cluster, or `None` until this node learns it from the leader.
+    pub fn cluster_uuid(&self) -> Option<String> {
+        self.client_options.cluster_uuid.get()
+    }
+
+    /// Returns a stream of `(index, entry)` pairs applied to the state machine from now on.
+    ///
+    /// A receiver which falls more than 100 entries behind gets `RecvError::Lagged`
+    /// and skips the missed entries. Witness nodes publish nothing.
+    pub fn subscribe(&self) -> broadcast::Receiver<(u64, LogEntry)> {
+        self.tx_applied.subscribe()
+    }
+
     pub async fn is_leader(&self) -> Result<bool> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
@@ -148,6 +213,65 @@ impl<
         }
     }
 
+    /// Returns the current term of this node.
+    pub async fn get_term(&self) -> Result<u64> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetTerm { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetTerm { term } => Ok(term),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the highest log index known to be committed by this node.
+    pub async fn get_commit_index(&self) -> Result<u64> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetCommitIndex { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetCommitIndex { commit_index } => Ok(commit_index),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the index of the last entry applied to the state machine.
+    pub async fn get_last_applied(&self) -> Result<u64> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetLastApplied { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetLastApplied { last_applied } => Ok(last_applied),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn get_role(&self) -> Result<NodeRole> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetRole { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetRole { role } => Ok(role),
+            _ => unreachable!(),
+        }
+    }
+
     pub async fn get_peers(&self) -> Result<Peers> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
@@ -162,13 +286,14 @@ impl<
         }
     }
 
-    pub async fn add_peer<A: ToSocketAddrs>(
+    /// Adds `addr` to the peers of this node. `addr` can be a hostname, see `Peer::host`.
+    pub async fn add_peer<A: ToSocketAddrs + ToString>(
         &self,
         id: u64,
         addr: A,
         role: Option<InitialRole>,
     ) -> Result<()> {
-        let addr = addr.to_socket_addrs().unwrap().next().unwrap().to_string();
+        let addr = addr.to_string();
         let (tx, rx) = oneshot::channel();
         self.tx_local
             .send(LocalRequestMsg::AddPeer {
@@ -215,6 +340,21 @@ impl<
         }
     }
 
+    /// Returns the term, log indexes, role, leader and, on the leader, the replication progress of the peers.
+    pub async fn status(&self) -> Result<RaftStatus> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetStatus { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetStatus { status } => Ok(status),
+            _ => unreachable!(),
+        }
+    }
+
     pub async fn state_machine(&self) -> Result<FSM> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
@@ -243,11 +383,111 @@ impl<
         }
     }
 
-    pub async fn propose(&self, proposal: Vec<u8>) -> Result<()> {
+    /// Returns the membership changes applied on this node, oldest first.
+    /// See `MembershipChangeRecord`.
+    pub async fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>> {
+        self.storage().await?.membership_history()
+    }
+
+    /// Returns the tombstones of the nodes removed from the cluster. See `NodeTombstone`.
+    pub async fn tombstones(&self) -> Result<Vec<NodeTombstone>> {
+        self.storage().await?.tombstones()
+    }
+
+    /// Purges the tombstones of `node_ids` kept on this node, or all of them if `node_ids` is empty,
+    /// so that `RequestId` can hand out their ids again.
+    /// Only do this once no message of the removed nodes can be in flight anymore.
+    pub async fn purge_tombstones(&self, node_ids: Vec<u64>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::PurgeTombstones {
+                node_ids,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::PurgeTombstones { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the size and bounds of the persisted log. See `StorageStats`.
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        self.storage().await?.stats()
+    }
+
+    /// Returns the persisted log entries in `[low, high)`.
+    ///
+    /// Fails with `StorageError::Compacted` if `low` has already been compacted away,
+    /// and is rejected if `high` goes past the last index + 1.
+    /// The entry data is returned as persisted, i.e. compressed if `entry_compression_threshold` is set.
+    pub async fn get_log_entries(&self, low: u64, high: u64) -> Result<Vec<Entry>> {
+        let storage = self.storage().await?;
+        let last_index = storage.last_index()?;
+
+        if low > high || high > last_index + 1 {
+            return Err(Error::Rejected(format!(
+                "Invalid log range [{}, {}) (last_index={})",
+                low, high, last_index
+            )));
+        }
+
+        if low == high {
+            return Ok(vec![]);
+        }
+
+        Ok(storage.entries(low, high, None, GetEntriesContext::empty(false))?)
+    }
+
+    /// Proposes a new entry and resolves once it is committed and applied,
+    /// with the output of `AbstractStateMachine::apply` in `ProposeOutcome::data`.
+    pub async fn propose(&self, proposal: impl Into<Bytes>) -> Result<ProposeOutcome> {
+        self.propose_inner(proposal.into(), None).await
+    }
+
+    /// Same as `propose`, but fails with `Error::Timeout` if the entry isn't committed in time.
+    /// The pending proposal is released when the deadline passes, as it is when the future is dropped.
+    pub async fn propose_with_timeout(
+        &self,
+        proposal: impl Into<Bytes>,
+        duration: Duration,
+    ) -> Result<ProposeOutcome> {
+        timeout(duration, self.propose_inner(proposal.into(), None))
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    /// Proposes a new entry on behalf of a client session.
+    ///
+    /// If an entry with the same session and sequence number has already been applied,
+    /// it is not applied again and the outcome of the first application is returned instead.
+    /// The nodes remember the last `Config::max_sessions` sessions, including in their snapshots.
+    pub async fn propose_with_session(
+        &self,
+        session: ProposalSession,
+        proposal: impl Into<Bytes>,
+    ) -> Result<ProposeOutcome> {
+        self.propose_inner(proposal.into(), Some(session)).await
+    }
+
+    #[tracing::instrument(name = "RaftNode::propose", skip_all)]
+    async fn propose_inner(
+        &self,
+        proposal: Bytes,
+        session: Option<ProposalSession>,
+    ) -> Result<ProposeOutcome> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
             .send(LocalRequestMsg::Propose {
                 proposal: proposal.clone(),
+                session,
+                span: Span::current(),
                 tx_msg: tx,
             })
             .await
@@ -256,18 +496,99 @@ impl<
         let resp = rx.await?;
         match resp {
             LocalResponseMsg::Propose { result } => match result {
-                ResponseResult::Success => (),
-                ResponseResult::Error(e) => return Err(e),
-                ResponseResult::WrongLeader { leader_addr, .. } => {
-                    let mut client = create_client(leader_addr).await?;
-                    client
-                        .propose(Request::new(ProposeArgs { msg: proposal }))
+                ProposeResponseResult::Success(outcome) => Ok(outcome),
+                ProposeResponseResult::Error(e) => Err(e),
+                ProposeResponseResult::WrongLeader { leader_addr, .. } => {
+                    let response = self
+                        .client_options
+                        .retry
+                        .retry_proposal(session.is_some(), || async {
+                            let mut client =
+                                create_client_with_options(&leader_addr, &self.client_options)
+                                    .await?;
+                            let response = client
+                                .propose(Request::new(ProposeArgs::new(proposal.clone(), session)))
+                                .await?;
+                            Ok(response.into_inner())
+                        })
                         .await?;
+
+                    if !response.error.is_empty() {
+                        return Err(Error::Rejected(
+                            String::from_utf8_lossy(&response.error).to_string(),
+                        ));
+                    }
+                    Ok(ProposeOutcome {
+                        index: response.index,
+                        term: response.term,
+                        data: response.data,
+                    })
+                }
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Proposes multiple entries at once to save the per-proposal channel and fsync overhead.
+    /// Resolves with the outcome of the last entry once all entries are committed.
+    /// The leader appends the entries at once, or drops them all, e.g. during a leadership transfer.
+    #[tracing::instrument(name = "RaftNode::propose_batch", skip_all, fields(len = entries.len()))]
+    pub async fn propose_batch(&self, entries: Vec<LogEntry>) -> Result<ProposeOutcome> {
+        if entries.is_empty() {
+            return Err(Error::Rejected("Empty batch proposal".to_owned()));
+        }
+
+        let proposals = entries
+            .iter()
+            .map(|entry| entry.encode_bytes())
+            .collect::<Result<Vec<_>>>()?;
+
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::ProposeBatch {
+                proposals: proposals.clone(),
+                span: Span::current(),
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Propose { result } => match result {
+                ProposeResponseResult::Success(outcome) => Ok(outcome),
+                ProposeResponseResult::Error(e) => Err(e),
+                // The whole batch is forwarded in one request, so that the leader appends it at once too.
+                ProposeResponseResult::WrongLeader { leader_addr, .. } => {
+                    let mut client = self
+                        .client_options
+                        .retry
+                        .retry(|| async {
+                            Ok(
+                                create_client_with_options(&leader_addr, &self.client_options)
+                                    .await?,
+                            )
+                        })
+                        .await?;
+                    let response = client
+                        .propose(Request::new(ProposeArgs::batch(proposals)))
+                        .await?
+                        .into_inner();
+
+                    if !response.error.is_empty() {
+                        return Err(Error::Rejected(
+                            String::from_utf8_lossy(&response.error).to_string(),
+                        ));
+                    }
+                    Ok(ProposeOutcome {
+                        index: response.index,
+                        term: response.term,
+                        data: response.data,
+                    })
                 }
             },
             _ => unreachable!(),
         }
-        Ok(())
     }
 
     pub async fn change_config(
@@ -287,12 +608,14 @@ impl<
         let resp = rx.await?;
         match resp {
             LocalResponseMsg::ConfigChange { result } => match result {
+                // The errors reaching the leader are returned to the caller.
                 ConfChangeResponseResult::WrongLeader { leader_addr, .. } => {
-                    let mut client = create_client(leader_addr).await.unwrap();
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options).await?;
 
                     let conf_change: ConfChangeRequest = conf_change;
                     let conf_change: raft_service::ChangeConfigArgs = conf_change.into();
-                    let res = client.change_config(conf_change).await.unwrap();
+                    let res = client.change_config(conf_change).await?;
 
                     let result = res.into_inner();
 
@@ -301,7 +624,7 @@ impl<
                     {
                         Ok(ConfChangeResponseResult::JoinSuccess {
                             assigned_ids: result.assigned_ids,
-                            peers: deserialize(result.peers.as_slice()).unwrap(),
+                            peers: deserialize(result.peers.as_slice())?,
                         })
                     } else {
                         Ok(ConfChangeResponseResult::Error(Error::Unknown))
@@ -313,179 +636,816 @@ impl<
         }
     }
 
-    pub async fn get_cluster_size(&self) -> Result<usize> {
+    /// Waits until the local state machine can be read linearizably, relying on the leader lease
+    /// instead of a ReadIndex heartbeat round trip.
+    ///
+    /// Requires `ReadOnlyOption::LeaseBased` (and therefore `check_quorum`) in `RaftConfig`,
+    /// and should be called on the leader.
+    pub async fn read_lease(&self) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::GetClusterSize { tx_msg: tx })
+            .send(LocalRequestMsg::ReadLease { tx_msg: tx })
             .await
             .unwrap();
 
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::GetClusterSize { size } => Ok(size),
+            LocalResponseMsg::ReadIndex { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { leader_id, .. } => Err(Error::Rejected(format!(
+                    "Lease read should be served by the leader (leader_id: {})",
+                    leader_id
+                ))),
+            },
             _ => unreachable!(),
         }
     }
 
-    pub async fn quit(&self) -> Result<()> {
+    /// Waits until the local state machine can be read linearizably using ReadIndex.
+    ///
+    /// When called on a follower, the ReadIndex request is forwarded to the leader over gRPC
+    /// and the call resolves once the returned index has been applied locally.
+    pub async fn read_index(&self) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::Quit { tx_msg: tx })
+            .send(LocalRequestMsg::ReadIndex { tx_msg: tx })
             .await
             .unwrap();
+
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::Quit {} => Ok(()),
+            LocalResponseMsg::ReadIndex { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { leader_addr, .. } => {
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options).await?;
+                    let response = client
+                        .read_index(forwarded_read_index_request())
+                        .await?
+                        .into_inner();
+
+                    match response.code() {
+                        ResultCode::Ok => self.wait_applied(response.index).await,
+                        _ => Err(Error::Rejected(
+                            String::from_utf8_lossy(&response.error).to_string(),
+                        )),
+                    }
+                }
+            },
             _ => unreachable!(),
         }
     }
 
-    pub async fn transfer_leader(&self, node_id: u64) -> Result<()> {
+    /// Answers a read-only query from the state machine, without going through the log.
+    ///
+    /// `QueryConsistency::Leader` queries are forwarded to the leader over gRPC when called on a follower.
+    pub async fn query(
+        &self,
+        query: FSM::Query,
+        consistency: QueryConsistency,
+    ) -> Result<FSM::QueryResponse>
+    where
+        FSM: AbstractStateMachineQuery,
+    {
+        match consistency {
+            QueryConsistency::Stale => {}
+            QueryConsistency::Linearizable => self.read_index().await?,
+            QueryConsistency::Leader if self.is_leader().await? => {}
+            QueryConsistency::Leader => {
+                let leader_id = self.get_leader_id().await?;
+                let leader_addr = self
+                    .get_peers()
+                    .await?
+                    .get(&leader_id)
+                    .map(|peer| peer.addr.to_string())
+                    .ok_or_else(|| {
+                        Error::Rejected("There is no leader in the cluster at the time".to_owned())
+                    })?;
+
+                // Queries don't change the state, so they are retried on every error of the policy.
+                let args = raft_service::QueryArgs {
+                    query: serialize(&query)?,
+                    consistency: raft_service::QueryConsistency::from(consistency) as i32,
+                };
+                let response = self
+                    .client_options
+                    .retry
+                    .retry(|| async {
+                        let mut client =
+                            create_client_with_options(&leader_addr, &self.client_options).await?;
+                        let response = client.query(Request::new(args.clone())).await?;
+                        Ok(response.into_inner())
+                    })
+                    .await?;
+
+                return match response.code() {
+                    ResultCode::Ok => Ok(deserialize(&response.data)?),
+                    _ => Err(Error::Rejected(
+                        String::from_utf8_lossy(&response.error).to_string(),
+                    )),
+                };
+            }
+        }
+
+        self.state_machine().await?.query(query).await
+    }
+
+    async fn wait_applied(&self, index: u64) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::TransferLeader {
-                node_id,
-                tx_msg: tx,
-            })
+            .send(LocalRequestMsg::WaitApplied { index, tx_msg: tx })
             .await
             .unwrap();
+
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::TransferLeader {} => Ok(()),
+            LocalResponseMsg::ReadIndex { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
             _ => unreachable!(),
         }
     }
 
-    pub async fn campaign(&self) -> Result<()> {
+    /// Returns the node ids of the voters in the current configuration.
+    pub async fn get_voters(&self) -> Result<Vec<u64>> {
+        self.get_members().await.map(|(voters, _)| voters)
+    }
+
+    /// Returns the node ids of the learners in the current configuration.
+    pub async fn get_learners(&self) -> Result<Vec<u64>> {
+        self.get_members().await.map(|(_, learners)| learners)
+    }
+
+    async fn get_members(&self) -> Result<(Vec<u64>, Vec<u64>)> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::Campaign { tx_msg: tx })
+            .send(LocalRequestMsg::GetMembers { tx_msg: tx })
             .await
             .unwrap();
+
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::Campaign {} => Ok(()),
+            LocalResponseMsg::GetMembers { voters, learners } => Ok((voters, learners)),
             _ => unreachable!(),
         }
     }
 
-    pub async fn demote(&self, term: u64, leader_id: u64) -> Result<()> {
+    pub async fn get_cluster_size(&self) -> Result<usize> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::Demote {
-                term,
-                leader_id,
-                tx_msg: tx,
-            })
+            .send(LocalRequestMsg::GetClusterSize { tx_msg: tx })
             .await
             .unwrap();
+
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::Demote {} => Ok(()),
+            LocalResponseMsg::GetClusterSize { size } => Ok(size),
             _ => unreachable!(),
         }
     }
 
-    pub async fn leave(&self) -> Result<()> {
+    /// Quits the node, and waits until the tasks it spawned, e.g. the ones sending the messages
+    /// and the snapshots, are torn down. The `RaftServer` run by `Raft::run` stops along with the node.
+    pub async fn quit(&self) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::Leave { tx_msg: tx })
+            .send(LocalRequestMsg::Quit { tx_msg: tx })
             .await
             .unwrap();
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::ConfigChange { result: _result } => Ok(()),
+            LocalResponseMsg::Quit {} => {
+                self.tasks.wait().await;
+                Ok(())
+            }
             _ => unreachable!(),
         }
     }
 
-    pub async fn leave_joint(&self) {
+    /// Returns the token cancelled once the node quits, e.g. to stop the tasks of the application along with it.
+    /// Cancelling it quits the node.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Quits the node without leaving the cluster leaderless, e.g. for a rolling restart.
+    ///
+    /// The leader first hands the leadership over with `hand_off_leadership`. The node then waits for the entries
+    /// being persisted and applied, and flushes its storage before quitting. A failed handoff is only logged,
+    /// the cluster electing a new leader as if the node had quit with `quit`.
+    pub async fn shutdown(&self) -> Result<()> {
+        if let Err(e) = self.hand_off_leadership().await {
+            self.logger.warn(&format!(
+                "Failed to hand the leadership over before shutting down: {}",
+                e
+            ));
+        }
+
+        let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::LeaveJoint {})
+            .send(LocalRequestMsg::Shutdown { tx_msg: tx })
             .await
             .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Shutdown { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
     }
 
-    pub async fn send_message(&self, message: RaftMessage) -> Result<()> {
+    /// Takes the node out of the write service before a maintenance, until `undrain`.
+    ///
+    /// The node rejects the new proposals with `Error::Draining` and hands the leadership over with
+    /// `hand_off_leadership`, handing it over again if it's elected meanwhile. It keeps voting and replicating
+    /// the log, so that the quorum isn't weakened. `RaftStatus::draining` reports the node as draining.
+    pub async fn drain(&self) -> Result<()> {
+        self.set_draining(true).await?;
+        self.hand_off_leadership().await
+    }
+
+    /// Puts a drained node back into the write service.
+    pub async fn undrain(&self) -> Result<()> {
+        self.set_draining(false).await
+    }
+
+    async fn set_draining(&self, draining: bool) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::SendMessage {
-                message: Box::new(message),
+            .send(LocalRequestMsg::SetDraining {
+                draining,
                 tx_msg: tx,
             })
             .await
             .unwrap();
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::SendMessage {} => Ok(()),
+            LocalResponseMsg::SetDraining {} => Ok(()),
             _ => unreachable!(),
         }
     }
 
-    pub async fn make_snapshot(&self, index: u64, term: u64) -> Result<()> {
+    /// Transfers the leadership to the most up-to-date active voter if this node is the leader,
+    /// and waits until it has moved. Does nothing on the other nodes, or without another active voter.
+    pub async fn hand_off_leadership(&self) -> Result<()> {
+        let status = self.status().await?;
+        if status.role != NodeRole::Leader {
+            return Ok(());
+        }
+
+        let voters = self.get_voters().await?;
+        let transferee = status
+            .progress
+            .iter()
+            .filter(|(id, progress)| {
+                **id != status.id && voters.contains(id) && progress.recent_active
+            })
+            .max_by_key(|(_, progress)| progress.matched)
+            .map(|(id, _)| *id);
+
+        match transferee {
+            Some(transferee) => {
+                self.logger.info(&format!(
+                    "Handing the leadership over to node {}...",
+                    transferee
+                ));
+                self.transfer_leader(transferee).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Transfers the leadership to `node_id` and waits until the leadership has actually moved.
+    ///
+    /// Fails with `Error::Timeout` if the transfer doesn't complete within an election timeout.
+    pub async fn transfer_leader(&self, node_id: u64) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::MakeSnapshot {
-                index,
-                term,
+            .send(LocalRequestMsg::TransferLeader {
+                node_id,
                 tx_msg: tx,
             })
             .await
             .unwrap();
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::MakeSnapshot {} => Ok(()),
+            LocalResponseMsg::TransferLeader { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
             _ => unreachable!(),
         }
     }
 
-    pub async fn join_cluster(&self, tickets: Vec<ClusterJoinTicket>) -> Result<()> {
+    /// Makes this node start an election and waits for its outcome.
+    ///
+    /// Resolves with `ResponseResult::Success` if this node became the leader,
+    /// `ResponseResult::WrongLeader` if another node won the election,
+    /// or `ResponseResult::Error(Error::Timeout)` if no leader was elected in time.
+    pub async fn campaign(&self) -> Result<ResponseResult> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::JoinCluster {
-                tickets,
+            .send(LocalRequestMsg::Campaign { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Campaign { result } => Ok(result),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Changes the election priority of this node at runtime.
+    pub async fn set_priority(&self, priority: i64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::SetPriority {
+                priority,
                 tx_msg: tx,
             })
             .await
             .unwrap();
         let resp = rx.await?;
         match resp {
-            LocalResponseMsg::JoinCluster {} => Ok(()),
+            LocalResponseMsg::SetPriority {} => Ok(()),
             _ => unreachable!(),
         }
     }
 
-    /// # Safety
-    /// TODO: Write this.
-    pub async unsafe fn get_raw_node(&self) -> Result<Arc<Mutex<&'static RawNode<LogStorage>>>> {
+    /// Changes the election and heartbeat ticks of this node at runtime, from the next tick on,
+    /// e.g. to stretch the timeouts during a network maintenance. Set them on every node,
+    /// and set the ticks of `RaftConfig` back once it's over.
+    pub async fn set_ticks(&self, election_tick: usize, heartbeat_tick: usize) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.tx_local
-            .send(LocalRequestMsg::GetRawNode { tx_msg: tx })
+            .send(LocalRequestMsg::SetTicks {
+                election_tick,
+                heartbeat_tick,
+                tx_msg: tx,
+            })
             .await
             .unwrap();
         let resp = rx.await?;
-
         match resp {
-            LocalResponseMsg::GetRawNode { raw_node } => Ok(raw_node),
+            LocalResponseMsg::SetTicks { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
             _ => unreachable!(),
         }
     }
 
-    pub(crate) async fn run(self) -> Result<()> {
-        self.inner
-            .lock()
-            .await
-            .expect("RaftNode oneshot mutex's owner should be only RaftNode.run!")
-            .run()
+    /// Registers a handler notified of leadership, membership and snapshot events from now on.
+    pub async fn register_event_handler(&self, handler: Arc<dyn RaftEventHandler>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::RegisterEventHandler {
+                handler,
+                tx_msg: tx,
+            })
             .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::RegisterEventHandler {} => Ok(()),
+            _ => unreachable!(),
+        }
     }
-}
 
-pub struct RaftNodeCore<
-    LogEntry: AbstractLogEntry + Send + 'static,
-    LogStorage: StableStorage + Send + Clone + 'static,
-    FSM: AbstractStateMachine + Clone + 'static,
-> {
+    /// Makes this node step down to a follower of `leader_id` in `term`.
+    pub async fn step_down(&self, term: u64, leader_id: u64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::StepDown {
+                term,
+                leader_id,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::StepDown {} => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Makes this node step down to a follower of `leader_id` in `term`.
+    #[deprecated(
+        note = "Renamed to `step_down`. Use `demote_voter` to turn a voter into a learner"
+    )]
+    pub async fn demote(&self, term: u64, leader_id: u64) -> Result<()> {
+        self.step_down(term, leader_id).await
+    }
+
+    /// Turns the voter `node_id` into a learner through a configuration change.
+    /// Unlike removing and re-adding the node, its log is preserved.
+    pub async fn demote_voter(&self, node_id: u64) -> Result<ConfChangeResponseResult> {
+        if !self.get_voters().await?.contains(&node_id) {
+            return Err(Error::Rejected(format!("Node {} is not a voter", node_id)));
+        }
+
+        if self.get_leader_id().await? == node_id {
+            return Err(Error::Rejected(format!(
+                "Node {} is the leader. Transfer the leadership before demoting it",
+                node_id
+            )));
+        }
+
+        let addr = match self.get_peers().await?.get(&node_id) {
+            Some(peer) => peer.addr,
+            None => {
+                return Err(Error::Rejected(format!(
+                    "Node {} not found from the peers",
+                    node_id
+                )))
+            }
+        };
+
+        let mut cs = ConfChangeSingle::default();
+        cs.set_change_type(ConfChangeType::AddLearnerNode);
+        cs.set_node_id(node_id);
+
+        let mut conf_change = ConfChangeV2::default();
+        conf_change.set_changes(vec![cs]);
+        conf_change.set_context(serialize(&vec![addr])?);
+
+        self.change_config(conf_change).await
+    }
+
+    /// Removes `node_id` from the configuration without its cooperation,
+    /// e.g. when its hardware is gone for good. Unlike `change_config`, its address isn't required.
+    /// Rejected unless the other voters reachable from the leader form a quorum to commit the removal.
+    /// Must be called on the leader. Otherwise, `ConfChangeResponseResult::WrongLeader` is returned.
+    pub async fn force_remove_peer(&self, node_id: u64) -> Result<ConfChangeResponseResult> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::ForceRemovePeer {
+                node_id,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::ConfigChange { result } => Ok(result),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Overrides the settings set in `update` on every node of the cluster, see `RuntimeConfig`.
+    /// Must be called on the leader, and fails if the update would make the config invalid.
+    pub async fn update_runtime_config(&self, update: RuntimeConfig) -> Result<ProposeOutcome> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::UpdateRuntimeConfig { update, tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Propose { result } => match result {
+                ProposeResponseResult::Success(outcome) => Ok(outcome),
+                ProposeResponseResult::Error(e) => Err(e),
+                ProposeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                } => Err(Error::Rejected(format!(
+                    "Node {} ({}) is the leader",
+                    leader_id, leader_addr
+                ))),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Replaces the labels of the member `node_id`, e.g. its `ZONE_LABEL`, on every node of the cluster.
+    /// The labels are replicated through the log and persisted, so they survive restarts.
+    /// Must be called on the leader.
+    pub async fn set_peer_labels(
+        &self,
+        node_id: u64,
+        labels: PeerLabels,
+    ) -> Result<ProposeOutcome> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::SetPeerLabels {
+                node_id,
+                labels,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Propose { result } => match result {
+                ProposeResponseResult::Success(outcome) => Ok(outcome),
+                ProposeResponseResult::Error(e) => Err(e),
+                ProposeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                } => Err(Error::Rejected(format!(
+                    "Node {} ({}) is the leader",
+                    leader_id, leader_addr
+                ))),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Fences `range`, rejecting the entries writing one of its keys from then on,
+    /// and returns its state taken at the index of the fence, see `AbstractStateMachine::snapshot_range`.
+    /// Must be called on the leader. Used to move the range to another group, see `Router::move_range`.
+    pub async fn fence_range(&self, range: KeyRange) -> Result<Vec<u8>> {
+        self.propose_shard_command(ShardCommand::Fence(range))
+            .await
+            .map(|outcome| outcome.data)
+    }
+
+    /// Lifts the fence of `range`, e.g. after it failed to move. Must be called on the leader.
+    pub async fn unfence_range(&self, range: KeyRange) -> Result<ProposeOutcome> {
+        self.propose_shard_command(ShardCommand::Unfence(range))
+            .await
+    }
+
+    /// Adds the state of a range returned by `fence_range` in another group. Must be called on the leader.
+    pub async fn restore_range(&self, snapshot: Vec<u8>) -> Result<ProposeOutcome> {
+        self.propose_shard_command(ShardCommand::RestoreRange(snapshot))
+            .await
+    }
+
+    /// Removes the keys of `range` once they moved to another group, and lifts its fence.
+    /// Must be called on the leader.
+    pub async fn remove_range(&self, range: KeyRange) -> Result<ProposeOutcome> {
+        self.propose_shard_command(ShardCommand::RemoveRange(range))
+            .await
+    }
+
+    async fn propose_shard_command(&self, command: ShardCommand) -> Result<ProposeOutcome> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::Shard {
+                command,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::Propose { result } => match result {
+                ProposeResponseResult::Success(outcome) => Ok(outcome),
+                ProposeResponseResult::Error(e) => Err(e),
+                ProposeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                } => Err(Error::Rejected(format!(
+                    "Node {} ({}) is the leader",
+                    leader_id, leader_addr
+                ))),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Re-points the member `node_id` to `new_addr`, e.g. after it restarted with a new IP.
+    /// The address is updated through a conf change keeping the role of the member,
+    /// so that every node of the cluster reconnects to the new address.
+    /// The members given as hostnames follow their DNS records instead, and are left as they are.
+    pub async fn update_peer_addr<A: ToSocketAddrs>(
+        &self,
+        node_id: u64,
+        new_addr: A,
+    ) -> Result<ConfChangeResponseResult> {
+        let new_addr = new_addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Rejected(format!("Invalid address of node {}", node_id)))?;
+
+        let change_type = match self.get_peers().await?.get(&node_id) {
+            Some(peer) if peer.role == InitialRole::Learner => ConfChangeType::AddLearnerNode,
+            Some(_) => ConfChangeType::AddNode,
+            None => {
+                return Ok(ConfChangeResponseResult::Error(Error::Rejected(format!(
+                    "Node {} is not a member of the cluster",
+                    node_id
+                ))))
+            }
+        };
+
+        let mut cs = ConfChangeSingle::default();
+        cs.set_change_type(change_type);
+        cs.set_node_id(node_id);
+
+        let mut conf_change = ConfChangeV2::default();
+        conf_change.set_changes(vec![cs]);
+        conf_change.set_context(serialize(&vec![new_addr])?);
+
+        self.change_config(conf_change).await
+    }
+
+    pub async fn leave(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::Leave { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::ConfigChange { result: _result } => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Proposes leaving the joint configuration entered by a multi-member `change_config`.
+    /// Resolves with `ConfChangeResponseResult::JointLeft` once the change is applied.
+    pub async fn leave_joint(&self) -> Result<ConfChangeResponseResult> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::LeaveJoint { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::ConfigChange { result } => match result {
+                ConfChangeResponseResult::WrongLeader { leader_addr, .. } => {
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options).await?;
+                    client.leave_joint(raft_service::Empty {}).await?;
+                    Ok(ConfChangeResponseResult::JointLeft {
+                        peers: self.get_peers().await?,
+                    })
+                }
+                _ => Ok(result),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn send_message(&self, message: RaftMessage) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::SendMessage {
+                message: Box::new(message),
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::SendMessage {} => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn make_snapshot(&self, index: u64, term: u64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::MakeSnapshot {
+                index,
+                term,
+                tx_msg: tx,
+            })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::MakeSnapshot {} => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Removes the log entries up to `index` to reclaim the disk space.
+    /// `index` must be applied. If the last snapshot doesn't cover it yet,
+    /// a snapshot of the applied entries is taken first.
+    pub async fn compact(&self, index: u64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::CompactLogs { index, tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+        match resp {
+            LocalResponseMsg::CompactLogs { result } => match result {
+                ResponseResult::Success => Ok(()),
+                ResponseResult::Error(e) => Err(e),
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn join_cluster(&self, tickets: Vec<ClusterJoinTicket>) -> Result<()> {
+        self.join_cluster_with_role(tickets, InitialRole::Voter)
+            .await
+    }
+
+    /// Joins the cluster as learners (non-voting members).
+    /// Learners receive the replicated log but are not counted in quorum.
+    pub async fn join_cluster_as_learner(&self, tickets: Vec<ClusterJoinTicket>) -> Result<()> {
+        self.join_cluster_with_role(tickets, InitialRole::Learner)
+            .await
+    }
+
+    /// Retries the join with the backoff of `Config::rejoin` until it succeeds.
+    async fn join_cluster_with_role(
+        &self,
+        tickets: Vec<ClusterJoinTicket>,
+        role: InitialRole,
+    ) -> Result<()> {
+        let mut backoff = Backoff::from(&self.rejoin);
+        loop {
+            let (tx, rx) = oneshot::channel();
+            self.tx_local
+                .send(LocalRequestMsg::JoinCluster {
+                    tickets: tickets.clone(),
+                    role: role.clone(),
+                    tx_msg: tx,
+                })
+                .await
+                .unwrap();
+            let error = match rx.await? {
+                LocalResponseMsg::JoinCluster {
+                    result: ResponseResult::Success,
+                } => return Ok(()),
+                LocalResponseMsg::JoinCluster {
+                    result: ResponseResult::Error(e),
+                } => e,
+                _ => unreachable!(),
+            };
+            match backoff.next_delay() {
+                Some(delay) => {
+                    self.logger.warn(&format!(
+                        "Failed to join the cluster, retrying in {:?}: {}",
+                        delay, error
+                    ));
+                    sleep(delay).await;
+                }
+                None => return Err(error),
+            }
+        }
+    }
+
+    /// # Safety
+    /// TODO: Write this.
+    pub async unsafe fn get_raw_node(&self) -> Result<Arc<Mutex<&'static RawNode<LogStorage>>>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_local
+            .send(LocalRequestMsg::GetRawNode { tx_msg: tx })
+            .await
+            .unwrap();
+        let resp = rx.await?;
+
+        match resp {
+            LocalResponseMsg::GetRawNode { raw_node } => Ok(raw_node),
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) async fn run(self) -> Result<()> {
+        self.inner
+            .lock()
+            .await
+            .expect("RaftNode oneshot mutex's owner should be only RaftNode.run!")
+            .run()
+            .await
+    }
+
+    /// Runs the node on the ticker shared by the groups of a `RaftGroupManager` instead of its own clock.
+    pub(crate) async fn run_with_ticker(self, ticker: watch::Receiver<u64>) -> Result<()> {
+        let mut core = self
+            .inner
+            .lock()
+            .await
+            .expect("RaftNode oneshot mutex's owner should be only RaftNode.run!");
+        let seen = *ticker.borrow();
+        core.ticker = Some((ticker, seen));
+        core.run().await
+    }
+}
+
+/// Committed entry waiting to be applied to the state machine with the rest of its batch.
+struct BatchedEntry {
+    index: u64,
+    term: u64,
+    response_seq: u64,
+    session: Option<ProposalSession>,
+    data: Bytes,
+    span: Span,
+}
+
+pub struct RaftNodeCore<
+    LogEntry: AbstractLogEntry + Send + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
+    FSM: AbstractStateMachine + Clone + 'static,
+> {
     pub raw_node: RawNode<LogStorage>,
     // pub log_storage: LogStorage,  # Since there is no particular reason to store it, we do not save the log_storage.
     pub fsm: FSM,
@@ -493,10 +1453,58 @@ pub struct RaftNodeCore<
     response_seq: AtomicU64,
     raft_addr: SocketAddr,
     config: Config,
+    cluster_uuid: ClusterUuid,
     should_exit: bool,
     last_snapshot_created: Instant,
+    last_snapshot_index: u64,
+    // Election and heartbeat ticks set at runtime, and the ones to set at the next tick boundary.
+    ticks: (usize, usize),
+    pending_ticks: Option<(usize, usize)>,
+    // Fraction of a raft-rs tick carried over to the next tick boundary.
+    tick_credit: f32,
+    // Ticker shared by the groups of a `RaftGroupManager`, and the last tick count seen from it.
+    ticker: Option<(watch::Receiver<u64>, u64)>,
     logger: Arc<dyn Logger>,
     response_senders: HashMap<u64, ResponseSender<LogEntry, LogStorage, FSM>>,
+    // Sequences of the proposals among `response_senders`, counted against `AdmissionConfig::max_in_flight`.
+    proposal_seqs: HashSet<u64>,
+    // Read requests whose read index is known but not yet applied to the state machine.
+    pending_reads: Vec<(
+        u64,
+        oneshot::Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    )>,
+    // Queries waiting for their read index to be applied to the state machine.
+    pending_queries: Vec<(u64, Vec<u8>, oneshot::Sender<ServerResponseMsg>)>,
+    // Leadership transfers waiting for the target node to become the leader.
+    pending_leader_transfers: Vec<(u64, Instant, ResponseSender<LogEntry, LogStorage, FSM>)>,
+    // When the leader first saw each voter unreachable, used by `config.dead_voter_removal`.
+    unreachable_voters: HashMap<u64, Instant>,
+    // Campaigns waiting for the election to be settled.
+    pending_campaigns: Vec<(
+        Instant,
+        oneshot::Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    )>,
+    // Last applied proposal of each client session, used to drop retried proposals.
+    sessions: SessionTable,
+    // Key ranges fenced while they move to another group.
+    fenced_ranges: Vec<KeyRange>,
+    // Publishes applied entries to the receivers returned by `RaftNode::subscribe`.
+    tx_applied: broadcast::Sender<(u64, LogEntry)>,
+    event_handlers: Vec<Arc<dyn RaftEventHandler>>,
+    // Publishes cluster events to the WatchClusterEvents observers.
+    tx_cluster_events: broadcast::Sender<ClusterEvent>,
+    systemd: SystemdNotifier,
+    shutdown: CancellationToken,
+    // Tasks spawned by the node, awaited before `run` returns.
+    tasks: TaskTracker,
+    last_leader_id: u64,
+    last_term: u64,
+    // Role reported by the last soft state, used to detect leadership changes.
+    last_role: StateRole,
+    // Base index of the last delta snapshot sent to each follower.
+    delta_snapshot_bases: HashMap<u64, u64>,
+    // Index of the delta snapshot received from the leader, not restored yet.
+    pending_delta_snapshot: Option<u64>,
 
     #[allow(dead_code)]
     tx_server: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
@@ -509,12 +1517,43 @@ pub struct RaftNodeCore<
     tx_self: mpsc::Sender<SelfMessage>,
     rx_self: mpsc::Receiver<SelfMessage>,
 
+    tx_apply: mpsc::Sender<ApplyRequest<FSM>>,
+    rx_apply_results: mpsc::UnboundedReceiver<Vec<Result<Vec<u8>>>>,
+    // Other ends of the apply task channels, until `run` spawns the task.
+    apply_task: Option<(
+        mpsc::Receiver<ApplyRequest<FSM>>,
+        mpsc::UnboundedSender<Vec<Result<Vec<u8>>>>,
+    )>,
+    // Batches handed to the apply task, oldest first, with the span they are applied in.
+    applying: VecDeque<(Span, Vec<BatchedEntry>)>,
+    // Last committed entry handled, either applied or handed to the apply task.
+    handled_index: u64,
+
+    tx_persist: mpsc::Sender<PersistBatch>,
+    rx_persisted: mpsc::UnboundedReceiver<Result<PersistedBatch>>,
+    // Other ends of the persist task channels, until `run` spawns the task.
+    persist_task: Option<(
+        mpsc::Receiver<PersistBatch>,
+        mpsc::UnboundedSender<Result<PersistedBatch>>,
+    )>,
+    // Readies handed to the persist task and not persisted yet.
+    persisting: usize,
+    // Set by the persist task while the storage is full, during which the proposals are rejected.
+    storage_full: Arc<AtomicBool>,
+    // Set by `RaftNode::drain`, during which the proposals are rejected and the leadership handed over.
+    draining: bool,
+    // Limits the rate of the proposals taken as the leader, see `AdmissionConfig::max_proposals_per_sec`.
+    proposal_limiter: Option<RateLimiter>,
+    rx_disk_usage: watch::Receiver<Option<DiskUsage>>,
+    // Other end of the disk monitor channel, until `run` spawns the monitor if `config.disk_usage` is set.
+    disk_monitor: Option<watch::Sender<Option<DiskUsage>>>,
+
     _phantom_log_entry_typ: PhantomData<LogEntry>,
 }
 
 impl<
         LogEntry: AbstractLogEntry + Send + 'static,
-        LogStorage: StableStorage + Send + Clone + 'static,
+        LogStorage: AbstractStableStorage + Send + Clone + 'static,
         FSM: AbstractStateMachine + Clone + Send + 'static,
     > RaftNodeCore<LogEntry, LogStorage, FSM>
 {
@@ -524,8 +1563,9 @@ impl<
         should_be_leader: bool,
         mut log_storage: LogStorage,
         fsm: FSM,
-        config: Config,
+        mut config: Config,
         raft_addr: SocketAddr,
+        cluster_uuid: ClusterUuid,
         logger: Arc<dyn Logger>,
         tx_server: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
         rx_server: mpsc::Receiver<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
@@ -541,7 +1581,7 @@ impl<
 
         let conf_state = snapshot.mut_metadata().mut_conf_state();
 
-        let peers = config
+        let mut peers = config
             .initial_peers
             .clone()
             .unwrap_or(Peers::new(node_id, raft_addr));
@@ -550,7 +1590,7 @@ impl<
             .clone()
             .inner
             .into_iter()
-            .filter(|(_, peer)| peer.role == InitialRole::Voter || peer.role == InitialRole::Leader)
+            .filter(|(_, peer)| peer.role.is_voter())
             .map(|(key, _)| key)
             .collect::<Vec<_>>();
 
@@ -577,16 +1617,77 @@ impl<
             logger.info("Bootstrapping from existing logs...");
         }
 
+        match log_storage.cluster_uuid()? {
+            Some(uuid) => cluster_uuid.set(uuid),
+            // The other nodes adopt the UUID from the first raft message carrying it.
+            None if should_be_leader => {
+                let uuid = ClusterUuid::generate();
+                logger.info(&format!("Generated cluster UUID {}", uuid));
+                log_storage.set_cluster_uuid(&uuid)?;
+                cluster_uuid.set(uuid);
+            }
+            None => {}
+        }
+
+        let runtime_config = log_storage.runtime_config()?;
+        if !runtime_config.is_empty() {
+            logger.info(&format!("Applying runtime config {:?}", runtime_config));
+            runtime_config.apply_to(&mut config);
+        }
+
+        // The labels set through the log take precedence over the ones of the initial peers.
+        for (id, labels) in log_storage.peer_labels()? {
+            if let Some(peer) = peers.get_mut(&id) {
+                peer.labels = labels;
+            }
+        }
+
+        let fenced_ranges = log_storage.fenced_ranges()?;
+
+        log_storage.set_node_identity(&NodeIdentity { node_id, raft_addr })?;
+        // Past the first bootstrap, the persisted peers are kept up to date by the membership changes.
+        if log_storage.peers()?.is_none() {
+            log_storage.set_peers(&peers)?;
+        }
+
+        if let Some(applied) = fsm.applied_index() {
+            Self::resume_applied_index(&mut log_storage, &mut config, applied, logger.as_ref())?;
+        }
+
         let mut raw_node = RawNode::new(&config.raft_config, log_storage.clone(), logger.clone())?;
+
+        // The priority in `RaftConfig` takes precedence over the one declared in the peers.
+        if config.raft_config.priority == 0 {
+            if let Some(peer) = peers.get(&node_id) {
+                raw_node.raft.set_priority(peer.priority);
+            }
+        }
         let response_seq = AtomicU64::new(0);
+        let ticks = (
+            config.raft_config.election_tick,
+            config.raft_config.heartbeat_tick,
+        );
         let last_snapshot_created = Instant::now();
+        let last_snapshot_index = log_storage.snapshot(0, 0)?.get_metadata().index;
+        let sessions = Self::load_sessions(&log_storage, &config)?;
 
         let (tx_self, rx_self) = mpsc::channel(100);
+        let (tx_apply, rx_apply) = mpsc::channel(100);
+        let (tx_apply_results, rx_apply_results) = mpsc::unbounded_channel();
+        let (tx_persist, rx_persist) = mpsc::channel(100);
+        let (tx_persisted, rx_persisted) = mpsc::unbounded_channel();
+        let proposal_limiter = config
+            .admission
+            .as_ref()
+            .and_then(|admission| admission.max_proposals_per_sec)
+            .map(RateLimiter::new);
+        let (tx_disk_usage, rx_disk_usage) = watch::channel(None);
 
         if should_be_leader {
             raw_node.raft.become_candidate();
             raw_node.raft.become_leader();
         }
+        let systemd = SystemdNotifier::new(logger.clone());
 
         Ok(RaftNodeCore {
             raw_node,
@@ -594,22 +1695,145 @@ impl<
             response_seq,
             config,
             raft_addr,
+            cluster_uuid,
             logger,
             last_snapshot_created,
+            last_snapshot_index,
+            ticks,
+            pending_ticks: None,
+            tick_credit: 0.0,
+            ticker: None,
             should_exit: false,
             peers: Arc::new(Mutex::new(peers)),
             response_senders: HashMap::new(),
+            proposal_seqs: HashSet::new(),
+            pending_reads: Vec::new(),
+            pending_queries: Vec::new(),
+            pending_leader_transfers: Vec::new(),
+            pending_campaigns: Vec::new(),
+            unreachable_voters: HashMap::new(),
+            sessions,
+            fenced_ranges,
+            tx_applied: broadcast::channel(100).0,
+            event_handlers: Vec::new(),
+            tx_cluster_events: broadcast::channel(100).0,
+            systemd,
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            last_leader_id: 0,
+            last_term: 0,
+            last_role: StateRole::Follower,
+            delta_snapshot_bases: HashMap::new(),
+            pending_delta_snapshot: None,
             tx_server,
             rx_server,
             tx_local,
             rx_local,
             tx_self,
             rx_self,
+            tx_apply,
+            rx_apply_results,
+            apply_task: Some((rx_apply, tx_apply_results)),
+            applying: VecDeque::new(),
+            handled_index: 0,
+            tx_persist,
+            rx_persisted,
+            persist_task: Some((rx_persist, tx_persisted)),
+            persisting: 0,
+            storage_full: Arc::new(AtomicBool::new(false)),
+            draining: false,
+            proposal_limiter,
+            rx_disk_usage,
+            disk_monitor: Some(tx_disk_usage),
             _phantom_log_entry_typ: PhantomData,
         })
     }
 
-    pub fn is_leader(&self) -> bool {
+    /// Loads the client sessions carried by the last snapshot. The entries after it are applied again,
+    /// which records the sessions of the proposals applied since, except for the ones the state machine
+    /// already applied before the restart, whose sessions are rebuilt from the log here.
+    fn load_sessions(log_storage: &LogStorage, config: &Config) -> Result<SessionTable> {
+        let mut sessions = SessionTable::new(config.max_sessions);
+        let snapshot = log_storage.snapshot(0, 0)?;
+        if !snapshot.get_data().is_empty() {
+            let data = match config.encryption_key_provider.as_deref() {
+                Some(provider) => decrypt(provider, snapshot.get_data())?,
+                None => snapshot.get_data().to_vec(),
+            };
+            if let (_, Some(records)) = split_sessions(data)? {
+                sessions.restore(records);
+            }
+        }
+
+        let low = snapshot
+            .get_metadata()
+            .index
+            .max(log_storage.first_index()? - 1)
+            + 1;
+        let high = config.raft_config.applied + 1;
+        if low < high {
+            let entries = log_storage.entries(low, high, None, GetEntriesContext::empty(false))?;
+            for entry in entries {
+                if entry.get_entry_type() != EntryType::EntryNormal || entry.get_data().is_empty() {
+                    continue;
+                }
+                let context = ProposalContext::decode(entry.get_context())?;
+                if context.flags & (RUNTIME_CONFIG_ENTRY | PEER_LABELS_ENTRY | SHARD_ENTRY) != 0 {
+                    continue;
+                }
+                // The output of the state machine is gone, so the retries get an empty one.
+                if let Some(ref session) = context.session {
+                    if let SessionCheck::Fresh = sessions.check(session) {
+                        let outcome = ProposeOutcome {
+                            index: entry.index,
+                            term: entry.term,
+                            data: vec![],
+                        };
+                        sessions.record(session, outcome);
+                    }
+                }
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Lets raft-rs resume applying after `applied`, the index the state machine reports it applied up to.
+    /// The entries up to it are committed and persisted, so the commit index is raised to it if it wasn't persisted.
+    fn resume_applied_index(
+        log_storage: &mut LogStorage,
+        config: &mut Config,
+        applied: u64,
+        logger: &dyn Logger,
+    ) -> Result<()> {
+        let first_index = log_storage.first_index()?;
+        let last_index = log_storage.last_index()?;
+        if applied + 1 < first_index {
+            logger.warn(&format!(
+                "The state machine applied up to index {}, before the snapshot at index {}. Applying from the snapshot.",
+                applied,
+                first_index - 1
+            ));
+            return Ok(());
+        }
+        if applied > last_index {
+            return Err(Error::StorageCorruption(format!(
+                "the state machine applied up to index {}, beyond the last log index {}",
+                applied, last_index
+            )));
+        }
+
+        if applied > log_storage.hard_state()?.commit {
+            log_storage.set_hard_state_commit(applied)?;
+        }
+        config.raft_config.applied = applied;
+        logger.info(&format!(
+            "Resuming applying after index {}, applied by the state machine.",
+            applied
+        ));
+        Ok(())
+    }
+
+    pub fn is_leader(&self) -> bool {
         self.raw_node.raft.leader_id == self.raw_node.raft.id
     }
 
@@ -621,11 +1845,46 @@ impl<
         self.raw_node.raft.leader_id
     }
 
+    // Persists the cluster UUID received from the other node if this node doesn't know it yet.
+    // Returns false if it's the UUID of another cluster.
+    fn adopt_cluster_uuid(&mut self, cluster_uuid: String) -> Result<bool> {
+        match self.cluster_uuid.get() {
+            Some(expected) if expected == cluster_uuid => Ok(true),
+            Some(expected) => {
+                self.logger.warn(&format!(
+                    "Dropped a raft message of another cluster, expected cluster UUID {} but got {}",
+                    expected, cluster_uuid
+                ));
+                Ok(false)
+            }
+            None => {
+                self.logger
+                    .info(&format!("Adopted cluster UUID {}", cluster_uuid));
+                self.raw_node.mut_store().set_cluster_uuid(&cluster_uuid)?;
+                self.cluster_uuid.set(cluster_uuid);
+                Ok(true)
+            }
+        }
+    }
+
+    // Attaches the cluster UUID to the requests sent to the other nodes.
+    fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            cluster_uuid: self.cluster_uuid.clone(),
+            ..self.config.client_options()
+        }
+    }
+
+    // Recorded as the requester of the membership changes requested through this node's API.
+    fn local_requester(&self) -> String {
+        format!("node {}", self.get_id())
+    }
+
     pub async fn get_peers(&self) -> Peers {
         self.peers.lock().await.to_owned()
     }
 
-    pub async fn add_peer<A: ToSocketAddrs>(
+    pub async fn add_peer<A: ToSocketAddrs + ToString>(
         &mut self,
         id: u64,
         addr: A,
@@ -633,7 +1892,7 @@ impl<
     ) -> Result<()> {
         let mut peers = self.peers.lock().await;
         peers.add_peer(id, addr, role);
-        peers.connect(id).await
+        peers.connect(id, &self.client_options()).await
     }
 
     pub async fn add_peers(&mut self, peers: HashMap<u64, SocketAddr>) -> Result<()> {
@@ -643,14 +1902,161 @@ impl<
         Ok(())
     }
 
+    pub async fn set_priority(&mut self, priority: i64) {
+        self.raw_node.raft.set_priority(priority);
+        if let Some(peer) = self.peers.lock().await.get_mut(&self.get_id()) {
+            peer.priority = priority;
+        }
+        self.logger.info(&format!(
+            "Node {} election priority changed to {}.",
+            self.get_id(),
+            priority
+        ));
+    }
+
     pub async fn make_snapshot(&mut self, index: u64, term: u64) -> Result<()> {
+        self.save_snapshot(index, term).await?;
+        // Read after `save_snapshot` waited for the entries being applied.
+        let last_applied = self.raw_node.raft.raft_log.applied;
+        self.compact_log_storage(last_applied)?;
+        Ok(())
+    }
+
+    /// Takes a snapshot of the state machine on `config.apply_executor`.
+    async fn snapshot_fsm(&self) -> Result<Vec<u8>> {
+        let fsm = self.fsm.clone();
+        self.config
+            .apply_executor
+            .run(async move { fsm.snapshot().await })
+            .await?
+    }
+
+    /// Restores the state machine from a snapshot on `config.apply_executor`.
+    async fn restore_fsm(&mut self, snapshot: Vec<u8>) -> Result<()> {
+        let mut fsm = self.fsm.clone();
+        let (fsm, result) = self
+            .config
+            .apply_executor
+            .run(async move {
+                let result = fsm.restore(snapshot).await;
+                (fsm, result)
+            })
+            .await?;
+        self.fsm = fsm;
+        result
+    }
+
+    /// Persists a snapshot of the state machine without compacting the log.
+    async fn save_snapshot(&mut self, index: u64, term: u64) -> Result<()> {
+        self.wait_for_apply().await?;
+        self.event_handlers
+            .iter()
+            .for_each(|handler| handler.on_before_snapshot(index, term));
+
         self.last_snapshot_created = Instant::now();
-        let snapshot_data = self.fsm.snapshot().await?;
+        self.last_snapshot_index = index;
+        // Witness nodes don't hold any state machine data.
+        let snapshot_data = if self.config.is_witness {
+            vec![]
+        } else {
+            let snapshot_data = self.snapshot_fsm().await?;
+            self.encrypt_snapshot(self.sessions.append_to(snapshot_data)?)?
+        };
 
-        let last_applied = self.raw_node.raft.raft_log.applied;
+        self.raw_node
+            .mut_store()
+            .create_snapshot(snapshot_data, index, term)?;
+
+        self.event_handlers
+            .iter()
+            .for_each(|handler| handler.on_after_snapshot(index, term));
+        Ok(())
+    }
+
+    /// Replaces the client sessions with the ones of the snapshot just restored,
+    /// dropping them if the snapshot was taken by a version which didn't carry them.
+    fn restore_sessions(&mut self, sessions: Option<Vec<SessionRecord>>) {
+        self.sessions.restore(sessions.unwrap_or_default());
+    }
+
+    fn encrypt_snapshot(&self, snapshot_data: Vec<u8>) -> Result<Vec<u8>> {
+        match self.config.encryption_key_provider {
+            Some(ref provider) => encrypt(provider.as_ref(), &snapshot_data),
+            None => Ok(snapshot_data),
+        }
+    }
+
+    fn decrypt_snapshot(&self, snapshot_data: &[u8]) -> Result<Vec<u8>> {
+        match self.config.encryption_key_provider {
+            Some(ref provider) => decrypt(provider.as_ref(), snapshot_data),
+            None => Ok(snapshot_data.to_vec()),
+        }
+    }
+
+    /// Removes the log entries before `index` from the storage,
+    /// notifying the event handlers of the compacted range.
+    fn compact_log_storage(&mut self, index: u64) -> Result<()> {
+        let first_index = self.raw_node.store().first_index()?;
+        if index <= first_index {
+            return Ok(());
+        }
+
+        self.event_handlers
+            .iter()
+            .for_each(|handler| handler.on_before_compaction(first_index, index));
+        self.raw_node.mut_store().compact(index)?;
+        self.event_handlers
+            .iter()
+            .for_each(|handler| handler.on_after_compaction(first_index, index));
+        Ok(())
+    }
+
+    /// Removes the log entries up to `index`.
+    /// Entries not covered by the last snapshot are needed for the recovery,
+    /// so a snapshot of the applied entries is taken first in that case.
+    async fn compact_logs(&mut self, index: u64) -> Result<()> {
+        self.wait_for_apply().await?;
+        let applied = self.raw_node.raft.raft_log.applied;
+        if index > applied {
+            return Err(Error::Rejected(format!(
+                "Cannot compact logs beyond the applied index (index={}, applied={})",
+                index, applied
+            )));
+        }
+
+        if index > self.last_snapshot_index {
+            let term = self.raw_node.raft.raft_log.term(applied)?;
+            self.save_snapshot(applied, term).await?;
+        }
+
+        self.compact_log_storage(index)?;
+        self.logger
+            .info(&format!("Compacted logs up to index {}.", index));
+        Ok(())
+    }
+
+    // Leaves the tombstones of the nodes removed by the conf change applied at `index`.
+    fn record_tombstones(&mut self, index: u64, conf_change: &ConfChangeV2) -> Result<()> {
         let store = self.raw_node.mut_store();
-        store.compact(last_applied)?;
-        store.create_snapshot(snapshot_data, index, term)?;
+        let mut tombstones = store.tombstones()?;
+        update_tombstones(&mut tombstones, index, conf_change);
+        store.set_tombstones(&tombstones)
+    }
+
+    fn purge_tombstones(&mut self, node_ids: &[u64]) -> Result<()> {
+        let store = self.raw_node.mut_store();
+        let mut tombstones = store.tombstones()?;
+        tombstones
+            .retain(|tombstone| !node_ids.is_empty() && !node_ids.contains(&tombstone.node_id));
+        store.set_tombstones(&tombstones)?;
+
+        self.logger.info(&format!(
+            "Purged the tombstones of the removed nodes. Remaining: {:?}",
+            tombstones
+                .iter()
+                .map(|tombstone| tombstone.node_id)
+                .collect::<Vec<_>>()
+        ));
         Ok(())
     }
 
@@ -658,78 +2064,356 @@ impl<
         inspect_raftnode(&self.raw_node)
     }
 
+    async fn peer_client(
+        node_id: u64,
+        peers: &Arc<Mutex<Peers>>,
+        logger: &Arc<dyn Logger>,
+        client_options: &ClientOptions,
+    ) -> std::result::Result<RaftClient, SendMessageError> {
+        match peers.lock().await.get_mut(&node_id) {
+            Some(peer) => {
+                if peer.client.is_none() {
+                    if let Err(e) = peer.connect(client_options).await {
+                        logger.debug(format!("Connection error: {:?}", e).as_str());
+                        peer.record_failure(false);
+                        return Err(SendMessageError::ConnectionError(node_id.to_string()));
+                    }
+                }
+                Ok(peer.client.clone().unwrap())
+            }
+            None => Err(SendMessageError::PeerNotFound(node_id.to_string())),
+        }
+    }
+
+    /// Records whether the request sent to the peer failed, evicting its client once it failed repeatedly.
+    /// The client of a peer given as a hostname is dropped as soon as the peer is unavailable,
+    /// so that the hostname is resolved again when the peer is reconnected, in case its IP changed.
+    async fn record_peer_outcome(
+        node_id: u64,
+        peers: &Arc<Mutex<Peers>>,
+        error: Option<&tonic::Status>,
+    ) {
+        if let Some(peer) = peers.lock().await.get_mut(&node_id) {
+            match error {
+                None => peer.record_success(),
+                Some(status) => peer.record_failure(
+                    peer.host.is_some() && status.code() == tonic::Code::Unavailable,
+                ),
+            }
+        }
+    }
+
+    /// Sends the messages to their peer, in a single `SendMessages` call if there are several of them.
     async fn send_message(
-        message: RaftMessage,
+        mut messages: Vec<RaftMessage>,
         peers: Arc<Mutex<Peers>>,
         tx_self: mpsc::Sender<SelfMessage>,
         logger: Arc<dyn Logger>,
+        client_options: ClientOptions,
     ) {
-        let node_id = message.get_to();
-
-        let mut ok = std::result::Result::<(), SendMessageError>::Ok(());
+        let node_id = messages[0].get_to();
 
-        let client = match peers.lock().await.get_mut(&node_id) {
-            Some(peer) => {
-                if peer.client.is_none() {
-                    if let Err(e) = peer.connect().await {
-                        logger.debug(format!("Connection error: {:?}", e).as_str());
-                        ok = Err(SendMessageError::ConnectionError(node_id.to_string()));
+        let ok = match Self::peer_client(node_id, &peers, &logger, &client_options).await {
+            Ok(mut client) => {
+                let sent = if messages.len() == 1 {
+                    client.send_message(Request::new(messages.remove(0))).await
+                } else {
+                    client
+                        .send_messages(Request::new(raft_service::MessageBatch { messages }))
+                        .await
+                };
+                Self::record_peer_outcome(node_id, &peers, sent.as_ref().err()).await;
+                match sent {
+                    Ok(_) => Ok(()),
+                    Err(e) => {
+                        logger.trace(&format!("Message transmission error: {:?}", e));
+                        Err(SendMessageError::TransmissionError(node_id.to_string()))
                     }
                 }
-                peer.client.clone()
-            }
-            None => {
-                ok = Err(SendMessageError::PeerNotFound(node_id.to_string()));
-                None
             }
+            Err(e) => Err(e),
         };
 
-        if let Some(mut client) = client {
-            let message = Request::new(message.clone());
-            if let Err(e) = client.send_message(message).await {
-                logger.trace(&format!("Message transmission error: {:?}", e));
-                ok = Err(SendMessageError::TransmissionError(node_id.to_string()));
+        if let Err(e) = ok {
+            logger.debug(&format!("Error occurred while sending message: {}", e));
+            let _ = tx_self
+                .send(SelfMessage::ReportUnreachable { node_id })
+                .await;
+        }
+    }
+
+    /// Streams the snapshot to the follower in chunks through the InstallSnapshot RPC,
+    /// instead of sending it in a single message.
+    /// An interrupted transfer is resumed from the offset the follower reports.
+    /// With a `SnapshotStore`, only the reference to the uploaded snapshot is streamed.
+    async fn send_snapshot(
+        mut message: RaftMessage,
+        peers: Arc<Mutex<Peers>>,
+        tx_self: mpsc::Sender<SelfMessage>,
+        logger: Arc<dyn Logger>,
+        client_options: ClientOptions,
+        chunk_size: usize,
+        snapshot_store: Option<(Arc<dyn SnapshotStore>, String)>,
+    ) {
+        let node_id = message.get_to();
+
+        // Deltas are small enough to be sent through the gRPC channel.
+        if let Some((store, cluster_id)) = snapshot_store {
+            if decode_delta_context(message.get_context()).is_none() {
+                if let Err(e) = upload_snapshot(store.as_ref(), &cluster_id, &mut message).await {
+                    logger.error(&format!("Failed to upload the snapshot: {}", e));
+                    let _ = tx_self
+                        .send(SelfMessage::ReportSnapshot {
+                            node_id,
+                            failed: true,
+                        })
+                        .await;
+                    return;
+                }
             }
         }
 
-        if let Err(e) = ok {
-            logger.debug(&format!("Error occurred while sending message: {}", e));
+        let ok = match Self::peer_client(node_id, &peers, &logger, &client_options).await {
+            Ok(mut client) => {
+                // Resume from the chunks the follower already received in an interrupted transfer.
+                let metadata = message.get_snapshot().get_metadata();
+                let offset = client
+                    .get_snapshot_offset(Request::new(raft_service::SnapshotOffsetArgs {
+                        from: message.get_from(),
+                        to: node_id,
+                        index: metadata.index,
+                        term: metadata.term,
+                    }))
+                    .await
+                    .map(|response| response.into_inner().offset)
+                    .unwrap_or(0);
+                if offset > 0 {
+                    logger.info(&format!(
+                        "Resuming snapshot transfer to node {} from offset {}",
+                        node_id, offset
+                    ));
+                }
+
+                let chunks = split_snapshot(message, chunk_size, offset);
+                let sent = client
+                    .install_snapshot(Request::new(tokio_stream::iter(chunks)))
+                    .await;
+                Self::record_peer_outcome(node_id, &peers, sent.as_ref().err()).await;
+                sent.map(|_| ()).map_err(|e| {
+                    logger.debug(&format!("Snapshot transmission error: {:?}", e));
+                    SendMessageError::TransmissionError(node_id.to_string())
+                })
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(ref e) = ok {
+            logger.debug(&format!("Error occurred while sending snapshot: {}", e));
             let _ = tx_self
                 .send(SelfMessage::ReportUnreachable { node_id })
                 .await;
         }
+        let _ = tx_self
+            .send(SelfMessage::ReportSnapshot {
+                node_id,
+                failed: ok.is_err(),
+            })
+            .await;
+    }
+
+    /// Replaces the full snapshot in `message` with the state machine delta since the
+    /// follower's matched index, if the follower is at most `config.delta_snapshot_max_lag` behind.
+    async fn into_delta_snapshot(&mut self, mut message: RaftMessage) -> RaftMessage {
+        let Some(max_lag) = self.config.delta_snapshot_max_lag else {
+            return message;
+        };
+        let to = message.get_to();
+        let Some(matched) = self.raw_node.raft.prs().get(to).map(|pr| pr.matched) else {
+            return message;
+        };
+
+        // The follower couldn't restore the last delta from this base. Fall back to the full snapshot.
+        if self.delta_snapshot_bases.remove(&to) == Some(matched) {
+            return message;
+        }
+
+        // Takes the results the apply task already reported, without waiting for the batches in flight,
+        // which would hold the loop up. The state machine is ahead of the applied index while a batch
+        // is in flight, so the full snapshot is sent meanwhile.
+        while let Ok(results) = self.rx_apply_results.try_recv() {
+            self.handle_applied_batch(results);
+        }
+        if !self.applying.is_empty() {
+            return message;
+        }
+        let applied = self.raw_node.raft.raft_log.applied;
+        let snapshot_index = message.get_snapshot().get_metadata().index;
+        if matched == 0 || applied < snapshot_index || applied - matched > max_lag {
+            return message;
+        }
+
+        let Ok(term) = self.raw_node.raft.raft_log.term(applied) else {
+            return message;
+        };
+        // The follower replaces its sessions with the ones of the delta, like with a full snapshot.
+        let delta = match self.fsm.snapshot_delta(matched).await {
+            Ok(Some(delta)) => self.sessions.append_to(delta),
+            Ok(None) => return message,
+            Err(e) => Err(e),
+        };
+        let delta = match delta {
+            Ok(delta) => delta,
+            Err(e) => {
+                self.logger
+                    .warn(&format!("Failed to take the snapshot delta: {}", e));
+                return message;
+            }
+        };
+
+        let conf_state = self.raw_node.raft.prs().conf().to_conf_state();
+        let snapshot = message.mut_snapshot();
+        snapshot.set_data(delta);
+        let metadata = snapshot.mut_metadata();
+        metadata.index = applied;
+        metadata.term = term;
+        metadata.set_conf_state(conf_state);
+        message.set_context(encode_delta_context(matched));
+
+        self.delta_snapshot_bases.insert(to, matched);
+        self.logger.debug(&format!(
+            "Sending the snapshot delta of entries {}..={} to node {}",
+            matched + 1,
+            applied,
+            to
+        ));
+        message
+    }
+
+    /// Sends the messages, coalescing the ones to the same peer into a single call unless
+    /// `config.batch_messages` is unset. The snapshots are streamed on their own.
+    /// Drops the messages to the peers whose circuit is open, reporting them unreachable to raft
+    /// as a failed send would, see `Config::circuit_breaker`.
+    async fn hold_back_messages(&mut self, messages: Vec<RaftMessage>) -> Vec<RaftMessage> {
+        let Some(circuit_breaker) = self.config.circuit_breaker.clone() else {
+            return messages;
+        };
+
+        let mut sent = Vec::with_capacity(messages.len());
+        let mut held_back = vec![];
+        let peers = self.peers.clone();
+        let mut peers = peers.lock().await;
+        for message in messages {
+            let to = message.get_to();
+            let Some(peer) = peers.get_mut(&to) else {
+                sent.push(message);
+                continue;
+            };
+            let was_open = peer.connection.state == ConnectionState::Open;
+            if peer.allow_request(&circuit_breaker) {
+                sent.push(message);
+                continue;
+            }
+            if !was_open {
+                self.logger.warn(&format!(
+                    "Holding back the messages to node {} for {}s after {} failed requests in a row",
+                    to, circuit_breaker.cooldown, peer.connection.consecutive_failures
+                ));
+            }
+            held_back.push((to, message.get_msg_type() == MessageType::MsgSnapshot));
+        }
+        drop(peers);
+
+        for (node_id, is_snapshot) in held_back {
+            self.raw_node.report_unreachable(node_id);
+            if is_snapshot {
+                self.raw_node
+                    .report_snapshot(node_id, SnapshotStatus::Failure);
+            }
+        }
+        sent
     }
 
     async fn send_messages(&mut self, messages: Vec<RaftMessage>) {
+        let messages = self.hold_back_messages(messages).await;
+        let mut batches: Vec<Vec<RaftMessage>> = vec![];
         for message in messages {
-            tokio::spawn(RaftNodeCore::<LogEntry, LogStorage, FSM>::send_message(
-                message,
+            if message.get_msg_type() == MessageType::MsgSnapshot {
+                let message = self.into_delta_snapshot(message).await;
+                self.spawn_task(RaftNodeCore::<LogEntry, LogStorage, FSM>::send_snapshot(
+                    message,
+                    self.peers.clone(),
+                    self.tx_self.clone(),
+                    self.logger.clone(),
+                    self.client_options(),
+                    self.config.snapshot_chunk_size,
+                    self.config
+                        .snapshot_store
+                        .clone()
+                        .map(|store| (store, self.config.cluster_id.clone())),
+                ));
+                continue;
+            }
+
+            if !self.config.batch_messages {
+                batches.push(vec![message]);
+                continue;
+            }
+            match batches
+                .iter_mut()
+                .find(|batch| batch[0].get_to() == message.get_to())
+            {
+                Some(batch) => batch.push(message),
+                None => batches.push(vec![message]),
+            }
+        }
+
+        for batch in batches {
+            self.spawn_task(RaftNodeCore::<LogEntry, LogStorage, FSM>::send_message(
+                batch,
                 self.peers.clone(),
                 self.tx_self.clone(),
                 self.logger.clone(),
+                self.client_options(),
             ));
         }
     }
 
     async fn handle_committed_entries(&mut self, committed_entries: Vec<Entry>) -> Result<()> {
-        for entry in committed_entries {
+        let last_entry = committed_entries
+            .last()
+            .map(|entry| (entry.get_index(), entry.get_term()));
+
+        let mut batch = vec![];
+        for entry in committed_entries.iter() {
             match entry.get_entry_type() {
                 EntryType::EntryNormal => {
-                    if entry.get_data().is_empty() {
-                        continue;
+                    if !entry.get_data().is_empty() {
+                        self.handle_committed_normal_entry(entry, &mut batch)
+                            .await?;
                     }
-
-                    self.handle_committed_normal_entry(&entry).await?;
                 }
                 EntryType::EntryConfChange | EntryType::EntryConfChangeV2 => {
-                    self.handle_committed_config_change_entry(&entry).await?;
+                    self.apply_batch(std::mem::take(&mut batch)).await?;
+                    self.wait_for_apply().await?;
+                    self.handle_committed_config_change_entry(entry).await?;
                 }
             }
+            self.handled_index = entry.index;
+        }
+        self.apply_batch(batch).await?;
+
+        // Witness nodes only vote, so they truncate the log as soon as the entries are committed.
+        if let (true, Some((index, term))) = (self.config.is_witness, last_entry) {
+            self.make_snapshot(index, term).await?;
         }
         Ok(())
     }
 
-    async fn handle_join(&mut self, tickets: Vec<ClusterJoinTicket>) -> Result<()> {
+    async fn handle_join(
+        &mut self,
+        tickets: Vec<ClusterJoinTicket>,
+        role: InitialRole,
+    ) -> Result<()> {
         let mut cc_v2 = ConfChangeV2::default();
         let mut changes = vec![];
         let mut addrs = vec![];
@@ -742,7 +2426,11 @@ impl<
 
         for ticket in tickets {
             let mut cs = ConfChangeSingle::default();
-            cs.set_change_type(ConfChangeType::AddNode);
+            cs.set_change_type(if role == InitialRole::Learner {
+                ConfChangeType::AddLearnerNode
+            } else {
+                ConfChangeType::AddNode
+            });
             cs.set_node_id(ticket.reserved_id);
             changes.push(cs);
             addrs.push(
@@ -756,11 +2444,15 @@ impl<
         let cc_v2: ConfChangeRequest = cc_v2.clone().into();
         let cc_v2: raft_service::ChangeConfigArgs = cc_v2.into();
 
-        let mut leader_client = RaftServiceClient::connect(format!("http://{}", peer_addr)).await?;
-        let response = leader_client
-            .change_config(cc_v2.clone())
-            .await?
-            .into_inner();
+        let options = self.client_options();
+        let response = options
+            .retry
+            .retry(|| async {
+                let mut leader_client = create_client_with_options(&peer_addr, &options).await?;
+                let response = leader_client.change_config(cc_v2.clone()).await?;
+                Ok(response.into_inner())
+            })
+            .await?;
 
         match response.result_type() {
             ChangeConfigResultType::ChangeConfigSuccess => Ok(()),
@@ -776,93 +2468,555 @@ impl<
         }
     }
 
-    async fn handle_committed_normal_entry(&mut self, entry: &Entry) -> Result<()> {
-        let response_seq: u64 = deserialize(entry.get_context())?;
-        let _data = self.fsm.apply(entry.get_data().to_vec()).await?;
-
-        if let Some(sender) = self.response_senders.remove(&response_seq) {
-            match sender {
-                ResponseSender::Local(tx_local) => {
-                    tx_local
-                        .send(LocalResponseMsg::Propose {
-                            result: ResponseResult::Success,
-                        })
-                        .unwrap();
-                }
-                ResponseSender::Server(tx_server) => {
-                    tx_server
-                        .send(ServerResponseMsg::Propose {
-                            result: ResponseResult::Success,
-                        })
-                        .unwrap();
-                }
-            }
+    /// Adds the entry to `batch` if it's applied to the state machine, and handles it right away otherwise.
+    async fn handle_committed_normal_entry(
+        &mut self,
+        entry: &Entry,
+        batch: &mut Vec<BatchedEntry>,
+    ) -> Result<()> {
+        let context = ProposalContext::decode(entry.get_context())?;
+        // The entries applied by raftify itself see the state machine up to date.
+        if context.flags & (RUNTIME_CONFIG_ENTRY | PEER_LABELS_ENTRY | SHARD_ENTRY) != 0 {
+            self.apply_batch(std::mem::take(batch)).await?;
+            self.wait_for_apply().await?;
         }
-
-        if let Some(snapshot_interval) = self.config.snapshot_interval {
-            if Instant::now()
-                > self.last_snapshot_created + Duration::from_secs_f32(snapshot_interval)
+        if context.flags & RUNTIME_CONFIG_ENTRY != 0 {
+            return self.handle_committed_runtime_config_entry(entry, &context);
+        }
+        if context.flags & PEER_LABELS_ENTRY != 0 {
+            return self
+                .handle_committed_peer_labels_entry(entry, &context)
+                .await;
+        }
+        if context.flags & SHARD_ENTRY != 0 {
+            return self.handle_committed_shard_entry(entry, &context).await;
+        }
+        let ProposalContext {
+            response_seq,
+            session,
+            ref trace_context,
+            ..
+        } = context;
+
+        let span = tracing::info_span!(
+            "raft.apply",
+            index = entry.index,
+            term = entry.term,
+            response_seq
+        );
+        telemetry::set_parent_from_trace_context(&span, trace_context);
+
+        // A retried proposal is checked against the outcome of its batched original.
+        if let Some(ref session) = session {
+            if batch.iter().any(|batched| {
+                batched
+                    .session
+                    .as_ref()
+                    .map_or(false, |s| s.session_id == session.session_id)
+            }) || self
+                .applying
+                .iter()
+                .flat_map(|(_, batch)| batch)
+                .any(|batched| {
+                    batched
+                        .session
+                        .as_ref()
+                        .map_or(false, |s| s.session_id == session.session_id)
+                })
             {
-                self.make_snapshot(entry.index, entry.term).await?;
+                self.apply_batch(std::mem::take(batch)).await?;
+                self.wait_for_apply().await?;
             }
         }
 
-        Ok(())
-    }
+        let check = match session {
+            Some(ref session) => self.sessions.check(session),
+            None => SessionCheck::Fresh,
+        };
 
-    async fn handle_committed_config_change_entry(&mut self, entry: &Entry) -> Result<()> {
-        if entry.get_context().is_empty() {
-            let conf_change_v2 = match entry.get_entry_type() {
-                EntryType::EntryConfChange => {
-                    to_confchange_v2(ConfChange::decode(entry.get_data())?)
+        let applied = match check {
+            SessionCheck::Duplicate(outcome) => {
+                self.logger.debug(&format!(
+                    "Skipping duplicated proposal (index={}, session={:?})",
+                    entry.index, session
+                ));
+                Ok(outcome)
+            }
+            SessionCheck::Stale => Err(Error::Rejected(format!(
+                "Stale proposal sequence in session {:?}",
+                session
+            ))),
+            SessionCheck::Fresh if self.config.is_witness => {
+                Ok(self.record_outcome(entry.index, entry.term, &session, vec![]))
+            }
+            SessionCheck::Fresh => {
+                let data = context.entry_data(entry.get_data())?;
+                match self.check_fenced_ranges(&data) {
+                    Ok(()) => {
+                        batch.push(BatchedEntry {
+                            index: entry.index,
+                            term: entry.term,
+                            response_seq,
+                            session,
+                            data: data.into(),
+                            span,
+                        });
+                        return Ok(());
+                    }
+                    Err(e) => Err(e),
                 }
-                EntryType::EntryConfChangeV2 => ConfChangeV2::decode(entry.get_data())?,
-                _ => unreachable!(),
-            };
+            }
+        };
 
-            let cs = self.raw_node.apply_conf_change(&conf_change_v2)?;
-            let store = self.raw_node.mut_store();
-            store.set_conf_state(&cs)?;
+        self.send_apply_result(entry.index, entry.term, response_seq, applied);
+        Ok(())
+    }
+
+    /// Hands the batched entries to the apply task, which applies them to the state machine at once.
+    async fn apply_batch(&mut self, batch: Vec<BatchedEntry>) -> Result<()> {
+        if batch.is_empty() {
             return Ok(());
         }
 
-        let conf_change_v2 = match entry.get_entry_type() {
-            EntryType::EntryConfChange => to_confchange_v2(ConfChange::decode(entry.get_data())?),
-            EntryType::EntryConfChangeV2 => ConfChangeV2::decode(entry.get_data())?,
-            _ => unreachable!(),
-        };
+        let span = tracing::info_span!(
+            "raft.apply_batch",
+            first_index = batch[0].index,
+            last_index = batch[batch.len() - 1].index
+        );
+        // The data is kept for the subscribers of the applied entries only, so that the apply task
+        // takes the buffers over instead of copying them.
+        let keep_data = self.tx_applied.receiver_count() > 0;
+        let log_entries = batch
+            .iter_mut()
+            .map(|batched| {
+                let data = if keep_data {
+                    batched.data.clone()
+                } else {
+                    std::mem::take(&mut batched.data)
+                };
+                (batched.index, Vec::from(data))
+            })
+            .collect();
+        self.tx_apply
+            .send(ApplyRequest::Batch(ApplyBatch {
+                log_entries,
+                span: span.clone(),
+            }))
+            .await
+            .map_err(|_| Error::Other("The apply task stopped".into()))?;
+        self.applying.push_back((span, batch));
+        Ok(())
+    }
 
-        let conf_changes = conf_change_v2.get_changes();
-        let addrs: Vec<SocketAddr> = deserialize(conf_change_v2.get_context())?;
+    /// Hands the state machine, just restored by the ready loop, to the apply task,
+    /// which otherwise keeps applying the entries on its clone of the previous one.
+    async fn replace_applying_fsm(&mut self) -> Result<()> {
+        self.tx_apply
+            .send(ApplyRequest::Replace(self.fsm.clone()))
+            .await
+            .map_err(|_| Error::Other("The apply task stopped".into()))
+    }
 
-        for (cc_idx, conf_change) in conf_changes.iter().enumerate() {
-            let node_id = conf_change.get_node_id();
-            let change_type = conf_change.get_change_type();
+    /// Waits until the state machine applied every batch handed to the apply task.
+    async fn wait_for_apply(&mut self) -> Result<()> {
+        while !self.applying.is_empty() {
+            let results = self
+                .rx_apply_results
+                .recv()
+                .await
+                .ok_or_else(|| Error::Other("The apply task stopped".into()))?;
+            self.handle_applied_batch(results);
+        }
+        Ok(())
+    }
+
+    /// Waits until the persist task persisted all the Readies handed to it,
+    /// before the log is replaced by a snapshot.
+    async fn wait_for_persist(&mut self) -> Result<()> {
+        while self.persisting > 0 {
+            let persisted = self
+                .rx_persisted
+                .recv()
+                .await
+                .ok_or_else(|| Error::Other("The persist task stopped".into()))?;
+            self.handle_persisted_batch(persisted?).await;
+        }
+        Ok(())
+    }
+
+    /// Waits for the Readies being persisted and the entries being applied, then flushes the storage,
+    /// so that the node shuts down with nothing in flight.
+    async fn flush(&mut self) -> Result<()> {
+        self.wait_for_persist().await?;
+        self.wait_for_apply().await?;
+        self.raw_node.mut_store().flush()
+    }
+
+    /// Sends the messages which waited for the Ready to be persisted, and lets raft-rs know it is.
+    /// The commit index and the committed entries it unblocks come with the next Ready.
+    async fn handle_persisted_batch(&mut self, persisted: PersistedBatch) {
+        if !persisted.persisted_messages.is_empty() {
+            self.send_messages(persisted.persisted_messages).await;
+        }
+        if let Some(number) = persisted.number {
+            self.persisting -= 1;
+            self.raw_node.on_persist_ready(number);
+        }
+    }
+
+    /// Answers the proposals of the oldest batch handed to the apply task, which it applied.
+    fn handle_applied_batch(&mut self, results: Vec<Result<Vec<u8>>>) {
+        let Some((batch_span, batch)) = self.applying.pop_front() else {
+            return;
+        };
+
+        for (batched, applied) in batch.into_iter().zip(results) {
+            batched.span.follows_from(&batch_span);
+            let _enter = batched.span.enter();
+
+            let applied = applied.map(|data| {
+                self.publish_applied_entry(batched.index, &batched.data);
+                self.record_outcome(batched.index, batched.term, &batched.session, data)
+            });
+            self.send_apply_result(batched.index, batched.term, batched.response_seq, applied);
+        }
+        self.advance_apply();
+    }
+
+    /// Marks the entries up to the oldest one still being applied as applied, for raft-rs.
+    fn advance_apply(&mut self) {
+        let applied = match self.applying.front() {
+            Some((_, batch)) => batch[0].index - 1,
+            None => self.handled_index,
+        };
+        if applied > self.raw_node.raft.raft_log.applied {
+            self.raw_node.advance_apply_to(applied);
+        }
+    }
+
+    fn record_outcome(
+        &mut self,
+        index: u64,
+        term: u64,
+        session: &Option<ProposalSession>,
+        data: Vec<u8>,
+    ) -> ProposeOutcome {
+        let outcome = ProposeOutcome { index, term, data };
+        if let Some(session) = session {
+            self.sessions.record(session, outcome.clone());
+        }
+        outcome
+    }
+
+    fn send_apply_result(
+        &mut self,
+        index: u64,
+        term: u64,
+        response_seq: u64,
+        applied: Result<ProposeOutcome>,
+    ) {
+        let result = match applied {
+            Ok(outcome) => ProposeResponseResult::Success(outcome),
+            Err(e) => {
+                self.logger.error(&format!(
+                    "Failed to apply entry (index={}, term={}): {}",
+                    index, term, e
+                ));
+                self.event_handlers
+                    .iter()
+                    .for_each(|handler| handler.on_error(&e));
+                ProposeResponseResult::Error(e)
+            }
+        };
+
+        if let Some(sender) = self.response_senders.remove(&response_seq) {
+            Self::send_propose_result(sender, result);
+        }
+    }
+
+    /// Overrides the config with a `RuntimeConfig` update, and persists the overrides
+    /// so that they outlive the compaction of the entry.
+    fn handle_committed_runtime_config_entry(
+        &mut self,
+        entry: &Entry,
+        context: &ProposalContext,
+    ) -> Result<()> {
+        let update = RuntimeConfig::decode(&context.entry_data(entry.get_data())?)?;
+
+        // The update was validated by the leader, so it's rejected here only if
+        // the config of this node differs from the leader's one.
+        let result = match update.validate_against(&self.config) {
+            Ok(()) => {
+                update.apply_to(&mut self.config);
+                let store = self.raw_node.mut_store();
+                let mut runtime_config = store.runtime_config()?;
+                runtime_config.merge(&update);
+                store.set_runtime_config(&runtime_config)?;
+
+                self.logger.info(&format!(
+                    "Runtime config updated (index={}): {:?}",
+                    entry.index, update
+                ));
+                ProposeResponseResult::Success(ProposeOutcome {
+                    index: entry.index,
+                    term: entry.term,
+                    data: vec![],
+                })
+            }
+            Err(e) => {
+                self.logger.warn(&format!(
+                    "Skipping the runtime config update (index={}): {}",
+                    entry.index, e
+                ));
+                ProposeResponseResult::Error(e)
+            }
+        };
+
+        if let Some(sender) = self.response_senders.remove(&context.response_seq) {
+            Self::send_propose_result(sender, result);
+        }
+        Ok(())
+    }
+
+    /// Replaces the labels of a peer, and persists them so that they outlive the compaction of the entry.
+    async fn handle_committed_peer_labels_entry(
+        &mut self,
+        entry: &Entry,
+        context: &ProposalContext,
+    ) -> Result<()> {
+        let (node_id, labels): (u64, PeerLabels) =
+            deserialize(&context.entry_data(entry.get_data())?)?;
+
+        if let Some(peer) = self.peers.lock().await.get_mut(&node_id) {
+            peer.labels = labels.clone();
+        }
+        let store = self.raw_node.mut_store();
+        let mut peer_labels = store.peer_labels()?;
+        peer_labels.insert(node_id, labels.clone());
+        store.set_peer_labels(&peer_labels)?;
+
+        self.logger.info(&format!(
+            "Labels of node {} set to {:?} (index={}).",
+            node_id, labels, entry.index
+        ));
+
+        if let Some(sender) = self.response_senders.remove(&context.response_seq) {
+            Self::send_propose_result(
+                sender,
+                ProposeResponseResult::Success(ProposeOutcome {
+                    index: entry.index,
+                    term: entry.term,
+                    data: vec![],
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Rejects the entries writing a key of a fenced range.
+    fn check_fenced_ranges(&self, data: &[u8]) -> Result<()> {
+        if self.fenced_ranges.is_empty() {
+            return Ok(());
+        }
+        match self.fsm.key_of(data) {
+            Some(key) => match self.fenced_ranges.iter().find(|range| range.contains(&key)) {
+                Some(range) => Err(Error::Rejected(format!(
+                    "The key is fenced while the range {:?} moves to another group",
+                    range
+                ))),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Applies a step of moving a key range between groups, see `Router::move_range`.
+    async fn handle_committed_shard_entry(
+        &mut self,
+        entry: &Entry,
+        context: &ProposalContext,
+    ) -> Result<()> {
+        let command: ShardCommand = deserialize(&context.entry_data(entry.get_data())?)?;
+        let name = command.name();
+        let result = match self.apply_shard_command(command).await {
+            Ok(data) => {
+                if !self.config.is_witness {
+                    self.fsm.set_applied_index(entry.index);
+                    self.replace_applying_fsm().await?;
+                }
+                self.logger.info(&format!(
+                    "Applied {} (index={}). Fenced ranges: {:?}",
+                    name, entry.index, self.fenced_ranges
+                ));
+                ProposeResponseResult::Success(ProposeOutcome {
+                    index: entry.index,
+                    term: entry.term,
+                    data,
+                })
+            }
+            Err(e) => {
+                self.logger.warn(&format!(
+                    "Failed to apply {} (index={}): {}",
+                    name, entry.index, e
+                ));
+                ProposeResponseResult::Error(e)
+            }
+        };
+
+        if let Some(sender) = self.response_senders.remove(&context.response_seq) {
+            Self::send_propose_result(sender, result);
+        }
+        Ok(())
+    }
+
+    async fn apply_shard_command(&mut self, command: ShardCommand) -> Result<Vec<u8>> {
+        // Witnesses keep no state machine, only the fences.
+        let is_witness = self.config.is_witness;
+        let mut fenced_ranges = self.fenced_ranges.clone();
+        let data = match command {
+            ShardCommand::Fence(range) => {
+                let state = if is_witness {
+                    vec![]
+                } else {
+                    self.fsm.snapshot_range(&range).await?
+                };
+                if !fenced_ranges.contains(&range) {
+                    fenced_ranges.push(range);
+                }
+                state
+            }
+            ShardCommand::Unfence(range) => {
+                fenced_ranges.retain(|fenced| *fenced != range);
+                vec![]
+            }
+            ShardCommand::RestoreRange(state) => {
+                if !is_witness {
+                    self.fsm.restore_range(state).await?;
+                }
+                vec![]
+            }
+            ShardCommand::RemoveRange(range) => {
+                if !is_witness {
+                    self.fsm.remove_range(&range).await?;
+                }
+                fenced_ranges.retain(|fenced| *fenced != range);
+                vec![]
+            }
+        };
+
+        if fenced_ranges != self.fenced_ranges {
+            self.raw_node
+                .mut_store()
+                .set_fenced_ranges(&fenced_ranges)?;
+            self.fenced_ranges = fenced_ranges;
+        }
+        Ok(data)
+    }
+
+    async fn handle_committed_config_change_entry(&mut self, entry: &Entry) -> Result<()> {
+        if entry.get_context().is_empty() {
+            let conf_change_v2 = match entry.get_entry_type() {
+                EntryType::EntryConfChange => {
+                    to_confchange_v2(ConfChange::decode(entry.get_data())?)
+                }
+                EntryType::EntryConfChangeV2 => ConfChangeV2::decode(entry.get_data())?,
+                _ => unreachable!(),
+            };
+
+            let cs = self.raw_node.apply_conf_change(&conf_change_v2)?;
+            self.record_tombstones(entry.index, &conf_change_v2)?;
+            let store = self.raw_node.mut_store();
+            store.set_conf_state(&cs)?;
+            store.append_membership_change(&MembershipChangeRecord::new(
+                entry.index,
+                entry.term,
+                &conf_change_v2,
+                None,
+                None,
+            ))?;
+            self.event_handlers
+                .iter()
+                .for_each(|handler| handler.on_membership_change(&cs));
+            self.publish_cluster_event(
+                ClusterEventType::MembershipChanged,
+                cs.voters.clone(),
+                cs.learners.clone(),
+            );
+            return Ok(());
+        }
+
+        let conf_change_v2 = match entry.get_entry_type() {
+            EntryType::EntryConfChange => to_confchange_v2(ConfChange::decode(entry.get_data())?),
+            EntryType::EntryConfChangeV2 => ConfChangeV2::decode(entry.get_data())?,
+            _ => unreachable!(),
+        };
+
+        let conf_changes = conf_change_v2.get_changes();
+        // An empty ConfChangeV2 leaves the joint configuration and carries no addresses.
+        let addrs: Vec<SocketAddr> = if conf_changes.is_empty() {
+            vec![]
+        } else {
+            deserialize(conf_change_v2.get_context())?
+        };
+
+        for (cc_idx, conf_change) in conf_changes.iter().enumerate() {
+            let node_id = conf_change.get_node_id();
+            let change_type = conf_change.get_change_type();
 
             match change_type {
                 ConfChangeType::AddNode => {
                     let addr = addrs[cc_idx];
-                    self.logger.info(&format!(
-                        "Node {} ({}) joined the cluster as voter.",
-                        node_id, addr
-                    ));
-                    self.peers.lock().await.add_peer(
-                        node_id,
-                        addr.to_string(),
-                        Some(InitialRole::Voter),
-                    );
+                    let mut peers = self.peers.lock().await;
+                    // The peers given as hostnames follow their DNS records instead.
+                    if peers.get(&node_id).is_some_and(|peer| {
+                        peer.role.is_voter() && peer.host.is_none() && peer.addr != addr
+                    }) {
+                        self.logger
+                            .info(&format!("Node {} moved to {}.", node_id, addr));
+                        peers.update_addr(node_id, addr);
+                        continue;
+                    }
+                    if peers.learners().contains(&node_id) {
+                        self.logger
+                            .info(&format!("Node {} promoted to voter.", node_id));
+                    } else {
+                        self.logger.info(&format!(
+                            "Node {} ({}) joined the cluster as voter.",
+                            node_id, addr
+                        ));
+                    }
+                    match peers.get_mut(&node_id) {
+                        Some(peer) if peer.host.is_some() => peer.role = InitialRole::Voter,
+                        _ => peers.add_peer(node_id, addr, Some(InitialRole::Voter)),
+                    }
                 }
                 ConfChangeType::AddLearnerNode => {
                     let addr = addrs[cc_idx];
-                    self.logger.info(&format!(
-                        "Node {} ({}) joined the cluster as learner.",
-                        node_id, addr
-                    ));
-                    self.peers.lock().await.add_peer(
-                        node_id,
-                        addr.to_string(),
-                        Some(InitialRole::Learner),
-                    );
+                    let mut peers = self.peers.lock().await;
+                    // The peers given as hostnames follow their DNS records instead.
+                    if peers.get(&node_id).is_some_and(|peer| {
+                        peer.role == InitialRole::Learner
+                            && peer.host.is_none()
+                            && peer.addr != addr
+                    }) {
+                        self.logger
+                            .info(&format!("Node {} moved to {}.", node_id, addr));
+                        peers.update_addr(node_id, addr);
+                        continue;
+                    }
+                    if peers.voters().contains(&node_id) {
+                        self.logger
+                            .info(&format!("Node {} demoted to learner.", node_id));
+                    } else {
+                        self.logger.info(&format!(
+                            "Node {} ({}) joined the cluster as learner.",
+                            node_id, addr
+                        ));
+                    }
+                    match peers.get_mut(&node_id) {
+                        Some(peer) if peer.host.is_some() => peer.role = InitialRole::Learner,
+                        _ => peers.add_peer(node_id, addr, Some(InitialRole::Learner)),
+                    }
                 }
                 ConfChangeType::RemoveNode => {
                     if node_id == self.get_id() {
@@ -871,50 +3025,91 @@ impl<
                         self.logger
                             .info(&format!("Node {} removed from the cluster.", node_id));
                         self.peers.lock().await.remove(&node_id);
+
+                        let store = self.raw_node.mut_store();
+                        let mut peer_labels = store.peer_labels()?;
+                        if peer_labels.remove(&node_id).is_some() {
+                            store.set_peer_labels(&peer_labels)?;
+                        }
                     }
                 }
             }
         }
 
-        match self.raw_node.apply_conf_change(&conf_change_v2) {
+        let ConfChangeContext {
+            response_seq,
+            requested_by,
+        } = ConfChangeContext::decode(entry.get_context())?;
+
+        let (is_joint, error) = match self.raw_node.apply_conf_change(&conf_change_v2) {
             Ok(conf_state) => {
-                let store = self.raw_node.mut_store();
-                store.set_conf_state(&conf_state)?;
+                let is_joint = !conf_state.voters_outgoing.is_empty();
+                self.raw_node.mut_store().set_conf_state(&conf_state)?;
+                let peers = self.peers.lock().await.clone();
+                self.raw_node.mut_store().set_peers(&peers)?;
+                self.record_tombstones(entry.index, &conf_change_v2)?;
                 self.make_snapshot(entry.index, entry.term).await?;
+                self.event_handlers
+                    .iter()
+                    .for_each(|handler| handler.on_membership_change(&conf_state));
+                self.publish_cluster_event(
+                    ClusterEventType::MembershipChanged,
+                    conf_state.voters.clone(),
+                    conf_state.learners.clone(),
+                );
+                (is_joint, None)
             }
             Err(e) => {
                 self.logger.error(&format!(
                     "Failed to apply the configuration change. Error: {:?}",
                     e
                 ));
+                (false, Some(e.to_string()))
             }
-        }
-
-        let response_seq: AtomicU64 = deserialize(entry.get_context())?;
+        };
 
-        let response_seq_value = response_seq.load(Ordering::Relaxed);
+        self.raw_node
+            .mut_store()
+            .append_membership_change(&MembershipChangeRecord::new(
+                entry.index,
+                entry.term,
+                &conf_change_v2,
+                requested_by,
+                error,
+            ))?;
 
-        if let Some(sender) = self.response_senders.remove(&response_seq_value) {
-            #[allow(unused_assignments)]
-            let mut response = ConfChangeResponseResult::Error(Error::Unknown);
+        if let Some(sender) = self.response_senders.remove(&response_seq) {
+            let is_removal =
+                |cc: &ConfChangeSingle| cc.get_change_type() == ConfChangeType::RemoveNode;
 
-            if conf_changes.iter().all(|cc| {
-                cc.get_change_type() == ConfChangeType::AddNode
-                    || cc.get_change_type() == ConfChangeType::AddLearnerNode
-            }) {
-                response = ConfChangeResponseResult::JoinSuccess {
+            let response = if conf_changes.is_empty() {
+                ConfChangeResponseResult::JointLeft {
+                    peers: self.peers.lock().await.clone(),
+                }
+            } else if is_joint {
+                ConfChangeResponseResult::JointEntered {
+                    assigned_ids: conf_changes
+                        .iter()
+                        .filter(|cc| !is_removal(*cc))
+                        .map(|cc| cc.get_node_id())
+                        .collect(),
+                    removed_ids: conf_changes
+                        .iter()
+                        .filter(|cc| is_removal(*cc))
+                        .map(|cc| cc.get_node_id())
+                        .collect(),
+                    peers: self.peers.lock().await.clone(),
+                }
+            } else if conf_changes.iter().all(|cc| !is_removal(cc)) {
+                ConfChangeResponseResult::JoinSuccess {
                     assigned_ids: conf_changes.iter().map(|cc| cc.get_node_id()).collect(),
                     peers: self.peers.lock().await.clone(),
-                };
-            } else if conf_changes
-                .iter()
-                .all(|cc| cc.get_change_type() == ConfChangeType::RemoveNode)
-            {
-                response = ConfChangeResponseResult::RemoveSuccess;
+                }
+            } else if conf_changes.iter().all(is_removal) {
+                ConfChangeResponseResult::RemoveSuccess
             } else {
-                // TODO: Handle mixed cases
-                unimplemented!();
-            }
+                ConfChangeResponseResult::Error(Error::Unknown)
+            };
 
             match sender {
                 ResponseSender::Local(tx_local) => {
@@ -933,16 +3128,112 @@ impl<
         Ok(())
     }
 
+    /// Proposes the entries back to back so that they are appended in the same Ready cycle.
+    /// The response is sent when the last entry is committed.
+    ///
+    /// `span` is the span of the proposer. Its context is stored in each entry
+    /// so that the spans applying the entry on every node are linked to it.
+    /// `flags` are set in the `ProposalContext` of each entry.
+    /// Returns the error rejecting `count` proposals the node can't take now:
+    /// `Error::Busy` if the state machine lags too far behind the commit index, see `config.max_apply_lag`,
+    /// `Error::TooManyInFlight` or `Error::RateLimited` beyond the limits of `config.admission`,
+    /// and `Error::StorageFull` while the storage can't persist the log or its disk is full, see `config.disk_usage`.
+    /// The entries applied by raftify itself are let through.
+    fn check_admission(&mut self, flags: u8, count: usize) -> Option<Error> {
+        if flags != 0 {
+            return None;
+        }
+
+        if let Some(max_apply_lag) = self.config.max_apply_lag {
+            let raft_log = &self.raw_node.raft.raft_log;
+            let apply_lag = raft_log.committed - raft_log.applied;
+            if apply_lag > max_apply_lag {
+                return Some(Error::Busy(format!(
+                    "{} committed entries are waiting to be applied",
+                    apply_lag
+                )));
+            }
+        }
+
+        if let Some(max_in_flight) = self
+            .config
+            .admission
+            .as_ref()
+            .and_then(|admission| admission.max_in_flight)
+        {
+            // The read index and configuration change requests are not counted.
+            let response_senders = &self.response_senders;
+            self.proposal_seqs
+                .retain(|response_seq| response_senders.contains_key(response_seq));
+            let in_flight = self.proposal_seqs.len();
+            if in_flight >= max_in_flight {
+                return Some(Error::TooManyInFlight(format!(
+                    "{} proposals are waiting to be applied",
+                    in_flight
+                )));
+            }
+        }
+
+        if self.storage_full.load(Ordering::Relaxed) {
+            return Some(Error::StorageFull(
+                "the storage is full, waiting for space to be reclaimed".to_owned(),
+            ));
+        }
+
+        let rejects_proposals = self
+            .config
+            .disk_usage
+            .as_ref()
+            .is_some_and(|config| config.hard_action == DiskFullAction::RejectProposals);
+        let disk_usage = *self.rx_disk_usage.borrow();
+        if let Some(usage) = disk_usage {
+            if rejects_proposals && usage.level == DiskUsageLevel::Hard {
+                return Some(Error::StorageFull(format!(
+                    "only {} of {} bytes are free on the disk of the log",
+                    usage.free_bytes, usage.total_bytes
+                )));
+            }
+        }
+
+        if let Some(limiter) = self.proposal_limiter.as_mut() {
+            if !limiter.try_acquire(count) {
+                return Some(Error::RateLimited(format!(
+                    "the node takes up to {} proposals per second",
+                    limiter.rate()
+                )));
+            }
+        }
+        None
+    }
+
     async fn handle_propose_request(
         &mut self,
-        proposal: Vec<u8>,
+        proposals: Vec<Bytes>,
+        session: Option<ProposalSession>,
+        span: Span,
         response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+        flags: u8,
     ) -> Result<()> {
+        // Rejected before being forwarded to the leader, so that the clients move to another node.
+        if self.draining && flags == 0 {
+            Self::send_propose_result(
+                response_sender,
+                ProposeResponseResult::Error(Error::Draining(
+                    "the node is drained for a maintenance".to_owned(),
+                )),
+            );
+            return Ok(());
+        }
+
         if !self.is_leader() {
             let leader_id = self.get_leader_id();
             if leader_id == 0 {
                 self.logger
                     .error("There is no leader in the cluster at the time. try later...");
+                Self::send_propose_result(
+                    response_sender,
+                    ProposeResponseResult::Error(Error::ProposalDropped),
+                );
                 return Ok(());
             }
 
@@ -956,46 +3247,152 @@ impl<
                 .addr
                 .to_string();
 
-            let raft_response: ResponseMessage<LogEntry, LogStorage, FSM> = match response_sender {
-                ResponseSender::Local(_) => LocalResponseMsg::Propose {
-                    result: ResponseResult::WrongLeader {
-                        leader_id,
-                        leader_addr,
-                    },
-                }
-                .into(),
-                ResponseSender::Server(_) => ServerResponseMsg::Propose {
-                    result: ResponseResult::WrongLeader {
-                        leader_id,
-                        leader_addr,
-                    },
-                }
-                .into(),
-            };
-
-            response_sender.send(raft_response);
+            Self::send_propose_result(
+                response_sender,
+                ProposeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                },
+            );
+        } else if let Some(e) = self.check_admission(flags, proposals.len()) {
+            Self::send_propose_result(response_sender, ProposeResponseResult::Error(e));
         } else {
-            let response_seq = self.response_seq.fetch_add(1, Ordering::Relaxed);
-            match response_sender {
-                ResponseSender::Local(tx_local) => {
-                    self.response_senders
-                        .insert(response_seq, ResponseSender::Local(tx_local));
-                }
-                ResponseSender::Server(tx_server) => {
-                    self.response_senders
-                        .insert(response_seq, ResponseSender::Server(tx_server));
+            let mut response_seq = 0;
+            let mut entries = Vec::with_capacity(proposals.len());
+            for proposal in proposals {
+                response_seq = self.response_seq.fetch_add(1, Ordering::Relaxed);
+                let propose_span = tracing::info_span!(parent: &span, "raft.propose", response_seq);
+                let _entered = propose_span.enter();
+                let mut context = ProposalContext {
+                    response_seq,
+                    session,
+                    trace_context: telemetry::trace_context(&propose_span),
+                    flags,
+                };
+                // Takes the buffer over without copying it if no one else shares it.
+                let proposal = compress_entry_data(
+                    &mut context,
+                    Vec::from(proposal),
+                    self.config.entry_compression_threshold,
+                );
+                let mut entry = Entry::default();
+                entry.set_data(proposal);
+                entry.set_context(context.encode()?);
+                entries.push(entry);
+            }
+
+            // The entries of a batch are appended at once, or all dropped together.
+            let mut message = RaftMessage::default();
+            message.set_msg_type(MessageType::MsgPropose);
+            message.from = self.raw_node.raft.id;
+            message.set_entries(entries.into());
+            match self.raw_node.raft.step(message) {
+                Ok(()) => {}
+                // e.g. a leadership transfer is in progress.
+                Err(crate::raft::Error::ProposalDropped) => {
+                    Self::send_propose_result(
+                        response_sender,
+                        ProposeResponseResult::Error(Error::ProposalDropped),
+                    );
+                    return Ok(());
                 }
-            };
+                Err(e) => return Err(e.into()),
+            }
 
-            self.raw_node.propose(serialize(&response_seq)?, proposal)?;
+            self.response_senders.insert(response_seq, response_sender);
+            self.proposal_seqs.insert(response_seq);
         }
 
         Ok(())
     }
 
+    /// Proposes a `RuntimeConfig` update, unless it would make the config invalid.
+    async fn handle_runtime_config_request(
+        &mut self,
+        update: RuntimeConfig,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) -> Result<()> {
+        if let Err(e) = update.validate_against(&self.config) {
+            Self::send_propose_result(response_sender, ProposeResponseResult::Error(e));
+            return Ok(());
+        }
+
+        self.handle_propose_request(
+            vec![update.encode()?.into()],
+            None,
+            Span::current(),
+            response_sender,
+            RUNTIME_CONFIG_ENTRY,
+        )
+        .await
+    }
+
+    /// Proposes the labels of a member of the cluster.
+    async fn handle_peer_labels_request(
+        &mut self,
+        node_id: u64,
+        labels: PeerLabels,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) -> Result<()> {
+        let conf = self.raw_node.raft.prs().conf();
+        if !conf.voters().contains(node_id) && !conf.learners().contains(&node_id) {
+            let error = Error::Rejected(format!("Node {} is not a member of the cluster", node_id));
+            Self::send_propose_result(response_sender, ProposeResponseResult::Error(error));
+            return Ok(());
+        }
+
+        self.handle_propose_request(
+            vec![serialize(&(node_id, labels))?.into()],
+            None,
+            Span::current(),
+            response_sender,
+            PEER_LABELS_ENTRY,
+        )
+        .await
+    }
+
+    fn publish_applied_entry(&self, index: u64, data: &Bytes) {
+        if self.tx_applied.receiver_count() == 0 || data.is_empty() {
+            return;
+        }
+
+        match LogEntry::decode_bytes(data.clone()) {
+            Ok(log_entry) => {
+                // Fails only when every subscriber has been dropped in the meantime.
+                let _ = self.tx_applied.send((index, log_entry));
+            }
+            Err(e) => self.logger.warn(&format!(
+                "Failed to decode the applied entry (index={}) for subscribers: {}",
+                index, e
+            )),
+        }
+    }
+
+    // The proposer may have stopped waiting, so a failed send is not an error.
+    fn send_propose_result(
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+        result: ProposeResponseResult,
+    ) {
+        match response_sender {
+            ResponseSender::Local(tx_local) => {
+                let _ = tx_local.send(LocalResponseMsg::Propose { result });
+            }
+            ResponseSender::Server(tx_server) => {
+                let _ = tx_server.send(ServerResponseMsg::Propose { result });
+            }
+        }
+    }
+
+    /// Forgets the requests whose callers already gave up waiting (e.g. the future was dropped).
+    fn prune_dropped_requests(&mut self) {
+        self.response_senders
+            .retain(|_, response_sender| !response_sender.is_closed());
+    }
+
     async fn handle_confchange_request(
         &mut self,
         conf_change: ConfChangeV2,
+        requested_by: Option<String>,
         response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
     ) -> Result<()> {
         if self.raw_node.raft.has_pending_conf() {
@@ -1005,31 +3402,31 @@ impl<
 
         if !self.is_leader() {
             let leader_id = self.get_leader_id();
-            if leader_id == 0 {
-                self.logger
-                    .error("There is no leader in the cluster at the time. try later...");
-                return Ok(());
-            }
-
-            let peers = self.peers.lock().await;
-            let leader_addr = peers.get(&leader_id).unwrap().addr.to_string();
-
-            let wrong_leader_result = ConfChangeResponseResult::WrongLeader {
-                leader_id,
-                leader_addr,
+            let leader_addr = self
+                .peers
+                .lock()
+                .await
+                .get(&leader_id)
+                .map(|peer| peer.addr.to_string());
+
+            let result = match leader_addr {
+                Some(leader_addr) => ConfChangeResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                },
+                None => ConfChangeResponseResult::Error(Error::Rejected(
+                    "There is no leader in the cluster at the time".to_owned(),
+                )),
             };
 
+            // The requester may have stopped waiting.
             match response_sender {
-                ResponseSender::Local(tx_local) => tx_local
-                    .send(LocalResponseMsg::ConfigChange {
-                        result: wrong_leader_result,
-                    })
-                    .unwrap(),
-                ResponseSender::Server(tx_server) => tx_server
-                    .send(ServerResponseMsg::ConfigChange {
-                        result: wrong_leader_result,
-                    })
-                    .unwrap(),
+                ResponseSender::Local(tx_local) => {
+                    let _ = tx_local.send(LocalResponseMsg::ConfigChange { result });
+                }
+                ResponseSender::Server(tx_server) => {
+                    let _ = tx_server.send(ServerResponseMsg::ConfigChange { result });
+                }
             }
         } else {
             let response_seq = self.response_seq.fetch_add(1, Ordering::Relaxed);
@@ -1051,13 +3448,746 @@ impl<
                 format_confchangev2(&conf_change)
             ));
 
+            let context = ConfChangeContext {
+                response_seq,
+                requested_by,
+            };
             self.raw_node
-                .propose_conf_change(serialize(&response_seq).unwrap(), conf_change)?;
+                .propose_conf_change(context.encode()?, conf_change)?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_read_index_request(
+        &mut self,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) -> Result<()> {
+        if !self.is_leader() {
+            let leader_id = self.get_leader_id();
+            let leader_addr = self
+                .peers
+                .lock()
+                .await
+                .get(&leader_id)
+                .map(|peer| peer.addr.to_string());
+
+            let raft_response: ResponseMessage<LogEntry, LogStorage, FSM> =
+                match (&response_sender, leader_addr) {
+                    (ResponseSender::Local(_), Some(leader_addr)) => LocalResponseMsg::ReadIndex {
+                        result: ResponseResult::WrongLeader {
+                            leader_id,
+                            leader_addr,
+                        },
+                    }
+                    .into(),
+                    (ResponseSender::Server(_), Some(leader_addr)) => {
+                        ServerResponseMsg::ReadIndex {
+                            result: ReadIndexResponseResult::WrongLeader {
+                                leader_id,
+                                leader_addr,
+                            },
+                        }
+                        .into()
+                    }
+                    (ResponseSender::Local(_), None) => LocalResponseMsg::ReadIndex {
+                        result: ResponseResult::Error(Error::Rejected(
+                            "There is no leader in the cluster at the time".to_owned(),
+                        )),
+                    }
+                    .into(),
+                    (ResponseSender::Server(_), None) => ServerResponseMsg::ReadIndex {
+                        result: ReadIndexResponseResult::Error(Error::Rejected(
+                            "There is no leader in the cluster at the time".to_owned(),
+                        )),
+                    }
+                    .into(),
+                };
+
+            response_sender.send(raft_response);
+        } else {
+            let response_seq = self.response_seq.fetch_add(1, Ordering::Relaxed);
+            self.response_senders.insert(response_seq, response_sender);
+            self.raw_node.read_index(serialize(&response_seq)?);
+        }
+
+        Ok(())
+    }
+
+    fn handle_read_states(&mut self, read_states: Vec<ReadState>) -> Result<()> {
+        for read_state in read_states {
+            let response_seq: u64 = deserialize(&read_state.request_ctx)?;
+
+            match self.response_senders.remove(&response_seq) {
+                Some(ResponseSender::Local(tx_local)) => {
+                    self.pending_reads.push((read_state.index, tx_local));
+                }
+                // The follower that forwarded the request waits for its own apply.
+                // Its call may have been cancelled meanwhile.
+                Some(ResponseSender::Server(tx_server)) => {
+                    let _ = tx_server.send(ServerResponseMsg::ReadIndex {
+                        result: ReadIndexResponseResult::Success {
+                            index: read_state.index,
+                        },
+                    });
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_pending_reads(&mut self) {
+        let applied = self.raw_node.raft.raft_log.applied;
+        let (readable, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_reads)
+            .into_iter()
+            .partition(|(read_index, _)| *read_index <= applied);
+        self.pending_reads = pending;
+
+        // The requesters which stopped waiting, e.g. on a timeout, are skipped.
+        for (_, tx_local) in readable {
+            let _ = tx_local.send(LocalResponseMsg::ReadIndex {
+                result: ResponseResult::Success,
+            });
+        }
+    }
+
+    async fn handle_query_request(
+        &mut self,
+        query: Vec<u8>,
+        leader_only: bool,
+        read_index: u64,
+        tx_msg: oneshot::Sender<ServerResponseMsg>,
+    ) {
+        if leader_only && !self.is_leader() {
+            let leader_id = self.get_leader_id();
+            let leader_addr = self
+                .peers
+                .lock()
+                .await
+                .get(&leader_id)
+                .map(|peer| peer.addr.to_string());
+
+            let result = match leader_addr {
+                Some(leader_addr) => QueryResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                },
+                None => QueryResponseResult::Error(Error::Rejected(
+                    "There is no leader in the cluster at the time".to_owned(),
+                )),
+            };
+            // The remote caller may have gone away.
+            let _ = tx_msg.send(ServerResponseMsg::Query { result });
+            return;
+        }
+
+        self.pending_queries.push((read_index, query, tx_msg));
+        self.handle_pending_queries().await;
+    }
+
+    async fn handle_pending_queries(&mut self) {
+        let applied = self.raw_node.raft.raft_log.applied;
+        let (readable, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_queries)
+            .into_iter()
+            .partition(|(read_index, _, _)| *read_index <= applied);
+        self.pending_queries = pending;
+
+        for (_, query, tx_msg) in readable {
+            let result = match self.fsm.query_encoded(&query).await {
+                Ok(data) => QueryResponseResult::Success { data },
+                Err(e) => QueryResponseResult::Error(e),
+            };
+            // The client may have given up on the query.
+            let _ = tx_msg.send(ServerResponseMsg::Query { result });
+        }
+    }
+
+    fn handle_transfer_leader_request(
+        &mut self,
+        node_id: u64,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) {
+        if !self.raw_node.raft.prs().conf().voters().contains(node_id) {
+            let error = Error::Rejected(format!("Node {} is not a voter of the cluster", node_id));
+            Self::send_transfer_leader_result(response_sender, ResponseResult::Error(error));
+            return;
+        }
+
+        if self.get_leader_id() == node_id {
+            Self::send_transfer_leader_result(response_sender, ResponseResult::Success);
+            return;
+        }
+
+        // raft-rs aborts the transfer when it isn't done within an election timeout.
+        self.raw_node.transfer_leader(node_id);
+        self.pending_leader_transfers.push((
+            node_id,
+            Instant::now() + self.election_timeout() * 2,
+            response_sender,
+        ));
+    }
+
+    fn election_timeout(&self) -> Duration {
+        let scale = self.ticks.0 as f32 / self.config.raft_config.election_tick as f32;
+        Duration::from_secs_f32(
+            self.config.tick_interval
+                * self.raw_node.raft.randomized_election_timeout() as f32
+                * scale,
+        )
+    }
+
+    /// Sets the election and heartbeat ticks from the next tick boundary on.
+    fn set_ticks(&mut self, election_tick: usize, heartbeat_tick: usize) -> Result<()> {
+        if heartbeat_tick == 0 || heartbeat_tick >= election_tick {
+            return Err(Error::Rejected(format!(
+                "heartbeat_tick {} should be greater than 0 and less than election_tick {}",
+                heartbeat_tick, election_tick
+            )));
+        }
+        self.pending_ticks = Some((election_tick, heartbeat_tick));
+        Ok(())
+    }
+
+    /// Ticks raft-rs, whose timeouts can't be changed once it's started.
+    /// The ticks set at runtime are applied by slowing its clock down or speeding it up instead:
+    /// by the election ticks on the followers, and by the heartbeat ticks on the leader,
+    /// whose check-quorum timeout is therefore scaled like its heartbeat.
+    fn tick(&mut self) {
+        if let Some(ticks) = self.pending_ticks.take() {
+            self.logger.info(&format!(
+                "Election tick changed to {}, heartbeat tick changed to {}.",
+                ticks.0, ticks.1
+            ));
+            self.ticks = ticks;
+            self.tick_credit = 0.0;
+        }
+
+        let raft_config = &self.config.raft_config;
+        let scale = if self.is_leader() {
+            raft_config.heartbeat_tick as f32 / self.ticks.1 as f32
+        } else {
+            raft_config.election_tick as f32 / self.ticks.0 as f32
+        };
+
+        self.tick_credit += scale;
+        while self.tick_credit >= 1.0 {
+            self.tick_credit -= 1.0;
+            self.raw_node.tick();
+        }
+    }
+
+    fn handle_pending_leader_transfers(&mut self) {
+        if self.pending_leader_transfers.is_empty() {
+            return;
+        }
+
+        let leader_id = self.get_leader_id();
+        let now = Instant::now();
+
+        for (node_id, deadline, response_sender) in
+            std::mem::take(&mut self.pending_leader_transfers)
+        {
+            if leader_id == node_id {
+                Self::send_transfer_leader_result(response_sender, ResponseResult::Success);
+            } else if now > deadline {
+                self.logger.warn(&format!(
+                    "Leadership transfer to node {} timed out.",
+                    node_id
+                ));
+                Self::send_transfer_leader_result(
+                    response_sender,
+                    ResponseResult::Error(Error::Timeout),
+                );
+            } else {
+                self.pending_leader_transfers
+                    .push((node_id, deadline, response_sender));
+            }
+        }
+    }
+
+    /// Promotes learners whose match index is within `learner_auto_promote_threshold` entries
+    /// of the leader's last index.
+    async fn promote_caught_up_learners(&mut self) -> Result<()> {
+        let threshold = match self.config.learner_auto_promote_threshold {
+            Some(threshold) => threshold,
+            None => return Ok(()),
+        };
+
+        // The leader drops the proposals while it transfers the leadership.
+        if !self.is_leader()
+            || self.raw_node.raft.has_pending_conf()
+            || self.raw_node.raft.lead_transferee.is_some()
+        {
+            return Ok(());
+        }
+
+        let last_index = self.raw_node.raft.raft_log.last_index();
+        let caught_up_learner = self
+            .raw_node
+            .raft
+            .prs()
+            .iter()
+            .filter(|(id, _)| self.raw_node.raft.prs().conf().learners().contains(*id))
+            .find(|(_, pr)| pr.matched + threshold >= last_index)
+            .map(|(id, _)| *id);
+
+        let node_id = match caught_up_learner {
+            Some(node_id) => node_id,
+            None => return Ok(()),
+        };
+
+        let addr = match self.peers.lock().await.get(&node_id) {
+            Some(peer) => peer.addr,
+            None => return Ok(()),
+        };
+
+        self.logger.info(&format!(
+            "Learner {} caught up with the leader. Promoting it to voter...",
+            node_id
+        ));
+
+        let mut cs = ConfChangeSingle::default();
+        cs.set_change_type(ConfChangeType::AddNode);
+        cs.set_node_id(node_id);
+
+        let mut conf_change = ConfChangeV2::default();
+        conf_change.set_changes(vec![cs]);
+        conf_change.set_context(serialize(&vec![addr])?);
+
+        // Nobody waits for the response, so the sequence is only used to tag the entry.
+        let context = ConfChangeContext {
+            response_seq: self.response_seq.fetch_add(1, Ordering::Relaxed),
+            requested_by: Some(self.local_requester()),
+        };
+        match self
+            .raw_node
+            .propose_conf_change(context.encode()?, conf_change)
+        {
+            Ok(()) => Ok(()),
+            // Promoted again at the next tick.
+            Err(crate::raft::Error::ProposalDropped) => {
+                self.logger.debug(&format!(
+                    "The promotion of learner {} was dropped, retrying at the next tick.",
+                    node_id
+                ));
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Proposes the removal of a voter unreachable for `config.dead_voter_removal.unreachable_for` seconds,
+    /// as long as the other reachable voters form a quorum and the cluster keeps `min_voters` voters.
+    async fn remove_dead_voters(&mut self) -> Result<()> {
+        let policy = match &self.config.dead_voter_removal {
+            Some(policy) => policy.clone(),
+            None => return Ok(()),
+        };
+
+        if !self.is_leader() {
+            self.unreachable_voters.clear();
+            return Ok(());
+        }
+
+        // `recent_active` is reset at every election timeout by check_quorum,
+        // so a voter stays inactive only while it doesn't respond to the heartbeats.
+        let voters = self.raw_node.raft.prs().conf().to_conf_state().voters;
+        let now = Instant::now();
+        for node_id in voters.iter().filter(|id| **id != self.get_id()) {
+            let is_active = self
+                .raw_node
+                .raft
+                .prs()
+                .get(*node_id)
+                .is_some_and(|pr| pr.recent_active);
+
+            if is_active {
+                self.unreachable_voters.remove(node_id);
+            } else {
+                self.unreachable_voters.entry(*node_id).or_insert(now);
+            }
+        }
+        self.unreachable_voters
+            .retain(|node_id, _| voters.contains(node_id));
+
+        // The leader drops the proposals while it transfers the leadership, e.g. while it's drained.
+        if voters.len() <= policy.min_voters
+            || self.raw_node.raft.has_pending_conf()
+            || self.raw_node.raft.lead_transferee.is_some()
+        {
+            return Ok(());
+        }
+
+        let dead_voter = self
+            .unreachable_voters
+            .iter()
+            .find(|(_, since)| since.elapsed().as_secs_f32() >= policy.unreachable_for)
+            .map(|(node_id, _)| *node_id);
+
+        let node_id = match dead_voter {
+            Some(node_id) => node_id,
+            None => return Ok(()),
+        };
+
+        if let Some(reason) = self.check_force_removal(node_id) {
+            self.logger.debug(&format!(
+                "Node {} is unreachable, but can't be removed. {}",
+                node_id, reason
+            ));
+            return Ok(());
         }
 
+        self.logger.warn(&format!(
+            "Node {} has been unreachable for {} seconds. Removing it from the cluster...",
+            node_id, policy.unreachable_for
+        ));
+
+        // The address of the removed node isn't used, so the node doesn't need to be in the peers.
+        let addr = self
+            .peers
+            .lock()
+            .await
+            .get(&node_id)
+            .map(|peer| peer.addr)
+            .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+
+        let mut cs = ConfChangeSingle::default();
+        cs.set_change_type(ConfChangeType::RemoveNode);
+        cs.set_node_id(node_id);
+
+        let mut conf_change = ConfChangeV2::default();
+        conf_change.set_changes(vec![cs]);
+        conf_change.set_context(serialize(&vec![addr])?);
+
+        // Nobody waits for the response, so the sequence is only used to tag the entry.
+        let context = ConfChangeContext {
+            response_seq: self.response_seq.fetch_add(1, Ordering::Relaxed),
+            requested_by: Some(self.local_requester()),
+        };
+        match self
+            .raw_node
+            .propose_conf_change(context.encode()?, conf_change)
+        {
+            Ok(()) => {
+                self.unreachable_voters.remove(&node_id);
+                Ok(())
+            }
+            // The node stays unreachable, so its removal is proposed again at the next tick.
+            Err(crate::raft::Error::ProposalDropped) => {
+                self.logger.debug(&format!(
+                    "The removal of node {} was dropped, retrying at the next tick.",
+                    node_id
+                ));
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Takes a snapshot of the applied entries once `config.snapshot_interval` seconds have passed
+    /// or `config.snapshot_log_count_threshold` entries have been applied since the last snapshot.
+    async fn make_periodic_snapshot(&mut self) -> Result<()> {
+        let applied = self.raw_node.raft.raft_log.applied;
+        if applied <= self.last_snapshot_index {
+            return Ok(());
+        }
+
+        let interval_elapsed = self.config.snapshot_interval.is_some_and(|interval| {
+            self.last_snapshot_created.elapsed() >= Duration::from_secs_f32(interval)
+        });
+        let count_exceeded = self
+            .config
+            .snapshot_log_count_threshold
+            .is_some_and(|threshold| applied - self.last_snapshot_index >= threshold);
+        if !interval_elapsed && !count_exceeded {
+            return Ok(());
+        }
+
+        // The snapshot covers the entries still being applied too.
+        self.wait_for_apply().await?;
+        let applied = self.raw_node.raft.raft_log.applied;
+        let term = self.raw_node.raft.raft_log.term(applied)?;
+        self.make_snapshot(applied, term).await?;
+        self.logger.debug(&format!(
+            "Periodic snapshot created (index={}, term={}).",
+            applied, term
+        ));
         Ok(())
     }
 
+    /// Remembers whether the snapshot in `message` is a delta, to be restored in `on_ready`.
+    /// Returns false if the delta doesn't start from the applied index of this node.
+    fn accept_snapshot_message(&mut self, message: &RaftMessage) -> bool {
+        let Some(base_index) = decode_delta_context(message.get_context()) else {
+            self.pending_delta_snapshot = None;
+            return true;
+        };
+
+        let applied = self.raw_node.raft.raft_log.applied;
+        if base_index != applied || self.config.is_witness {
+            self.logger.warn(&format!(
+                "Dropping the snapshot delta since index {}, applied index is {}.",
+                base_index, applied
+            ));
+            return false;
+        }
+
+        self.pending_delta_snapshot = Some(message.get_snapshot().get_metadata().index);
+        true
+    }
+
+    /// Reports the node ready to systemd once it joined the cluster, and pets the watchdog of systemd,
+    /// which restarts the unit if the loop stops coming by.
+    fn notify_systemd(&mut self) {
+        if !self.systemd.is_ready() && self.get_leader_id() != 0 {
+            let id = self.get_id();
+            let conf = self.raw_node.raft.prs().conf();
+            if conf.voters().contains(id) || conf.learners().contains(&id) {
+                self.systemd.notify_ready(id);
+            }
+        }
+        self.systemd.pet_watchdog();
+    }
+
+    /// Transfers the leadership to the most up-to-date voter if a witness or a draining node became the leader.
+    fn hand_off_unwanted_leadership(&mut self) {
+        if !(self.config.is_witness || self.draining)
+            || !self.is_leader()
+            || self.raw_node.raft.lead_transferee.is_some()
+        {
+            return;
+        }
+
+        let self_id = self.get_id();
+        let voters = self.raw_node.raft.prs().conf().voters().ids();
+        let transferee = self
+            .raw_node
+            .raft
+            .prs()
+            .iter()
+            .filter(|(id, _)| **id != self_id && voters.contains(**id))
+            .max_by_key(|(_, pr)| pr.matched)
+            .map(|(id, _)| *id);
+
+        if let Some(transferee) = transferee {
+            self.logger.info(&format!(
+                "{} node {} became the leader. Transferring the leadership to node {}...",
+                if self.config.is_witness {
+                    "Witness"
+                } else {
+                    "Draining"
+                },
+                self_id,
+                transferee
+            ));
+            self.raw_node.transfer_leader(transferee);
+        }
+    }
+
+    /// Transfers the leadership to the node preferred by `config.leader_placement`, if any.
+    async fn apply_leader_placement(&mut self) {
+        let policy = match &self.config.leader_placement {
+            Some(policy) => policy.clone(),
+            None => return,
+        };
+
+        if !self.is_leader() || self.raw_node.raft.lead_transferee.is_some() {
+            return;
+        }
+
+        // Only the nodes able to take over right away are offered, so that the cluster
+        // doesn't go without a leader while a lagging node catches up.
+        let self_id = self.get_id();
+        let last_index = self.raw_node.raft.raft_log.last_index();
+        let voters = self.raw_node.raft.prs().conf().voters().ids();
+        let mut candidates: Vec<u64> = self
+            .raw_node
+            .raft
+            .prs()
+            .iter()
+            .filter(|(id, pr)| {
+                **id != self_id
+                    && voters.contains(**id)
+                    && pr.recent_active
+                    && pr.matched == last_index
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        candidates.sort();
+
+        let transferee = policy.transferee(self_id, &candidates, &*self.peers.lock().await);
+        if let Some(transferee) = transferee.filter(|id| candidates.contains(id)) {
+            self.logger.info(&format!(
+                "Transferring the leadership to node {} preferred by the leader placement policy...",
+                transferee
+            ));
+            self.raw_node.transfer_leader(transferee);
+        }
+    }
+
+    async fn handle_pending_campaigns(&mut self) {
+        if self.pending_campaigns.is_empty() {
+            return;
+        }
+
+        let leader_id = self.get_leader_id();
+        let now = Instant::now();
+
+        for (deadline, tx_msg) in std::mem::take(&mut self.pending_campaigns) {
+            let result = if self.is_leader() {
+                ResponseResult::Success
+            } else if leader_id != 0 {
+                let leader_addr = match self.peers.lock().await.get(&leader_id) {
+                    Some(peer) => peer.addr.to_string(),
+                    None => String::new(),
+                };
+                ResponseResult::WrongLeader {
+                    leader_id,
+                    leader_addr,
+                }
+            } else if now > deadline {
+                ResponseResult::Error(Error::Timeout)
+            } else {
+                self.pending_campaigns.push((deadline, tx_msg));
+                continue;
+            };
+
+            // The requester may have stopped waiting for the election.
+            let _ = tx_msg.send(LocalResponseMsg::Campaign { result });
+        }
+    }
+
+    // The requester may have stopped waiting, so a failed send is not an error.
+    fn send_transfer_leader_result(
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+        result: ResponseResult,
+    ) {
+        match response_sender {
+            ResponseSender::Local(tx_local) => {
+                let _ = tx_local.send(LocalResponseMsg::TransferLeader { result });
+            }
+            ResponseSender::Server(tx_server) => {
+                let _ = tx_server.send(ServerResponseMsg::TransferLeader { result });
+            }
+        }
+    }
+
+    async fn handle_leave_joint_request(
+        &mut self,
+        requested_by: Option<String>,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) -> Result<()> {
+        let conf_state = self.raw_node.raft.prs().conf().to_conf_state();
+
+        // raft-rs silently drops an empty conf change outside of the joint state,
+        // which would leave the caller waiting forever.
+        if self.is_leader() && conf_state.voters_outgoing.is_empty() {
+            let result = ConfChangeResponseResult::Error(Error::Rejected(
+                "The cluster is not in a joint configuration".to_owned(),
+            ));
+
+            match response_sender {
+                ResponseSender::Local(tx_local) => tx_local
+                    .send(LocalResponseMsg::ConfigChange { result })
+                    .unwrap(),
+                ResponseSender::Server(tx_server) => tx_server
+                    .send(ServerResponseMsg::ConfigChange { result })
+                    .unwrap(),
+            }
+            return Ok(());
+        }
+
+        self.handle_confchange_request(ConfChangeV2::default(), requested_by, response_sender)
+            .await
+    }
+
+    /// Returns why `node_id` can't be force-removed, if it can't.
+    fn check_force_removal(&self, node_id: u64) -> Option<String> {
+        let conf_state = self.raw_node.raft.prs().conf().to_conf_state();
+
+        if node_id == self.get_id() {
+            return Some(format!(
+                "Node {} is the leader. Transfer the leadership before removing it",
+                node_id
+            ));
+        }
+        if !conf_state.voters.contains(&node_id) && !conf_state.learners.contains(&node_id) {
+            return Some(format!("Node {} is not a member of the cluster", node_id));
+        }
+        if !conf_state.voters_outgoing.is_empty() {
+            return Some("The cluster is in a joint configuration".to_owned());
+        }
+
+        // The removal is committed by the quorum of the current voters, including the removed one.
+        let quorum = conf_state.voters.len() / 2 + 1;
+        let reachable = conf_state
+            .voters
+            .iter()
+            .filter(|id| **id != node_id)
+            .filter(|id| {
+                **id == self.get_id()
+                    || self
+                        .raw_node
+                        .raft
+                        .prs()
+                        .get(**id)
+                        .is_some_and(|pr| pr.recent_active)
+            })
+            .count();
+
+        if reachable < quorum {
+            return Some(format!(
+                "Only {} voters other than node {} are reachable, less than the quorum {}",
+                reachable, node_id, quorum
+            ));
+        }
+        None
+    }
+
+    async fn handle_force_remove_peer_request(
+        &mut self,
+        node_id: u64,
+        requested_by: Option<String>,
+        response_sender: ResponseSender<LogEntry, LogStorage, FSM>,
+    ) -> Result<()> {
+        if self.is_leader() {
+            if let Some(reason) = self.check_force_removal(node_id) {
+                let result = ConfChangeResponseResult::Error(Error::Rejected(reason));
+
+                match response_sender {
+                    ResponseSender::Local(tx_local) => tx_local
+                        .send(LocalResponseMsg::ConfigChange { result })
+                        .unwrap(),
+                    ResponseSender::Server(tx_server) => tx_server
+                        .send(ServerResponseMsg::ConfigChange { result })
+                        .unwrap(),
+                }
+                return Ok(());
+            }
+        }
+
+        // The address of the removed node isn't used, so the node doesn't need to be in the peers.
+        let addr = self
+            .peers
+            .lock()
+            .await
+            .get(&node_id)
+            .map(|peer| peer.addr)
+            .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+
+        let mut conf_change = ConfChange::default();
+        conf_change.set_node_id(node_id);
+        conf_change.set_change_type(ConfChangeType::RemoveNode);
+        conf_change.set_context(serialize(&vec![addr])?);
+
+        self.handle_confchange_request(to_confchange_v2(conf_change), requested_by, response_sender)
+            .await
+    }
+
     async fn handle_local_request_msg(
         &mut self,
         message: LocalRequestMsg<LogEntry, LogStorage, FSM>,
@@ -1082,6 +4212,34 @@ impl<
                     })
                     .unwrap();
             }
+            LocalRequestMsg::GetTerm { tx_msg } => {
+                tx_msg
+                    .send(LocalResponseMsg::GetTerm {
+                        term: self.raw_node.raft.term,
+                    })
+                    .unwrap();
+            }
+            LocalRequestMsg::GetCommitIndex { tx_msg } => {
+                tx_msg
+                    .send(LocalResponseMsg::GetCommitIndex {
+                        commit_index: self.raw_node.raft.raft_log.committed,
+                    })
+                    .unwrap();
+            }
+            LocalRequestMsg::GetLastApplied { tx_msg } => {
+                tx_msg
+                    .send(LocalResponseMsg::GetLastApplied {
+                        last_applied: self.raw_node.raft.raft_log.applied,
+                    })
+                    .unwrap();
+            }
+            LocalRequestMsg::GetRole { tx_msg } => {
+                tx_msg
+                    .send(LocalResponseMsg::GetRole {
+                        role: self.raw_node.raft.state.into(),
+                    })
+                    .unwrap();
+            }
             LocalRequestMsg::GetPeers { tx_msg } => {
                 tx_msg
                     .send(LocalResponseMsg::GetPeers {
@@ -1123,10 +4281,87 @@ impl<
                     })
                     .unwrap();
             }
-            LocalRequestMsg::Propose { proposal, tx_msg } => {
-                self.handle_propose_request(proposal, ResponseSender::Local(tx_msg))
+            LocalRequestMsg::GetStatus { tx_msg } => {
+                tx_msg
+                    .send(LocalResponseMsg::GetStatus {
+                        status: RaftStatus::new(
+                            &self.raw_node,
+                            *self.rx_disk_usage.borrow(),
+                            self.storage_full.load(Ordering::Relaxed),
+                            self.draining,
+                            self.peers
+                                .lock()
+                                .await
+                                .iter()
+                                .filter(|(node_id, _)| *node_id != self.get_id())
+                                .map(|(node_id, peer)| (node_id, peer.connection.clone()))
+                                .collect(),
+                        ),
+                    })
+                    .unwrap();
+            }
+            LocalRequestMsg::Propose {
+                proposal,
+                session,
+                span,
+                tx_msg,
+            } => {
+                self.handle_propose_request(
+                    vec![proposal],
+                    session,
+                    span,
+                    ResponseSender::Local(tx_msg),
+                    0,
+                )
+                .await?;
+            }
+            LocalRequestMsg::ProposeBatch {
+                proposals,
+                span,
+                tx_msg,
+            } => {
+                self.handle_propose_request(
+                    proposals,
+                    None,
+                    span,
+                    ResponseSender::Local(tx_msg),
+                    0,
+                )
+                .await?;
+            }
+            LocalRequestMsg::UpdateRuntimeConfig { update, tx_msg } => {
+                self.handle_runtime_config_request(update, ResponseSender::Local(tx_msg))
+                    .await?;
+            }
+            LocalRequestMsg::SetPeerLabels {
+                node_id,
+                labels,
+                tx_msg,
+            } => {
+                self.handle_peer_labels_request(node_id, labels, ResponseSender::Local(tx_msg))
                     .await?;
             }
+            LocalRequestMsg::Shard { command, tx_msg } => {
+                self.handle_propose_request(
+                    vec![serialize(&command)?.into()],
+                    None,
+                    Span::current(),
+                    ResponseSender::Local(tx_msg),
+                    SHARD_ENTRY,
+                )
+                .await?;
+            }
+            LocalRequestMsg::GetMembers { tx_msg } => {
+                let conf_state = self.raw_node.raft.prs().conf().to_conf_state();
+                let mut voters = conf_state.voters;
+                let mut learners = conf_state.learners;
+                voters.sort();
+                learners.sort();
+
+                tx_msg
+                    .send(LocalResponseMsg::GetMembers { voters, learners })
+                    .unwrap();
+            }
             LocalRequestMsg::GetClusterSize { tx_msg } => {
                 let size = self.raw_node.raft.prs().iter().collect::<Vec<_>>().len();
                 tx_msg
@@ -1147,17 +4382,35 @@ impl<
                 self.should_exit = true;
                 tx_msg.send(LocalResponseMsg::Quit {}).unwrap();
             }
+            LocalRequestMsg::Shutdown { tx_msg } => {
+                let result = match self.flush().await {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                self.should_exit = true;
+                tx_msg.send(LocalResponseMsg::Shutdown { result }).unwrap();
+            }
             LocalRequestMsg::Campaign { tx_msg } => {
-                self.raw_node.campaign()?;
-                tx_msg.send(LocalResponseMsg::Campaign {}).unwrap();
+                if self.is_leader() {
+                    tx_msg
+                        .send(LocalResponseMsg::Campaign {
+                            result: ResponseResult::Success,
+                        })
+                        .unwrap();
+                } else {
+                    self.raw_node.campaign()?;
+                    // Retried elections may take a few election timeouts to settle.
+                    let deadline = Instant::now() + self.election_timeout() * 3;
+                    self.pending_campaigns.push((deadline, tx_msg));
+                }
             }
-            LocalRequestMsg::Demote {
+            LocalRequestMsg::StepDown {
                 tx_msg,
                 term,
                 leader_id,
             } => {
                 self.raw_node.raft.become_follower(term, leader_id);
-                tx_msg.send(LocalResponseMsg::Demote {}).unwrap();
+                tx_msg.send(LocalResponseMsg::StepDown {}).unwrap();
             }
             LocalRequestMsg::Leave { tx_msg } => {
                 let mut conf_change = ConfChange::default();
@@ -1167,6 +4420,7 @@ impl<
 
                 self.handle_confchange_request(
                     to_confchange_v2(conf_change),
+                    Some(self.local_requester()),
                     ResponseSender::Local(tx_msg),
                 )
                 .await?;
@@ -1176,8 +4430,12 @@ impl<
                 tx_msg,
             } => {
                 let conf_change: ConfChangeV2 = conf_change.into();
-                self.handle_confchange_request(conf_change, ResponseSender::Local(tx_msg))
-                    .await?;
+                self.handle_confchange_request(
+                    conf_change,
+                    Some(self.local_requester()),
+                    ResponseSender::Local(tx_msg),
+                )
+                .await?;
             }
             LocalRequestMsg::MakeSnapshot {
                 index,
@@ -1187,9 +4445,36 @@ impl<
                 self.make_snapshot(index, term).await?;
                 tx_msg.send(LocalResponseMsg::MakeSnapshot {}).unwrap();
             }
-            LocalRequestMsg::JoinCluster { tickets, tx_msg } => {
-                self.handle_join(tickets).await?;
-                tx_msg.send(LocalResponseMsg::JoinCluster {}).unwrap();
+            LocalRequestMsg::CompactLogs { index, tx_msg } => {
+                let result = match self.compact_logs(index).await {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg
+                    .send(LocalResponseMsg::CompactLogs { result })
+                    .unwrap();
+            }
+            LocalRequestMsg::PurgeTombstones { node_ids, tx_msg } => {
+                let result = match self.purge_tombstones(&node_ids) {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg
+                    .send(LocalResponseMsg::PurgeTombstones { result })
+                    .unwrap();
+            }
+            LocalRequestMsg::JoinCluster {
+                tickets,
+                role,
+                tx_msg,
+            } => {
+                let result = match self.handle_join(tickets, role).await {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg
+                    .send(LocalResponseMsg::JoinCluster { result })
+                    .unwrap();
             }
             LocalRequestMsg::SendMessage { message, tx_msg } => {
                 self.logger.debug(&format!(
@@ -1200,13 +4485,77 @@ impl<
                 let _ = self.raw_node.step(*message);
                 tx_msg.send(LocalResponseMsg::SendMessage {}).unwrap();
             }
-            LocalRequestMsg::LeaveJoint {} => {
-                let zero = ConfChangeV2::default();
-                self.raw_node.propose_conf_change(vec![], zero)?;
+            LocalRequestMsg::LeaveJoint { tx_msg } => {
+                self.handle_leave_joint_request(
+                    Some(self.local_requester()),
+                    ResponseSender::Local(tx_msg),
+                )
+                .await?;
+            }
+            LocalRequestMsg::ForceRemovePeer { node_id, tx_msg } => {
+                self.handle_force_remove_peer_request(
+                    node_id,
+                    Some(self.local_requester()),
+                    ResponseSender::Local(tx_msg),
+                )
+                .await?;
+            }
+            LocalRequestMsg::SetPriority { priority, tx_msg } => {
+                self.set_priority(priority).await;
+                tx_msg.send(LocalResponseMsg::SetPriority {}).unwrap();
+            }
+            LocalRequestMsg::SetDraining { draining, tx_msg } => {
+                if draining != self.draining {
+                    self.logger.info(&format!(
+                        "Node {} {} draining.",
+                        self.get_id(),
+                        if draining { "started" } else { "stopped" }
+                    ));
+                }
+                self.draining = draining;
+                tx_msg.send(LocalResponseMsg::SetDraining {}).unwrap();
+            }
+            LocalRequestMsg::SetTicks {
+                election_tick,
+                heartbeat_tick,
+                tx_msg,
+            } => {
+                let result = match self.set_ticks(election_tick, heartbeat_tick) {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg.send(LocalResponseMsg::SetTicks { result }).unwrap();
+            }
+            LocalRequestMsg::RegisterEventHandler { handler, tx_msg } => {
+                self.event_handlers.push(handler);
+                tx_msg
+                    .send(LocalResponseMsg::RegisterEventHandler {})
+                    .unwrap();
             }
             LocalRequestMsg::TransferLeader { node_id, tx_msg } => {
-                self.raw_node.transfer_leader(node_id);
-                tx_msg.send(LocalResponseMsg::TransferLeader {}).unwrap();
+                self.handle_transfer_leader_request(node_id, ResponseSender::Local(tx_msg));
+            }
+            LocalRequestMsg::ReadLease { tx_msg } => {
+                if self.config.raft_config.read_only_option != ReadOnlyOption::LeaseBased {
+                    tx_msg
+                        .send(LocalResponseMsg::ReadIndex {
+                            result: ResponseResult::Error(Error::ConfigInvalid(
+                                "read_lease requires read_only_option to be LeaseBased".to_owned(),
+                            )),
+                        })
+                        .unwrap();
+                } else {
+                    self.handle_read_index_request(ResponseSender::Local(tx_msg))
+                        .await?;
+                }
+            }
+            LocalRequestMsg::ReadIndex { tx_msg } => {
+                self.handle_read_index_request(ResponseSender::Local(tx_msg))
+                    .await?;
+            }
+            LocalRequestMsg::WaitApplied { index, tx_msg } => {
+                self.pending_reads.push((index, tx_msg));
+                self.handle_pending_reads();
             }
         }
 
@@ -1218,6 +4567,14 @@ impl<
             SelfMessage::ReportUnreachable { node_id } => {
                 self.raw_node.report_unreachable(node_id);
             }
+            SelfMessage::ReportSnapshot { node_id, failed } => {
+                let status = if failed {
+                    SnapshotStatus::Failure
+                } else {
+                    SnapshotStatus::Finish
+                };
+                self.raw_node.report_snapshot(node_id, status);
+            }
         }
 
         Ok(())
@@ -1230,13 +4587,36 @@ impl<
         match message {
             ServerRequestMsg::ChangeConfig {
                 conf_change,
+                requested_by,
                 tx_msg,
             } => {
                 let conf_change: ConfChangeV2 = conf_change.into();
-                self.handle_confchange_request(conf_change, ResponseSender::Server(tx_msg))
-                    .await?;
+                self.handle_confchange_request(
+                    conf_change,
+                    requested_by,
+                    ResponseSender::Server(tx_msg),
+                )
+                .await?;
             }
-            ServerRequestMsg::SendMessage { message } => {
+            ServerRequestMsg::SendMessage {
+                message,
+                cluster_uuid,
+            } => {
+                if let Some(cluster_uuid) = cluster_uuid {
+                    if !self.adopt_cluster_uuid(cluster_uuid)? {
+                        return Ok(());
+                    }
+                }
+
+                let span = tracing::debug_span!(
+                    "raft.step",
+                    node_id = self.raw_node.raft.id,
+                    from = message.from,
+                    term = message.term,
+                    msg_type = ?message.get_msg_type(),
+                );
+                let _entered = span.enter();
+
                 let is_heartbeat_message = message.get_msg_type() == MessageType::MsgHeartbeat
                     || message.get_msg_type() == MessageType::MsgHeartbeatResponse;
 
@@ -1248,10 +4628,37 @@ impl<
                         format_message(&message)
                     ));
                 }
-                let _ = self.raw_node.step(*message);
+                if message.get_msg_type() != MessageType::MsgSnapshot
+                    || self.accept_snapshot_message(&message)
+                {
+                    let _ = self.raw_node.step(*message);
+                }
             }
-            ServerRequestMsg::Propose { proposal, tx_msg } => {
-                self.handle_propose_request(proposal, ResponseSender::Server(tx_msg))
+            ServerRequestMsg::Propose {
+                proposals,
+                session,
+                span,
+                tx_msg,
+            } => {
+                self.handle_propose_request(
+                    proposals,
+                    session,
+                    span,
+                    ResponseSender::Server(tx_msg),
+                    0,
+                )
+                .await?;
+            }
+            ServerRequestMsg::UpdateRuntimeConfig { update, tx_msg } => {
+                self.handle_runtime_config_request(update, ResponseSender::Server(tx_msg))
+                    .await?;
+            }
+            ServerRequestMsg::SetPeerLabels {
+                node_id,
+                labels,
+                tx_msg,
+            } => {
+                self.handle_peer_labels_request(node_id, labels, ResponseSender::Server(tx_msg))
                     .await?;
             }
             ServerRequestMsg::RequestId { raft_addr, tx_msg } => {
@@ -1283,7 +4690,14 @@ impl<
                                 .info(&format!("Node {} connection restored.", existing_node_id));
                             existing_node_id
                         } else {
-                            let reserved = peers.reserve_id();
+                            // Skips the ids of the removed nodes, whose messages may still be in flight.
+                            let reserved = self
+                                .raw_node
+                                .store()
+                                .tombstones()?
+                                .iter()
+                                .map(|tombstone| tombstone.node_id + 1)
+                                .fold(peers.reserve_id(), u64::max);
                             self.logger.info(&format!(
                                 "Node {} reserved new node_id {}.",
                                 self.get_id(),
@@ -1304,9 +4718,27 @@ impl<
                 }
             }
             ServerRequestMsg::LeaveJoint { tx_msg } => {
-                self.raw_node
-                    .propose_conf_change(vec![], ConfChangeV2::default())?;
-                tx_msg.send(ServerResponseMsg::LeaveJoint {}).unwrap();
+                self.handle_leave_joint_request(None, ResponseSender::Server(tx_msg))
+                    .await?;
+            }
+            ServerRequestMsg::ForceRemovePeer {
+                node_id,
+                requested_by,
+                tx_msg,
+            } => {
+                self.handle_force_remove_peer_request(
+                    node_id,
+                    requested_by,
+                    ResponseSender::Server(tx_msg),
+                )
+                .await?;
+            }
+            ServerRequestMsg::GetMembershipHistory { tx_msg } => {
+                tx_msg
+                    .send(ServerResponseMsg::GetMembershipHistory {
+                        result: self.raw_node.store().membership_history(),
+                    })
+                    .unwrap();
             }
             ServerRequestMsg::DebugNode { tx_msg } => {
                 tx_msg
@@ -1328,7 +4760,74 @@ impl<
                 self.make_snapshot(last_index, last_term).await?;
                 tx_msg.send(ServerResponseMsg::CreateSnapshot {}).unwrap();
             }
+            ServerRequestMsg::ReadIndex { tx_msg } => {
+                self.handle_read_index_request(ResponseSender::Server(tx_msg))
+                    .await?;
+            }
+            ServerRequestMsg::Query {
+                query,
+                leader_only,
+                read_index,
+                tx_msg,
+            } => {
+                self.handle_query_request(query, leader_only, read_index, tx_msg)
+                    .await;
+            }
+            ServerRequestMsg::SetPriority { priority, tx_msg } => {
+                self.set_priority(priority).await;
+                tx_msg.send(ServerResponseMsg::SetPriority {}).unwrap();
+            }
+            ServerRequestMsg::SetTicks {
+                election_tick,
+                heartbeat_tick,
+                tx_msg,
+            } => {
+                let result = match self.set_ticks(election_tick, heartbeat_tick) {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg.send(ServerResponseMsg::SetTicks { result }).unwrap();
+            }
+            ServerRequestMsg::CompactLogs { index, tx_msg } => {
+                let result = match self.compact_logs(index).await {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg
+                    .send(ServerResponseMsg::CompactLogs { result })
+                    .unwrap();
+            }
+            ServerRequestMsg::PurgeTombstones { node_ids, tx_msg } => {
+                let result = match self.purge_tombstones(&node_ids) {
+                    Ok(()) => ResponseResult::Success,
+                    Err(e) => ResponseResult::Error(e),
+                };
+                tx_msg
+                    .send(ServerResponseMsg::PurgeTombstones { result })
+                    .unwrap();
+            }
+            ServerRequestMsg::GetHealth { tx_msg } => {
+                let raft_log = &self.raw_node.raft.raft_log;
+                tx_msg
+                    .send(ServerResponseMsg::GetHealth {
+                        leader_id: self.raw_node.raft.leader_id,
+                        applied: raft_log.applied,
+                        committed: raft_log.committed,
+                    })
+                    .unwrap();
+            }
+            ServerRequestMsg::WatchClusterEvents { tx_msg } => {
+                tx_msg
+                    .send(ServerResponseMsg::WatchClusterEvents {
+                        rx_events: self.tx_cluster_events.subscribe(),
+                    })
+                    .unwrap();
+            }
+            ServerRequestMsg::TransferLeader { node_id, tx_msg } => {
+                self.handle_transfer_leader_request(node_id, ResponseSender::Server(tx_msg));
+            }
             ServerRequestMsg::SetPeers { tx_msg, peers } => {
+                self.raw_node.mut_store().set_peers(&peers)?;
                 self.peers.lock().await.replace(peers);
                 tx_msg.send(ServerResponseMsg::SetPeers {}).unwrap();
             }
@@ -1340,7 +4839,92 @@ impl<
         Ok(())
     }
 
+    /// Spawns a task which is cancelled once the node quits, and awaited before `run` returns.
+    fn spawn_task<F: Future<Output = ()> + Send + 'static>(&self, task: F) {
+        self.tasks
+            .spawn(self.shutdown.clone().run_until_cancelled_owned(task));
+    }
+
     pub async fn run(mut self) -> Result<()> {
+        if let Some((rx_apply, tx_apply_results)) = self.apply_task.take() {
+            self.spawn_task(run_apply_task(
+                self.fsm.clone(),
+                self.config.apply_executor.clone(),
+                rx_apply,
+                tx_apply_results,
+            ));
+        }
+        // Also takes the Readies the ready loop fails to persist since the storage is full.
+        if let Some((rx_persist, tx_persisted)) = self.persist_task.take() {
+            self.spawn_task(run_persist_task(
+                self.raw_node.store().clone(),
+                self.config.group_commit_window.map(Duration::from_secs_f32),
+                rx_persist,
+                tx_persisted,
+                self.storage_full.clone(),
+                self.logger.clone(),
+            ));
+        }
+        if let Some(disk_usage) = self.config.disk_usage.clone() {
+            if let Some(tx_disk_usage) = self.disk_monitor.take() {
+                self.spawn_task(run_disk_monitor(
+                    self.config.log_dir.clone(),
+                    disk_usage,
+                    tx_disk_usage,
+                    self.logger.clone(),
+                ));
+            }
+        }
+        let result = self.run_loop().await;
+
+        // Tears down the tasks of the node, and the RaftServer sharing the token.
+        self.shutdown.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+
+        if let Err(ref e) = result {
+            self.event_handlers
+                .iter()
+                .for_each(|handler| handler.on_error(e));
+        }
+        result
+    }
+
+    /// Handles the messages queued meanwhile, up to `config.max_messages_per_ready`,
+    /// so that their entries and messages are appended, persisted and sent in the same Ready.
+    async fn drain_pending_messages(&mut self) -> Result<()> {
+        let mut drained = 0;
+        while drained < self.config.max_messages_per_ready && !self.should_exit {
+            let before = drained;
+            if let Ok(msg) = self.rx_server.try_recv() {
+                self.handle_server_request_msg(msg).await?;
+                drained += 1;
+            }
+            if let Ok(msg) = self.rx_local.try_recv() {
+                self.handle_local_request_msg(msg).await?;
+                drained += 1;
+            }
+            if let Ok(msg) = self.rx_self.try_recv() {
+                self.handle_self_message(msg).await?;
+                drained += 1;
+            }
+            if drained == before {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns how many times the shared ticker ticked since the last call, if the node runs on one.
+    fn shared_ticks(&mut self) -> Option<u64> {
+        let (ticker, seen) = self.ticker.as_mut()?;
+        let ticks = *ticker.borrow_and_update();
+        let elapsed = ticks - *seen;
+        *seen = ticks;
+        Some(elapsed)
+    }
+
+    async fn run_loop(&mut self) -> Result<()> {
         let mut tick_timer = Duration::from_secs_f32(self.config.tick_interval);
         let fixed_tick_timer = tick_timer;
         let mut now = Instant::now();
@@ -1349,6 +4933,7 @@ impl<
             if self.should_exit {
                 self.logger
                     .info(&format!("Node {} quit the cluster.", self.get_id()));
+                self.systemd.notify_stopping();
                 return Ok(());
             }
 
@@ -1368,19 +4953,148 @@ impl<
                         self.handle_local_request_msg(msg).await?;
                     }
                 }
+                Some(results) = self.rx_apply_results.recv() => {
+                    self.handle_applied_batch(results);
+                    self.handle_pending_reads();
+                    self.handle_pending_queries().await;
+                }
+                Some(persisted) = self.rx_persisted.recv() => {
+                    self.handle_persisted_batch(persisted?).await;
+                }
+                _ = self.shutdown.cancelled() => {
+                    self.should_exit = true;
+                }
             }
+            self.drain_pending_messages().await?;
 
-            let elapsed = now.elapsed();
-            now = Instant::now();
-            if elapsed > tick_timer {
-                tick_timer = fixed_tick_timer;
-                self.raw_node.tick();
+            if let Some(ticks) = self.shared_ticks() {
+                for _ in 0..ticks {
+                    self.tick();
+                }
             } else {
-                tick_timer -= elapsed;
+                let elapsed = now.elapsed();
+                now = Instant::now();
+                if elapsed > tick_timer {
+                    tick_timer = fixed_tick_timer;
+                    self.tick();
+                } else {
+                    tick_timer -= elapsed;
+                }
+            }
+
+            self.on_ready().await?;
+            self.notify_systemd();
+            self.handle_pending_leader_transfers();
+            self.handle_pending_campaigns().await;
+            self.prune_dropped_requests();
+            self.promote_caught_up_learners().await?;
+            self.remove_dead_voters().await?;
+            self.hand_off_unwanted_leadership();
+            self.apply_leader_placement().await;
+            self.make_periodic_snapshot().await?;
+            self.handle_disk_usage().await?;
+        }
+    }
+
+    /// Compacts the log once the disk monitor finds the disk used beyond the hard threshold,
+    /// if `DiskUsageConfig::hard_action` asks to.
+    async fn handle_disk_usage(&mut self) -> Result<()> {
+        if !self.rx_disk_usage.has_changed().unwrap_or(false) {
+            return Ok(());
+        }
+        let Some(usage) = *self.rx_disk_usage.borrow_and_update() else {
+            return Ok(());
+        };
+        let compact = self
+            .config
+            .disk_usage
+            .as_ref()
+            .is_some_and(|config| config.hard_action == DiskFullAction::Compact);
+        if !compact || usage.level != DiskUsageLevel::Hard {
+            return Ok(());
+        }
+
+        self.wait_for_apply().await?;
+        let applied = self.raw_node.raft.raft_log.applied;
+        if applied <= self.last_snapshot_index {
+            return Ok(());
+        }
+        let term = self.raw_node.raft.raft_log.term(applied)?;
+        self.make_snapshot(applied, term).await?;
+        self.logger.warn(&format!(
+            "Compacted the log up to index {} since only {} bytes are free on the disk.",
+            applied, usage.free_bytes
+        ));
+        Ok(())
+    }
+
+    fn publish_cluster_event(
+        &self,
+        event_type: ClusterEventType,
+        voters: Vec<u64>,
+        learners: Vec<u64>,
+    ) {
+        if self.tx_cluster_events.receiver_count() == 0 {
+            return;
+        }
+
+        let event = ClusterEvent {
+            event_type: event_type.into(),
+            term: self.raw_node.raft.term,
+            leader_id: self.raw_node.raft.leader_id,
+            voters,
+            learners,
+        };
+        let _ = self.tx_cluster_events.send(event);
+    }
+
+    fn handle_role_change(&mut self, role: StateRole, leader_id: u64) {
+        let term = self.raw_node.raft.term;
+        if role == StateRole::Leader && self.last_role != StateRole::Leader {
+            self.event_handlers
+                .iter()
+                .for_each(|handler| handler.on_become_leader(term));
+        } else if role != StateRole::Leader && self.last_role == StateRole::Leader {
+            // A leader steps down in its own term without knowing the new leader
+            // only when check_quorum finds the quorum inactive.
+            if self.config.raft_config.check_quorum
+                && term == self.last_term
+                && leader_id == INVALID_ID
+            {
+                self.logger.warn(&format!(
+                    "Stepped down in term {} since the quorum wasn't active for an election timeout.",
+                    term
+                ));
+                self.event_handlers
+                    .iter()
+                    .for_each(|handler| handler.on_quorum_lost(term));
+                self.publish_cluster_event(ClusterEventType::QuorumLost, vec![], vec![]);
             }
+            self.event_handlers
+                .iter()
+                .for_each(|handler| handler.on_step_down(term, leader_id));
+        }
+        self.last_role = role;
+    }
 
-            self.on_ready().await?
+    #[tracing::instrument(
+        name = "raft.ready",
+        skip_all,
+        fields(
+            node_id = self.raw_node.raft.id,
+            term = self.raw_node.raft.term,
+            leader_id = self.raw_node.raft.leader_id,
+        )
+    )]
+    fn persist_ready(&mut self, ready: &Ready) -> Result<()> {
+        let store = self.raw_node.mut_store();
+        if !ready.entries().is_empty() {
+            store.append(ready.entries())?;
+        }
+        if let Some(hs) = ready.hs() {
+            store.set_hard_state(hs)?;
         }
+        Ok(())
     }
 
     async fn on_ready(&mut self) -> Result<()> {
@@ -1393,50 +5107,120 @@ impl<
             self.send_messages(ready.take_messages()).await;
         }
 
+        if !ready.read_states().is_empty() {
+            self.handle_read_states(ready.take_read_states())?;
+        }
+
+        if let Some(ss) = ready.ss() {
+            self.handle_role_change(ss.raft_state, ss.leader_id);
+            if ss.leader_id != self.last_leader_id {
+                self.last_leader_id = ss.leader_id;
+                self.publish_cluster_event(ClusterEventType::LeaderChanged, vec![], vec![]);
+            }
+        }
+
         if *ready.snapshot() != Snapshot::default() {
+            self.wait_for_apply().await?;
+            self.wait_for_persist().await?;
             self.logger
                 .info("Restoring state machine and snapshot metadata...");
-            let snapshot = ready.snapshot();
-            if !snapshot.get_data().is_empty() {
-                self.fsm.restore(snapshot.get_data().to_vec()).await?;
+            let mut snapshot = ready.snapshot().clone();
+            let index = snapshot.get_metadata().index;
+            if self.pending_delta_snapshot.take() == Some(index) {
+                let (delta, sessions) = split_sessions(std::mem::take(&mut snapshot.data))?;
+                self.fsm.restore_delta(delta).await?;
+                self.fsm.set_applied_index(index);
+                self.restore_sessions(sessions);
+                // Persist the full state, since the delta can't be restored on its own.
+                let snapshot_data = self.snapshot_fsm().await?;
+                snapshot.set_data(self.encrypt_snapshot(self.sessions.append_to(snapshot_data)?)?);
+            } else if !snapshot.get_data().is_empty() && !self.config.is_witness {
+                let (snapshot_data, sessions) =
+                    split_sessions(self.decrypt_snapshot(snapshot.get_data())?)?;
+                self.restore_fsm(snapshot_data).await?;
+                self.fsm.set_applied_index(index);
+                self.restore_sessions(sessions);
             }
+            self.replace_applying_fsm().await?;
             let store = self.raw_node.mut_store();
             store.apply_snapshot(snapshot.clone())?;
+
+            let metadata = snapshot.get_metadata();
+            self.last_snapshot_index = metadata.get_index();
+            self.event_handlers.iter().for_each(|handler| {
+                handler.on_snapshot_applied(metadata.get_index(), metadata.get_term())
+            });
         }
 
         self.handle_committed_entries(ready.take_committed_entries())
             .await?;
 
-        if !ready.entries().is_empty() {
-            let entries = &ready.entries()[..];
-            let store = self.raw_node.mut_store();
-            store.append(entries)?;
-        }
-
         if let Some(hs) = ready.hs() {
-            let store = self.raw_node.mut_store();
-            store.set_hard_state(hs)?;
+            if hs.term != self.last_term {
+                self.last_term = hs.term;
+                self.publish_cluster_event(ClusterEventType::TermChanged, vec![], vec![]);
+            }
         }
 
-        if !ready.persisted_messages().is_empty() {
-            self.send_messages(ready.take_persisted_messages()).await;
-        }
+        // Once a Ready is handed to the persist task, the next ones are too until it is persisted,
+        // since `advance` would mark it persisted.
+        let persisted = if self.config.async_append || self.persisting > 0 {
+            false
+        } else {
+            match self.persist_ready(&ready) {
+                Ok(()) => true,
+                Err(e) if e.is_storage_full() => {
+                    self.logger.error(&format!(
+                        "Failed to persist the Ready since the storage is full, retrying in the background: {}",
+                        e
+                    ));
+                    self.storage_full.store(true, Ordering::Relaxed);
+                    false
+                }
+                Err(e) => return Err(e),
+            }
+        };
 
-        let mut light_rd = self.raw_node.advance(ready);
+        if !persisted {
+            let batch = PersistBatch {
+                number: ready.number(),
+                entries: ready.take_entries(),
+                hard_state: ready.hs().cloned(),
+                persisted_messages: ready.take_persisted_messages(),
+            };
+            self.raw_node.advance_append_async(ready);
+            self.tx_persist
+                .send(batch)
+                .await
+                .map_err(|_| Error::Other("The persist task stopped".into()))?;
+            self.persisting += 1;
+        } else {
+            if !ready.persisted_messages().is_empty() {
+                self.send_messages(ready.take_persisted_messages()).await;
+            }
 
-        if let Some(commit) = light_rd.commit_index() {
-            let store = self.raw_node.mut_store();
-            store.set_hard_state_commit(commit)?;
-        }
+            let mut light_rd = self.raw_node.advance(ready);
 
-        if !light_rd.messages().is_empty() {
-            self.send_messages(light_rd.take_messages()).await;
-        }
+            if let Some(commit) = light_rd.commit_index() {
+                let store = self.raw_node.mut_store();
+                // The commit index is learned from the leader again after a restart if it isn't persisted.
+                match store.set_hard_state_commit(commit) {
+                    Err(e) if !e.is_storage_full() => return Err(e),
+                    _ => {}
+                }
+            }
 
-        self.handle_committed_entries(light_rd.take_committed_entries())
-            .await?;
+            if !light_rd.messages().is_empty() {
+                self.send_messages(light_rd.take_messages()).await;
+            }
+
+            self.handle_committed_entries(light_rd.take_committed_entries())
+                .await?;
+        }
 
-        self.raw_node.advance_apply();
+        self.advance_apply();
+        self.handle_pending_reads();
+        self.handle_pending_queries().await;
 
         Ok(())
     }
@@ -0,0 +1,163 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    time::{sleep, timeout_at, Instant},
+};
+
+use crate::{
+    raft::{
+        eraftpb::{Entry, HardState, Message as RaftMessage, MessageType},
+        logger::Logger,
+    },
+    AbstractStableStorage, Error, Result,
+};
+
+/// Seconds between the attempts to persist the batches while the storage is full.
+const STORAGE_FULL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Entries and hard state of a Ready handed to the persist task.
+pub(crate) struct PersistBatch {
+    pub number: u64,
+    pub entries: Vec<Entry>,
+    pub hard_state: Option<HardState>,
+    /// Messages to send once the batch is persisted, e.g. the votes and the append responses.
+    pub persisted_messages: Vec<RaftMessage>,
+}
+
+/// Persisted batch, with the number of its Ready to pass to `RawNode::on_persist_ready`.
+/// `number` is `None` when only the vote was persisted, with the vote responses waiting for it.
+pub(crate) struct PersistedBatch {
+    pub number: Option<u64>,
+    pub persisted_messages: Vec<RaftMessage>,
+}
+
+/// Persists the batches off the ready loop, so that the leader sends the next appends to the followers
+/// while it syncs the previous entries to its disk. The batches are persisted in order.
+///
+/// The batches queued meanwhile, and the ones arriving within `group_commit_window` when set,
+/// are persisted together with `AbstractStableStorage::persist_readies`.
+///
+/// While the storage is full, the batches are retried until space is reclaimed, with `storage_full` set.
+/// Meanwhile the vote is persisted alone if it fits, so that the node still votes.
+pub(crate) async fn run_persist_task<LogStorage: AbstractStableStorage + Send + 'static>(
+    mut storage: LogStorage,
+    group_commit_window: Option<Duration>,
+    mut rx_persist: mpsc::Receiver<PersistBatch>,
+    tx_persisted: mpsc::UnboundedSender<Result<PersistedBatch>>,
+    storage_full: Arc<AtomicBool>,
+    logger: Arc<dyn Logger>,
+) {
+    while let Some(batch) = rx_persist.recv().await {
+        let mut batches = vec![batch];
+        if let Some(window) = group_commit_window {
+            let deadline = Instant::now() + window;
+            while let Ok(Some(batch)) = timeout_at(deadline, rx_persist.recv()).await {
+                batches.push(batch);
+            }
+        }
+        while let Ok(batch) = rx_persist.try_recv() {
+            batches.push(batch);
+        }
+
+        loop {
+            let persisted = tokio::task::spawn_blocking(move || {
+                let readies: Vec<_> = batches
+                    .iter()
+                    .map(|batch| (batch.entries.as_slice(), batch.hard_state.as_ref()))
+                    .collect();
+                let result = storage.persist_readies(&readies);
+                (storage, batches, result)
+            })
+            .await;
+            let (persisted_storage, persisted_batches, result) = match persisted {
+                Ok(persisted) => persisted,
+                Err(e) => {
+                    let _ = tx_persisted.send(Err(Error::Other(Box::new(e))));
+                    return;
+                }
+            };
+            storage = persisted_storage;
+            batches = persisted_batches;
+
+            match result {
+                Ok(()) => break,
+                Err(e) if e.is_storage_full() => {
+                    if !storage_full.swap(true, Ordering::Relaxed) {
+                        logger.error(&format!(
+                            "The storage is full, rejecting the proposals until space is reclaimed: {}",
+                            e
+                        ));
+                    }
+                    match persist_vote(&mut storage, &mut batches) {
+                        Ok(votes) if !votes.is_empty() => {
+                            let persisted = PersistedBatch {
+                                number: None,
+                                persisted_messages: votes,
+                            };
+                            if tx_persisted.send(Ok(persisted)).is_err() {
+                                return;
+                            }
+                        }
+                        _ => {}
+                    }
+                    sleep(STORAGE_FULL_RETRY_INTERVAL).await;
+                }
+                Err(e) => {
+                    let _ = tx_persisted.send(Err(e));
+                    return;
+                }
+            }
+        }
+
+        if storage_full.swap(false, Ordering::Relaxed) {
+            logger.info("The storage has space again, taking the proposals.");
+        }
+        for batch in batches {
+            let persisted = PersistedBatch {
+                number: Some(batch.number),
+                persisted_messages: batch.persisted_messages,
+            };
+            if tx_persisted.send(Ok(persisted)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Persists the last hard state of `batches` alone, with its commit index clamped to the persisted log,
+/// and takes the vote responses, which only wait for the vote, out of their persisted messages.
+fn persist_vote<LogStorage: AbstractStableStorage>(
+    storage: &mut LogStorage,
+    batches: &mut [PersistBatch],
+) -> Result<Vec<RaftMessage>> {
+    let Some(mut hard_state) = batches
+        .iter()
+        .rev()
+        .find_map(|batch| batch.hard_state.clone())
+    else {
+        return Ok(vec![]);
+    };
+    hard_state.commit = hard_state.commit.min(storage.last_index()?);
+    storage.set_hard_state(&hard_state)?;
+
+    let mut votes = vec![];
+    for batch in batches {
+        let (batch_votes, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut batch.persisted_messages)
+            .into_iter()
+            .partition(|message| {
+                matches!(
+                    message.get_msg_type(),
+                    MessageType::MsgRequestVoteResponse | MessageType::MsgRequestPreVoteResponse
+                )
+            });
+        votes.extend(batch_votes);
+        batch.persisted_messages = rest;
+    }
+    Ok(votes)
+}
@@ -5,21 +5,29 @@ use crate::{
         local_response_message::LocalResponseMsg, server_response_message::ServerResponseMsg,
         ResponseMessage,
     },
-    AbstractLogEntry, AbstractStateMachine, StableStorage,
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine,
 };
 
 pub(crate) enum ResponseSender<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage + 'static,
+    LogStorage: AbstractStableStorage + 'static,
     FSM: AbstractStateMachine,
 > {
     Local(oneshot::Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>),
     Server(oneshot::Sender<ServerResponseMsg>),
 }
 
-impl<LogEntry: AbstractLogEntry, LogStorage: StableStorage, FSM: AbstractStateMachine>
+impl<LogEntry: AbstractLogEntry, LogStorage: AbstractStableStorage, FSM: AbstractStateMachine>
     ResponseSender<LogEntry, LogStorage, FSM>
 {
+    /// Returns true if the requester has stopped waiting for the response.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            ResponseSender::Local(tx_local) => tx_local.is_closed(),
+            ResponseSender::Server(tx_server) => tx_server.is_closed(),
+        }
+    }
+
     pub fn send(self, response: ResponseMessage<LogEntry, LogStorage, FSM>) {
         match self {
             ResponseSender::Local(tx_local) => {
@@ -9,6 +9,13 @@ pub enum InitialRole {
     Learner,
 }
 
+impl InitialRole {
+    /// Returns true for the roles of the voters, the initial leader being one of them.
+    pub fn is_voter(&self) -> bool {
+        matches!(self, InitialRole::Leader | InitialRole::Voter)
+    }
+}
+
 impl fmt::Display for InitialRole {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
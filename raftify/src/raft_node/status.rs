@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    raft::{ProgressState, RawNode, StateRole},
+    AbstractStableStorage, DiskUsage, PeerConnection,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NodeRole {
+    Follower,
+    Candidate,
+    Leader,
+    PreCandidate,
+}
+
+impl From<StateRole> for NodeRole {
+    fn from(role: StateRole) -> Self {
+        match role {
+            StateRole::Follower => NodeRole::Follower,
+            StateRole::Candidate => NodeRole::Candidate,
+            StateRole::Leader => NodeRole::Leader,
+            StateRole::PreCandidate => NodeRole::PreCandidate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReplicationState {
+    Probe,
+    Replicate,
+    Snapshot,
+}
+
+impl From<ProgressState> for ReplicationState {
+    fn from(state: ProgressState) -> Self {
+        match state {
+            ProgressState::Probe => ReplicationState::Probe,
+            ProgressState::Replicate => ReplicationState::Replicate,
+            ProgressState::Snapshot => ReplicationState::Snapshot,
+        }
+    }
+}
+
+/// Replication progress of a peer, as tracked by the leader.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeerProgress {
+    pub matched: u64,
+    pub next_index: u64,
+    pub state: ReplicationState,
+    pub recent_active: bool,
+    pub paused: bool,
+}
+
+/// Point-in-time status of a `RaftNode`, for programmatic consumers of what `inspect` prints.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RaftStatus {
+    pub id: u64,
+    pub role: NodeRole,
+    pub term: u64,
+    /// 0 if the leader is unknown.
+    pub leader_id: u64,
+    pub commit_index: u64,
+    pub applied_index: u64,
+    /// Committed entries not applied to the state machine yet.
+    pub apply_lag: u64,
+    pub last_log_index: u64,
+    /// Progress of every member including the node itself. Empty unless the node is the leader.
+    pub progress: BTreeMap<u64, PeerProgress>,
+    /// Last measure of the disk of the log. `None` unless `Config::disk_usage` is set.
+    pub disk_usage: Option<DiskUsage>,
+    /// Whether the storage is full, in which case the node rejects the proposals with `Error::StorageFull`.
+    pub storage_full: bool,
+    /// Whether the node is drained, in which case it rejects the proposals with `Error::Draining`
+    /// and hands the leadership over, but still votes and replicates the log. See `RaftNode::drain`.
+    pub draining: bool,
+    /// Connection of the node to each of the other peers, see `PeerConnection`.
+    pub connections: BTreeMap<u64, PeerConnection>,
+}
+
+impl RaftStatus {
+    pub(crate) fn new<T: AbstractStableStorage>(
+        raw_node: &RawNode<T>,
+        disk_usage: Option<DiskUsage>,
+        storage_full: bool,
+        draining: bool,
+        connections: BTreeMap<u64, PeerConnection>,
+    ) -> Self {
+        let raft = &raw_node.raft;
+
+        let progress = if raft.state == StateRole::Leader {
+            raft.prs()
+                .iter()
+                .map(|(node_id, pr)| {
+                    (
+                        *node_id,
+                        PeerProgress {
+                            matched: pr.matched,
+                            next_index: pr.next_idx,
+                            state: pr.state.into(),
+                            recent_active: pr.recent_active,
+                            paused: pr.paused,
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        Self {
+            id: raft.id,
+            role: raft.state.into(),
+            term: raft.term,
+            leader_id: raft.leader_id,
+            commit_index: raft.raft_log.committed,
+            applied_index: raft.raft_log.applied,
+            apply_lag: raft.raft_log.committed - raft.raft_log.applied,
+            last_log_index: raft.raft_log.last_index(),
+            progress,
+            disk_usage,
+            storage_full,
+            draining,
+            connections,
+        }
+    }
+}
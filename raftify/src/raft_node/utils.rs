@@ -1,9 +1,15 @@
+use prost::Message as PMessage;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Debug};
 
 use crate::{
-    raft::{formatter::format_snapshot, RawNode},
-    Result, StableStorage,
+    raft::{
+        eraftpb::{ConfChange, ConfChangeV2, Entry, EntryType},
+        formatter::{format_confchange, format_confchangev2, format_snapshot},
+        RawNode,
+    },
+    session::ProposalContext,
+    AbstractLogEntry, AbstractStableStorage, Result,
 };
 
 static EXPECTED_FORMAT_NOT_EXIST: &str = "Expected format not exist!";
@@ -102,7 +108,7 @@ pub fn format_debugging_info(hashmap: &HashMap<String, Value>) -> String {
     result
 }
 
-pub fn inspect_raftnode<T: StableStorage>(raw_node: &RawNode<T>) -> Result<String> {
+pub fn inspect_raftnode<T: AbstractStableStorage>(raw_node: &RawNode<T>) -> Result<String> {
     let id = raw_node.raft.id;
     let leader_id = raw_node.raft.leader_id;
 
@@ -173,3 +179,89 @@ pub fn inspect_raftnode<T: StableStorage>(raw_node: &RawNode<T>) -> Result<Strin
 
     Ok(result.to_string())
 }
+
+/// Returns the data of a normal entry as it was proposed, decompressing it if needed.
+pub fn decoded_entry_data(entry: &Entry) -> Result<Vec<u8>> {
+    if entry.get_context().is_empty() {
+        return Ok(entry.get_data().to_vec());
+    }
+    ProposalContext::decode(entry.get_context())?.entry_data(entry.get_data())
+}
+
+fn entry_data_json<LogEntry: AbstractLogEntry + Debug>(entry: &Entry) -> Value {
+    let data = entry.get_data();
+    if data.is_empty() {
+        return Value::Null;
+    }
+
+    let decoded = match entry.get_entry_type() {
+        EntryType::EntryNormal => decoded_entry_data(entry)
+            .ok()
+            .and_then(|data| LogEntry::decode(&data).ok())
+            .map(|log_entry| format!("{:?}", log_entry)),
+        EntryType::EntryConfChange => ConfChange::decode(data)
+            .ok()
+            .map(|cc| format_confchange(&cc)),
+        EntryType::EntryConfChangeV2 => ConfChangeV2::decode(data)
+            .ok()
+            .map(|cc| format_confchangev2(&cc)),
+    };
+
+    // Fall back to the raw bytes if the data can't be decoded.
+    decoded.map(Value::String).unwrap_or_else(|| json!(data))
+}
+
+/// Decodes the hard state, conf state, snapshot metadata and entries persisted in `store` into JSON.
+/// The data of the normal entries is decoded with `LogEntry`.
+pub fn persisted_storage_json<LogEntry: AbstractLogEntry + Debug, T: AbstractStableStorage>(
+    store: &T,
+) -> Result<Value> {
+    let hard_state = store.hard_state()?;
+    let conf_state = store.conf_state()?;
+    let snapshot = store.snapshot(0, 0)?;
+    let metadata = snapshot.get_metadata();
+    let snapshot_conf_state = metadata.get_conf_state();
+
+    let entries = store
+        .all_entries()?
+        .iter()
+        .map(|entry| {
+            json!({
+                "index": entry.index,
+                "term": entry.term,
+                "entry_type": format!("{:?}", entry.get_entry_type()),
+                "data": entry_data_json::<LogEntry>(entry),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "hard_state": {
+            "term": hard_state.term,
+            "vote": hard_state.vote,
+            "commit": hard_state.commit,
+        },
+        "conf_state": {
+            "voters": conf_state.voters,
+            "learners": conf_state.learners,
+            "voters_outgoing": conf_state.voters_outgoing,
+            "learners_next": conf_state.learners_next,
+            "auto_leave": conf_state.auto_leave,
+        },
+        "snapshot": {
+            "index": metadata.index,
+            "term": metadata.term,
+            "conf_state": {
+                "voters": snapshot_conf_state.voters,
+                "learners": snapshot_conf_state.learners,
+                "voters_outgoing": snapshot_conf_state.voters_outgoing,
+                "learners_next": snapshot_conf_state.learners_next,
+                "auto_leave": snapshot_conf_state.auto_leave,
+            },
+            "data_size": snapshot.get_data().len(),
+        },
+        "first_index": store.first_index()?,
+        "last_index": store.last_index()?,
+        "entries": entries,
+    }))
+}
@@ -1,20 +1,36 @@
 use bincode::serialize;
 use std::{
+    convert::Infallible,
     net::{SocketAddr, ToSocketAddrs},
+    path::Path,
+    pin::Pin,
     sync::Arc,
     time::Duration,
 };
 use tokio::{
-    sync::{
-        mpsc,
-        oneshot::{self, Receiver},
-    },
+    sync::{mpsc, oneshot},
     time::timeout,
 };
-use tonic::{transport::Server, Request, Response, Status};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    metadata::MetadataValue,
+    server::NamedService,
+    service::Interceptor,
+    transport::{server::Router, Body},
+    Request, Response, Status,
+};
+use tonic_health::{
+    server::{health_reporter, HealthReporter},
+    ServingStatus,
+};
+use tracing::Span;
 
 use super::{
     macro_utils::function_name,
+    raft_admin_service::raft_admin_service_server::RaftAdminServiceServer,
     raft_service::{
         self,
         raft_service_server::{RaftService, RaftServiceServer},
@@ -22,33 +38,59 @@ use super::{
     Config, Error,
 };
 use crate::{
-    create_client,
+    admission::{client_id_of, ClientRateLimiter},
+    auth::check_auth,
+    cluster_uuid::{check_cluster_uuid, cluster_uuid_of},
+    create_client_with_options,
     raft::{eraftpb::Message as RaftMessage, logger::Logger},
-    raft_service::ProposeArgs,
     request::{
         common::confchange_request::ConfChangeRequest, server_request_message::ServerRequestMsg,
     },
     response::server_response_message::{
-        ConfChangeResponseResult, RequestIdResponseResult, ResponseResult, ServerResponseMsg,
+        ConfChangeResponseResult, ProposeResponseResult, QueryResponseResult,
+        ReadIndexResponseResult, RequestIdResponseResult, ResponseResult, ServerResponseMsg,
     },
-    AbstractLogEntry, AbstractStateMachine, StableStorage,
+    snapshot_store::download_snapshot,
+    snapshot_stream::{PartialSnapshot, SnapshotAssembler},
+    telemetry, AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, ClientOptions,
+    ClusterUuid, Gossip,
 };
 
+// Marks the read index requests forwarded to the leader, so that they aren't forwarded again
+// by a node which is not the leader anymore.
+const FORWARDED_HEADER: &str = "x-raftify-forwarded";
+
+/// Returns a read index request to forward to the leader.
+pub(crate) fn forwarded_read_index_request() -> Request<raft_service::Empty> {
+    let mut request = Request::new(raft_service::Empty {});
+    request
+        .metadata_mut()
+        .insert(FORWARDED_HEADER, MetadataValue::from_static("1"));
+    request
+}
+
+/// Adds a user-defined service to the router of `RaftServer`.
+type ServiceMount = Arc<dyn Fn(Router) -> Router + Send + Sync>;
+
 #[derive(Clone)]
 pub struct RaftServer<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage + 'static,
+    LogStorage: AbstractStableStorage + 'static,
     FSM: AbstractStateMachine,
 > {
-    tx: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
+    pub(crate) tx: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
     raft_addr: SocketAddr,
-    config: Config,
+    pub(crate) config: Config,
+    pub(crate) cluster_uuid: ClusterUuid,
+    gossip: Option<Gossip>,
+    services: Vec<ServiceMount>,
+    client_limiter: Option<ClientRateLimiter>,
     logger: Arc<dyn Logger>,
 }
 
 impl<
         LogEntry: AbstractLogEntry + 'static,
-        LogStorage: StableStorage + Send + Sync + 'static,
+        LogStorage: AbstractStableStorage + Send + Sync + 'static,
         FSM: AbstractStateMachine + 'static,
     > RaftServer<LogEntry, LogStorage, FSM>
 {
@@ -56,18 +98,53 @@ impl<
         tx: mpsc::Sender<ServerRequestMsg<LogEntry, LogStorage, FSM>>,
         raft_addr: A,
         config: Config,
+        cluster_uuid: ClusterUuid,
+        gossip: Option<Gossip>,
         logger: Arc<dyn Logger>,
     ) -> Self {
         let raft_addr = raft_addr.to_socket_addrs().unwrap().next().unwrap();
+        let client_limiter = config
+            .admission
+            .as_ref()
+            .and_then(|admission| admission.max_proposals_per_sec_per_client)
+            .map(ClientRateLimiter::new);
         RaftServer {
             tx,
             raft_addr,
             config,
+            cluster_uuid,
+            gossip,
+            services: Vec::new(),
+            client_limiter,
             logger,
         }
     }
 
-    pub(crate) async fn run(self, rx_quit_signal: Receiver<()>) -> Result<(), Error> {
+    /// Serves `service` on the same listener as the raft services.
+    pub(crate) fn add_service<S>(&mut self, service: S)
+    where
+        S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.services
+            .push(Arc::new(move |router| router.add_service(service.clone())));
+    }
+
+    // Attaches the cluster UUID to the requests forwarded to the leader.
+    pub(crate) fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            cluster_uuid: self.cluster_uuid.clone(),
+            ..self.config.client_options()
+        }
+    }
+
+    /// Serves the gRPC requests until `shutdown` is cancelled.
+    pub(crate) async fn run(self, shutdown: CancellationToken) -> Result<(), Error> {
         let raft_addr = self.raft_addr;
         let logger = self.logger.clone();
         logger.debug(&format!(
@@ -75,26 +152,97 @@ impl<
             raft_addr
         ));
 
-        let quit_signal = async {
-            rx_quit_signal.await.ok();
-        };
+        let mut builder = self.config.grpc.server_builder();
+        if let Some(tls) = &self.config.tls {
+            builder = builder.tls_config(tls.server_tls_config())?;
+        }
 
-        Server::builder()
-            .add_service(RaftServiceServer::new(self))
-            .serve_with_shutdown(raft_addr, quit_signal)
-            .await?;
+        let (health_reporter, health_service) = health_reporter();
+        let health_task = tokio::spawn(
+            shutdown
+                .clone()
+                .run_until_cancelled_owned(self.clone().report_health(health_reporter)),
+        );
+
+        let mut check_auth = check_auth(self.config.auth.clone());
+        let mut check_cluster_uuid = check_cluster_uuid(self.cluster_uuid.clone());
+        let interceptor = move |request: Request<()>| -> Result<Request<()>, Status> {
+            check_cluster_uuid.call(check_auth.call(request)?)
+        };
 
+        let services = self.services.clone();
+        let router = builder
+            .add_service(health_service)
+            .add_service(RaftAdminServiceServer::new(self.clone()))
+            .add_service(RaftServiceServer::with_interceptor(self, interceptor));
+        let result = services
+            .iter()
+            .fold(router, |router, mount| mount(router))
+            .serve_with_shutdown(raft_addr, shutdown.clone().cancelled_owned())
+            .await;
+
+        // Also quits the node if the server failed.
+        shutdown.cancel();
+        let _ = health_task.await;
+        result?;
         Ok(())
     }
+
+    /// Keeps the `grpc.health.v1.Health` status up to date.
+    /// The node is SERVING while it knows the leader and applies the committed entries.
+    async fn report_health(self, mut reporter: HealthReporter) {
+        let interval = Duration::from_secs(1);
+        let mut last_applied = 0;
+
+        loop {
+            let (tx_msg, rx_msg) = oneshot::channel();
+            let _ = self.tx.send(ServerRequestMsg::GetHealth { tx_msg }).await;
+
+            // No reply in time means the Raft loop is stuck.
+            let serving = match timeout(interval, rx_msg).await {
+                Ok(Ok(ServerResponseMsg::GetHealth {
+                    leader_id,
+                    applied,
+                    committed,
+                })) => {
+                    let progressing = applied >= committed || applied > last_applied;
+                    last_applied = applied;
+                    leader_id != 0 && progressing
+                }
+                _ => false,
+            };
+
+            let status = if serving {
+                ServingStatus::Serving
+            } else {
+                ServingStatus::NotServing
+            };
+            reporter.set_service_status("", status).await;
+            reporter
+                .set_service_status(<RaftServiceServer<Self> as NamedService>::NAME, status)
+                .await;
+
+            tokio::time::sleep(interval).await;
+        }
+    }
 }
 
 impl<
         LogEntry: AbstractLogEntry + 'static,
-        LogStorage: StableStorage + 'static,
+        LogStorage: AbstractStableStorage + 'static,
         FSM: AbstractStateMachine + 'static,
     > RaftServer<LogEntry, LogStorage, FSM>
 {
-    fn print_send_error(&self, function_name: &str) {
+    /// Partially received snapshot sent from `from` to `to`, kept under `config.log_dir`.
+    fn partial_snapshot(&self, to: u64, from: u64) -> PartialSnapshot {
+        PartialSnapshot::new(
+            &Path::new(&self.config.log_dir).join("partial_snapshots"),
+            to,
+            from,
+        )
+    }
+
+    pub(crate) fn print_send_error(&self, function_name: &str) {
         self.logger.error(&format!(
             "Error occurred in sending message ('RaftServer --> RaftNode'). Function: '{}'",
             function_name
@@ -105,7 +253,7 @@ impl<
 #[tonic::async_trait]
 impl<
         LogEntry: AbstractLogEntry + 'static,
-        LogStorage: StableStorage + Sync + Send + 'static,
+        LogStorage: AbstractStableStorage + Sync + Send + 'static,
         FSM: AbstractStateMachine + 'static,
     > RaftService for RaftServer<LogEntry, LogStorage, FSM>
 {
@@ -147,7 +295,10 @@ impl<
                     }))
                 }
                 RequestIdResponseResult::WrongLeader { leader_addr, .. } => {
-                    let mut client = create_client(leader_addr).await.unwrap();
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options())
+                            .await
+                            .unwrap();
                     let reply = client.request_id(request_args).await?.into_inner();
 
                     Ok(Response::new(reply))
@@ -157,10 +308,13 @@ impl<
         }
     }
 
+    #[tracing::instrument(name = "RaftServer::change_config", skip_all)]
     async fn change_config(
         &self,
         request: Request<raft_service::ChangeConfigArgs>,
     ) -> Result<Response<raft_service::ChangeConfigResponse>, Status> {
+        telemetry::set_parent_from_metadata(&Span::current(), request.metadata());
+        let requested_by = request.remote_addr().map(|addr| addr.to_string());
         let request_args = request.into_inner();
         let sender = self.tx.clone();
         let (tx_msg, rx_msg) = oneshot::channel();
@@ -169,6 +323,7 @@ impl<
 
         let message = ServerRequestMsg::ChangeConfig {
             conf_change: conf_change_request,
+            requested_by,
             tx_msg,
         };
 
@@ -200,7 +355,18 @@ impl<
                             reply.assigned_ids = assigned_ids;
                             reply.peers = serialize(&peers).unwrap();
                         }
-                        ConfChangeResponseResult::RemoveSuccess {} => {
+                        ConfChangeResponseResult::JointEntered {
+                            assigned_ids,
+                            peers,
+                            ..
+                        } => {
+                            reply.result_type =
+                                raft_service::ChangeConfigResultType::ChangeConfigSuccess as i32;
+                            reply.assigned_ids = assigned_ids;
+                            reply.peers = serialize(&peers).unwrap();
+                        }
+                        ConfChangeResponseResult::RemoveSuccess {}
+                        | ConfChangeResponseResult::JointLeft { .. } => {
                             reply.result_type =
                                 raft_service::ChangeConfigResultType::ChangeConfigSuccess as i32;
                         }
@@ -215,7 +381,10 @@ impl<
                                 raft_service::ChangeConfigResultType::ChangeConfigWrongLeader
                                     as i32;
 
-                            let mut client = create_client(leader_addr).await.unwrap();
+                            let mut client =
+                                create_client_with_options(leader_addr, &self.client_options())
+                                    .await
+                                    .unwrap();
                             reply = client.change_config(request_args).await?.into_inner();
                         }
                     },
@@ -243,15 +412,19 @@ impl<
         Ok(Response::new(reply))
     }
 
+    #[tracing::instrument(name = "RaftServer::send_message", skip_all)]
     async fn send_message(
         &self,
         request: Request<RaftMessage>,
     ) -> Result<Response<raft_service::Empty>, Status> {
+        telemetry::set_parent_from_metadata(&Span::current(), request.metadata());
+        let cluster_uuid = cluster_uuid_of(request.metadata());
         let request_args = request.into_inner();
         let sender = self.tx.clone();
         match sender
             .send(ServerRequestMsg::SendMessage {
                 message: Box::new(request_args),
+                cluster_uuid,
             })
             .await
         {
@@ -262,17 +435,120 @@ impl<
         Ok(Response::new(raft_service::Empty {}))
     }
 
+    #[tracing::instrument(name = "RaftServer::send_messages", skip_all)]
+    async fn send_messages(
+        &self,
+        request: Request<raft_service::MessageBatch>,
+    ) -> Result<Response<raft_service::Empty>, Status> {
+        telemetry::set_parent_from_metadata(&Span::current(), request.metadata());
+        let cluster_uuid = cluster_uuid_of(request.metadata());
+        let request_args = request.into_inner();
+        let sender = self.tx.clone();
+        for message in request_args.messages {
+            match sender
+                .send(ServerRequestMsg::SendMessage {
+                    message: Box::new(message),
+                    cluster_uuid: cluster_uuid.clone(),
+                })
+                .await
+            {
+                Ok(_) => (),
+                Err(_) => self.print_send_error(function_name!()),
+            }
+        }
+
+        Ok(Response::new(raft_service::Empty {}))
+    }
+
+    #[tracing::instrument(name = "RaftServer::install_snapshot", skip_all)]
+    async fn install_snapshot(
+        &self,
+        request: Request<tonic::Streaming<raft_service::SnapshotChunk>>,
+    ) -> Result<Response<raft_service::Empty>, Status> {
+        telemetry::set_parent_from_metadata(&Span::current(), request.metadata());
+        let cluster_uuid = cluster_uuid_of(request.metadata());
+        let mut stream = request.into_inner();
+        let mut assembler = SnapshotAssembler::default();
+        let mut resumable = false;
+
+        while let Some(chunk) = stream.message().await? {
+            if !resumable {
+                if let Some(message) = chunk.message.as_ref() {
+                    assembler = SnapshotAssembler::with_partial(
+                        self.partial_snapshot(message.get_to(), message.get_from()),
+                    );
+                    resumable = true;
+                }
+            }
+
+            let message = assembler
+                .push(chunk)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+            if let Some(mut message) = message {
+                download_snapshot(self.config.snapshot_store.as_deref(), &mut message)
+                    .await
+                    .map_err(|e| Status::unavailable(e.to_string()))?;
+
+                let sender = self.tx.clone();
+                match sender
+                    .send(ServerRequestMsg::SendMessage {
+                        message: Box::new(message),
+                        cluster_uuid,
+                    })
+                    .await
+                {
+                    Ok(_) => (),
+                    Err(_) => self.print_send_error(function_name!()),
+                }
+                return Ok(Response::new(raft_service::Empty {}));
+            }
+        }
+
+        Err(Status::aborted(
+            "Snapshot stream ended before the last chunk was received",
+        ))
+    }
+
+    #[tracing::instrument(name = "RaftServer::get_snapshot_offset", skip_all)]
+    async fn get_snapshot_offset(
+        &self,
+        request: Request<raft_service::SnapshotOffsetArgs>,
+    ) -> Result<Response<raft_service::SnapshotOffsetResponse>, Status> {
+        let args = request.into_inner();
+        let offset = self
+            .partial_snapshot(args.to, args.from)
+            .offset(args.index, args.term);
+        Ok(Response::new(raft_service::SnapshotOffsetResponse {
+            offset,
+        }))
+    }
+
+    #[tracing::instrument(name = "RaftServer::propose", skip_all)]
     async fn propose(
         &self,
         request: Request<raft_service::ProposeArgs>,
     ) -> Result<Response<raft_service::ProposeResponse>, Status> {
+        telemetry::set_parent_from_metadata(&Span::current(), request.metadata());
+        if let Some(limiter) = &self.client_limiter {
+            let client_id = client_id_of(&request);
+            if !limiter.try_acquire(&client_id) {
+                let error = Error::RateLimited(format!(
+                    "client \"{}\" proposes faster than the node allows",
+                    client_id
+                ));
+                return Err(error.overload_status().unwrap());
+            }
+        }
         let request_args = request.into_inner();
         let sender = self.tx.clone();
 
         let (tx_msg, rx_msg) = oneshot::channel();
         match sender
             .send(ServerRequestMsg::Propose {
-                proposal: request_args.msg.clone(),
+                proposals: request_args.proposals(),
+                session: request_args.session(),
+                span: Span::current(),
                 tx_msg,
             })
             .await
@@ -283,31 +559,42 @@ impl<
 
         let response = rx_msg.await.unwrap();
         match response {
-            ServerResponseMsg::Propose { result } => {
-                match result {
-                    ResponseResult::Success => Ok(Response::new(raft_service::ProposeResponse {
+            ServerResponseMsg::Propose { result } => match result {
+                ProposeResponseResult::Success(outcome) => {
+                    Ok(Response::new(raft_service::ProposeResponse {
+                        index: outcome.index,
+                        term: outcome.term,
+                        data: outcome.data,
                         ..Default::default()
-                    })),
-                    ResponseResult::Error(error) => {
-                        Ok(Response::new(raft_service::ProposeResponse {
-                            error: error.to_string().as_bytes().to_vec(),
-                        }))
-                    }
-                    ResponseResult::WrongLeader { leader_addr, .. } => {
-                        // TODO: Handle this kind of errors
-                        let mut client = create_client(leader_addr).await.unwrap();
-                        let _ = client
-                            .propose(ProposeArgs {
-                                msg: request_args.msg,
-                            })
-                            .await?;
-
-                        Ok(Response::new(raft_service::ProposeResponse {
-                            ..Default::default()
-                        }))
+                    }))
+                }
+                ProposeResponseResult::Error(error) => {
+                    if let Some(status) = error.overload_status() {
+                        return Err(status);
                     }
+                    Ok(Response::new(raft_service::ProposeResponse {
+                        error: error.to_string().as_bytes().to_vec(),
+                        ..Default::default()
+                    }))
                 }
-            }
+                ProposeResponseResult::WrongLeader { leader_addr, .. } => {
+                    let options = self.client_options();
+                    options
+                        .retry
+                        .retry_proposal(request_args.session().is_some(), || async {
+                            let mut client =
+                                create_client_with_options(&leader_addr, &options).await?;
+                            Ok(client.propose(request_args.clone()).await?)
+                        })
+                        .await
+                        .map_err(|error| match error {
+                            Error::RemoteCall(status) => status,
+                            error => error
+                                .overload_status()
+                                .unwrap_or_else(|| Status::unavailable(error.to_string())),
+                        })
+                }
+            },
             _ => unreachable!(),
         }
     }
@@ -363,7 +650,7 @@ impl<
         &self,
         request: Request<raft_service::Empty>,
     ) -> Result<Response<raft_service::Empty>, Status> {
-        let _request_args = request.into_inner();
+        let request_args = request.into_inner();
         let (tx_msg, rx_msg) = oneshot::channel();
         let sender = self.tx.clone();
         match sender.send(ServerRequestMsg::LeaveJoint { tx_msg }).await {
@@ -373,7 +660,20 @@ impl<
         let response = rx_msg.await.unwrap();
 
         match response {
-            ServerResponseMsg::LeaveJoint {} => Ok(Response::new(raft_service::Empty {})),
+            ServerResponseMsg::ConfigChange { result } => match result {
+                ConfChangeResponseResult::WrongLeader { leader_addr, .. } => {
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options())
+                            .await
+                            .unwrap();
+                    client.leave_joint(request_args).await?;
+                    Ok(Response::new(raft_service::Empty {}))
+                }
+                ConfChangeResponseResult::Error(e) => {
+                    Err(Status::failed_precondition(e.to_string()))
+                }
+                _ => Ok(Response::new(raft_service::Empty {})),
+            },
             _ => unreachable!(),
         }
     }
@@ -423,4 +723,241 @@ impl<
             _ => unreachable!(),
         }
     }
+
+    async fn read_index(
+        &self,
+        request: Request<raft_service::Empty>,
+    ) -> Result<Response<raft_service::ReadIndexResponse>, Status> {
+        let forwarded = request.metadata().contains_key(FORWARDED_HEADER);
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender.send(ServerRequestMsg::ReadIndex { tx_msg }).await {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::ReadIndex { result } => match result {
+                ReadIndexResponseResult::Success { index } => {
+                    Ok(Response::new(raft_service::ReadIndexResponse {
+                        code: raft_service::ResultCode::Ok as i32,
+                        index,
+                        ..Default::default()
+                    }))
+                }
+                ReadIndexResponseResult::Error(e) => {
+                    Ok(Response::new(raft_service::ReadIndexResponse {
+                        code: raft_service::ResultCode::Error as i32,
+                        error: e.to_string().as_bytes().to_vec(),
+                        ..Default::default()
+                    }))
+                }
+                ReadIndexResponseResult::WrongLeader { leader_addr, .. } => {
+                    if forwarded {
+                        return Ok(Response::new(raft_service::ReadIndexResponse {
+                            code: raft_service::ResultCode::WrongLeader as i32,
+                            error: format!("Not the leader anymore, the leader is {}", leader_addr)
+                                .into_bytes(),
+                            ..Default::default()
+                        }));
+                    }
+                    let mut client =
+                        create_client_with_options(leader_addr, &self.client_options())
+                            .await
+                            .map_err(|e| Status::unavailable(e.to_string()))?;
+                    let reply = client
+                        .read_index(forwarded_read_index_request())
+                        .await?
+                        .into_inner();
+
+                    Ok(Response::new(reply))
+                }
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    async fn query(
+        &self,
+        request: Request<raft_service::QueryArgs>,
+    ) -> Result<Response<raft_service::QueryResponse>, Status> {
+        let request_args = request.into_inner();
+        let consistency = request_args.consistency();
+
+        let read_index = match consistency {
+            raft_service::QueryConsistency::QueryLinearizable => {
+                let response = self
+                    .read_index(Request::new(raft_service::Empty {}))
+                    .await?
+                    .into_inner();
+                if response.code() != raft_service::ResultCode::Ok {
+                    return Ok(Response::new(raft_service::QueryResponse {
+                        code: response.code,
+                        error: response.error,
+                        ..Default::default()
+                    }));
+                }
+                response.index
+            }
+            _ => 0,
+        };
+
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::Query {
+                query: request_args.query.clone(),
+                leader_only: consistency == raft_service::QueryConsistency::QueryLeader,
+                read_index,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::Query { result } => match result {
+                QueryResponseResult::Success { data } => {
+                    Ok(Response::new(raft_service::QueryResponse {
+                        code: raft_service::ResultCode::Ok as i32,
+                        data,
+                        ..Default::default()
+                    }))
+                }
+                QueryResponseResult::Error(e) => Ok(Response::new(raft_service::QueryResponse {
+                    code: raft_service::ResultCode::Error as i32,
+                    error: e.to_string().as_bytes().to_vec(),
+                    ..Default::default()
+                })),
+                QueryResponseResult::WrongLeader { leader_addr, .. } => {
+                    let options = self.client_options();
+                    options
+                        .retry
+                        .retry(|| async {
+                            let mut client =
+                                create_client_with_options(&leader_addr, &options).await?;
+                            Ok(client.query(request_args.clone()).await?)
+                        })
+                        .await
+                        .map_err(|error| match error {
+                            Error::RemoteCall(status) => status,
+                            error => Status::unavailable(error.to_string()),
+                        })
+                }
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    async fn transfer_leader(
+        &self,
+        request: Request<raft_service::TransferLeaderArgs>,
+    ) -> Result<Response<raft_service::TransferLeaderResponse>, Status> {
+        let request_args = request.into_inner();
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::TransferLeader {
+                node_id: request_args.node_id,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::TransferLeader { result } => match result {
+                ResponseResult::Success => {
+                    Ok(Response::new(raft_service::TransferLeaderResponse {
+                        code: raft_service::ResultCode::Ok as i32,
+                        ..Default::default()
+                    }))
+                }
+                ResponseResult::Error(e) => {
+                    Ok(Response::new(raft_service::TransferLeaderResponse {
+                        code: raft_service::ResultCode::Error as i32,
+                        error: e.to_string().as_bytes().to_vec(),
+                    }))
+                }
+                ResponseResult::WrongLeader { .. } => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+    async fn set_priority(
+        &self,
+        request: Request<raft_service::SetPriorityArgs>,
+    ) -> Result<Response<raft_service::Empty>, Status> {
+        let request_args = request.into_inner();
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::SetPriority {
+                priority: request_args.priority,
+                tx_msg,
+            })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::SetPriority {} => Ok(Response::new(raft_service::Empty {})),
+            _ => unreachable!(),
+        }
+    }
+
+    type WatchClusterEventsStream =
+        Pin<Box<dyn Stream<Item = Result<raft_service::ClusterEvent, Status>> + Send>>;
+
+    async fn watch_cluster_events(
+        &self,
+        _request: Request<raft_service::Empty>,
+    ) -> Result<Response<Self::WatchClusterEventsStream>, Status> {
+        let (tx_msg, rx_msg) = oneshot::channel();
+        let sender = self.tx.clone();
+        match sender
+            .send(ServerRequestMsg::WatchClusterEvents { tx_msg })
+            .await
+        {
+            Ok(_) => (),
+            Err(_) => self.print_send_error(function_name!()),
+        }
+        let response = rx_msg.await.unwrap();
+
+        match response {
+            ServerResponseMsg::WatchClusterEvents { rx_events } => {
+                // Observers that fall behind skip the missed events instead of being disconnected.
+                let stream = BroadcastStream::new(rx_events)
+                    .filter_map(|event| event.ok())
+                    .map(Ok::<_, Status>);
+                Ok(Response::new(Box::pin(stream)))
+            }
+            _ => unreachable!(),
+        }
+    }
+    async fn gossip(
+        &self,
+        request: Request<raft_service::GossipArgs>,
+    ) -> Result<Response<raft_service::GossipResponse>, Status> {
+        let gossip = self
+            .gossip
+            .as_ref()
+            .ok_or_else(|| Status::unimplemented("Gossip is not enabled on this node"))?;
+
+        gossip
+            .handle(request.into_inner(), &self.client_options())
+            .await
+            .map(Response::new)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
 }
@@ -1,11 +1,14 @@
-use std::{collections::HashMap, net::SocketAddr};
+use bytes::Bytes;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
-use crate::{raft::eraftpb::Message as RaftMessage, StableStorage};
+use crate::{raft::eraftpb::Message as RaftMessage, AbstractStableStorage};
 use tokio::sync::oneshot::Sender;
+use tracing::Span;
 
 use crate::{
-    response::local_response_message::LocalResponseMsg, AbstractLogEntry, AbstractStateMachine,
-    ClusterJoinTicket, InitialRole,
+    response::local_response_message::LocalResponseMsg, shard::ShardCommand, AbstractLogEntry,
+    AbstractStateMachine, ClusterJoinTicket, InitialRole, PeerLabels, ProposalSession,
+    RaftEventHandler, RuntimeConfig,
 };
 
 use super::common::confchange_request::ConfChangeRequest;
@@ -14,7 +17,7 @@ use super::common::confchange_request::ConfChangeRequest;
 #[derive(Debug)]
 pub enum LocalRequestMsg<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage + 'static,
+    LogStorage: AbstractStableStorage + 'static,
     FSM: AbstractStateMachine,
 > {
     IsLeader {
@@ -26,6 +29,18 @@ pub enum LocalRequestMsg<
     GetLeaderId {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    GetTerm {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    GetCommitIndex {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    GetLastApplied {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    GetRole {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     GetPeers {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
@@ -42,6 +57,9 @@ pub enum LocalRequestMsg<
     DebugNode {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    GetStatus {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     GetStateMachine {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
@@ -51,13 +69,19 @@ pub enum LocalRequestMsg<
     GetClusterSize {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    GetMembers {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     Quit {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    Shutdown {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     Campaign {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
-    Demote {
+    StepDown {
         term: u64,
         leader_id: u64,
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
@@ -66,6 +90,23 @@ pub enum LocalRequestMsg<
         node_id: u64,
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    SetPriority {
+        priority: i64,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    SetDraining {
+        draining: bool,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    SetTicks {
+        election_tick: usize,
+        heartbeat_tick: usize,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    RegisterEventHandler {
+        handler: Arc<dyn RaftEventHandler>,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     Leave {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
@@ -74,8 +115,36 @@ pub enum LocalRequestMsg<
         term: u64,
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    CompactLogs {
+        index: u64,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    PurgeTombstones {
+        node_ids: Vec<u64>,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
     Propose {
-        proposal: Vec<u8>,
+        proposal: Bytes,
+        session: Option<ProposalSession>,
+        span: Span,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    ProposeBatch {
+        proposals: Vec<Bytes>,
+        span: Span,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    UpdateRuntimeConfig {
+        update: RuntimeConfig,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    SetPeerLabels {
+        node_id: u64,
+        labels: PeerLabels,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    Shard {
+        command: ShardCommand,
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
     ChangeConfig {
@@ -88,10 +157,27 @@ pub enum LocalRequestMsg<
     },
     JoinCluster {
         tickets: Vec<ClusterJoinTicket>,
+        role: InitialRole,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    LeaveJoint {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    ForceRemovePeer {
+        node_id: u64,
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
-    LeaveJoint {},
     GetRawNode {
         tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
     },
+    ReadLease {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    ReadIndex {
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
+    WaitApplied {
+        index: u64,
+        tx_msg: Sender<LocalResponseMsg<LogEntry, LogStorage, FSM>>,
+    },
 }
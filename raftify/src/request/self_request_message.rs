@@ -3,4 +3,5 @@
 #[derive(Debug)]
 pub enum SelfMessage {
     ReportUnreachable { node_id: u64 },
+    ReportSnapshot { node_id: u64, failed: bool },
 }
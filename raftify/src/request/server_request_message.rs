@@ -1,10 +1,13 @@
+use bytes::Bytes;
 use std::marker::PhantomData;
 
 use tokio::sync::oneshot::Sender;
+use tracing::Span;
 
 use crate::{
     raft::eraftpb::Message as RaftMessage, response::server_response_message::ServerResponseMsg,
-    AbstractLogEntry, AbstractStateMachine, Peers, StableStorage,
+    AbstractLogEntry, AbstractStableStorage, AbstractStateMachine, PeerLabels, Peers,
+    ProposalSession, RuntimeConfig,
 };
 
 use super::common::confchange_request::ConfChangeRequest;
@@ -13,7 +16,7 @@ use super::common::confchange_request::ConfChangeRequest;
 #[derive(Debug)]
 pub enum ServerRequestMsg<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage,
+    LogStorage: AbstractStableStorage,
     FSM: AbstractStateMachine,
 > {
     RequestId {
@@ -21,18 +24,26 @@ pub enum ServerRequestMsg<
         tx_msg: Sender<ServerResponseMsg>,
     },
     Propose {
-        proposal: Vec<u8>,
+        proposals: Vec<Bytes>,
+        session: Option<ProposalSession>,
+        span: Span,
         tx_msg: Sender<ServerResponseMsg>,
     },
     ChangeConfig {
         conf_change: ConfChangeRequest,
+        requested_by: Option<String>,
         tx_msg: Sender<ServerResponseMsg>,
     },
     DebugNode {
         tx_msg: Sender<ServerResponseMsg>,
     },
+    GetMembershipHistory {
+        tx_msg: Sender<ServerResponseMsg>,
+    },
     SendMessage {
         message: Box<RaftMessage>,
+        // Cluster UUID attached to the request by the sender
+        cluster_uuid: Option<String>,
     },
     GetPeers {
         tx_msg: Sender<ServerResponseMsg>,
@@ -44,9 +55,61 @@ pub enum ServerRequestMsg<
     LeaveJoint {
         tx_msg: Sender<ServerResponseMsg>,
     },
+    ForceRemovePeer {
+        node_id: u64,
+        requested_by: Option<String>,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
     CreateSnapshot {
         tx_msg: Sender<ServerResponseMsg>,
     },
+    ReadIndex {
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    Query {
+        query: Vec<u8>,
+        // Whether only the leader answers the query
+        leader_only: bool,
+        // Index the state machine should have applied before answering
+        read_index: u64,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    TransferLeader {
+        node_id: u64,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    SetPriority {
+        priority: i64,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    SetTicks {
+        election_tick: usize,
+        heartbeat_tick: usize,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    WatchClusterEvents {
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    CompactLogs {
+        index: u64,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    PurgeTombstones {
+        node_ids: Vec<u64>,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    UpdateRuntimeConfig {
+        update: RuntimeConfig,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    SetPeerLabels {
+        node_id: u64,
+        labels: PeerLabels,
+        tx_msg: Sender<ServerResponseMsg>,
+    },
+    GetHealth {
+        tx_msg: Sender<ServerResponseMsg>,
+    },
     _Phantom(PhantomData<LogEntry>),
     _Phantom2(PhantomData<FSM>),
     _Phantom3(PhantomData<LogStorage>),
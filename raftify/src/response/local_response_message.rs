@@ -1,18 +1,18 @@
 use std::{fmt, marker::PhantomData, sync::Arc};
 
-use crate::{raft::RawNode, StableStorage};
+use crate::{raft::RawNode, AbstractStableStorage};
 use tokio::sync::Mutex;
 
-use crate::{AbstractLogEntry, AbstractStateMachine, Peers};
+use crate::{AbstractLogEntry, AbstractStateMachine, NodeRole, Peers, RaftStatus};
 
 use super::{
-    server_response_message::{ConfChangeResponseResult, ResponseResult},
+    server_response_message::{ConfChangeResponseResult, ProposeResponseResult, ResponseResult},
     ResponseMessage,
 };
 
 pub enum LocalResponseMsg<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage + 'static,
+    LogStorage: AbstractStableStorage + 'static,
     FSM: AbstractStateMachine,
 > {
     IsLeader {
@@ -24,6 +24,18 @@ pub enum LocalResponseMsg<
     GetLeaderId {
         leader_id: u64,
     },
+    GetTerm {
+        term: u64,
+    },
+    GetCommitIndex {
+        commit_index: u64,
+    },
+    GetLastApplied {
+        last_applied: u64,
+    },
+    GetRole {
+        role: NodeRole,
+    },
     GetPeers {
         peers: Peers,
     },
@@ -38,33 +50,63 @@ pub enum LocalResponseMsg<
     GetClusterSize {
         size: usize,
     },
+    GetMembers {
+        voters: Vec<u64>,
+        learners: Vec<u64>,
+    },
     GetRawNode {
         raw_node: Arc<Mutex<&'static RawNode<LogStorage>>>,
     },
     Quit {},
-    Campaign {},
+    Shutdown {
+        result: ResponseResult,
+    },
+    Campaign {
+        result: ResponseResult,
+    },
     MakeSnapshot {},
-    JoinCluster {},
+    CompactLogs {
+        result: ResponseResult,
+    },
+    PurgeTombstones {
+        result: ResponseResult,
+    },
+    JoinCluster {
+        result: ResponseResult,
+    },
     SendMessage {},
-    Demote {},
-    // LeaveJoint {},
-    TransferLeader {},
+    StepDown {},
+    SetPriority {},
+    SetDraining {},
+    SetTicks {
+        result: ResponseResult,
+    },
+    RegisterEventHandler {},
+    TransferLeader {
+        result: ResponseResult,
+    },
     DebugNode {
         result_json: String,
     },
+    GetStatus {
+        status: RaftStatus,
+    },
     _Phantom(PhantomData<LogEntry>),
 
     // Rerouting available
     Propose {
-        result: ResponseResult,
+        result: ProposeResponseResult,
     },
     ConfigChange {
         result: ConfChangeResponseResult,
     },
+    ReadIndex {
+        result: ResponseResult,
+    },
 }
 
-impl<LogEntry: AbstractLogEntry, LogStorage: StableStorage, FSM: AbstractStateMachine> fmt::Debug
-    for LocalResponseMsg<LogEntry, LogStorage, FSM>
+impl<LogEntry: AbstractLogEntry, LogStorage: AbstractStableStorage, FSM: AbstractStateMachine>
+    fmt::Debug for LocalResponseMsg<LogEntry, LogStorage, FSM>
 {
     #[allow(clippy::recursive_format_impl)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -79,7 +121,7 @@ impl<LogEntry: AbstractLogEntry, LogStorage: StableStorage, FSM: AbstractStateMa
     }
 }
 
-impl<LogEntry: AbstractLogEntry, LogStorage: StableStorage, FSM: AbstractStateMachine>
+impl<LogEntry: AbstractLogEntry, LogStorage: AbstractStableStorage, FSM: AbstractStateMachine>
     From<LocalResponseMsg<LogEntry, LogStorage, FSM>>
     for ResponseMessage<LogEntry, LogStorage, FSM>
 {
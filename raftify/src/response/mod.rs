@@ -1,6 +1,6 @@
 use std::marker::PhantomData;
 
-use crate::{AbstractLogEntry, AbstractStateMachine, StableStorage};
+use crate::{AbstractLogEntry, AbstractStableStorage, AbstractStateMachine};
 
 use self::{local_response_message::LocalResponseMsg, server_response_message::ServerResponseMsg};
 
@@ -9,7 +9,7 @@ pub mod server_response_message;
 
 pub enum ResponseMessage<
     LogEntry: AbstractLogEntry,
-    LogStorage: StableStorage + 'static,
+    LogStorage: AbstractStableStorage + 'static,
     FSM: AbstractStateMachine,
 > {
     Server(ServerResponseMsg),
@@ -1,4 +1,9 @@
-use crate::{AbstractLogEntry, AbstractStateMachine, Error, Peers, StableStorage};
+use tokio::sync::broadcast;
+
+use crate::{
+    raft_service::ClusterEvent, AbstractLogEntry, AbstractStableStorage, AbstractStateMachine,
+    Error, MembershipChangeRecord, Peers, Result,
+};
 
 use super::ResponseMessage;
 
@@ -9,6 +14,27 @@ pub enum ResponseResult {
     WrongLeader { leader_id: u64, leader_addr: String },
 }
 
+/// Result of a committed proposal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProposeOutcome {
+    pub index: u64,
+    pub term: u64,
+    /// Bytes returned by `AbstractStateMachine::apply` for this entry.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum ProposeResponseResult {
+    /// The proposal has been committed and applied to the state machine.
+    Success(ProposeOutcome),
+    /// Either the proposal could not be appended or the state machine failed to apply it.
+    Error(Error),
+    WrongLeader {
+        leader_id: u64,
+        leader_addr: String,
+    },
+}
+
 #[derive(Debug)]
 pub enum ConfChangeResponseResult {
     JoinSuccess {
@@ -16,6 +42,15 @@ pub enum ConfChangeResponseResult {
         peers: Peers,
     },
     RemoveSuccess,
+    /// Entered the joint configuration. `leave_joint` should be called to finish the change.
+    JointEntered {
+        assigned_ids: Vec<u64>,
+        removed_ids: Vec<u64>,
+        peers: Peers,
+    },
+    JointLeft {
+        peers: Peers,
+    },
     Error(Error),
     WrongLeader {
         leader_id: u64,
@@ -37,24 +72,81 @@ pub enum RequestIdResponseResult {
     },
 }
 
+#[derive(Debug)]
+pub enum ReadIndexResponseResult {
+    Success { index: u64 },
+    Error(Error),
+    WrongLeader { leader_id: u64, leader_addr: String },
+}
+
+#[derive(Debug)]
+pub enum QueryResponseResult {
+    Success { data: Vec<u8> },
+    Error(Error),
+    WrongLeader { leader_id: u64, leader_addr: String },
+}
+
 #[derive(Debug)]
 pub enum ServerResponseMsg {
-    ReportUnreachable { result: ResponseResult },
-    DebugNode { result_json: String },
-    GetPeers { peers: Peers },
+    ReportUnreachable {
+        result: ResponseResult,
+    },
+    DebugNode {
+        result_json: String,
+    },
+    GetPeers {
+        peers: Peers,
+    },
+    GetMembershipHistory {
+        result: Result<Vec<MembershipChangeRecord>>,
+    },
     SetPeers {},
-    SendMessage { result: ResponseResult },
+    SendMessage {
+        result: ResponseResult,
+    },
     CreateSnapshot {},
-    LeaveJoint {},
     JoinCluster {},
+    TransferLeader {
+        result: ResponseResult,
+    },
+    SetPriority {},
+    SetTicks {
+        result: ResponseResult,
+    },
+    WatchClusterEvents {
+        rx_events: broadcast::Receiver<ClusterEvent>,
+    },
+    CompactLogs {
+        result: ResponseResult,
+    },
+    PurgeTombstones {
+        result: ResponseResult,
+    },
+    GetHealth {
+        leader_id: u64,
+        applied: u64,
+        committed: u64,
+    },
 
     // Rerouting available
-    Propose { result: ResponseResult },
-    ConfigChange { result: ConfChangeResponseResult },
-    RequestId { result: RequestIdResponseResult },
+    Propose {
+        result: ProposeResponseResult,
+    },
+    ConfigChange {
+        result: ConfChangeResponseResult,
+    },
+    RequestId {
+        result: RequestIdResponseResult,
+    },
+    ReadIndex {
+        result: ReadIndexResponseResult,
+    },
+    Query {
+        result: QueryResponseResult,
+    },
 }
 
-impl<LogEntry: AbstractLogEntry, LogStorage: StableStorage, FSM: AbstractStateMachine>
+impl<LogEntry: AbstractLogEntry, LogStorage: AbstractStableStorage, FSM: AbstractStateMachine>
     From<ServerResponseMsg> for ResponseMessage<LogEntry, LogStorage, FSM>
 {
     fn from(msg: ServerResponseMsg) -> Self {
@@ -0,0 +1,342 @@
+use parking_lot::RwLock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    sync::Arc,
+};
+use tonic::Request;
+
+use crate::{
+    create_client_with_options, raft_service::ProposeArgs, AbstractLogEntry, AbstractStableStorage,
+    AbstractStateMachine, ClientOptions, Error, KeyRange, ProposeOutcome, RaftGroupManager,
+    RaftNode, Result,
+};
+
+/// Maps the keys of the application to the raft groups holding them.
+pub trait KeyPartitioner: Send + Sync {
+    /// Returns the group holding `key`, or `None` if no group does.
+    fn group_of(&self, key: &[u8]) -> Option<u64>;
+}
+
+/// Spreads the keys over a fixed set of groups by their hash.
+/// The hash is stable across processes and releases, so every node maps a key to the same group.
+#[derive(Debug, Clone)]
+pub struct HashPartitioner(pub Vec<u64>);
+
+impl KeyPartitioner for HashPartitioner {
+    fn group_of(&self, key: &[u8]) -> Option<u64> {
+        // FNV-1a
+        let hash = key.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+        (!self.0.is_empty()).then(|| self.0[(hash % self.0.len() as u64) as usize])
+    }
+}
+
+/// Assigns contiguous key ranges to the groups, by the first key of each range.
+/// A range ends where the next one starts, and the keys before the first range belong to no group.
+#[derive(Debug, Clone, Default)]
+pub struct RangePartitioner {
+    starts: BTreeMap<Vec<u8>, u64>,
+}
+
+impl RangePartitioner {
+    /// Creates a partitioner from the first keys of the ranges and their groups.
+    pub fn new(ranges: impl IntoIterator<Item = (Vec<u8>, u64)>) -> Self {
+        Self {
+            starts: ranges.into_iter().collect(),
+        }
+    }
+
+    /// Returns the first keys of the ranges and their groups, in key order.
+    pub fn ranges(&self) -> Vec<(Vec<u8>, u64)> {
+        self.starts
+            .iter()
+            .map(|(start, group_id)| (start.clone(), *group_id))
+            .collect()
+    }
+
+    /// Returns the range holding `key` and its group.
+    pub fn range_of(&self, key: &[u8]) -> Option<(KeyRange, u64)> {
+        let (start, group_id) = self.starts.range::<[u8], _>(..=key).next_back()?;
+        let end = self
+            .starts
+            .range::<[u8], _>((Bound::Excluded(start.as_slice()), Bound::Unbounded))
+            .next()
+            .map(|(end, _)| end.clone());
+        Some((KeyRange::new(start.clone(), end), *group_id))
+    }
+
+    /// Returns a copy in which the keys from `at` to the end of its range belong to `group_id`.
+    pub fn split(&self, at: Vec<u8>, group_id: u64) -> Self {
+        let mut starts = self.starts.clone();
+        starts.insert(at, group_id);
+        Self { starts }
+    }
+
+    /// Returns a copy in which the range starting at `start` belongs to the range before it.
+    pub fn merge(&self, start: &[u8]) -> Self {
+        let mut starts = self.starts.clone();
+        starts.remove(start);
+        Self { starts }
+    }
+}
+
+impl KeyPartitioner for RangePartitioner {
+    fn group_of(&self, key: &[u8]) -> Option<u64> {
+        self.starts
+            .range::<[u8], _>(..=key)
+            .next_back()
+            .map(|(_, group_id)| *group_id)
+    }
+}
+
+/// Routes the requests of a multi-raft application to the group holding their key.
+///
+/// The groups hosted by the `RaftGroupManager` are called locally, and forward the proposals to their leader.
+/// The other groups are called through the nodes set with `set_remote_group`,
+/// which forward the proposals to the leader of the group in turn.
+///
+/// ```ignore
+/// let router = Router::new(manager.clone(), Arc::new(HashPartitioner(vec![1, 2, 3])));
+/// router.set_remote_group(3, vec!["10.0.0.2:60061".to_owned()]);
+/// router.propose(b"user-42", LogEntry::Insert { key: 42, value: "v".to_owned() }.encode()?).await?;
+/// ```
+#[derive(Clone)]
+pub struct Router<
+    LogEntry: AbstractLogEntry + 'static,
+    LogStorage: AbstractStableStorage + Send + Clone + 'static,
+    FSM: AbstractStateMachine + Clone + 'static,
+> {
+    manager: RaftGroupManager<LogEntry, LogStorage, FSM>,
+    partitioner: Arc<RwLock<Arc<dyn KeyPartitioner>>>,
+    // Addresses of the nodes hosting the groups which aren't hosted locally.
+    remote_groups: Arc<RwLock<HashMap<u64, Vec<String>>>>,
+}
+
+impl<
+        LogEntry: AbstractLogEntry + 'static,
+        LogStorage: AbstractStableStorage + Send + Sync + Clone + 'static,
+        FSM: AbstractStateMachine + Send + Sync + Clone + 'static,
+    > Router<LogEntry, LogStorage, FSM>
+{
+    pub fn new(
+        manager: RaftGroupManager<LogEntry, LogStorage, FSM>,
+        partitioner: Arc<dyn KeyPartitioner>,
+    ) -> Self {
+        Self {
+            manager,
+            partitioner: Arc::new(RwLock::new(partitioner)),
+            remote_groups: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Replaces the partitioner, e.g. once the groups are rebalanced.
+    pub fn set_partitioner(&self, partitioner: Arc<dyn KeyPartitioner>) {
+        *self.partitioner.write() = partitioner;
+    }
+
+    /// Sets the addresses of the nodes hosting the group `group_id`, tried in order.
+    pub fn set_remote_group(&self, group_id: u64, addrs: Vec<String>) {
+        self.remote_groups.write().insert(group_id, addrs);
+    }
+
+    pub fn group_of(&self, key: &[u8]) -> Result<u64> {
+        self.partitioner.read().group_of(key).ok_or_else(|| {
+            Error::Rejected(format!(
+                "No raft group holds the key {:?}",
+                String::from_utf8_lossy(key)
+            ))
+        })
+    }
+
+    /// Proposes `proposal` to the group holding `key`, and resolves once it is applied.
+    pub async fn propose(&self, key: &[u8], proposal: Vec<u8>) -> Result<ProposeOutcome> {
+        let group_id = self.group_of(key)?;
+        if let Some(raft_node) = self.manager.group(group_id) {
+            return raft_node.propose(proposal).await;
+        }
+
+        let mut last_error = None;
+        for addr in self.remote_addrs(group_id)? {
+            match self.propose_remote(group_id, &addr, proposal.clone()).await {
+                // The node couldn't be reached, try the next one.
+                Err(e @ (Error::Grpc(_) | Error::RemoteCall(_))) => last_error = Some(e),
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            Error::Rejected(format!("No node is known to host raft group {}", group_id))
+        }))
+    }
+
+    /// Reads the state machine of the group holding `key` once it is up to date with the leader,
+    /// through `RaftNode::read_index`. Only the groups hosted locally can be read.
+    pub async fn query<R>(&self, key: &[u8], read: impl FnOnce(FSM) -> R) -> Result<R> {
+        let group_id = self.group_of(key)?;
+        let raft_node = self.local_group(group_id)?;
+        raft_node.read_index().await?;
+        Ok(read(raft_node.state_machine().await?))
+    }
+
+    /// Moves the keys of `range` from the group `from` to the group `to`, and routes the keys with `partitioner`
+    /// once they moved. Both groups should be hosted locally on their leader, and `to` should run on all its nodes.
+    ///
+    /// `from` fences the range at the index its state is taken at, see `RaftNode::fence_range`,
+    /// so that the writes to the range coming after it are rejected instead of being lost,
+    /// and can be retried once they are routed to `to`. If `to` fails to restore the range,
+    /// the fence is lifted and the range stays in `from`.
+    pub async fn move_range(
+        &self,
+        range: KeyRange,
+        from: u64,
+        to: u64,
+        partitioner: Arc<dyn KeyPartitioner>,
+    ) -> Result<()> {
+        let source = self.local_group(from)?;
+        let target = self.local_group(to)?;
+
+        let state = source.fence_range(range.clone()).await?;
+        if let Err(e) = target.restore_range(state).await {
+            source.unfence_range(range).await?;
+            return Err(e);
+        }
+        self.set_partitioner(partitioner);
+        source.remove_range(range).await?;
+        Ok(())
+    }
+
+    /// Moves the keys from `at` to the end of their range in `partitioner` to the group `to`,
+    /// and returns the partitioner now in use, see `move_range`.
+    pub async fn split(
+        &self,
+        partitioner: &RangePartitioner,
+        at: &[u8],
+        to: u64,
+    ) -> Result<RangePartitioner> {
+        let (range, from) = partitioner.range_of(at).ok_or_else(|| {
+            Error::Rejected(format!("No range holds {:?}", String::from_utf8_lossy(at)))
+        })?;
+        let split = partitioner.split(at.to_vec(), to);
+        self.move_range(
+            KeyRange::new(at, range.end),
+            from,
+            to,
+            Arc::new(split.clone()),
+        )
+        .await?;
+        Ok(split)
+    }
+
+    /// Moves the range starting at `start` in `partitioner` to the group of the range before it,
+    /// and returns the partitioner now in use, see `move_range`.
+    pub async fn merge(
+        &self,
+        partitioner: &RangePartitioner,
+        start: &[u8],
+    ) -> Result<RangePartitioner> {
+        let (range, from) = partitioner
+            .range_of(start)
+            .filter(|(range, _)| range.start == start)
+            .ok_or_else(|| {
+                Error::Rejected(format!(
+                    "No range starts at {:?}",
+                    String::from_utf8_lossy(start)
+                ))
+            })?;
+        let merged = partitioner.merge(start);
+        let to = merged.group_of(start).ok_or_else(|| {
+            Error::Rejected(format!(
+                "No range comes before {:?}",
+                String::from_utf8_lossy(start)
+            ))
+        })?;
+        self.move_range(range, from, to, Arc::new(merged.clone()))
+            .await?;
+        Ok(merged)
+    }
+
+    fn local_group(&self, group_id: u64) -> Result<RaftNode<LogEntry, LogStorage, FSM>> {
+        self.manager.group(group_id).ok_or_else(|| {
+            Error::Rejected(format!("Raft group {} is not hosted locally", group_id))
+        })
+    }
+
+    fn remote_addrs(&self, group_id: u64) -> Result<Vec<String>> {
+        self.remote_groups
+            .read()
+            .get(&group_id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::Rejected(format!(
+                    "Raft group {} is neither hosted locally nor known remotely",
+                    group_id
+                ))
+            })
+    }
+
+    async fn propose_remote(
+        &self,
+        group_id: u64,
+        addr: &str,
+        proposal: Vec<u8>,
+    ) -> Result<ProposeOutcome> {
+        let options = ClientOptions {
+            group_id: Some(group_id),
+            ..self.manager.client_options()
+        };
+        let response = options
+            .retry
+            .retry_proposal(false, || async {
+                let mut client = create_client_with_options(addr, &options).await?;
+                let response = client
+                    .propose(Request::new(ProposeArgs::new(proposal.clone(), None)))
+                    .await?;
+                Ok(response.into_inner())
+            })
+            .await?;
+
+        if !response.error.is_empty() {
+            return Err(Error::Rejected(
+                String::from_utf8_lossy(&response.error).to_string(),
+            ));
+        }
+        Ok(ProposeOutcome {
+            index: response.index,
+            term: response.term,
+            data: response.data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitioners() {
+        let partitioner = HashPartitioner(vec![1, 2, 3]);
+        let group_id = partitioner.group_of(b"user-42").unwrap();
+        assert!([1, 2, 3].contains(&group_id));
+        assert_eq!(partitioner.group_of(b"user-42"), Some(group_id));
+        assert_eq!(HashPartitioner(vec![]).group_of(b"user-42"), None);
+
+        let partitioner = RangePartitioner::new([(b"b".to_vec(), 1), (b"m".to_vec(), 2)]);
+        assert_eq!(partitioner.group_of(b"a"), None);
+        assert_eq!(partitioner.group_of(b"b"), Some(1));
+        assert_eq!(partitioner.group_of(b"lzz"), Some(1));
+        assert_eq!(partitioner.group_of(b"m"), Some(2));
+        assert_eq!(partitioner.group_of(b"zzz"), Some(2));
+
+        let split = partitioner.split(b"f".to_vec(), 3);
+        assert_eq!(
+            split.range_of(b"g"),
+            Some((KeyRange::new(b"f".to_vec(), Some(b"m".to_vec())), 3))
+        );
+        assert_eq!(split.merge(b"f").ranges(), partitioner.ranges());
+        assert_eq!(
+            partitioner.range_of(b"z"),
+            Some((KeyRange::new(b"m".to_vec(), None), 2))
+        );
+    }
+}
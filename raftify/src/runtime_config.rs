@@ -0,0 +1,171 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{Config, Error, Result};
+
+/// Settings changed cluster-wide at runtime through `RaftNode::update_runtime_config`.
+///
+/// The update is proposed as a log entry, so every node applies it in the same order
+/// and overrides its `Config` with the fields which are set, without restarting.
+/// A field set to `Some(None)` disables the setting, which is `null` in JSON.
+///
+/// The overrides are persisted apart from the raft log, so they survive log compaction.
+/// A node restored from a snapshot only gets the updates applied after the snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    #[serde(
+        default,
+        deserialize_with = "set_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub snapshot_interval: Option<Option<f32>>,
+
+    #[serde(
+        default,
+        deserialize_with = "set_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub snapshot_log_count_threshold: Option<Option<u64>>,
+
+    #[serde(
+        default,
+        deserialize_with = "set_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub delta_snapshot_max_lag: Option<Option<u64>>,
+
+    #[serde(
+        default,
+        deserialize_with = "set_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entry_compression_threshold: Option<Option<usize>>,
+
+    #[serde(
+        default,
+        deserialize_with = "set_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub learner_auto_promote_threshold: Option<Option<u64>>,
+}
+
+/// Deserializes a field which is present, `null` included, as set.
+fn set_field<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Option<T>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// Overwrites the fields of `$target` which are set in `$update`.
+macro_rules! override_set {
+    ($update:expr, $target:expr, [$($field:ident),* $(,)?]) => {
+        $(if let Some(value) = $update.$field {
+            $target.$field = value;
+        })*
+    };
+    ($update:expr, $target:expr, keep_set [$($field:ident),* $(,)?]) => {
+        $(if $update.$field.is_some() {
+            $target.$field = $update.$field;
+        })*
+    };
+}
+
+impl RuntimeConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(crate) fn encode(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| Error::EncodingError(e.to_string()))
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|e| Error::DecodingError(e.to_string()))
+    }
+
+    pub(crate) fn apply_to(&self, config: &mut Config) {
+        override_set!(
+            self,
+            config,
+            [
+                snapshot_interval,
+                snapshot_log_count_threshold,
+                delta_snapshot_max_lag,
+                entry_compression_threshold,
+                learner_auto_promote_threshold,
+            ]
+        );
+    }
+
+    /// Merges a later update into `self`, keeping the fields which the update doesn't set.
+    pub(crate) fn merge(&mut self, update: &RuntimeConfig) {
+        override_set!(
+            update,
+            self,
+            keep_set [
+                snapshot_interval,
+                snapshot_log_count_threshold,
+                delta_snapshot_max_lag,
+                entry_compression_threshold,
+                learner_auto_promote_threshold,
+            ]
+        );
+    }
+
+    /// Checks that `config` stays valid once the update is applied.
+    pub(crate) fn validate_against(&self, config: &Config) -> Result<()> {
+        let mut config = config.clone();
+        self.apply_to(&mut config);
+        config.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_config_overrides_set_fields() {
+        let mut config = Config {
+            snapshot_interval: Some(60.0),
+            entry_compression_threshold: Some(1024),
+            ..Default::default()
+        };
+
+        let mut persisted = RuntimeConfig {
+            snapshot_interval: Some(Some(30.0)),
+            entry_compression_threshold: Some(None),
+            ..Default::default()
+        };
+        persisted.merge(&RuntimeConfig {
+            snapshot_interval: Some(Some(10.0)),
+            snapshot_log_count_threshold: Some(Some(1000)),
+            ..Default::default()
+        });
+        persisted.apply_to(&mut config);
+
+        assert_eq!(config.snapshot_interval, Some(10.0));
+        assert_eq!(config.snapshot_log_count_threshold, Some(1000));
+        assert_eq!(config.entry_compression_threshold, None);
+        assert_eq!(config.learner_auto_promote_threshold, None);
+
+        let invalid = RuntimeConfig {
+            snapshot_interval: Some(Some(0.0)),
+            ..Default::default()
+        };
+        assert!(invalid.validate_against(&config).is_err());
+    }
+
+    #[test]
+    fn test_runtime_config_tells_unset_from_disabled() {
+        let update = RuntimeConfig::decode(br#"{"snapshot_interval": null}"#).unwrap();
+        assert_eq!(update.snapshot_interval, Some(None));
+        assert_eq!(update.snapshot_log_count_threshold, None);
+        assert_eq!(
+            RuntimeConfig::decode(&update.encode().unwrap()).unwrap(),
+            update
+        );
+
+        assert!(RuntimeConfig::decode(br#"{"snapshot_intervall": 10.0}"#).is_err());
+    }
+}
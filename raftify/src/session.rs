@@ -0,0 +1,379 @@
+use bincode::{deserialize, serialize, serialized_size};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::size_of,
+};
+
+use crate::{
+    raft_service::ProposeArgs, response::server_response_message::ProposeOutcome,
+    telemetry::TraceContext, Error, Result,
+};
+
+/// Set in `ProposalContext::flags` when the entry data is compressed with LZ4.
+pub(crate) const ENTRY_COMPRESSED: u8 = 1;
+/// Set in `ProposalContext::flags` when the entry data is a `RuntimeConfig` update
+/// applied by raftify itself instead of the state machine.
+pub(crate) const RUNTIME_CONFIG_ENTRY: u8 = 2;
+/// Set in `ProposalContext::flags` when the entry data is the labels of a peer
+/// applied by raftify itself instead of the state machine.
+pub(crate) const PEER_LABELS_ENTRY: u8 = 4;
+/// Set in `ProposalContext::flags` when the entry data is a step of moving a key range
+/// between groups, applied by raftify itself along with the state machine.
+pub(crate) const SHARD_ENTRY: u8 = 8;
+
+/// Identifies a proposal within a client session.
+///
+/// The client picks a `session_id` unique in the cluster and increments `sequence`
+/// for every new command. A retried proposal must reuse the same sequence number,
+/// so that the cluster can detect it and skip applying it twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalSession {
+    pub session_id: u64,
+    pub sequence: u64,
+}
+
+impl ProposeArgs {
+    pub(crate) fn new(msg: impl Into<Bytes>, session: Option<ProposalSession>) -> Self {
+        let (session_id, sequence) = session.map_or((0, 0), |s| (s.session_id, s.sequence));
+        Self {
+            msg: msg.into(),
+            session_id,
+            sequence,
+            batch: vec![],
+        }
+    }
+
+    pub(crate) fn batch(proposals: Vec<Bytes>) -> Self {
+        Self {
+            batch: proposals,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the entries to propose, the batch if any and `msg` otherwise.
+    pub(crate) fn proposals(&self) -> Vec<Bytes> {
+        if self.batch.is_empty() {
+            vec![self.msg.clone()]
+        } else {
+            self.batch.clone()
+        }
+    }
+
+    pub(crate) fn session(&self) -> Option<ProposalSession> {
+        (self.session_id != 0).then_some(ProposalSession {
+            session_id: self.session_id,
+            sequence: self.sequence,
+        })
+    }
+}
+
+/// Context attached to normal entries proposed through `RaftNode`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ProposalContext {
+    pub response_seq: u64,
+    pub session: Option<ProposalSession>,
+    pub trace_context: TraceContext,
+    // Appended after the serialized fields only when not zero,
+    // so that contexts written by older versions decode with no flags set.
+    #[serde(skip)]
+    pub flags: u8,
+}
+
+impl ProposalContext {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = serialize(self)?;
+        if self.flags != 0 {
+            bytes.push(self.flags);
+        }
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        // Entries written by older versions only carry the response sequence.
+        if bytes.len() == size_of::<u64>() {
+            return Ok(Self {
+                response_seq: deserialize(bytes)?,
+                session: None,
+                trace_context: TraceContext::default(),
+                flags: 0,
+            });
+        }
+        let mut context: Self = deserialize(bytes)?;
+        let size = serialized_size(&context)? as usize;
+        context.flags = bytes.get(size).copied().unwrap_or(0);
+        Ok(context)
+    }
+
+    /// Returns the entry data, decompressed if the entry is compressed.
+    pub fn entry_data(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if self.flags & ENTRY_COMPRESSED == 0 {
+            return Ok(data.to_vec());
+        }
+        lz4_flex::decompress_size_prepended(data).map_err(|e| {
+            Error::DecodingError(format!("Failed to decompress the entry data: {}", e))
+        })
+    }
+}
+
+/// Compresses `data` if it is larger than `threshold`, setting the flag in `context`.
+pub(crate) fn compress_entry_data(
+    context: &mut ProposalContext,
+    data: Vec<u8>,
+    threshold: Option<usize>,
+) -> Vec<u8> {
+    match threshold {
+        Some(threshold) if data.len() > threshold => {
+            context.flags |= ENTRY_COMPRESSED;
+            lz4_flex::compress_prepend_size(&data)
+        }
+        _ => data,
+    }
+}
+
+/// Ends the snapshot data carrying the session table after the data of the state machine.
+const SESSIONS_TRAILER_MAGIC: &[u8; 8] = b"RFYSESS1";
+
+/// Session as carried by the snapshots: id, last applied sequence, and the index, term and data of its outcome.
+pub(crate) type SessionRecord = (u64, u64, u64, u64, Vec<u8>);
+
+/// Last applied sequence number and its outcome for each client session.
+///
+/// Every replica must detect the same duplicates, so the table travels with the snapshots, see
+/// `append_sessions`, and is rebuilt from the entries applied since. It keeps at most `capacity` sessions,
+/// evicting the one applied the longest ago, which every replica does at the same entry.
+#[derive(Debug)]
+pub(crate) struct SessionTable {
+    capacity: usize,
+    sessions: HashMap<u64, (u64, ProposeOutcome)>,
+    // Session ids by the index of their last applied entry, the oldest first.
+    by_index: BTreeMap<u64, u64>,
+}
+
+pub(crate) enum SessionCheck {
+    /// The proposal has not been applied yet.
+    Fresh,
+    /// The proposal is a retry of the last applied one.
+    Duplicate(ProposeOutcome),
+    /// The proposal is older than the last applied one in the session.
+    Stale,
+}
+
+impl SessionTable {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            sessions: HashMap::new(),
+            by_index: BTreeMap::new(),
+        }
+    }
+
+    pub fn check(&self, session: &ProposalSession) -> SessionCheck {
+        match self.sessions.get(&session.session_id) {
+            Some((last, outcome)) if *last == session.sequence => {
+                SessionCheck::Duplicate(outcome.clone())
+            }
+            Some((last, _)) if *last > session.sequence => SessionCheck::Stale,
+            _ => SessionCheck::Fresh,
+        }
+    }
+
+    pub fn record(&mut self, session: &ProposalSession, outcome: ProposeOutcome) {
+        let index = outcome.index;
+        if let Some((_, previous)) = self
+            .sessions
+            .insert(session.session_id, (session.sequence, outcome))
+        {
+            self.by_index.remove(&previous.index);
+        }
+        self.by_index.insert(index, session.session_id);
+
+        while self.sessions.len() > self.capacity {
+            let Some((_, session_id)) = self.by_index.pop_first() else {
+                break;
+            };
+            self.sessions.remove(&session_id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Appends the table to `data`, the snapshot of the state machine. See `split_sessions`.
+    pub fn append_to(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        let records: Vec<SessionRecord> = self
+            .by_index
+            .values()
+            .map(|session_id| {
+                let (sequence, outcome) = &self.sessions[session_id];
+                (
+                    *session_id,
+                    *sequence,
+                    outcome.index,
+                    outcome.term,
+                    outcome.data.clone(),
+                )
+            })
+            .collect();
+        let encoded = serialize(&records)?;
+        data.extend_from_slice(&encoded);
+        data.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+        data.extend_from_slice(SESSIONS_TRAILER_MAGIC);
+        Ok(data)
+    }
+
+    /// Replaces the table with the sessions `split_sessions` took off a snapshot.
+    pub fn restore(&mut self, records: Vec<SessionRecord>) {
+        self.sessions.clear();
+        self.by_index.clear();
+        // The records are ordered by index, so that the oldest are evicted if the capacity shrank.
+        for (session_id, sequence, index, term, data) in records {
+            self.record(
+                &ProposalSession {
+                    session_id,
+                    sequence,
+                },
+                ProposeOutcome { index, term, data },
+            );
+        }
+    }
+}
+
+/// Splits the snapshot data into the snapshot of the state machine and the sessions appended by
+/// `SessionTable::append_to`. The snapshots taken by older versions carry no sessions.
+pub(crate) fn split_sessions(mut data: Vec<u8>) -> Result<(Vec<u8>, Option<Vec<SessionRecord>>)> {
+    let footer = size_of::<u64>() + SESSIONS_TRAILER_MAGIC.len();
+    if data.len() < footer || !data.ends_with(SESSIONS_TRAILER_MAGIC) {
+        return Ok((data, None));
+    }
+    let len_start = data.len() - footer;
+    let len = u64::from_le_bytes(
+        data[len_start..len_start + size_of::<u64>()]
+            .try_into()
+            .unwrap(),
+    );
+    let Some(start) = len_start.checked_sub(len as usize) else {
+        return Err(Error::DecodingError(
+            "The sessions of the snapshot are truncated".to_owned(),
+        ));
+    };
+    let records = deserialize(&data[start..len_start])?;
+    data.truncate(start);
+    Ok((data, Some(records)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_table_detects_duplicates() {
+        let mut table = SessionTable::new(16);
+        let first = ProposalSession {
+            session_id: 7,
+            sequence: 1,
+        };
+        let second = ProposalSession {
+            session_id: 7,
+            sequence: 2,
+        };
+        assert!(matches!(table.check(&first), SessionCheck::Fresh));
+
+        let outcome = ProposeOutcome {
+            index: 5,
+            term: 1,
+            data: vec![1],
+        };
+        table.record(&first, outcome.clone());
+        assert!(matches!(table.check(&first), SessionCheck::Duplicate(o) if o == outcome));
+        assert!(matches!(table.check(&second), SessionCheck::Fresh));
+
+        table.record(&second, outcome);
+        assert!(matches!(table.check(&first), SessionCheck::Stale));
+    }
+
+    #[test]
+    fn test_session_table_travels_with_the_snapshot() {
+        let mut table = SessionTable::new(2);
+        for (session_id, index) in [(1, 10), (2, 11), (3, 12)] {
+            table.record(
+                &ProposalSession {
+                    session_id,
+                    sequence: 1,
+                },
+                ProposeOutcome {
+                    index,
+                    term: 1,
+                    data: vec![session_id as u8],
+                },
+            );
+        }
+        // The session applied the longest ago is evicted.
+        assert_eq!(table.len(), 2);
+        let first = ProposalSession {
+            session_id: 1,
+            sequence: 1,
+        };
+        assert!(matches!(table.check(&first), SessionCheck::Fresh));
+
+        let data = table.append_to(vec![1, 2, 3]).unwrap();
+        let (fsm_data, records) = split_sessions(data).unwrap();
+        assert_eq!(fsm_data, vec![1, 2, 3]);
+
+        let mut restored = SessionTable::new(2);
+        restored.restore(records.unwrap());
+        let third = ProposalSession {
+            session_id: 3,
+            sequence: 1,
+        };
+        assert!(matches!(restored.check(&third), SessionCheck::Duplicate(o) if o.index == 12));
+
+        // The snapshots of older versions carry no sessions.
+        let (fsm_data, records) = split_sessions(vec![1, 2, 3]).unwrap();
+        assert_eq!(fsm_data, vec![1, 2, 3]);
+        assert!(records.is_none());
+    }
+
+    #[test]
+    fn test_proposal_context_decodes_legacy_context() {
+        let legacy = serialize(&42u64).unwrap();
+        let context = ProposalContext::decode(&legacy).unwrap();
+        assert_eq!(context.response_seq, 42);
+        assert!(context.session.is_none());
+
+        let context = ProposalContext {
+            response_seq: 3,
+            session: None,
+            trace_context: TraceContext::default(),
+            flags: 0,
+        };
+        let decoded = ProposalContext::decode(&context.encode().unwrap()).unwrap();
+        assert_eq!(decoded.response_seq, 3);
+        assert!(decoded.session.is_none());
+        assert_eq!(decoded.flags, 0);
+    }
+
+    #[test]
+    fn test_compressed_entry_data_roundtrip() {
+        let mut context = ProposalContext {
+            response_seq: 3,
+            session: None,
+            trace_context: TraceContext::default(),
+            flags: 0,
+        };
+        let data = vec![7; 1024];
+
+        let small = compress_entry_data(&mut context, vec![7; 16], Some(64));
+        assert_eq!(small, vec![7; 16]);
+        assert_eq!(context.flags, 0);
+
+        let compressed = compress_entry_data(&mut context, data.clone(), Some(64));
+        assert!(compressed.len() < data.len());
+
+        let decoded = ProposalContext::decode(&context.encode().unwrap()).unwrap();
+        assert_eq!(decoded.flags, ENTRY_COMPRESSED);
+        assert_eq!(decoded.entry_data(&compressed).unwrap(), data);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Keys from `start`, included, to `end`, excluded, or to the last key when `end` is `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRange {
+    pub start: Vec<u8>,
+    pub end: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    pub fn new(start: impl Into<Vec<u8>>, end: Option<Vec<u8>>) -> Self {
+        Self {
+            start: start.into(),
+            end,
+        }
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        key >= self.start.as_slice() && self.end.as_deref().map_or(true, |end| key < end)
+    }
+}
+
+/// Steps of moving a key range between two groups, applied by raftify itself instead of the state machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum ShardCommand {
+    /// Takes the state of the range, and rejects the entries writing a key of the range from then on.
+    Fence(KeyRange),
+    /// Lifts the fence of a range which didn't move.
+    Unfence(KeyRange),
+    /// Adds the state of a range moved from another group.
+    RestoreRange(Vec<u8>),
+    /// Drops the range moved to another group, and lifts its fence.
+    RemoveRange(KeyRange),
+}
+
+impl ShardCommand {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ShardCommand::Fence(_) => "Fence",
+            ShardCommand::Unfence(_) => "Unfence",
+            ShardCommand::RestoreRange(_) => "RestoreRange",
+            ShardCommand::RemoveRange(_) => "RemoveRange",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_range_contains() {
+        let range = KeyRange::new(b"b".to_vec(), Some(b"m".to_vec()));
+        assert!(!range.contains(b"a"));
+        assert!(range.contains(b"b"));
+        assert!(range.contains(b"lzz"));
+        assert!(!range.contains(b"m"));
+
+        let range = KeyRange::new(b"m".to_vec(), None);
+        assert!(range.contains(b"zzz"));
+    }
+}
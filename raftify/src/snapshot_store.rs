@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{raft::eraftpb::Message as RaftMessage, Error, Result};
+
+/// Marks the context of a snapshot message whose data is a `SnapshotReference`.
+const SNAPSHOT_REFERENCE_CONTEXT: &[u8] = b"raftify-snapshot-ref";
+
+/// Object store holding the snapshots, e.g. S3 or GCS.
+///
+/// When `Config::snapshot_store` is set, the leader uploads the snapshot before sending it,
+/// and the snapshot message only carries the returned URL and a checksum.
+/// The followers download the snapshot from the URL before restoring it.
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    /// Uploads the snapshot under `key` and returns the URL to download it from.
+    async fn upload(&self, key: &str, data: Vec<u8>) -> Result<String>;
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+impl std::fmt::Debug for dyn SnapshotStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SnapshotStore")
+    }
+}
+
+/// `SnapshotStore` on a directory shared by the nodes, e.g. a network file system mount.
+#[derive(Debug, Clone)]
+pub struct FileSnapshotStore {
+    dir: PathBuf,
+}
+
+impl FileSnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for FileSnapshotStore {
+    async fn upload(&self, key: &str, data: Vec<u8>) -> Result<String> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, data).await?;
+        Ok(format!("file://{}", path.display()))
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let path = url
+            .strip_prefix("file://")
+            .ok_or_else(|| Error::Rejected(format!("Unsupported snapshot URL: {}", url)))?;
+        Ok(tokio::fs::read(path).await?)
+    }
+}
+
+/// Location of a snapshot uploaded to the `SnapshotStore`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SnapshotReference {
+    pub url: String,
+    // CRC32 of the snapshot data
+    pub checksum: u32,
+}
+
+/// Uploads the snapshot data in `message` and replaces it with a reference to the upload.
+pub(crate) async fn upload_snapshot(
+    store: &dyn SnapshotStore,
+    cluster_id: &str,
+    message: &mut RaftMessage,
+) -> Result<()> {
+    let snapshot = message.mut_snapshot();
+    let metadata = snapshot.get_metadata();
+    let key = format!(
+        "{}/snapshot-{}-{}",
+        cluster_id, metadata.index, metadata.term
+    );
+
+    let data = std::mem::take(&mut snapshot.data);
+    let checksum = crc32fast::hash(&data);
+    let url = store.upload(&key, data).await?;
+
+    snapshot.data = bincode::serialize(&SnapshotReference { url, checksum })?;
+    message.set_context(SNAPSHOT_REFERENCE_CONTEXT.to_vec());
+    Ok(())
+}
+
+/// Downloads the snapshot referenced by `message`, if any, and restores its data.
+pub(crate) async fn download_snapshot(
+    store: Option<&dyn SnapshotStore>,
+    message: &mut RaftMessage,
+) -> Result<()> {
+    if message.get_context() != SNAPSHOT_REFERENCE_CONTEXT {
+        return Ok(());
+    }
+    let store = store.ok_or_else(|| {
+        Error::Rejected("Received a snapshot reference without snapshot_store".to_owned())
+    })?;
+
+    let reference: SnapshotReference = bincode::deserialize(message.get_snapshot().get_data())?;
+    let data = store.download(&reference.url).await?;
+    if crc32fast::hash(&data) != reference.checksum {
+        return Err(Error::Rejected(format!(
+            "Snapshot checksum mismatch: {}",
+            reference.url
+        )));
+    }
+
+    message.mut_snapshot().data = data;
+    message.set_context(vec![]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_message(data: Vec<u8>) -> RaftMessage {
+        let mut message = RaftMessage::default();
+        message.mut_snapshot().data = data;
+        message.mut_snapshot().mut_metadata().index = 10;
+        message.mut_snapshot().mut_metadata().term = 2;
+        message
+    }
+
+    #[tokio::test]
+    async fn test_upload_and_download_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSnapshotStore::new(dir.path());
+        let original = snapshot_message(vec![7; 1024]);
+
+        let mut message = original.clone();
+        upload_snapshot(&store, "test", &mut message).await.unwrap();
+        assert!(dir.path().join("test/snapshot-10-2").exists());
+        assert_ne!(
+            message.get_snapshot().get_data(),
+            original.get_snapshot().get_data()
+        );
+
+        download_snapshot(Some(&store), &mut message).await.unwrap();
+        assert_eq!(message, original);
+    }
+
+    #[tokio::test]
+    async fn test_reject_corrupted_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSnapshotStore::new(dir.path());
+
+        let mut message = snapshot_message(vec![7; 1024]);
+        upload_snapshot(&store, "test", &mut message).await.unwrap();
+        std::fs::write(dir.path().join("test/snapshot-10-2"), vec![0; 1024]).unwrap();
+
+        assert!(download_snapshot(Some(&store), &mut message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_skip_full_snapshot() {
+        let original = snapshot_message(vec![7; 16]);
+        let mut message = original.clone();
+        download_snapshot(None, &mut message).await.unwrap();
+        assert_eq!(message, original);
+    }
+}
@@ -0,0 +1,298 @@
+use prost::Message as PMessage;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{raft::eraftpb::Message as RaftMessage, raft_service::SnapshotChunk, Error, Result};
+
+/// Splits the snapshot carried by `message` into chunks of at most `chunk_size` bytes,
+/// skipping the data before `start_offset` which the receiver already has.
+/// Only the first chunk carries the message itself, with its snapshot data left empty.
+pub(crate) fn split_snapshot(
+    mut message: RaftMessage,
+    chunk_size: usize,
+    start_offset: u64,
+) -> Vec<SnapshotChunk> {
+    let data = std::mem::take(&mut message.mut_snapshot().data);
+    let start_offset = start_offset.min(data.len() as u64);
+    let mut message = Some(message);
+
+    let mut chunks = data[start_offset as usize..]
+        .chunks(chunk_size.max(1))
+        .scan(start_offset, |offset, data| {
+            let chunk = SnapshotChunk {
+                message: message.take(),
+                offset: *offset,
+                checksum: crc32fast::hash(data),
+                data: data.to_vec(),
+                last: false,
+            };
+            *offset += data.len() as u64;
+            Some(chunk)
+        })
+        .collect::<Vec<_>>();
+
+    // An empty snapshot (or a fully transferred one) is still sent as a single chunk.
+    if chunks.is_empty() {
+        chunks.push(SnapshotChunk {
+            message,
+            offset: start_offset,
+            checksum: crc32fast::hash(&[]),
+            ..Default::default()
+        });
+    }
+
+    chunks.last_mut().unwrap().last = true;
+    chunks
+}
+
+/// Chunks of a snapshot received from a single peer, persisted on disk
+/// so that an interrupted transfer can be resumed from the last received chunk.
+pub(crate) struct PartialSnapshot {
+    message_path: PathBuf,
+    data_path: PathBuf,
+}
+
+impl PartialSnapshot {
+    pub fn new(dir: &Path, to: u64, from: u64) -> Self {
+        Self {
+            message_path: dir.join(format!("snapshot-{}-{}.msg", to, from)),
+            data_path: dir.join(format!("snapshot-{}-{}.data", to, from)),
+        }
+    }
+
+    /// Returns the offset to resume the snapshot at `index` and `term` from.
+    /// Returns 0 if there is no partially received data for that snapshot.
+    pub fn offset(&self, index: u64, term: u64) -> u64 {
+        match self.load_message() {
+            Ok(Some(message))
+                if message.get_snapshot().get_metadata().index == index
+                    && message.get_snapshot().get_metadata().term == term =>
+            {
+                fs::metadata(&self.data_path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    fn load_message(&self) -> Result<Option<RaftMessage>> {
+        if !self.message_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(RaftMessage::decode(
+            fs::read(&self.message_path)?.as_slice(),
+        )?))
+    }
+
+    fn load_data(&self) -> Result<Vec<u8>> {
+        Ok(fs::read(&self.data_path)?)
+    }
+
+    /// Starts a new transfer, discarding the previously received chunks.
+    fn begin(&self, message: &RaftMessage) -> Result<()> {
+        if let Some(dir) = self.message_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.data_path, [])?;
+        fs::write(&self.message_path, message.encode_to_vec())?;
+        Ok(())
+    }
+
+    fn append(&self, data: &[u8]) -> Result<()> {
+        let mut file = OpenOptions::new().append(true).open(&self.data_path)?;
+        file.write_all(data)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        for path in [&self.message_path, &self.data_path] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reassembles the chunks produced by `split_snapshot` on the receiving node.
+#[derive(Default)]
+pub(crate) struct SnapshotAssembler {
+    message: Option<RaftMessage>,
+    data: Vec<u8>,
+    partial: Option<PartialSnapshot>,
+}
+
+impl SnapshotAssembler {
+    /// Creates an assembler which persists the received chunks to `partial`.
+    pub fn with_partial(partial: PartialSnapshot) -> Self {
+        Self {
+            partial: Some(partial),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the snapshot message with its data restored once the last chunk is pushed.
+    pub fn push(&mut self, chunk: SnapshotChunk) -> Result<Option<RaftMessage>> {
+        if let Some(message) = chunk.message.as_ref() {
+            self.start(message, chunk.offset)?;
+        }
+
+        if chunk.offset != self.data.len() as u64 {
+            return Err(Error::Rejected(format!(
+                "Unexpected snapshot chunk offset (expected={}, actual={})",
+                self.data.len(),
+                chunk.offset
+            )));
+        }
+
+        if crc32fast::hash(&chunk.data) != chunk.checksum {
+            return Err(Error::Rejected(format!(
+                "Snapshot chunk checksum mismatch at offset {}",
+                chunk.offset
+            )));
+        }
+
+        if let Some(partial) = self.partial.as_ref() {
+            partial.append(&chunk.data)?;
+        }
+        self.data.extend_from_slice(&chunk.data);
+
+        if !chunk.last {
+            return Ok(None);
+        }
+
+        let mut message = self.message.take().ok_or_else(|| {
+            Error::Rejected("Snapshot stream doesn't carry the snapshot message".to_owned())
+        })?;
+        message.mut_snapshot().data = std::mem::take(&mut self.data);
+
+        if let Some(partial) = self.partial.as_ref() {
+            partial.clear()?;
+        }
+        Ok(Some(message))
+    }
+
+    /// Starts a new transfer from the beginning, or resumes the persisted one
+    /// if the stream starts at a non-zero offset.
+    fn start(&mut self, message: &RaftMessage, offset: u64) -> Result<()> {
+        self.message = Some(message.clone());
+        self.data.clear();
+
+        let Some(partial) = self.partial.as_ref() else {
+            return Ok(());
+        };
+
+        if offset == 0 {
+            return partial.begin(message);
+        }
+
+        let metadata = message.get_snapshot().get_metadata();
+        if partial.offset(metadata.index, metadata.term) != offset {
+            return Err(Error::Rejected(format!(
+                "No partially received snapshot to resume from offset {}",
+                offset
+            )));
+        }
+        self.data = partial.load_data()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raft::eraftpb::MessageType;
+
+    fn snapshot_message(data: Vec<u8>) -> RaftMessage {
+        let mut message = RaftMessage::default();
+        message.set_msg_type(MessageType::MsgSnapshot);
+        message.from = 1;
+        message.to = 2;
+        message.mut_snapshot().data = data;
+        message.mut_snapshot().mut_metadata().index = 10;
+        message.mut_snapshot().mut_metadata().term = 2;
+        message
+    }
+
+    fn reassemble(chunks: Vec<SnapshotChunk>) -> Result<Option<RaftMessage>> {
+        let mut assembler = SnapshotAssembler::default();
+        let mut result = None;
+        for chunk in chunks {
+            result = assembler.push(chunk)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_split_and_reassemble_snapshot() {
+        let message = snapshot_message((0..=255).collect());
+        let chunks = split_snapshot(message.clone(), 100, 0);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks.iter().map(|c| c.offset).collect::<Vec<_>>(),
+            vec![0, 100, 200]
+        );
+        assert!(chunks[0].message.is_some());
+        assert!(chunks[1].message.is_none());
+        assert!(chunks[2].last);
+
+        assert_eq!(reassemble(chunks).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn test_split_empty_snapshot() {
+        let message = snapshot_message(vec![]);
+        let chunks = split_snapshot(message.clone(), 100, 0);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(reassemble(chunks).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn test_reject_corrupted_chunk() {
+        let mut chunks = split_snapshot(snapshot_message(vec![1; 10]), 4, 0);
+        chunks[1].data[0] = 0;
+        assert!(reassemble(chunks).is_err());
+    }
+
+    #[test]
+    fn test_reject_missing_chunk() {
+        let mut chunks = split_snapshot(snapshot_message(vec![1; 10]), 4, 0);
+        chunks.remove(1);
+        assert!(reassemble(chunks).is_err());
+    }
+
+    #[test]
+    fn test_resume_interrupted_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let message = snapshot_message((0..=255).collect());
+
+        // The transfer is interrupted after the first two chunks.
+        let mut assembler = SnapshotAssembler::with_partial(PartialSnapshot::new(dir.path(), 2, 1));
+        for chunk in split_snapshot(message.clone(), 100, 0).into_iter().take(2) {
+            assert_eq!(assembler.push(chunk).unwrap(), None);
+        }
+
+        let partial = PartialSnapshot::new(dir.path(), 2, 1);
+        assert_eq!(partial.offset(10, 2), 200);
+        assert_eq!(partial.offset(11, 2), 0);
+
+        let chunks = split_snapshot(message.clone(), 100, 200);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].message.is_some());
+
+        let mut assembler = SnapshotAssembler::with_partial(partial);
+        let mut result = None;
+        for chunk in chunks {
+            result = assembler.push(chunk).unwrap();
+        }
+        assert_eq!(result, Some(message));
+        assert_eq!(PartialSnapshot::new(dir.path(), 2, 1).offset(10, 2), 0);
+    }
+}
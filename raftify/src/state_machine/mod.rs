@@ -1,15 +1,145 @@
+use bincode::{deserialize, serialize};
+use serde::{de::DeserializeOwned, Serialize};
 use tonic::async_trait;
 
-use crate::Result;
+use crate::{raft_service, Error, KeyRange, Result};
 
+/// The entries are applied by a clone of the state machine running on a task of its own,
+/// so the clones of a state machine must share its state.
 #[async_trait]
 pub trait AbstractStateMachine: Clone + Send + Sync {
     async fn apply(&mut self, log_entry: Vec<u8>) -> Result<Vec<u8>>;
+
+    /// Applies the consecutive committed entries of a ready at once, returning the result of each entry in order.
+    /// Override it to share a lock or a transaction between the entries; they are applied one by one by default.
+    async fn apply_batch(&mut self, log_entries: Vec<Vec<u8>>) -> Vec<Result<Vec<u8>>> {
+        let mut results = Vec::with_capacity(log_entries.len());
+        for log_entry in log_entries {
+            results.push(self.apply(log_entry).await);
+        }
+        results
+    }
+
+    /// Applies the consecutive committed entries of a ready along with their index, which raftify calls instead
+    /// of `apply_batch`. Override it to commit the entries and the last index, see `applied_index`,
+    /// in the same transaction. Calls `apply_batch`, then `set_applied_index` with the last index, by default.
+    async fn apply_indexed_batch(
+        &mut self,
+        log_entries: Vec<(u64, Vec<u8>)>,
+    ) -> Vec<Result<Vec<u8>>> {
+        let last_index = log_entries.last().map(|(index, _)| *index);
+        let results = self
+            .apply_batch(log_entries.into_iter().map(|(_, data)| data).collect())
+            .await;
+        if let Some(last_index) = last_index {
+            self.set_applied_index(last_index);
+        }
+        results
+    }
+
     async fn snapshot(&self) -> Result<Vec<u8>>;
     async fn restore(&mut self, snapshot: Vec<u8>) -> Result<()>;
 
+    /// Called after the entries up to `index` are applied or a snapshot at `index` is restored.
+    /// State machines supporting delta snapshots track their changes by this index.
+    fn set_applied_index(&mut self, _index: u64) {}
+
+    /// Returns the index of the last entry applied to the state machine, if it persists its state.
+    /// Store it atomically with the state, by overriding `apply_indexed_batch` to commit the entries
+    /// and their last index in the same transaction. A restarted node resumes applying after it,
+    /// instead of re-applying the entries since the snapshot. `None`, by default, for the state machines starting empty.
+    fn applied_index(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns the changes made after `since_index`, or `None` if delta snapshots are not supported.
+    /// Followers only slightly behind receive this delta instead of the full snapshot.
+    async fn snapshot_delta(&self, _since_index: u64) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Applies a delta produced by `snapshot_delta` on top of the current state.
+    async fn restore_delta(&mut self, _delta: Vec<u8>) -> Result<()> {
+        Err(Error::Rejected(
+            "Delta snapshots are not supported by the state machine".to_owned(),
+        ))
+    }
+
+    /// Returns the key written by `log_entry`, if any. The groups reject the entries writing a key
+    /// of a range fenced while it moves to another group, see `Router::move_range`.
+    fn key_of(&self, _log_entry: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Returns the state of the keys in `range`, restored in another group by `restore_range`.
+    async fn snapshot_range(&self, _range: &KeyRange) -> Result<Vec<u8>> {
+        Err(Error::Rejected(
+            "Moving key ranges is not supported by the state machine".to_owned(),
+        ))
+    }
+
+    /// Adds the state produced by `snapshot_range` in another group to the current state.
+    async fn restore_range(&mut self, _snapshot: Vec<u8>) -> Result<()> {
+        Err(Error::Rejected(
+            "Moving key ranges is not supported by the state machine".to_owned(),
+        ))
+    }
+
+    /// Removes the keys in `range` once they moved to another group.
+    async fn remove_range(&mut self, _range: &KeyRange) -> Result<()> {
+        Err(Error::Rejected(
+            "Moving key ranges is not supported by the state machine".to_owned(),
+        ))
+    }
+
+    /// Answers an encoded read-only query, served by the `Query` RPC.
+    /// State machines implementing `AbstractStateMachineQuery` return `self.answer_encoded(query)`.
+    async fn query_encoded(&self, _query: &[u8]) -> Result<Vec<u8>> {
+        Err(Error::Rejected(
+            "Queries are not supported by the state machine".to_owned(),
+        ))
+    }
+
     fn encode(&self) -> Result<Vec<u8>>;
     fn decode(bytes: &[u8]) -> Result<Self>
     where
         Self: Sized;
 }
+
+/// Read-only queries of a state machine, answered without going through the log.
+/// See `RaftNode::query` for the consistency they can be answered with.
+#[async_trait]
+pub trait AbstractStateMachineQuery: AbstractStateMachine {
+    type Query: Serialize + DeserializeOwned + Send + Sync;
+    type QueryResponse: Serialize + DeserializeOwned + Send + Sync;
+
+    async fn query(&self, query: Self::Query) -> Result<Self::QueryResponse>;
+
+    /// Decodes the query, answers it and encodes the response, for `AbstractStateMachine::query_encoded`.
+    async fn answer_encoded(&self, query: &[u8]) -> Result<Vec<u8>> {
+        let response = self.query(deserialize(query)?).await?;
+        Ok(serialize(&response)?)
+    }
+}
+
+/// How up to date the state a query is answered from is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryConsistency {
+    /// Answered from the local state, which may lag behind the leader.
+    Stale,
+    /// Answered from the local state once it caught up with the leader's commit index, using ReadIndex.
+    Linearizable,
+    /// Answered from the leader's state, without a ReadIndex round trip.
+    /// A leader not knowing yet that it was deposed may answer from a stale state.
+    Leader,
+}
+
+impl From<QueryConsistency> for raft_service::QueryConsistency {
+    fn from(consistency: QueryConsistency) -> Self {
+        match consistency {
+            QueryConsistency::Stale => raft_service::QueryConsistency::QueryStale,
+            QueryConsistency::Linearizable => raft_service::QueryConsistency::QueryLinearizable,
+            QueryConsistency::Leader => raft_service::QueryConsistency::QueryLeader,
+        }
+    }
+}
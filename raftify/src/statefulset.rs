@@ -0,0 +1,137 @@
+use std::{env, fs};
+
+use crate::{Error, InitialRole, Peers, Result};
+
+const NAMESPACE_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Topology of a raftify cluster deployed as a Kubernetes StatefulSet with a headless service,
+/// from which every pod derives its node id and peers, so that all the pods share the same configuration.
+///
+/// The pod with ordinal `n` becomes node `n + 1`, the pod with ordinal 0 being the initial leader.
+/// The peers are the `replicas` pods, reached at `<pod>.<service>.<namespace>.svc.<cluster_domain>:<port>`.
+#[derive(Debug, Clone)]
+pub struct StatefulSetTopology {
+    /// Name of the headless service governing the StatefulSet.
+    pub service: String,
+    pub replicas: u64,
+    pub port: u16,
+    /// Read from the `POD_NAMESPACE` environment variable or the service account if not set.
+    pub namespace: Option<String>,
+    pub cluster_domain: String,
+}
+
+impl StatefulSetTopology {
+    pub fn new(service: impl Into<String>, replicas: u64, port: u16) -> Self {
+        Self {
+            service: service.into(),
+            replicas,
+            port,
+            namespace: None,
+            cluster_domain: "cluster.local".to_owned(),
+        }
+    }
+
+    /// Returns the node id of the pod this process runs in.
+    pub fn node_id(&self) -> Result<u64> {
+        let (_, ordinal) = self.pod()?;
+        Ok(ordinal + 1)
+    }
+
+    /// Returns the peers of the pod this process runs in.
+    pub fn peers(&self) -> Result<Peers> {
+        let (statefulset, _) = self.pod()?;
+        Ok(self.peers_of(&statefulset, &self.namespace()?))
+    }
+
+    fn peers_of(&self, statefulset: &str, namespace: &str) -> Peers {
+        let mut peers = Peers::with_empty();
+        for ordinal in 0..self.replicas {
+            let addr = format!(
+                "{}-{}.{}.{}.svc.{}:{}",
+                statefulset, ordinal, self.service, namespace, self.cluster_domain, self.port
+            );
+            let role = if ordinal == 0 {
+                InitialRole::Leader
+            } else {
+                InitialRole::Voter
+            };
+            peers.add_peer(ordinal + 1, addr, Some(role));
+        }
+        peers
+    }
+
+    /// Returns the StatefulSet name and the ordinal of the pod this process runs in,
+    /// parsed from its hostname which Kubernetes sets to the pod name.
+    fn pod(&self) -> Result<(String, u64)> {
+        let pod_name = env::var("HOSTNAME")
+            .map_err(|_| Error::ConfigInvalid("HOSTNAME is not set".to_owned()))?;
+        let (statefulset, ordinal) = parse_pod_name(&pod_name)?;
+
+        if ordinal >= self.replicas {
+            return Err(Error::ConfigInvalid(format!(
+                "Pod {} is out of the {} replicas",
+                pod_name, self.replicas
+            )));
+        }
+        Ok((statefulset.to_owned(), ordinal))
+    }
+
+    fn namespace(&self) -> Result<String> {
+        if let Some(namespace) = &self.namespace {
+            return Ok(namespace.clone());
+        }
+        if let Ok(namespace) = env::var("POD_NAMESPACE") {
+            return Ok(namespace);
+        }
+        fs::read_to_string(NAMESPACE_FILE)
+            .map(|namespace| namespace.trim().to_owned())
+            .map_err(|_| {
+                Error::ConfigInvalid(
+                    "The namespace of the pod is unknown. Set POD_NAMESPACE".to_owned(),
+                )
+            })
+    }
+}
+
+/// Splits the name of a StatefulSet pod, `<statefulset>-<ordinal>`.
+fn parse_pod_name(pod_name: &str) -> Result<(&str, u64)> {
+    pod_name
+        .rsplit_once('-')
+        .and_then(|(statefulset, ordinal)| Some((statefulset, ordinal.parse().ok()?)))
+        .filter(|(statefulset, _)| !statefulset.is_empty())
+        .ok_or_else(|| {
+            Error::ConfigInvalid(format!("{} is not the name of a StatefulSet pod", pod_name))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pod_name() {
+        assert_eq!(parse_pod_name("raft-2").unwrap(), ("raft", 2));
+        assert_eq!(parse_pod_name("my-raft-10").unwrap(), ("my-raft", 10));
+        assert!(parse_pod_name("raft").is_err());
+        assert!(parse_pod_name("raft-a").is_err());
+        assert!(parse_pod_name("-1").is_err());
+    }
+
+    #[test]
+    fn test_statefulset_peers() {
+        let topology = StatefulSetTopology::new("raft-headless", 3, 60061);
+        let peers = topology.peers_of("raft", "default");
+
+        assert_eq!(peers.voters(), vec![1, 2, 3]);
+        let leader = peers.get(&1).unwrap();
+        assert_eq!(leader.role, InitialRole::Leader);
+        assert_eq!(
+            leader.host.as_deref(),
+            Some("raft-0.raft-headless.default.svc.cluster.local:60061")
+        );
+        assert_eq!(
+            peers.get(&3).unwrap().host.as_deref(),
+            Some("raft-2.raft-headless.default.svc.cluster.local:60061")
+        );
+    }
+}
@@ -3,7 +3,47 @@ use prost::Message;
 use std::{borrow::Cow, fmt::Write};
 
 use super::constant::ENTRY_KEY_LENGTH;
-use crate::raft::eraftpb::Entry;
+use crate::{error::ChecksumMismatch, raft::eraftpb::Entry};
+
+/// Leads the values persisted with a trailing CRC32C checksum.
+/// Protobuf encoded values written before the checksums were introduced never start with it,
+/// since their first byte is the tag of a field numbered below 16.
+const CHECKSUM_MARKER: u8 = 0xFF;
+const CHECKSUM_SIZE: usize = 4;
+
+/// Wraps `payload` with the checksum marker and its CRC32C checksum.
+pub fn seal_checksum(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(payload.len() + 1 + CHECKSUM_SIZE);
+    bytes.push(CHECKSUM_MARKER);
+    bytes.extend_from_slice(payload);
+    bytes.extend_from_slice(&crc32c::crc32c(payload).to_le_bytes());
+    bytes
+}
+
+/// Returns the payload sealed by `seal_checksum`, verifying its checksum.
+/// Values persisted without a checksum are returned as they are.
+pub fn open_checksum(bytes: &[u8]) -> std::result::Result<&[u8], ChecksumMismatch> {
+    let Some(sealed) = bytes.strip_prefix(&[CHECKSUM_MARKER]) else {
+        return Ok(bytes);
+    };
+    if sealed.len() < CHECKSUM_SIZE {
+        return Err(ChecksumMismatch(format!(
+            "Value is shorter than its checksum ({} bytes)",
+            bytes.len()
+        )));
+    }
+
+    let (payload, checksum) = sealed.split_at(sealed.len() - CHECKSUM_SIZE);
+    let expected = u32::from_le_bytes(checksum.try_into().unwrap());
+    let actual = crc32c::crc32c(payload);
+    if actual != expected {
+        return Err(ChecksumMismatch(format!(
+            "Checksum mismatch (expected={:#010x}, actual={:#010x})",
+            expected, actual
+        )));
+    }
+    Ok(payload)
+}
 
 pub fn format_entry_key_string(entry_key: &str) -> String {
     let entry_key: u64 = entry_key.parse().unwrap();
@@ -42,7 +82,7 @@ impl BytesEncode<'_> for HeedEntry {
     fn bytes_encode(item: &Self::EItem) -> std::result::Result<Cow<'_, [u8]>, BoxedError> {
         let mut bytes = vec![];
         item.encode(&mut bytes)?;
-        Ok(Cow::Owned(bytes))
+        Ok(Cow::Owned(seal_checksum(&bytes)))
     }
 }
 
@@ -50,6 +90,48 @@ impl BytesDecode<'_> for HeedEntry {
     type DItem = Entry;
 
     fn bytes_decode(bytes: &[u8]) -> std::result::Result<Self::DItem, BoxedError> {
-        Ok(Entry::decode(bytes)?)
+        Ok(Entry::decode(open_checksum(bytes)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> Entry {
+        Entry {
+            term: 2,
+            index: 10,
+            data: b"hello".to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let entry = entry();
+        let bytes = HeedEntry::bytes_encode(&entry).unwrap();
+        assert_eq!(HeedEntry::bytes_decode(&bytes).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_decode_entry_without_checksum() {
+        let bytes = entry().encode_to_vec();
+        assert_eq!(HeedEntry::bytes_decode(&bytes).unwrap(), entry());
+    }
+
+    #[test]
+    fn test_reject_corrupted_entry() {
+        let entry = entry();
+        let mut bytes = HeedEntry::bytes_encode(&entry).unwrap().into_owned();
+        bytes[3] ^= 1;
+        assert!(HeedEntry::bytes_decode(&bytes)
+            .unwrap_err()
+            .is::<ChecksumMismatch>());
+
+        // Torn write
+        assert!(HeedEntry::bytes_decode(&bytes[..2])
+            .unwrap_err()
+            .is::<ChecksumMismatch>());
     }
 }
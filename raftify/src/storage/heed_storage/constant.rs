@@ -2,5 +2,12 @@ pub const SNAPSHOT_KEY: &str = "snapshot";
 pub const LAST_INDEX_KEY: &str = "last_index";
 pub const HARD_STATE_KEY: &str = "hard_state";
 pub const CONF_STATE_KEY: &str = "conf_state";
+pub const CLUSTER_UUID_KEY: &str = "cluster_uuid";
+pub const TOMBSTONES_KEY: &str = "tombstones";
+pub const RUNTIME_CONFIG_KEY: &str = "runtime_config";
+pub const PEER_LABELS_KEY: &str = "peer_labels";
+pub const FENCED_RANGES_KEY: &str = "fenced_ranges";
+pub const NODE_IDENTITY_KEY: &str = "node_identity";
+pub const PEERS_KEY: &str = "peers";
 
 pub const ENTRY_KEY_LENGTH: usize = 10;
@@ -0,0 +1,136 @@
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::raft::{eraftpb::Entry, util::limit_size};
+
+/// Recent log entries kept in memory, so that replicating them to the followers which lag
+/// slightly behind doesn't read them back from LMDB.
+/// The cached entries are contiguous, and are updated once the transaction writing them is committed.
+pub(super) struct EntryCache {
+    capacity: usize,
+    entries: Mutex<VecDeque<Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EntryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Caches the appended entries, replacing the cached entries they overwrite.
+    pub fn append(&self, appended: &[Entry]) {
+        if self.capacity == 0 || appended.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        let first_index = appended[0].index;
+        match entries.front().map(|entry| entry.index) {
+            Some(front) if front <= first_index && first_index <= front + entries.len() as u64 => {
+                entries.truncate((first_index - front) as usize);
+            }
+            // Not contiguous with the cached entries, e.g. after a snapshot.
+            _ => entries.clear(),
+        }
+        entries.extend(appended.iter().cloned());
+        let excess = entries.len().saturating_sub(self.capacity);
+        entries.drain(..excess);
+    }
+
+    /// Drops the entries before `index`, which are compacted.
+    pub fn compact(&self, index: u64) {
+        let mut entries = self.entries.lock();
+        while entries.front().map_or(false, |entry| entry.index < index) {
+            entries.pop_front();
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Returns the entries from `low` to `high`, excluded, if they are all cached.
+    pub fn entries(&self, low: u64, high: u64, max_size: Option<u64>) -> Option<Vec<Entry>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let entries = self.entries.lock();
+        let cached = entries
+            .front()
+            .map(|entry| entry.index)
+            .filter(|&front| front <= low && low <= high && high <= front + entries.len() as u64)
+            .map(|front| {
+                let start = (low - front) as usize;
+                let end = (high - front) as usize;
+                entries.range(start..end).cloned().collect::<Vec<_>>()
+            });
+
+        match cached {
+            Some(mut cached) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                limit_size(&mut cached, max_size);
+                Some(cached)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_entry(index: u64, term: u64) -> Entry {
+        Entry {
+            index,
+            term,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entry_cache() {
+        let cache = EntryCache::new(3);
+        cache.append(&[new_entry(1, 1), new_entry(2, 1), new_entry(3, 1)]);
+        cache.append(&[new_entry(3, 2), new_entry(4, 2)]);
+
+        // Entry 1 is evicted, and entry 3 is overwritten.
+        assert!(cache.entries(1, 3, None).is_none());
+        let entries = cache.entries(2, 5, None).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| (e.index, e.term))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (3, 2), (4, 2)]
+        );
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        cache.compact(4);
+        assert!(cache.entries(3, 5, None).is_none());
+        assert_eq!(cache.entries(4, 5, None).unwrap().len(), 1);
+
+        cache.append(&[new_entry(10, 3)]);
+        assert!(cache.entries(4, 5, None).is_none());
+        assert_eq!(cache.entries(10, 11, None).unwrap()[0].term, 3);
+    }
+}
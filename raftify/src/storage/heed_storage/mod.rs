@@ -1,28 +1,81 @@
 mod codec;
 mod constant;
+mod entry_cache;
+mod write_latency;
 
-use self::codec::{format_entry_key_string, HeedEntry, HeedEntryKeyString};
-use super::{utils::append_compacted_logs, StableStorage, StorageType};
+use self::codec::{
+    format_entry_key_string, open_checksum, seal_checksum, HeedEntry, HeedEntryKeyString,
+};
+use super::{
+    utils::{append_compacted_logs, rotate_compacted_logs},
+    AbstractStableStorage, NodeIdentity, StorageStats, StorageType,
+};
 use crate::{
-    config::Config,
-    error::Result,
+    config::{Config, LmdbSyncMode},
+    error::{Error, Result},
     raft::{self, prelude::*, GetEntriesContext},
+    KeyRange, MembershipChangeRecord, NodeTombstone, PeerLabels, Peers, RuntimeConfig,
 };
 use bincode::{deserialize, serialize};
-use constant::{CONF_STATE_KEY, HARD_STATE_KEY, LAST_INDEX_KEY, SNAPSHOT_KEY};
+use constant::{
+    CLUSTER_UUID_KEY, CONF_STATE_KEY, FENCED_RANGES_KEY, HARD_STATE_KEY, LAST_INDEX_KEY,
+    NODE_IDENTITY_KEY, PEERS_KEY, PEER_LABELS_KEY, RUNTIME_CONFIG_KEY, SNAPSHOT_KEY,
+    TOMBSTONES_KEY,
+};
+use entry_cache::EntryCache;
 use heed::{
     types::{Bytes as HeedBytes, Str as HeedStr},
-    Database, Env,
+    Database, Env, EnvFlags, MdbError,
 };
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use prost::Message as PMessage;
 use raft::{logger::Logger, util::limit_size};
 use std::{
     cmp::max,
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
+use write_latency::WriteLatency;
+
+const MAP_SIZE_ALIGNMENT: usize = 1024 * 1024;
+// Commits whose latency `StorageStats::write_latency_p99_us` is computed from.
+const WRITE_LATENCY_SAMPLES: usize = 1024;
+
+/// Damaged record found by `HeedStorage::scrub`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDamage {
+    /// Index of the damaged log entry. `None` if the damage is in the metadata.
+    pub index: Option<u64>,
+    pub reason: String,
+}
+
+/// Result of `HeedStorage::scrub`.
+#[derive(Debug, Clone, Default)]
+pub struct ScrubReport {
+    pub entry_count: u64,
+    pub damages: Vec<StorageDamage>,
+    /// Index from which the log was truncated when repairing, if it was.
+    pub truncated_from: Option<u64>,
+}
+
+impl ScrubReport {
+    pub fn is_clean(&self) -> bool {
+        self.damages.is_empty()
+    }
+}
+
+/// Result of `HeedStorage::force_new_cluster`.
+#[derive(Debug, Clone, Default)]
+pub struct ForceNewClusterReport {
+    /// Number of the uncommitted entries discarded.
+    pub discarded_entries: u64,
+    /// Number of the committed conf change entries replaced with empty entries.
+    pub replaced_conf_changes: u64,
+    pub last_index: u64,
+}
 
 #[derive(Clone)]
 pub struct HeedStorage(Arc<RwLock<HeedStorageCore>>);
@@ -45,22 +98,46 @@ impl HeedStorage {
     }
 }
 
-impl StableStorage for HeedStorage {
+impl AbstractStableStorage for HeedStorage {
     const STORAGE_TYPE: StorageType = StorageType::Heed;
 
     fn compact(&mut self, index: u64) -> Result<()> {
         let store = self.wl();
-        let mut writer = store.env.write_txn()?;
-        store.compact(&mut writer, index)?;
-        writer.commit()?;
+        store.write(|writer| store.compact(writer, index))?;
+        store.entry_cache.compact(index);
         Ok(())
     }
 
     fn append(&mut self, entries: &[Entry]) -> Result<()> {
         let store = self.wl();
-        let mut writer = store.env.write_txn()?;
-        store.append(&mut writer, entries)?;
-        writer.commit()?;
+        store.write(|writer| store.append(writer, entries))?;
+        store.entry_cache.append(entries);
+        Ok(())
+    }
+
+    fn persist_readies(&mut self, readies: &[(&[Entry], Option<&HardState>)]) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| {
+            for (entries, hard_state) in readies {
+                if !entries.is_empty() {
+                    store.append(writer, entries)?;
+                }
+                if let Some(hard_state) = hard_state {
+                    store.set_hard_state(writer, hard_state)?;
+                }
+            }
+            Ok(())
+        })?;
+        for (entries, _) in readies {
+            store.entry_cache.append(entries);
+        }
+        Ok(())
+    }
+
+    /// Syncs the commits left unsynced by `LmdbSyncMode::NoMetaSync` or `LmdbSyncMode::NoSync`.
+    fn flush(&mut self) -> Result<()> {
+        let store = self.wl();
+        store.env.force_sync()?;
         Ok(())
     }
 
@@ -73,20 +150,17 @@ impl StableStorage for HeedStorage {
 
     fn set_hard_state(&mut self, hard_state: &HardState) -> Result<()> {
         let store = self.wl();
-        let mut writer = store.env.write_txn()?;
-        store.set_hard_state(&mut writer, hard_state)?;
-        writer.commit()?;
+        store.write(|writer| store.set_hard_state(writer, hard_state))?;
         Ok(())
     }
 
     fn set_hard_state_commit(&mut self, commit: u64) -> Result<()> {
         let store = self.wl();
-        let reader = store.env.read_txn()?;
-        let mut hard_state = store.hard_state(&reader)?;
-        hard_state.set_commit(commit);
-        let mut writer = store.env.write_txn()?;
-        store.set_hard_state(&mut writer, &hard_state)?;
-        Ok(())
+        store.write(|writer| {
+            let mut hard_state = store.hard_state(writer)?;
+            hard_state.set_commit(commit);
+            store.set_hard_state(writer, &hard_state)
+        })
     }
 
     fn conf_state(&self) -> Result<ConfState> {
@@ -98,33 +172,28 @@ impl StableStorage for HeedStorage {
 
     fn set_conf_state(&mut self, conf_state: &ConfState) -> Result<()> {
         let store = self.wl();
-        let mut writer = store.env.write_txn()?;
-        store.set_conf_state(&mut writer, conf_state)?;
-        writer.commit()?;
+        store.write(|writer| store.set_conf_state(writer, conf_state))?;
         Ok(())
     }
 
     fn create_snapshot(&mut self, data: Vec<u8>, index: u64, term: u64) -> Result<()> {
-        let store = self.wl();
-        let mut writer = store.env.write_txn()?;
-        let conf_state = store.conf_state(&writer)?;
-
         let mut snapshot = Snapshot::default();
         snapshot.set_data(data);
 
         let meta = snapshot.mut_metadata();
-        meta.set_conf_state(conf_state);
         meta.index = index;
         meta.term = term;
 
-        store.set_snapshot(&mut writer, &snapshot)?;
-        writer.commit()?;
-        Ok(())
+        let store = self.wl();
+        store.write(|writer| {
+            let conf_state = store.conf_state(writer)?;
+            snapshot.mut_metadata().set_conf_state(conf_state);
+            store.set_snapshot(writer, &snapshot)
+        })
     }
 
     fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<()> {
         let store = self.wl();
-        let mut writer = store.env.write_txn()?;
         let metadata = snapshot.get_metadata();
         let conf_state = metadata.get_conf_state();
 
@@ -136,15 +205,17 @@ impl StableStorage for HeedStorage {
         //     ));
         // }
 
-        let mut hard_state = store.hard_state(&writer)?;
-        hard_state.set_term(max(hard_state.term, metadata.term));
-        hard_state.set_commit(metadata.index);
-
-        store.set_hard_state(&mut writer, &hard_state)?;
-        store.set_conf_state(&mut writer, conf_state)?;
-        store.set_last_index(&mut writer, metadata.index)?;
-        store.set_snapshot(&mut writer, &snapshot)?;
-        writer.commit()?;
+        store.write(|writer| {
+            let mut hard_state = store.hard_state(writer)?;
+            hard_state.set_term(max(hard_state.term, metadata.term));
+            hard_state.set_commit(metadata.index);
+
+            store.set_hard_state(writer, &hard_state)?;
+            store.set_conf_state(writer, conf_state)?;
+            store.set_last_index(writer, metadata.index)?;
+            store.set_snapshot(writer, &snapshot)
+        })?;
+        store.entry_cache.clear();
         Ok(())
     }
 
@@ -157,6 +228,108 @@ impl StableStorage for HeedStorage {
             .map_err(|e| raft::Error::Store(raft::StorageError::Other(e.into())))?;
         Ok(entries)
     }
+
+    fn append_membership_change(&mut self, record: &MembershipChangeRecord) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.append_membership_change(writer, record))?;
+        Ok(())
+    }
+
+    fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.membership_history(&reader)
+    }
+
+    fn tombstones(&self) -> Result<Vec<NodeTombstone>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.tombstones(&reader)
+    }
+
+    fn set_tombstones(&mut self, tombstones: &[NodeTombstone]) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_tombstones(writer, tombstones))?;
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.stats(&reader)
+    }
+
+    fn cluster_uuid(&self) -> Result<Option<String>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.cluster_uuid(&reader)
+    }
+
+    fn set_cluster_uuid(&mut self, cluster_uuid: &str) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_cluster_uuid(writer, cluster_uuid))?;
+        Ok(())
+    }
+
+    fn runtime_config(&self) -> Result<RuntimeConfig> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.runtime_config(&reader)
+    }
+
+    fn set_runtime_config(&mut self, runtime_config: &RuntimeConfig) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_runtime_config(writer, runtime_config))?;
+        Ok(())
+    }
+
+    fn peer_labels(&self) -> Result<HashMap<u64, PeerLabels>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.peer_labels(&reader)
+    }
+
+    fn set_peer_labels(&mut self, peer_labels: &HashMap<u64, PeerLabels>) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_peer_labels(writer, peer_labels))?;
+        Ok(())
+    }
+
+    fn fenced_ranges(&self) -> Result<Vec<KeyRange>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.fenced_ranges(&reader)
+    }
+
+    fn set_fenced_ranges(&mut self, fenced_ranges: &[KeyRange]) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_fenced_ranges(writer, fenced_ranges))?;
+        Ok(())
+    }
+
+    fn node_identity(&self) -> Result<Option<NodeIdentity>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.node_identity(&reader)
+    }
+
+    fn set_node_identity(&mut self, node_identity: &NodeIdentity) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_node_identity(writer, node_identity))?;
+        Ok(())
+    }
+
+    fn peers(&self) -> Result<Option<Peers>> {
+        let store = self.rl();
+        let reader = store.env.read_txn()?;
+        store.peers(&reader)
+    }
+
+    fn set_peers(&mut self, peers: &Peers) -> Result<()> {
+        let store = self.wl();
+        store.write(|writer| store.set_peers(writer, peers))?;
+        Ok(())
+    }
 }
 
 impl Storage for HeedStorage {
@@ -185,6 +358,10 @@ impl Storage for HeedStorage {
         ctx: GetEntriesContext,
     ) -> raft::Result<Vec<Entry>> {
         let store = self.rl();
+        // The cached entries aren't compacted.
+        if let Some(entries) = store.entry_cache.entries(low, high, max_size) {
+            return Ok(entries);
+        }
         let reader = store.env.read_txn().unwrap();
 
         if low
@@ -261,12 +438,41 @@ impl Storage for HeedStorage {
 }
 
 impl HeedStorage {
+    /// Validates the checksums of the persisted log entries and metadata and the continuity
+    /// of the log indexes.
+    /// If `repair` is set, truncates the log at the first damaged entry
+    /// so that the node can rejoin the cluster and get the rest of the log re-replicated.
+    pub fn scrub(&mut self, repair: bool) -> Result<ScrubReport> {
+        let store = self.wl();
+        let mut writer = store.env.write_txn()?;
+        let report = store.scrub(&mut writer, repair)?;
+        if report.truncated_from.is_some() {
+            writer.commit()?;
+            store.entry_cache.clear();
+        }
+        Ok(report)
+    }
+
+    /// Rewrites the membership to a single-voter configuration of `node_id`,
+    /// so that the node can be restarted as a new cluster after losing the quorum permanently.
+    ///
+    /// This is UNSAFE. The uncommitted entries are discarded, and the entries committed
+    /// by the lost nodes but not replicated to this node are lost.
+    /// The other nodes must never be restarted with their previous logs afterwards.
+    pub fn force_new_cluster(&mut self, node_id: u64) -> Result<ForceNewClusterReport> {
+        let store = self.wl();
+        let report = store.write(|writer| store.force_new_cluster(writer, node_id))?;
+        store.entry_cache.clear();
+        Ok(report)
+    }
+
     #[allow(dead_code)]
     pub fn replace_entries(&mut self, entries: &[Entry]) -> raft::Result<()> {
         let store = self.wl();
         let mut writer = store.env.write_txn().unwrap();
         store.replace_entries(&mut writer, entries).unwrap();
         writer.commit().unwrap();
+        store.entry_cache.clear();
         Ok(())
     }
 }
@@ -275,16 +481,26 @@ pub struct HeedStorageCore {
     env: Env,
     entries_db: Database<HeedEntryKeyString, HeedEntry>,
     metadata_db: Database<HeedStr, HeedBytes>,
+    membership_history_db: Database<HeedEntryKeyString, HeedBytes>,
+    entry_cache: EntryCache,
+    write_latency: WriteLatency,
     config: Config,
     logger: Arc<dyn Logger>,
 }
 
 impl HeedStorageCore {
     pub fn create(log_dir_path: PathBuf, config: &Config, logger: Arc<dyn Logger>) -> Result<Self> {
+        let flags = match config.lmdb_sync_mode {
+            LmdbSyncMode::Full => EnvFlags::empty(),
+            LmdbSyncMode::NoMetaSync => EnvFlags::NO_META_SYNC,
+            LmdbSyncMode::NoSync => EnvFlags::NO_SYNC,
+        };
         let env = unsafe {
             heed::EnvOpenOptions::new()
                 .map_size(config.lmdb_map_size as usize)
                 .max_dbs(3000)
+                .max_readers(config.lmdb_max_readers)
+                .flags(flags)
                 .open(log_dir_path)?
         };
 
@@ -294,12 +510,17 @@ impl HeedStorageCore {
             env.create_database(&mut writer, Some("entries"))?;
         let metadata_db: Database<HeedStr, HeedBytes> =
             env.create_database(&mut writer, Some("meta"))?;
+        let membership_history_db: Database<HeedEntryKeyString, HeedBytes> =
+            env.create_database(&mut writer, Some("membership_history"))?;
 
         writer.commit()?;
 
         let storage = Self {
             metadata_db,
             entries_db,
+            membership_history_db,
+            entry_cache: EntryCache::new(config.entry_cache_size),
+            write_latency: WriteLatency::new(WRITE_LATENCY_SAMPLES),
             env,
             logger,
             config: config.clone(),
@@ -308,6 +529,66 @@ impl HeedStorageCore {
         Ok(storage)
     }
 
+    /// Runs `write` in a write transaction and commits it.
+    /// When the map is full, grows it by `config.lmdb_map_growth_factor` and runs `write` again.
+    /// The caller holds the write lock of the storage, so that no other transaction is open while the map grows.
+    fn write<T>(&self, mut write: impl FnMut(&mut heed::RwTxn) -> Result<T>) -> Result<T> {
+        loop {
+            let mut writer = self.env.write_txn()?;
+            let result = match write(&mut writer) {
+                Ok(value) => self.commit(writer).map(|_| value),
+                Err(e) => {
+                    writer.abort();
+                    Err(e)
+                }
+            };
+            match result {
+                Err(Error::Database(heed::Error::Mdb(MdbError::MapFull))) => self.grow_map()?,
+                result => return result,
+            }
+        }
+    }
+
+    /// Commits `writer`, recording how long LMDB took to sync it to the disk.
+    fn commit(&self, writer: heed::RwTxn) -> Result<()> {
+        let started = Instant::now();
+        writer.commit()?;
+        let latency = started.elapsed();
+        self.write_latency.record(latency);
+
+        if let Some(threshold) = self.config.slow_write_threshold {
+            if latency > Duration::from_secs_f32(threshold) {
+                self.logger.warn(&format!(
+                    "Slow storage write: latency_ms={}, threshold_ms={}, p99_ms={}, log_dir={}",
+                    latency.as_millis(),
+                    (threshold * 1000.0) as u64,
+                    self.write_latency.p99().unwrap_or(latency).as_millis(),
+                    self.config.log_dir
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn grow_map(&self) -> Result<()> {
+        let Some(growth_factor) = self.config.lmdb_map_growth_factor else {
+            return Err(Error::Database(heed::Error::Mdb(MdbError::MapFull)));
+        };
+        let map_size = self.env.info().map_size;
+        // Rounded up to a multiple of the page sizes of the platforms.
+        let new_size = ((map_size as f64 * growth_factor as f64) as usize)
+            .div_ceil(MAP_SIZE_ALIGNMENT)
+            * MAP_SIZE_ALIGNMENT;
+        self.logger.warn(&format!(
+            "The LMDB map is full, growing it from {} to {} bytes.",
+            map_size, new_size
+        ));
+        // Safe since the caller holds the write lock of the storage, and the transaction which
+        // found the map full is closed, so no transaction is open in the process.
+        unsafe { self.env.resize(new_size)? };
+        Ok(())
+    }
+
     pub fn compact(&self, writer: &mut heed::RwTxn, index: u64) -> Result<()> {
         // TODO, check that compaction is legal
         //let last_index = self.last_index(&writer)?;
@@ -320,11 +601,8 @@ impl HeedStorageCore {
             let iter = self.entries_db.range(writer, &(..index.clone()))?;
 
             let entries = iter
-                .filter_map(|e| match e {
-                    Ok((_, e)) => Some(e),
-                    _ => None,
-                })
-                .collect::<Vec<Entry>>();
+                .map(|e| e.map(|(_, e)| e))
+                .collect::<heed::Result<Vec<Entry>>>()?;
 
             self.save_compacted_entries(entries.as_slice())?;
         }
@@ -337,7 +615,7 @@ impl HeedStorageCore {
         self.metadata_db.put(
             writer,
             HARD_STATE_KEY,
-            hard_state.encode_to_vec().as_slice(),
+            seal_checksum(&hard_state.encode_to_vec()).as_slice(),
         )?;
 
         Ok(())
@@ -348,6 +626,8 @@ impl HeedStorageCore {
 
         match hard_state {
             Some(hard_state) => {
+                let hard_state = open_checksum(hard_state)
+                    .map_err(|e| Error::StorageCorruption(format!("Hard state: {}", e)))?;
                 let hard_state = HardState::decode(hard_state)?;
                 Ok(hard_state)
             }
@@ -376,6 +656,120 @@ impl HeedStorageCore {
         }
     }
 
+    fn tombstones(&self, reader: &heed::RoTxn) -> Result<Vec<NodeTombstone>> {
+        match self.metadata_db.get(reader, TOMBSTONES_KEY)? {
+            Some(tombstones) => Ok(deserialize(tombstones)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn set_tombstones(&self, writer: &mut heed::RwTxn, tombstones: &[NodeTombstone]) -> Result<()> {
+        self.metadata_db
+            .put(writer, TOMBSTONES_KEY, serialize(tombstones)?.as_slice())?;
+        Ok(())
+    }
+
+    fn cluster_uuid(&self, reader: &heed::RoTxn) -> Result<Option<String>> {
+        let cluster_uuid = self.metadata_db.get(reader, CLUSTER_UUID_KEY)?;
+        Ok(cluster_uuid.map(|uuid| String::from_utf8_lossy(uuid).into_owned()))
+    }
+
+    fn set_cluster_uuid(&self, writer: &mut heed::RwTxn, cluster_uuid: &str) -> Result<()> {
+        self.metadata_db
+            .put(writer, CLUSTER_UUID_KEY, cluster_uuid.as_bytes())?;
+        Ok(())
+    }
+
+    fn runtime_config(&self, reader: &heed::RoTxn) -> Result<RuntimeConfig> {
+        match self.metadata_db.get(reader, RUNTIME_CONFIG_KEY)? {
+            Some(runtime_config) => RuntimeConfig::decode(runtime_config),
+            None => Ok(RuntimeConfig::default()),
+        }
+    }
+
+    fn set_runtime_config(
+        &self,
+        writer: &mut heed::RwTxn,
+        runtime_config: &RuntimeConfig,
+    ) -> Result<()> {
+        self.metadata_db.put(
+            writer,
+            RUNTIME_CONFIG_KEY,
+            runtime_config.encode()?.as_slice(),
+        )?;
+        Ok(())
+    }
+
+    fn peer_labels(&self, reader: &heed::RoTxn) -> Result<HashMap<u64, PeerLabels>> {
+        match self.metadata_db.get(reader, PEER_LABELS_KEY)? {
+            Some(peer_labels) => Ok(deserialize(peer_labels)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    fn set_peer_labels(
+        &self,
+        writer: &mut heed::RwTxn,
+        peer_labels: &HashMap<u64, PeerLabels>,
+    ) -> Result<()> {
+        self.metadata_db
+            .put(writer, PEER_LABELS_KEY, serialize(peer_labels)?.as_slice())?;
+        Ok(())
+    }
+
+    fn node_identity(&self, reader: &heed::RoTxn) -> Result<Option<NodeIdentity>> {
+        match self.metadata_db.get(reader, NODE_IDENTITY_KEY)? {
+            Some(node_identity) => Ok(Some(deserialize(node_identity)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_node_identity(
+        &self,
+        writer: &mut heed::RwTxn,
+        node_identity: &NodeIdentity,
+    ) -> Result<()> {
+        self.metadata_db.put(
+            writer,
+            NODE_IDENTITY_KEY,
+            serialize(node_identity)?.as_slice(),
+        )?;
+        Ok(())
+    }
+
+    fn peers(&self, reader: &heed::RoTxn) -> Result<Option<Peers>> {
+        match self.metadata_db.get(reader, PEERS_KEY)? {
+            Some(peers) => Ok(Some(deserialize(peers)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_peers(&self, writer: &mut heed::RwTxn, peers: &Peers) -> Result<()> {
+        self.metadata_db
+            .put(writer, PEERS_KEY, serialize(peers)?.as_slice())?;
+        Ok(())
+    }
+
+    fn fenced_ranges(&self, reader: &heed::RoTxn) -> Result<Vec<KeyRange>> {
+        match self.metadata_db.get(reader, FENCED_RANGES_KEY)? {
+            Some(fenced_ranges) => Ok(deserialize(fenced_ranges)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn set_fenced_ranges(
+        &self,
+        writer: &mut heed::RwTxn,
+        fenced_ranges: &[KeyRange],
+    ) -> Result<()> {
+        self.metadata_db.put(
+            writer,
+            FENCED_RANGES_KEY,
+            serialize(fenced_ranges)?.as_slice(),
+        )?;
+        Ok(())
+    }
+
     fn set_snapshot(&self, writer: &mut heed::RwTxn, snapshot: &Snapshot) -> Result<()> {
         self.metadata_db
             .put(writer, SNAPSHOT_KEY, snapshot.encode_to_vec().as_slice())?;
@@ -447,11 +841,8 @@ impl HeedStorageCore {
         let max_size: Option<u64> = max_size.into();
 
         let mut entries = iter
-            .filter_map(|e| match e {
-                Ok((_, e)) => Some(e),
-                _ => None,
-            })
-            .collect();
+            .map(|e| e.map(|(_, e)| e))
+            .collect::<heed::Result<Vec<Entry>>>()?;
 
         limit_size(&mut entries, max_size);
         Ok(entries)
@@ -460,14 +851,219 @@ impl HeedStorageCore {
     fn all_entries(&self, reader: &heed::RoTxn) -> Result<Vec<Entry>> {
         let iter = self.entries_db.iter(reader)?;
         let entries = iter
-            .filter_map(|e| match e {
-                Ok((_, e)) => Some(e),
-                _ => None,
-            })
-            .collect();
+            .map(|e| e.map(|(_, e)| e))
+            .collect::<heed::Result<Vec<Entry>>>()?;
         Ok(entries)
     }
 
+    fn append_membership_change(
+        &self,
+        writer: &mut heed::RwTxn,
+        record: &MembershipChangeRecord,
+    ) -> Result<()> {
+        self.membership_history_db.put(
+            writer,
+            &record.index.to_string(),
+            record.encode()?.as_slice(),
+        )?;
+        Ok(())
+    }
+
+    fn membership_history(&self, reader: &heed::RoTxn) -> Result<Vec<MembershipChangeRecord>> {
+        self.membership_history_db
+            .iter(reader)?
+            .map(|record| MembershipChangeRecord::decode(record?.1))
+            .collect()
+    }
+
+    fn stats(&self, reader: &heed::RoTxn) -> Result<StorageStats> {
+        let snapshot = self.snapshot(reader, 0, 0)?;
+        let metadata = snapshot.get_metadata();
+
+        Ok(StorageStats {
+            first_index: self.first_index(reader)?,
+            last_index: self.last_index(reader)?,
+            entry_count: self.entries_db.len(reader)?,
+            disk_size: self.env.real_disk_size()?,
+            map_size: Some(self.env.info().map_size as u64),
+            map_used: Some(self.env.non_free_pages_size()?),
+            entry_cache_hits: Some(self.entry_cache.hits()),
+            entry_cache_misses: Some(self.entry_cache.misses()),
+            write_latency_p99_us: self
+                .write_latency
+                .p99()
+                .map(|latency| latency.as_micros() as u64),
+            snapshot_index: metadata.index,
+            snapshot_term: metadata.term,
+        })
+    }
+
+    fn scrub(&self, writer: &mut heed::RwTxn, repair: bool) -> Result<ScrubReport> {
+        let mut report = ScrubReport::default();
+        let mut metadata_damage = |reason: String| {
+            report.damages.push(StorageDamage {
+                index: None,
+                reason,
+            })
+        };
+
+        let hard_state = self
+            .hard_state(writer)
+            .map_err(|e| metadata_damage(format!("Hard state: {}", e)))
+            .ok();
+        if let Err(e) = self.conf_state(writer) {
+            metadata_damage(format!("Conf state: {}", e));
+        }
+        let snapshot_index = match self.snapshot(writer, 0, 0) {
+            Ok(snapshot) => snapshot.get_metadata().index,
+            Err(e) => {
+                metadata_damage(format!("Snapshot: {}", e));
+                0
+            }
+        };
+        let last_index = self
+            .last_index(writer)
+            .map_err(|e| metadata_damage(format!("Last index: {}", e)))
+            .ok();
+
+        let mut expected_index = None;
+        let mut first_damaged_index = None;
+        for item in self
+            .entries_db
+            .remap_data_type::<HeedBytes>()
+            .iter(writer)?
+        {
+            let (key, bytes) = item?;
+            report.entry_count += 1;
+
+            let Ok(index) = key.parse::<u64>() else {
+                report.damages.push(StorageDamage {
+                    index: None,
+                    reason: format!("Invalid entry key: {:?}", key),
+                });
+                continue;
+            };
+
+            if let Some(expected_index) = expected_index.filter(|expected| *expected != index) {
+                report.damages.push(StorageDamage {
+                    index: Some(expected_index),
+                    reason: format!("Missing entries before index {}", index),
+                });
+                first_damaged_index.get_or_insert(expected_index);
+            }
+            expected_index = Some(index + 1);
+
+            let reason = match open_checksum(bytes) {
+                Ok(payload) => match Entry::decode(payload) {
+                    Ok(entry) if entry.index != index => {
+                        Some(format!("Entry has a mismatched index {}", entry.index))
+                    }
+                    Ok(_) => None,
+                    Err(e) => Some(format!("Failed to decode entry: {}", e)),
+                },
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(reason) = reason {
+                report.damages.push(StorageDamage {
+                    index: Some(index),
+                    reason,
+                });
+                first_damaged_index.get_or_insert(index);
+            }
+        }
+
+        let actual_last_index = expected_index
+            .map(|index| index - 1)
+            .unwrap_or(snapshot_index);
+        if let Some(last_index) = last_index.filter(|index| *index != actual_last_index) {
+            report.damages.push(StorageDamage {
+                index: None,
+                reason: format!(
+                    "Last index {} doesn't match the last entry {}",
+                    last_index, actual_last_index
+                ),
+            });
+        }
+
+        let Some(truncate_from) = first_damaged_index.filter(|_| repair) else {
+            return Ok(report);
+        };
+
+        let truncate_key = format_entry_key_string(truncate_from.to_string().as_str());
+        self.entries_db.delete_range(writer, &(truncate_key..))?;
+
+        let last_index = max(truncate_from - 1, snapshot_index);
+        self.set_last_index(writer, last_index)?;
+        // The truncated entries might have been committed.
+        // Lowering the commit index lets the node re-replicate them from the leader.
+        if let Some(mut hard_state) = hard_state.filter(|hs| hs.commit > last_index) {
+            hard_state.set_commit(last_index);
+            self.set_hard_state(writer, &hard_state)?;
+        }
+
+        self.logger.warn(&format!(
+            "Truncated the log from index {} while repairing the storage.",
+            truncate_from
+        ));
+        report.truncated_from = Some(truncate_from);
+        Ok(report)
+    }
+
+    fn force_new_cluster(
+        &self,
+        writer: &mut heed::RwTxn,
+        node_id: u64,
+    ) -> Result<ForceNewClusterReport> {
+        let mut hard_state = self.hard_state(writer)?;
+        let mut snapshot = self.snapshot(writer, 0, 0)?;
+        let commit = max(hard_state.commit, snapshot.get_metadata().index);
+
+        let last_index = self.last_index(writer)?;
+        let discarded_entries = last_index.saturating_sub(commit);
+        let discard_key = format_entry_key_string((commit + 1).to_string().as_str());
+        self.entries_db.delete_range(writer, &(discard_key..))?;
+        self.set_last_index(writer, commit)?;
+
+        // Re-applying the committed conf changes on restart would bring back the lost nodes.
+        let conf_changes = self
+            .entries_db
+            .iter(writer)?
+            .map(|e| e.map(|(_, e)| e))
+            .filter(|e| {
+                e.as_ref()
+                    .map_or(true, |e| e.get_entry_type() != EntryType::EntryNormal)
+            })
+            .collect::<heed::Result<Vec<Entry>>>()?;
+
+        for mut entry in conf_changes.iter().cloned() {
+            entry.set_entry_type(EntryType::EntryNormal);
+            entry.data.clear();
+            entry.context.clear();
+            self.entries_db
+                .put(writer, &entry.index.to_string(), &entry)?;
+        }
+
+        let mut conf_state = ConfState::default();
+        conf_state.set_voters(vec![node_id]);
+        self.set_conf_state(writer, &conf_state)?;
+        snapshot.mut_metadata().set_conf_state(conf_state);
+        self.set_snapshot(writer, &snapshot)?;
+
+        hard_state.set_commit(commit);
+        self.set_hard_state(writer, &hard_state)?;
+
+        self.logger.warn(&format!(
+            "Forced a new cluster with the single voter {}. Discarded {} uncommitted entries.",
+            node_id, discarded_entries
+        ));
+
+        Ok(ForceNewClusterReport {
+            discarded_entries,
+            replaced_conf_changes: conf_changes.len() as u64,
+            last_index: commit,
+        })
+    }
+
     fn append(&self, writer: &mut heed::RwTxn, entries: &[Entry]) -> Result<()> {
         if entries.is_empty() {
             return Ok(());
@@ -495,7 +1091,6 @@ impl HeedStorageCore {
         for entry in entries {
             let index = entry.index;
             last_index = std::cmp::max(index, last_index);
-            // TODO: Handle MDBFullError.
             self.entries_db.put(writer, &index.to_string(), entry)?;
         }
         self.set_last_index(writer, last_index)?;
@@ -509,10 +1104,14 @@ impl HeedStorageCore {
 
         match fs::metadata(&dest_path) {
             Ok(metadata) if metadata.len() > self.config.compacted_log_size_threshold => {
-                self.logger.debug(
-                    "Compacted log size is over threshold. Removing all previous compacted logs.",
-                );
-                fs::remove_file(&dest_path)?;
+                let segment_path = rotate_compacted_logs(
+                    Path::new(&dest_path),
+                    self.config.encryption_key_provider.as_deref(),
+                )?;
+                self.logger.debug(&format!(
+                    "Compacted log size is over threshold. Rotated previous compacted logs to {:?}.",
+                    segment_path
+                ));
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
             Err(e) => return Err(e.into()),
@@ -532,7 +1131,6 @@ impl HeedStorageCore {
         for entry in entries {
             let index = entry.index;
             last_index = std::cmp::max(index, last_index);
-            // TODO: Handle MDBFullError.
             self.entries_db.put(writer, &index.to_string(), entry)?;
         }
         self.set_last_index(writer, last_index)?;
@@ -549,11 +1147,12 @@ mod test {
 
     use crate::raft::{
         default_logger,
-        eraftpb::{Entry, Snapshot},
+        eraftpb::{ConfState, Entry, EntryType, HardState, Snapshot},
         logger::Slogger,
         Config as RaftConfig, Error as RaftError, GetEntriesContext, Storage, StorageError,
     };
-    use crate::{Config, HeedStorage, StableStorage};
+    use crate::{AbstractStableStorage, Config, HeedStorage, RuntimeConfig};
+    use heed::types::Bytes as HeedBytes;
     use prost::Message;
 
     fn new_entry(index: u64, term: u64) -> Entry {
@@ -929,6 +1528,226 @@ mod test {
         teardown(tempdir);
     }
 
+    #[test]
+    fn test_storage_stats() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let ents = vec![new_entry(3, 3), new_entry(4, 4), new_entry(5, 5)];
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        storage.replace_entries(&ents).unwrap();
+        storage.create_snapshot(vec![], 4, 4).unwrap();
+
+        let stats = storage.stats().unwrap();
+        assert_eq!(stats.first_index, 3);
+        assert_eq!(stats.last_index, 5);
+        assert_eq!(stats.entry_count, 3);
+        assert_eq!(stats.snapshot_index, 4);
+        assert_eq!(stats.snapshot_term, 4);
+        assert_eq!(stats.map_size, Some(cfg.lmdb_map_size));
+        assert!(stats.disk_size > 0);
+        // Recorded by the commit of the snapshot.
+        assert!(stats.write_latency_p99_us.is_some());
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_persist_readies() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        let first = vec![new_entry(1, 1), new_entry(2, 1)];
+        // Overwrites the conflicting entry of the first Ready.
+        let second = vec![new_entry(2, 2), new_entry(3, 2)];
+        let hard_state = HardState {
+            term: 2,
+            commit: 1,
+            ..Default::default()
+        };
+        storage
+            .persist_readies(&[(&first, None), (&second, Some(&hard_state))])
+            .unwrap();
+
+        assert_eq!(storage.last_index().unwrap(), 3);
+        assert_eq!(storage.term(2).unwrap(), 2);
+        assert_eq!(storage.hard_state().unwrap(), hard_state);
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_grow_map() {
+        let tempdir = setup();
+        let mut cfg = build_config(&tempdir);
+        cfg.lmdb_map_size = 1024 * 1024;
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        let ents: Vec<Entry> = (1..=8)
+            .map(|index| {
+                let mut entry = new_entry(index, 1);
+                entry.data = vec![0; 256 * 1024];
+                entry
+            })
+            .collect();
+        storage.append(&ents).unwrap();
+
+        assert_eq!(storage.last_index().unwrap(), 8);
+        let stats = storage.stats().unwrap();
+        assert!(stats.map_size.unwrap() > cfg.lmdb_map_size);
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_cluster_uuid() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        assert_eq!(storage.cluster_uuid().unwrap(), None);
+
+        storage.set_cluster_uuid("cluster-a").unwrap();
+        assert_eq!(
+            storage.cluster_uuid().unwrap().as_deref(),
+            Some("cluster-a")
+        );
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_runtime_config() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        assert!(storage.runtime_config().unwrap().is_empty());
+
+        let runtime_config = RuntimeConfig {
+            snapshot_interval: Some(Some(30.0)),
+            entry_compression_threshold: Some(None),
+            ..Default::default()
+        };
+        storage.set_runtime_config(&runtime_config).unwrap();
+        assert_eq!(storage.runtime_config().unwrap(), runtime_config);
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_scrub() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let ents = vec![new_entry(3, 3), new_entry(4, 4), new_entry(5, 5)];
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        storage.replace_entries(&ents).unwrap();
+        let mut hard_state = storage.hard_state().unwrap();
+        hard_state.commit = 5;
+        storage.set_hard_state(&hard_state).unwrap();
+
+        let report = storage.scrub(false).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.entry_count, 3);
+
+        {
+            let store = storage.wl();
+            let mut writer = store.env.write_txn().unwrap();
+            store
+                .entries_db
+                .remap_data_type::<HeedBytes>()
+                .put(&mut writer, &"4".to_owned(), b"\xffgarbage")
+                .unwrap();
+            writer.commit().unwrap();
+        }
+
+        let report = storage.scrub(false).unwrap();
+        assert_eq!(report.damages.len(), 1);
+        assert_eq!(report.damages[0].index, Some(4));
+        assert_eq!(report.truncated_from, None);
+        assert_eq!(storage.last_index().unwrap(), 5);
+
+        let report = storage.scrub(true).unwrap();
+        assert_eq!(report.truncated_from, Some(4));
+        assert_eq!(storage.last_index().unwrap(), 3);
+        assert_eq!(storage.hard_state().unwrap().commit, 3);
+        assert!(storage.scrub(false).unwrap().is_clean());
+
+        teardown(tempdir);
+    }
+
+    #[test]
+    fn test_storage_force_new_cluster() {
+        let tempdir = setup();
+        let cfg = build_config(&tempdir);
+        let logger = Arc::new(Slogger {
+            slog: build_logger(),
+        });
+
+        let mut conf_change = new_entry(4, 4);
+        conf_change.set_entry_type(EntryType::EntryConfChangeV2);
+        conf_change.data = vec![1, 2, 3];
+
+        let ents = vec![
+            new_entry(3, 3),
+            conf_change,
+            new_entry(5, 5),
+            new_entry(6, 5),
+        ];
+        let mut storage = HeedStorage::create(&tempdir, &cfg, logger).unwrap();
+        storage.replace_entries(&ents).unwrap();
+        let mut conf_state = ConfState::default();
+        conf_state.set_voters(vec![1, 2, 3]);
+        storage.set_conf_state(&conf_state).unwrap();
+        let mut hard_state = storage.hard_state().unwrap();
+        hard_state.commit = 5;
+        storage.set_hard_state(&hard_state).unwrap();
+
+        let report = storage.force_new_cluster(2).unwrap();
+        assert_eq!(report.discarded_entries, 1);
+        assert_eq!(report.replaced_conf_changes, 1);
+        assert_eq!(report.last_index, 5);
+
+        assert_eq!(storage.last_index().unwrap(), 5);
+        assert_eq!(storage.conf_state().unwrap().voters, vec![2]);
+        assert_eq!(
+            storage
+                .snapshot(0, 0)
+                .unwrap()
+                .get_metadata()
+                .get_conf_state()
+                .voters,
+            vec![2]
+        );
+
+        let entries = storage.all_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].get_entry_type(), EntryType::EntryNormal);
+        assert!(entries[1].data.is_empty());
+
+        teardown(tempdir);
+    }
+
     #[test]
     fn test_storage_apply_snapshot() {
         let tempdir = setup();
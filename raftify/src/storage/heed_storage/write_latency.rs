@@ -0,0 +1,59 @@
+use parking_lot::Mutex;
+use std::{collections::VecDeque, time::Duration};
+
+/// Latencies of the last commits of `HeedStorage`, which sync the written pages to the disk.
+pub(super) struct WriteLatency {
+    capacity: usize,
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl WriteLatency {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// Returns the latency under which 99% of the recorded commits completed.
+    pub fn p99(&self) -> Option<Duration> {
+        let mut samples: Vec<Duration> = self.samples.lock().iter().copied().collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let rank = (samples.len() * 99).div_ceil(100);
+        Some(samples[rank - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_latency_p99() {
+        let latency = WriteLatency::new(100);
+        assert_eq!(latency.p99(), None);
+
+        for millis in 1..=200 {
+            latency.record(Duration::from_millis(millis));
+        }
+        // Only the last 100 commits are kept.
+        assert_eq!(latency.p99(), Some(Duration::from_millis(199)));
+
+        // A single slow commit out of 100 doesn't move it, two do.
+        latency.record(Duration::from_secs(3));
+        assert_eq!(latency.p99(), Some(Duration::from_millis(200)));
+        latency.record(Duration::from_secs(3));
+        assert_eq!(latency.p99(), Some(Duration::from_secs(3)));
+    }
+}
@@ -1,31 +1,65 @@
+use parking_lot::RwLock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
 use crate::{
     error::Result,
     raft::{
         self,
         eraftpb::{ConfState, Entry, HardState, Snapshot},
         storage::{MemStorage as MemStorageCore, Storage},
-        INVALID_INDEX,
+        GetEntriesContext, INVALID_INDEX,
     },
-    StableStorage,
+    AbstractStableStorage, KeyRange, MembershipChangeRecord, NodeTombstone, PeerLabels, Peers,
+    RuntimeConfig,
 };
 
-use super::StorageType;
+use super::{NodeIdentity, StorageStats, StorageType};
 
+/// Volatile storage which keeps everything in memory.
+/// Useful for unit tests and short-lived clusters which don't need a log directory on disk.
 #[derive(Clone)]
 pub struct MemStorage {
     core: MemStorageCore,
-    snapshot: Snapshot,
+    snapshot: Arc<RwLock<Snapshot>>,
+    membership_history: Arc<RwLock<BTreeMap<u64, MembershipChangeRecord>>>,
+    tombstones: Arc<RwLock<Vec<NodeTombstone>>>,
+    cluster_uuid: Arc<RwLock<Option<String>>>,
+    runtime_config: Arc<RwLock<RuntimeConfig>>,
+    peer_labels: Arc<RwLock<HashMap<u64, PeerLabels>>>,
+    fenced_ranges: Arc<RwLock<Vec<KeyRange>>>,
+    node_identity: Arc<RwLock<Option<NodeIdentity>>>,
+    peers: Arc<RwLock<Option<Peers>>>,
 }
 
 impl MemStorage {
     pub fn create() -> Self {
         let core = MemStorageCore::default();
         let snapshot: Snapshot = Default::default();
-        Self { core, snapshot }
+        Self {
+            core,
+            snapshot: Arc::new(RwLock::new(snapshot)),
+            membership_history: Arc::new(RwLock::new(BTreeMap::new())),
+            tombstones: Arc::new(RwLock::new(Vec::new())),
+            cluster_uuid: Arc::new(RwLock::new(None)),
+            runtime_config: Arc::new(RwLock::new(RuntimeConfig::default())),
+            peer_labels: Arc::new(RwLock::new(HashMap::new())),
+            fenced_ranges: Arc::new(RwLock::new(Vec::new())),
+            node_identity: Arc::new(RwLock::new(None)),
+            peers: Arc::new(RwLock::new(None)),
+        }
     }
 }
 
-impl StableStorage for MemStorage {
+impl Default for MemStorage {
+    fn default() -> Self {
+        Self::create()
+    }
+}
+
+impl AbstractStableStorage for MemStorage {
     const STORAGE_TYPE: StorageType = StorageType::InMemory;
 
     fn append(&mut self, entries: &[Entry]) -> Result<()> {
@@ -54,7 +88,8 @@ impl StableStorage for MemStorage {
     }
 
     fn conf_state(&self) -> Result<ConfState> {
-        todo!()
+        let raft_state = self.core.initial_state()?;
+        Ok(raft_state.conf_state)
     }
 
     fn set_conf_state(&mut self, conf_state: &ConfState) -> Result<()> {
@@ -70,7 +105,7 @@ impl StableStorage for MemStorage {
         metadata.set_index(index);
         metadata.set_term(term);
         snapshot.set_metadata(metadata);
-        self.snapshot = snapshot;
+        *self.snapshot.write() = snapshot;
         Ok(())
     }
 
@@ -83,7 +118,8 @@ impl StableStorage for MemStorage {
             return Ok(());
         }
 
-        store.apply_snapshot(snapshot)?;
+        store.apply_snapshot(snapshot.clone())?;
+        *self.snapshot.write() = snapshot;
         Ok(())
     }
 
@@ -94,7 +130,108 @@ impl StableStorage for MemStorage {
     }
 
     fn all_entries(&self) -> raft::Result<Vec<Entry>> {
-        todo!()
+        let first_index = self.core.first_index()?;
+        let last_index = self.core.last_index()?;
+        if last_index < first_index {
+            return Ok(vec![]);
+        }
+
+        let max_size: Option<u64> = None;
+        self.core.entries(
+            first_index,
+            last_index + 1,
+            max_size,
+            GetEntriesContext::empty(false),
+        )
+    }
+
+    fn append_membership_change(&mut self, record: &MembershipChangeRecord) -> Result<()> {
+        self.membership_history
+            .write()
+            .insert(record.index, record.clone());
+        Ok(())
+    }
+
+    fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>> {
+        Ok(self.membership_history.read().values().cloned().collect())
+    }
+
+    fn tombstones(&self) -> Result<Vec<NodeTombstone>> {
+        Ok(self.tombstones.read().clone())
+    }
+
+    fn set_tombstones(&mut self, tombstones: &[NodeTombstone]) -> Result<()> {
+        *self.tombstones.write() = tombstones.to_vec();
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        let first_index = self.core.first_index()?;
+        let last_index = self.core.last_index()?;
+        let snapshot = self.snapshot.read();
+
+        Ok(StorageStats {
+            first_index,
+            last_index,
+            entry_count: (last_index + 1).saturating_sub(first_index),
+            snapshot_index: snapshot.get_metadata().index,
+            snapshot_term: snapshot.get_metadata().term,
+            ..Default::default()
+        })
+    }
+
+    fn cluster_uuid(&self) -> Result<Option<String>> {
+        Ok(self.cluster_uuid.read().clone())
+    }
+
+    fn set_cluster_uuid(&mut self, cluster_uuid: &str) -> Result<()> {
+        *self.cluster_uuid.write() = Some(cluster_uuid.to_owned());
+        Ok(())
+    }
+
+    fn runtime_config(&self) -> Result<RuntimeConfig> {
+        Ok(self.runtime_config.read().clone())
+    }
+
+    fn set_runtime_config(&mut self, runtime_config: &RuntimeConfig) -> Result<()> {
+        *self.runtime_config.write() = runtime_config.clone();
+        Ok(())
+    }
+
+    fn peer_labels(&self) -> Result<HashMap<u64, PeerLabels>> {
+        Ok(self.peer_labels.read().clone())
+    }
+
+    fn set_peer_labels(&mut self, peer_labels: &HashMap<u64, PeerLabels>) -> Result<()> {
+        *self.peer_labels.write() = peer_labels.clone();
+        Ok(())
+    }
+
+    fn fenced_ranges(&self) -> Result<Vec<KeyRange>> {
+        Ok(self.fenced_ranges.read().clone())
+    }
+
+    fn set_fenced_ranges(&mut self, fenced_ranges: &[KeyRange]) -> Result<()> {
+        *self.fenced_ranges.write() = fenced_ranges.to_vec();
+        Ok(())
+    }
+
+    fn node_identity(&self) -> Result<Option<NodeIdentity>> {
+        Ok(self.node_identity.read().clone())
+    }
+
+    fn set_node_identity(&mut self, node_identity: &NodeIdentity) -> Result<()> {
+        *self.node_identity.write() = Some(node_identity.clone());
+        Ok(())
+    }
+
+    fn peers(&self) -> Result<Option<Peers>> {
+        Ok(self.peers.read().clone())
+    }
+
+    fn set_peers(&mut self, peers: &Peers) -> Result<()> {
+        *self.peers.write() = Some(peers.clone());
+        Ok(())
     }
 }
 
@@ -128,11 +265,92 @@ impl Storage for MemStorage {
     }
 
     fn snapshot(&self, _request_index: u64, _to: u64) -> raft::Result<Snapshot> {
-        Ok(self.snapshot.clone())
+        Ok(self.snapshot.read().clone())
     }
 }
 
 // Ref: https://github.com/tikv/raft-rs/blob/master/src/storage.rs
-// TODO: Implement these tests for MemStorage
 #[cfg(test)]
-mod test {}
+mod test {
+    use crate::raft::{
+        eraftpb::{ConfState, Entry, Snapshot},
+        Storage,
+    };
+    use crate::{AbstractStableStorage, MemStorage};
+
+    fn new_entry(index: u64, term: u64) -> Entry {
+        let mut e = Entry::default();
+        e.term = term;
+        e.index = index;
+        e
+    }
+
+    fn new_snapshot(index: u64, term: u64, voters: Vec<u64>) -> Snapshot {
+        let mut s = Snapshot::default();
+        s.mut_metadata().index = index;
+        s.mut_metadata().term = term;
+        s.mut_metadata().mut_conf_state().voters = voters;
+        s
+    }
+
+    #[test]
+    fn test_storage_all_entries() {
+        let mut storage = MemStorage::create();
+        let ents = vec![new_entry(1, 1), new_entry(2, 1), new_entry(3, 2)];
+        storage.append(&ents).unwrap();
+
+        assert_eq!(storage.all_entries().unwrap(), ents);
+        assert_eq!(storage.last_index(), Ok(3));
+    }
+
+    #[test]
+    fn test_storage_stats() {
+        let mut storage = MemStorage::create();
+        let ents = vec![new_entry(1, 1), new_entry(2, 1), new_entry(3, 2)];
+        storage.append(&ents).unwrap();
+        storage.create_snapshot(vec![], 2, 1).unwrap();
+
+        let stats = storage.stats().unwrap();
+        assert_eq!(stats.first_index, 1);
+        assert_eq!(stats.last_index, 3);
+        assert_eq!(stats.entry_count, 3);
+        assert_eq!(stats.snapshot_index, 2);
+        assert_eq!(stats.disk_size, 0);
+        assert_eq!(stats.map_size, None);
+    }
+
+    #[test]
+    fn test_storage_conf_state() {
+        let mut storage = MemStorage::create();
+        let mut conf_state = ConfState::default();
+        conf_state.voters = vec![1, 2, 3];
+        storage.set_conf_state(&conf_state).unwrap();
+
+        assert_eq!(storage.conf_state().unwrap(), conf_state);
+    }
+
+    #[test]
+    fn test_storage_create_snapshot_shared_between_clones() {
+        let mut storage = MemStorage::create();
+        let cloned = storage.clone();
+        storage.append(&[new_entry(1, 1), new_entry(2, 1)]).unwrap();
+        storage.create_snapshot(vec![1, 2, 3], 2, 1).unwrap();
+
+        let snapshot = cloned.snapshot(0, 0).unwrap();
+        assert_eq!(snapshot.get_data(), &[1, 2, 3]);
+        assert_eq!(snapshot.get_metadata().index, 2);
+        assert_eq!(snapshot.get_metadata().term, 1);
+    }
+
+    #[test]
+    fn test_storage_apply_snapshot() {
+        let mut storage = MemStorage::create();
+        storage
+            .apply_snapshot(new_snapshot(4, 4, vec![1, 2, 3]))
+            .unwrap();
+
+        assert_eq!(storage.first_index(), Ok(5));
+        assert_eq!(storage.conf_state().unwrap().voters, vec![1, 2, 3]);
+        assert_eq!(storage.snapshot(0, 0).unwrap().get_metadata().index, 4);
+    }
+}
@@ -9,10 +9,14 @@ pub mod rocksdb_storage;
 
 pub mod utils;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     error::Result,
     raft::{self, prelude::*},
+    KeyRange, MembershipChangeRecord, NodeTombstone, PeerLabels, Peers, RuntimeConfig,
 };
+use std::{collections::HashMap, net::SocketAddr};
 
 pub enum StorageType {
     InMemory,
@@ -21,7 +25,49 @@ pub enum StorageType {
     Custom,
 }
 
-pub trait StableStorage: Storage {
+/// Id and address the node was bootstrapped with, persisted to restart it with `Raft::bootstrap_from_storage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeIdentity {
+    pub node_id: u64,
+    pub raft_addr: SocketAddr,
+}
+
+/// Size and bounds of the persisted log, returned by `AbstractStableStorage::stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub first_index: u64,
+    pub last_index: u64,
+    pub entry_count: u64,
+    /// Bytes taken on disk. 0 for `MemStorage`.
+    pub disk_size: u64,
+    /// Size of the LMDB memory map. Only reported by `HeedStorage`.
+    pub map_size: Option<u64>,
+    /// Bytes of the LMDB memory map in use. Only reported by `HeedStorage`.
+    pub map_used: Option<u64>,
+    /// Reads of the log served by the in-memory entry cache. Only reported by `HeedStorage`.
+    pub entry_cache_hits: Option<u64>,
+    /// Reads of the log which missed the entry cache. Only reported by `HeedStorage`.
+    pub entry_cache_misses: Option<u64>,
+    /// Latency in microseconds under which 99% of the recent commits, including their sync to the disk,
+    /// completed. Only reported by `HeedStorage`.
+    pub write_latency_p99_us: Option<u64>,
+    pub snapshot_index: u64,
+    pub snapshot_term: u64,
+}
+
+impl StorageStats {
+    /// Returns the share of the log reads served by the entry cache, if the storage has one which was read.
+    pub fn entry_cache_hit_rate(&self) -> Option<f64> {
+        let hits = self.entry_cache_hits?;
+        let reads = hits + self.entry_cache_misses?;
+        (reads > 0).then(|| hits as f64 / reads as f64)
+    }
+}
+
+/// Persistence layer used by `RaftNode`.
+/// Implement this trait (on top of raft-rs's `Storage`) to plug in your own storage backend
+/// instead of the built-in `HeedStorage`, `RocksDBStorage` or `MemStorage`.
+pub trait AbstractStableStorage: Storage {
     const STORAGE_TYPE: StorageType;
 
     fn append(&mut self, entries: &[Entry]) -> Result<()>;
@@ -34,4 +80,51 @@ pub trait StableStorage: Storage {
     fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<()>;
     fn compact(&mut self, index: u64) -> Result<()>;
     fn all_entries(&self) -> raft::Result<Vec<Entry>>;
+    /// Persists `record` in the membership change audit log, replacing the record of the same index.
+    fn append_membership_change(&mut self, record: &MembershipChangeRecord) -> Result<()>;
+    /// Returns the membership change audit log in the log index order.
+    fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>>;
+    /// Returns the tombstones of the removed nodes, see `NodeTombstone`.
+    fn tombstones(&self) -> Result<Vec<NodeTombstone>>;
+    fn set_tombstones(&mut self, tombstones: &[NodeTombstone]) -> Result<()>;
+    /// Returns the current size and bounds of the persisted log.
+    fn stats(&self) -> Result<StorageStats>;
+    /// Returns the UUID of the cluster this node belongs to, or `None` if it's not known yet.
+    fn cluster_uuid(&self) -> Result<Option<String>>;
+    fn set_cluster_uuid(&mut self, cluster_uuid: &str) -> Result<()>;
+    /// Returns the runtime config overrides applied so far, see `RuntimeConfig`.
+    fn runtime_config(&self) -> Result<RuntimeConfig>;
+    fn set_runtime_config(&mut self, runtime_config: &RuntimeConfig) -> Result<()>;
+    /// Returns the labels of the peers set so far, see `RaftNode::set_peer_labels`.
+    fn peer_labels(&self) -> Result<HashMap<u64, PeerLabels>>;
+    fn set_peer_labels(&mut self, peer_labels: &HashMap<u64, PeerLabels>) -> Result<()>;
+    /// Returns the key ranges fenced while they move to another group, see `Router::move_range`.
+    fn fenced_ranges(&self) -> Result<Vec<KeyRange>>;
+    fn set_fenced_ranges(&mut self, fenced_ranges: &[KeyRange]) -> Result<()>;
+    /// Returns the identity of the node which owns the storage, `None` if it was never bootstrapped.
+    fn node_identity(&self) -> Result<Option<NodeIdentity>>;
+    fn set_node_identity(&mut self, node_identity: &NodeIdentity) -> Result<()>;
+    /// Returns the peers of the node as of its last membership change, `None` if it was never bootstrapped.
+    fn peers(&self) -> Result<Option<Peers>>;
+    fn set_peers(&mut self, peers: &Peers) -> Result<()>;
+
+    /// Persists the entries and the hard states of several Readies, in their order.
+    /// Override it to persist them in a single transaction, so that a group of Readies costs a single sync.
+    fn persist_readies(&mut self, readies: &[(&[Entry], Option<&HardState>)]) -> Result<()> {
+        for (entries, hard_state) in readies {
+            if !entries.is_empty() {
+                self.append(entries)?;
+            }
+            if let Some(hard_state) = hard_state {
+                self.set_hard_state(hard_state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Syncs to the disk what the storage wrote so far, before the node shuts down.
+    /// Only needed by the storages which don't sync every write.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
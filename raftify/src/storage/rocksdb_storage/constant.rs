@@ -2,8 +2,16 @@ pub const SNAPSHOT_KEY: &[u8] = b"snapshot";
 pub const LAST_INDEX_KEY: &[u8] = b"last_index";
 pub const HARD_STATE_KEY: &[u8] = b"hard_state";
 pub const CONF_STATE_KEY: &[u8] = b"conf_state";
+pub const CLUSTER_UUID_KEY: &[u8] = b"cluster_uuid";
+pub const TOMBSTONES_KEY: &[u8] = b"tombstones";
+pub const RUNTIME_CONFIG_KEY: &[u8] = b"runtime_config";
+pub const PEER_LABELS_KEY: &[u8] = b"peer_labels";
+pub const FENCED_RANGES_KEY: &[u8] = b"fenced_ranges";
+pub const NODE_IDENTITY_KEY: &[u8] = b"node_identity";
+pub const PEERS_KEY: &[u8] = b"peers";
 
 pub const ENTRY_KEY_LENGTH: usize = 10;
 
 pub const METADATA_CF_KEY: &str = "meta_data";
 pub const LOG_ENTRY_CF_KEY: &str = "log_entries";
+pub const MEMBERSHIP_HISTORY_CF_KEY: &str = "membership_history";
@@ -5,20 +5,26 @@ use crate::raft::eraftpb::Entry;
 use crate::raft::logger::Logger;
 use crate::raft::prelude::{ConfState, HardState, Snapshot};
 use crate::raft::{GetEntriesContext, RaftState, Storage};
-use crate::{Result, StableStorage};
+use crate::{
+    AbstractStableStorage, KeyRange, MembershipChangeRecord, NodeIdentity, NodeTombstone,
+    PeerLabels, Peers, Result, RuntimeConfig,
+};
 use codec::format_entry_key_string;
 use constant::{
-    CONF_STATE_KEY, HARD_STATE_KEY, LAST_INDEX_KEY, LOG_ENTRY_CF_KEY, METADATA_CF_KEY, SNAPSHOT_KEY,
+    CLUSTER_UUID_KEY, CONF_STATE_KEY, FENCED_RANGES_KEY, HARD_STATE_KEY, LAST_INDEX_KEY,
+    LOG_ENTRY_CF_KEY, MEMBERSHIP_HISTORY_CF_KEY, METADATA_CF_KEY, NODE_IDENTITY_KEY, PEERS_KEY,
+    PEER_LABELS_KEY, RUNTIME_CONFIG_KEY, SNAPSHOT_KEY, TOMBSTONES_KEY,
 };
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use prost::Message;
 use raft::util::limit_size;
 use rocksdb::{ColumnFamilyDescriptor, Options, DB as RocksDB};
 use std::cmp::max;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use super::StorageType;
+use super::{StorageStats, StorageType};
 
 #[derive(Clone)]
 pub struct RocksDBStorage(Arc<RwLock<RocksDBStorageCore>>);
@@ -46,7 +52,7 @@ impl RocksDBStorage {
     }
 }
 
-impl StableStorage for RocksDBStorage {
+impl AbstractStableStorage for RocksDBStorage {
     const STORAGE_TYPE: StorageType = StorageType::RocksDB;
 
     fn append(&mut self, entries: &[Entry]) -> Result<()> {
@@ -54,6 +60,12 @@ impl StableStorage for RocksDBStorage {
         store.append(entries)
     }
 
+    fn flush(&mut self) -> Result<()> {
+        let store = self.wl();
+        store.db.flush().unwrap();
+        Ok(())
+    }
+
     fn hard_state(&self) -> Result<HardState> {
         let store = self.rl();
         let hard_state = store.hard_state()?;
@@ -109,6 +121,91 @@ impl StableStorage for RocksDBStorage {
         let entries = store.all_entries()?;
         Ok(entries)
     }
+
+    fn append_membership_change(&mut self, record: &MembershipChangeRecord) -> Result<()> {
+        let store = self.wl();
+        store.append_membership_change(record)
+    }
+
+    fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>> {
+        let store = self.rl();
+        store.membership_history()
+    }
+
+    fn tombstones(&self) -> Result<Vec<NodeTombstone>> {
+        let store = self.rl();
+        store.tombstones()
+    }
+
+    fn set_tombstones(&mut self, tombstones: &[NodeTombstone]) -> Result<()> {
+        let store = self.wl();
+        store.set_tombstones(tombstones)
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        let store = self.rl();
+        store.stats()
+    }
+
+    fn cluster_uuid(&self) -> Result<Option<String>> {
+        let store = self.rl();
+        store.cluster_uuid()
+    }
+
+    fn set_cluster_uuid(&mut self, cluster_uuid: &str) -> Result<()> {
+        let store = self.wl();
+        store.set_cluster_uuid(cluster_uuid)
+    }
+
+    fn runtime_config(&self) -> Result<RuntimeConfig> {
+        let store = self.rl();
+        store.runtime_config()
+    }
+
+    fn set_runtime_config(&mut self, runtime_config: &RuntimeConfig) -> Result<()> {
+        let store = self.wl();
+        store.set_runtime_config(runtime_config)
+    }
+
+    fn peer_labels(&self) -> Result<HashMap<u64, PeerLabels>> {
+        let store = self.rl();
+        store.peer_labels()
+    }
+
+    fn set_peer_labels(&mut self, peer_labels: &HashMap<u64, PeerLabels>) -> Result<()> {
+        let store = self.wl();
+        store.set_peer_labels(peer_labels)
+    }
+
+    fn fenced_ranges(&self) -> Result<Vec<KeyRange>> {
+        let store = self.rl();
+        store.fenced_ranges()
+    }
+
+    fn set_fenced_ranges(&mut self, fenced_ranges: &[KeyRange]) -> Result<()> {
+        let store = self.wl();
+        store.set_fenced_ranges(fenced_ranges)
+    }
+
+    fn node_identity(&self) -> Result<Option<NodeIdentity>> {
+        let store = self.rl();
+        store.node_identity()
+    }
+
+    fn set_node_identity(&mut self, node_identity: &NodeIdentity) -> Result<()> {
+        let store = self.wl();
+        store.set_node_identity(node_identity)
+    }
+
+    fn peers(&self) -> Result<Option<Peers>> {
+        let store = self.rl();
+        store.peers()
+    }
+
+    fn set_peers(&mut self, peers: &Peers) -> Result<()> {
+        let store = self.wl();
+        store.set_peers(peers)
+    }
 }
 
 impl Storage for RocksDBStorage {
@@ -165,6 +262,7 @@ impl RocksDBStorageCore {
         let cf_descriptors = vec![
             ColumnFamilyDescriptor::new(LOG_ENTRY_CF_KEY, cf_opts.clone()),
             ColumnFamilyDescriptor::new(METADATA_CF_KEY, cf_opts.clone()),
+            ColumnFamilyDescriptor::new(MEMBERSHIP_HISTORY_CF_KEY, cf_opts.clone()),
         ];
 
         let db = RocksDB::open_cf_descriptors(&db_opts, path, cf_descriptors).unwrap();
@@ -259,6 +357,124 @@ impl RocksDBStorageCore {
         Ok(())
     }
 
+    fn tombstones(&self) -> Result<Vec<NodeTombstone>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, TOMBSTONES_KEY).unwrap() {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn set_tombstones(&self, tombstones: &[NodeTombstone]) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(cf_handle, TOMBSTONES_KEY, bincode::serialize(tombstones)?)
+            .unwrap();
+        Ok(())
+    }
+
+    fn cluster_uuid(&self) -> Result<Option<String>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        let result = self.db.get_cf(cf_handle, CLUSTER_UUID_KEY).unwrap();
+        Ok(result.map(|uuid| String::from_utf8_lossy(&uuid).into_owned()))
+    }
+
+    fn set_cluster_uuid(&self, cluster_uuid: &str) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(cf_handle, CLUSTER_UUID_KEY, cluster_uuid.as_bytes())
+            .unwrap();
+        Ok(())
+    }
+
+    fn runtime_config(&self) -> Result<RuntimeConfig> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, RUNTIME_CONFIG_KEY).unwrap() {
+            Some(data) => RuntimeConfig::decode(&data),
+            None => Ok(RuntimeConfig::default()),
+        }
+    }
+
+    fn set_runtime_config(&self, runtime_config: &RuntimeConfig) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(cf_handle, RUNTIME_CONFIG_KEY, runtime_config.encode()?)
+            .unwrap();
+        Ok(())
+    }
+
+    fn peer_labels(&self) -> Result<HashMap<u64, PeerLabels>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, PEER_LABELS_KEY).unwrap() {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    fn set_peer_labels(&self, peer_labels: &HashMap<u64, PeerLabels>) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(cf_handle, PEER_LABELS_KEY, bincode::serialize(peer_labels)?)
+            .unwrap();
+        Ok(())
+    }
+
+    fn node_identity(&self) -> Result<Option<NodeIdentity>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, NODE_IDENTITY_KEY).unwrap() {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_node_identity(&self, node_identity: &NodeIdentity) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(
+                cf_handle,
+                NODE_IDENTITY_KEY,
+                bincode::serialize(node_identity)?,
+            )
+            .unwrap();
+        Ok(())
+    }
+
+    fn peers(&self) -> Result<Option<Peers>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, PEERS_KEY).unwrap() {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_peers(&self, peers: &Peers) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(cf_handle, PEERS_KEY, bincode::serialize(peers)?)
+            .unwrap();
+        Ok(())
+    }
+
+    fn fenced_ranges(&self) -> Result<Vec<KeyRange>> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        match self.db.get_cf(cf_handle, FENCED_RANGES_KEY).unwrap() {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn set_fenced_ranges(&self, fenced_ranges: &[KeyRange]) -> Result<()> {
+        let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
+        self.db
+            .put_cf(
+                cf_handle,
+                FENCED_RANGES_KEY,
+                bincode::serialize(fenced_ranges)?,
+            )
+            .unwrap();
+        Ok(())
+    }
+
     fn set_last_index(&self, index: u64) -> Result<()> {
         let cf_handle = self.db.cf_handle(METADATA_CF_KEY).unwrap();
         self.db
@@ -339,6 +555,45 @@ impl RocksDBStorageCore {
         Ok(entries)
     }
 
+    fn append_membership_change(&self, record: &MembershipChangeRecord) -> Result<()> {
+        let cf_handle = self.db.cf_handle(MEMBERSHIP_HISTORY_CF_KEY).unwrap();
+        let index = format_entry_key_string(record.index.to_string().as_str());
+        self.db.put_cf(cf_handle, index, record.encode()?).unwrap();
+        Ok(())
+    }
+
+    fn membership_history(&self) -> Result<Vec<MembershipChangeRecord>> {
+        let cf_handle = self.db.cf_handle(MEMBERSHIP_HISTORY_CF_KEY).unwrap();
+        self.db
+            .iterator_cf(cf_handle, rocksdb::IteratorMode::Start)
+            .map(|item| MembershipChangeRecord::decode(&item.unwrap().1))
+            .collect()
+    }
+
+    fn stats(&self) -> Result<StorageStats> {
+        let cf_handle = self.db.cf_handle(LOG_ENTRY_CF_KEY).unwrap();
+        let entry_count = self
+            .db
+            .iterator_cf(cf_handle, rocksdb::IteratorMode::Start)
+            .count() as u64;
+        let disk_size = self
+            .db
+            .property_int_value("rocksdb.total-sst-files-size")
+            .unwrap()
+            .unwrap_or(0);
+        let snapshot = self.snapshot(0, 0)?;
+
+        Ok(StorageStats {
+            first_index: self.first_index()?,
+            last_index: self.last_index()?,
+            entry_count,
+            disk_size,
+            snapshot_index: snapshot.get_metadata().index,
+            snapshot_term: snapshot.get_metadata().term,
+            ..Default::default()
+        })
+    }
+
     fn initial_state(&self) -> crate::raft::Result<RaftState> {
         let hard_state = self.hard_state().unwrap();
         let conf_state = self.conf_state().unwrap();
@@ -468,7 +723,7 @@ mod test {
         logger::Slogger,
         Config as RaftConfig, Error as RaftError, GetEntriesContext, Storage, StorageError,
     };
-    use crate::{Config, HeedStorage, StableStorage};
+    use crate::{AbstractStableStorage, Config, HeedStorage};
     use prost::Message;
 
     fn new_entry(index: u64, term: u64) -> Entry {
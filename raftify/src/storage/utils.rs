@@ -1,12 +1,20 @@
 use chrono::Utc;
 use serde_json::{json, Value};
 use std::{
-    fs::{File, OpenOptions},
+    fs::{self, File, OpenOptions},
     io::{self, Read, Seek, Write as StdIoWrite},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
-use crate::raft::{eraftpb::Entry, formatter::Bytes, formatter::CUSTOM_FORMATTER};
+use crate::{
+    encryption::encrypt,
+    raft::{eraftpb::Entry, formatter::Bytes, formatter::CUSTOM_FORMATTER, logger::Logger},
+    CompactedLogRetention, Config, KeyProvider, Result,
+};
+
+const COMPACTED_LOG_SEGMENT_PREFIX: &str = "compacted_logs-";
 
 fn entry_type_to_str(entry_type: i32) -> &'static str {
     match entry_type {
@@ -60,3 +68,169 @@ pub fn append_compacted_logs(dest_path: &Path, new_data: &[Entry]) -> io::Result
 
     Ok(())
 }
+
+/// Moves the current compacted log file aside as a segment named after the rotation time,
+/// so that the following compacted entries are written to a new file.
+/// The segment is encrypted if `key_provider` is given.
+pub fn rotate_compacted_logs(
+    dest_path: &Path,
+    key_provider: Option<&dyn KeyProvider>,
+) -> Result<PathBuf> {
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.6f");
+
+    let segment_path = match key_provider {
+        Some(key_provider) => {
+            let segment_path = dest_path.with_file_name(format!(
+                "{}{}.json.enc",
+                COMPACTED_LOG_SEGMENT_PREFIX, timestamp
+            ));
+            fs::write(&segment_path, encrypt(key_provider, &fs::read(dest_path)?)?)?;
+            fs::remove_file(dest_path)?;
+            segment_path
+        }
+        None => {
+            let segment_path = dest_path.with_file_name(format!(
+                "{}{}.json",
+                COMPACTED_LOG_SEGMENT_PREFIX, timestamp
+            ));
+            fs::rename(dest_path, &segment_path)?;
+            segment_path
+        }
+    };
+    Ok(segment_path)
+}
+
+/// Deletes the compacted log segments in `dir` exceeding the retention, oldest first,
+/// or moves them to `retention.archive_dir` if it is set. Returns the swept segments.
+pub fn sweep_compacted_logs(
+    dir: &Path,
+    retention: &CompactedLogRetention,
+) -> io::Result<Vec<PathBuf>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let is_segment = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(COMPACTED_LOG_SEGMENT_PREFIX));
+        if is_segment {
+            let metadata = entry.metadata()?;
+            segments.push((entry.path(), metadata.modified()?, metadata.len()));
+        }
+    }
+    // Segment names are ordered by the rotation time.
+    segments.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let now = SystemTime::now();
+    let mut total_bytes = segments.iter().map(|(_, _, len)| len).sum::<u64>();
+    let mut swept = Vec::new();
+
+    for (path, modified, len) in segments {
+        let expired = retention.max_age.is_some_and(|max_age| {
+            now.duration_since(modified).unwrap_or_default() > Duration::from_secs_f32(max_age)
+        });
+        let oversized = retention
+            .max_total_bytes
+            .is_some_and(|max_total_bytes| total_bytes > max_total_bytes);
+        if !expired && !oversized {
+            break;
+        }
+
+        match retention.archive_dir {
+            Some(ref archive_dir) => {
+                fs::create_dir_all(archive_dir)?;
+                let archive_path = Path::new(archive_dir).join(path.file_name().unwrap());
+                // `rename` doesn't work across file systems.
+                fs::copy(&path, archive_path)?;
+                fs::remove_file(&path)?;
+            }
+            None => fs::remove_file(&path)?,
+        }
+        total_bytes -= len;
+        swept.push(path);
+    }
+
+    Ok(swept)
+}
+
+/// Sweeps the compacted log segments every `compacted_log_retention.sweep_interval` seconds.
+pub(crate) async fn run_compacted_log_sweeper(config: Config, logger: Arc<dyn Logger>) {
+    let retention = config.compacted_log_retention;
+    let dir = Path::new(&config.compacted_log_dir);
+    let mut interval = tokio::time::interval(Duration::from_secs_f32(retention.sweep_interval));
+
+    loop {
+        interval.tick().await;
+        match sweep_compacted_logs(dir, &retention) {
+            Ok(swept) if !swept.is_empty() => {
+                logger.debug(&format!("Swept compacted log segments: {:?}", swept));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                logger.warn(&format!("Failed to sweep compacted log segments: {}", e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_segment(dir: &Path, name: &str, len: usize) -> PathBuf {
+        let path = dir.join(format!("{}{}.json", COMPACTED_LOG_SEGMENT_PREFIX, name));
+        fs::write(&path, vec![b' '; len]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_sweep_oldest_segments_over_max_total_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let oldest = write_segment(dir.path(), "1", 100);
+        let older = write_segment(dir.path(), "2", 100);
+        let newest = write_segment(dir.path(), "3", 100);
+        fs::write(dir.path().join("compacted_logs.json"), "[]").unwrap();
+
+        let retention = CompactedLogRetention {
+            max_total_bytes: Some(150),
+            ..Default::default()
+        };
+        let swept = sweep_compacted_logs(dir.path(), &retention).unwrap();
+
+        assert_eq!(swept, vec![oldest.clone(), older.clone()]);
+        assert!(!oldest.exists() && !older.exists());
+        assert!(newest.exists());
+        assert!(dir.path().join("compacted_logs.json").exists());
+    }
+
+    #[test]
+    fn test_archive_expired_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let segment = write_segment(dir.path(), "1", 10);
+
+        let retention = CompactedLogRetention {
+            max_age: Some(0.0),
+            archive_dir: Some(archive_dir.path().to_str().unwrap().to_owned()),
+            ..Default::default()
+        };
+        std::thread::sleep(Duration::from_millis(10));
+        let swept = sweep_compacted_logs(dir.path(), &retention).unwrap();
+
+        assert_eq!(swept, vec![segment.clone()]);
+        assert!(!segment.exists());
+        assert!(archive_dir
+            .path()
+            .join(segment.file_name().unwrap())
+            .exists());
+    }
+
+    #[test]
+    fn test_keep_segments_without_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        write_segment(dir.path(), "1", 100);
+
+        let swept = sweep_compacted_logs(dir.path(), &CompactedLogRetention::default()).unwrap();
+        assert!(swept.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Readiness and watchdog notifications of the units of `Type=notify` running a node.
+//!
+//! With the `systemd` feature, the node reports `READY=1` once it joined the cluster, and pets the watchdog
+//! from its ready loop when `WatchdogSec` is set, so that systemd restarts the unit if the loop gets wedged.
+//! Without it, or outside of systemd, the notifications are dropped.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::raft::logger::Logger;
+
+pub(crate) struct SystemdNotifier {
+    ready: bool,
+    /// Half of `WatchdogSec`, as `sd_watchdog_enabled(3)` recommends. `None` without a watchdog.
+    watchdog_interval: Option<Duration>,
+    last_pet: Instant,
+    #[cfg_attr(not(feature = "systemd"), allow(dead_code))]
+    logger: Arc<dyn Logger>,
+}
+
+impl SystemdNotifier {
+    pub fn new(logger: Arc<dyn Logger>) -> Self {
+        #[allow(unused_mut)]
+        let mut watchdog_interval = None;
+        #[cfg(feature = "systemd")]
+        {
+            let mut usec = 0;
+            if sd_notify::watchdog_enabled(false, &mut usec) {
+                watchdog_interval = Some(Duration::from_micros(usec) / 2);
+            }
+        }
+
+        Self {
+            ready: false,
+            watchdog_interval,
+            last_pet: Instant::now(),
+            logger,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn notify_ready(&mut self, node_id: u64) {
+        self.ready = true;
+        self.notify(&format!(
+            "READY=1\nSTATUS=Node {} joined the cluster",
+            node_id
+        ));
+    }
+
+    /// Pets the watchdog if half of its timeout elapsed since the last time.
+    pub fn pet_watchdog(&mut self) {
+        match self.watchdog_interval {
+            Some(interval) if self.last_pet.elapsed() >= interval => {
+                self.last_pet = Instant::now();
+                self.notify("WATCHDOG=1");
+            }
+            _ => {}
+        }
+    }
+
+    pub fn notify_stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    fn notify(&self, _state: &str) {
+        #[cfg(feature = "systemd")]
+        {
+            let states: Vec<sd_notify::NotifyState> =
+                _state.lines().map(sd_notify::NotifyState::Custom).collect();
+            if let Err(e) = sd_notify::notify(false, &states) {
+                self.logger
+                    .warn(&format!("Failed to notify systemd of {:?}: {}", _state, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raft::{default_logger, logger::Slogger};
+
+    #[test]
+    fn test_pet_watchdog_every_half_timeout() {
+        let mut notifier = SystemdNotifier::new(Arc::new(Slogger {
+            slog: default_logger(),
+        }));
+        notifier.watchdog_interval = Some(Duration::from_secs(3600));
+
+        let last_pet = notifier.last_pet;
+        notifier.pet_watchdog();
+        assert_eq!(notifier.last_pet, last_pet);
+
+        std::thread::sleep(Duration::from_millis(10));
+        notifier.watchdog_interval = Some(Duration::from_millis(5));
+        notifier.pet_watchdog();
+        assert!(notifier.last_pet > last_pet);
+
+        assert!(!notifier.is_ready());
+        notifier.notify_ready(1);
+        assert!(notifier.is_ready());
+    }
+}
@@ -0,0 +1,100 @@
+//! Trace context propagation for the spans of the propose/commit/apply path.
+//!
+//! Spans are always recorded through `tracing`. With the `otel` feature, their OpenTelemetry
+//! context is also carried in gRPC metadata and in the context of the proposed entries,
+//! so a single write can be traced from the proposer to the followers applying it.
+//! The globally configured text map propagator is used for both.
+
+use std::collections::HashMap;
+use tonic::metadata::MetadataMap;
+use tracing::Span;
+
+/// Serialized OpenTelemetry context, stored along with each proposed entry.
+pub(crate) type TraceContext = HashMap<String, String>;
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::propagation::{Extractor, Injector};
+    use tonic::metadata::{KeyRef, MetadataKey, MetadataMap, MetadataValue};
+
+    pub struct MetadataInjector<'a>(pub &'a mut MetadataMap);
+
+    impl Injector for MetadataInjector<'_> {
+        fn set(&mut self, key: &str, value: String) {
+            if let (Ok(key), Ok(value)) = (
+                MetadataKey::from_bytes(key.as_bytes()),
+                MetadataValue::try_from(value),
+            ) {
+                self.0.insert(key, value);
+            }
+        }
+    }
+
+    pub struct MetadataExtractor<'a>(pub &'a MetadataMap);
+
+    impl Extractor for MetadataExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0
+                .keys()
+                .map(|key| match key {
+                    KeyRef::Ascii(key) => key.as_str(),
+                    KeyRef::Binary(key) => key.as_str(),
+                })
+                .collect()
+        }
+    }
+}
+
+/// Attaches the context of the current span to an outgoing request.
+pub(crate) fn inject_metadata(_metadata: &mut MetadataMap) {
+    #[cfg(feature = "otel")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let context = Span::current().context();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut otel::MetadataInjector(_metadata))
+        });
+    }
+}
+
+/// Makes `span` a child of the span that sent the incoming request.
+pub(crate) fn set_parent_from_metadata(_span: &Span, _metadata: &MetadataMap) {
+    #[cfg(feature = "otel")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&otel::MetadataExtractor(_metadata))
+        });
+        _span.set_parent(context);
+    }
+}
+
+pub(crate) fn trace_context(_span: &Span) -> TraceContext {
+    #[allow(unused_mut)]
+    let mut trace_context = TraceContext::new();
+    #[cfg(feature = "otel")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let context = _span.context();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut trace_context)
+        });
+    }
+    trace_context
+}
+
+/// Makes `span` a child of the span that proposed the entry.
+pub(crate) fn set_parent_from_trace_context(_span: &Span, _trace_context: &TraceContext) {
+    #[cfg(feature = "otel")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(_trace_context)
+        });
+        _span.set_parent(context);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{fmt, fs, path::Path};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+
+use crate::Result;
+
+/// TLS settings shared by the gRPC server and the clients connecting to other peers.
+///
+/// The same certificate is used as the server identity and, when the CA is given,
+/// as the client identity for mutual TLS.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    /// PEM encoded certificate chain of this node.
+    pub cert: Vec<u8>,
+    /// PEM encoded private key of `cert`.
+    pub key: Vec<u8>,
+    /// PEM encoded CA certificate used to verify the other peers.
+    pub ca_cert: Option<Vec<u8>>,
+    /// Reject clients that don't present a certificate signed by `ca_cert`.
+    pub require_client_auth: bool,
+    /// Name to verify the peer certificates against.
+    /// When `None`, the IP address of the peer is used for hostname verification.
+    pub domain_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// Loads the PEM files from disk.
+    pub fn from_pem_files<P: AsRef<Path>>(cert: P, key: P, ca_cert: Option<P>) -> Result<Self> {
+        Ok(Self {
+            cert: fs::read(cert)?,
+            key: fs::read(key)?,
+            ca_cert: ca_cert.map(fs::read).transpose()?,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn server_tls_config(&self) -> ServerTlsConfig {
+        let config = ServerTlsConfig::new().identity(Identity::from_pem(&self.cert, &self.key));
+
+        match &self.ca_cert {
+            Some(ca_cert) => config
+                .client_ca_root(Certificate::from_pem(ca_cert))
+                .client_auth_optional(!self.require_client_auth),
+            None => config,
+        }
+    }
+
+    pub(crate) fn client_tls_config(&self) -> ClientTlsConfig {
+        let mut config = ClientTlsConfig::new().identity(Identity::from_pem(&self.cert, &self.key));
+
+        if let Some(ca_cert) = &self.ca_cert {
+            config = config.ca_certificate(Certificate::from_pem(ca_cert));
+        }
+        if let Some(domain_name) = &self.domain_name {
+            config = config.domain_name(domain_name);
+        }
+        config
+    }
+}
+
+// Keep the private key out of the logs.
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("ca_cert", &self.ca_cert.is_some())
+            .field("require_client_auth", &self.require_client_auth)
+            .field("domain_name", &self.domain_name)
+            .finish()
+    }
+}
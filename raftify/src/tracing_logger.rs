@@ -0,0 +1,41 @@
+use crate::raft::logger::Logger;
+
+/// `Logger` emitting the logs as `tracing` events with the `raftify` target.
+///
+/// The events are emitted inside the spans of the Raft loop, so subscribers get
+/// structured fields such as `node_id`, `term`, `leader_id` and `msg_type` along with the messages.
+/// `Slogger` keeps working for the applications built on slog.
+#[derive(Debug, Clone, Default)]
+pub struct TracingLogger;
+
+impl TracingLogger {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Logger for TracingLogger {
+    fn info(&self, s: &str) {
+        tracing::info!(target: "raftify", "{}", s);
+    }
+
+    fn debug(&self, s: &str) {
+        tracing::debug!(target: "raftify", "{}", s);
+    }
+
+    fn trace(&self, s: &str) {
+        tracing::trace!(target: "raftify", "{}", s);
+    }
+
+    fn error(&self, s: &str) {
+        tracing::error!(target: "raftify", "{}", s);
+    }
+
+    fn warn(&self, s: &str) {
+        tracing::warn!(target: "raftify", "{}", s);
+    }
+
+    fn fatal(&self, s: &str) {
+        tracing::error!(target: "raftify", fatal = true, "{}", s);
+    }
+}